@@ -1,4 +1,4 @@
-use super::input_mode::InputMode;
+use super::{input_mode::InputMode, ipc_service::Candidates};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ClientAction {
@@ -9,6 +9,8 @@ pub enum ClientAction {
     AppendText(String),
     AppendTextRaw(String),
     AppendTextDirect(String),
+    /// Kana typed on the JIS kana layout; a voicing mark joins the kana before it.
+    AppendTextKana(String),
     CommitTextDirect(String),
     RestoreReconversionOriginal,
     RemoveText,
@@ -30,10 +32,24 @@ pub enum ClientAction {
         kind: LearningCommitKind,
         was_temporary_latin: bool,
     },
+    // Ctrl+Delete on the highlighted candidate.
+    ForgetSelectedCandidate,
+    // Ctrl+Backspace right after a commit: reopen it as the composition.
+    UndoCommit,
+    // Tab/Shift+Tab: preview the next (or previous) prediction.
+    SelectPrediction(i32),
     SetTemporaryLatin(bool),
     SetTemporaryLatinShiftPending(bool),
 
     SetIMEMode(InputMode),
+    // Suspend or resume learning and context upload.
+    ToggleIncognito,
+
+    // Zenzai-refined candidates that arrived after `base` was shown.
+    ApplyRefinedCandidates {
+        base: Candidates,
+        refined: Candidates,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
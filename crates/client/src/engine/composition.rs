@@ -13,6 +13,7 @@ use crate::{
     engine::user_action::UserAction,
     extension::VKeyExt as _,
     trace::{diagnostic_log, diagnostic_log_enabled, diagnostic_log_lazy},
+    tsf::candidate_refinement::{RefinedCandidates, RefinementWindow},
     tsf::factory::{TextServiceFactory, TextServiceFactory_Impl},
 };
 
@@ -22,10 +23,11 @@ use super::{
     },
     full_width::{convert_kana_symbol, to_fullwidth, to_halfwidth},
     input_mode::InputMode,
+    input_scope::{input_scope_policy, InputScopePolicy},
     ipc_service::{
         client_performance_log_enabled, current_input_trace_request_id,
         is_non_destructive_ipc_error, requires_ipc_recovery, Candidates, ClauseSnapshotOperation,
-        ClientInputTraceGuard, IPCService, WindowRpcDelivery,
+        ClientInputTraceGuard, IPCService, InputLedgerSnapshot, WindowRpcDelivery,
     },
     romaji_lookup::RomajiLookup,
     state::{keyboard_disabled_from_context, AppConfigSnapshot, IMEState},
@@ -35,7 +37,10 @@ use super::{
 #[cfg(test)]
 use shared::RomajiRule;
 use shared::{
-    zenzai_cpu_backend_supported, AppConfig, NumpadInputMode, ReconversionKey, SpaceInputMode,
+    kana_layout,
+    keymap::{KeyChord, KeymapState, VK_EISU},
+    zenzai_cpu_backend_supported, AppConfig, ConversionMode, KanaInputMethod, LearningMode,
+    NumpadInputMode, ReconversionKey, SpaceInputMode,
     LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_MAX,
     LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_MIN,
 };
@@ -51,8 +56,8 @@ use windows::Win32::{
         },
         TextServices::{
             ITfComposition, ITfCompositionSink_Impl, ITfContext, ITfInputScope, ITfRange,
-            ITfTextInputProcessor, InputScope, GUID_PROP_INPUTSCOPE, IS_NUMERIC_PASSWORD,
-            IS_PASSWORD, IS_PRIVATE, TF_DEFAULT_SELECTION, TF_SELECTION,
+            ITfTextInputProcessor, InputScope, GUID_PROP_INPUTSCOPE, TF_DEFAULT_SELECTION,
+            TF_SELECTION,
         },
     },
 };
@@ -74,7 +79,7 @@ const KEYBOARD_TYPE_JAPANESE: i32 = 0x7;
 const VK_CONVERT_KEY_CODE: usize = 0x1C;
 const VK_SPACE_KEY_CODE: usize = 0x20;
 const VK_OEM_SLASH_KEY_CODE: usize = 0xBF;
-const MAX_RECONVERSION_SURFACE_COUNT: usize = 128;
+pub(crate) const MAX_RECONVERSION_SURFACE_COUNT: usize = 128;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct ModifierState {
@@ -196,6 +201,7 @@ fn language_bar_deferred_action(
         match mode {
             InputMode::Kana => UserAction::InputModeOn,
             InputMode::Latin => UserAction::InputModeOff,
+            mode => UserAction::SelectInputMode(mode.clone()),
         }
     } else {
         UserAction::ToggleInputMode
@@ -341,6 +347,14 @@ pub struct Composition {
     pub temporary_latin_shift_pending: bool,
     pub reconversion_original: Option<String>,
     pub tip_composition: Option<ITfComposition>,
+    /// Text and subtext last written under `tip_composition`. They differ from
+    /// `preview`/`suffix` while the reading is shown instead of the conversion.
+    pub displayed_text: String,
+    pub displayed_sub_text: String,
+    /// Completions shown under the composition for the current reading.
+    pub predictions: Candidates,
+    /// The commit `UserAction::UndoCommit` can reopen, until the next key.
+    last_commit: Option<Arc<CommittedComposition>>,
     deferred_actions: Vec<DeferredClientAction>,
     deferred_inputs: VecDeque<DeferredInputEvent>,
     deferred_projection: Option<DeferredProjection>,
@@ -443,6 +457,26 @@ pub struct FutureClauseSnapshot {
     remainder_origin: Option<Arc<str>>,
 }
 
+/// A committed composition, kept so that it can be reopened for editing.
+#[derive(Clone, Debug)]
+struct CommittedComposition {
+    /// The text the commit left in the document.
+    text: String,
+    raw_input: String,
+    raw_hiragana: String,
+    state: CompositionState,
+    /// Whether the composition was a single clause. Clause splits are kept
+    /// only by the client, so several clauses reopen as one.
+    single_clause: bool,
+    selection_index: i32,
+    corresponding_count: i32,
+    selected_text: String,
+    selected_sub_text: String,
+    input_ledger: InputLedgerSnapshot,
+    /// Reading/word pairs this commit learned for the first time.
+    learned_entries: Vec<(String, String)>,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct PendingLearningCommit {
     candidate_id: u64,
@@ -564,6 +598,11 @@ impl TextServiceFactory {
         matches!(wparam.0, 0x10 | 0xA0 | 0xA1)
     }
 
+    #[inline]
+    fn is_modifier_key(wparam: WPARAM) -> bool {
+        matches!(wparam.0, 0x10..=0x12 | 0x5B | 0x5C | 0xA0..=0xA5)
+    }
+
     #[inline]
     fn is_shift_alphabet_shortcut(wparam: WPARAM, is_shift_pressed: bool) -> bool {
         is_shift_pressed && (0x41..=0x5A).contains(&wparam.0)
@@ -717,7 +756,7 @@ impl TextServiceFactory {
     #[inline]
     fn input_text_for_mode(input_char: char, mode: &InputMode) -> String {
         match mode {
-            InputMode::Kana if input_char.is_ascii_uppercase() => {
+            _ if mode.uses_romaji() && input_char.is_ascii_uppercase() => {
                 input_char.to_ascii_lowercase().to_string()
             }
             _ => input_char.to_string(),
@@ -725,15 +764,36 @@ impl TextServiceFactory {
     }
 
     #[inline]
-    fn ctrl_conversion_shortcut_function(
+    fn keymap_state(state: &CompositionState) -> KeymapState {
+        match state {
+            CompositionState::None => KeymapState::Precomposition,
+            CompositionState::Composing => KeymapState::Composition,
+            CompositionState::Previewing | CompositionState::Selecting => KeymapState::Conversion,
+        }
+    }
+
+    #[inline]
+    fn keymap_chord(
         key_code: usize,
         is_ctrl_pressed: bool,
+        is_shift_pressed: bool,
         is_alt_pressed: bool,
-    ) -> Option<Function> {
-        if is_ctrl_pressed && !is_alt_pressed {
-            Function::from_ctrl_shortcut_key_code(key_code)
+        is_eisu: bool,
+        is_alt_backquote: bool,
+    ) -> KeyChord {
+        // 英数 and Alt+` are recognised from layout and scan-code rules rather
+        // than the virtual key alone, so they are looked up in their bare form.
+        if is_eisu {
+            KeyChord::new(VK_EISU, false, false, false)
+        } else if is_alt_backquote {
+            KeyChord::new(0xC0, false, false, true)
         } else {
-            None
+            KeyChord::new(
+                key_code as u16,
+                is_ctrl_pressed,
+                is_shift_pressed,
+                is_alt_pressed,
+            )
         }
     }
 
@@ -764,6 +824,43 @@ impl TextServiceFactory {
         }
     }
 
+    /// かな入力 reads a key by its place on the JIS kana layout rather than by the
+    /// character the keyboard layout would type.
+    #[inline]
+    fn kana_input_action(
+        vkey: usize,
+        is_shift_pressed: bool,
+        is_alt_pressed: bool,
+        mode: &InputMode,
+        temporary_latin: bool,
+        app_config: &AppConfig,
+    ) -> Option<UserAction> {
+        if app_config.general.input_method != KanaInputMethod::Kana
+            || !mode.uses_romaji()
+            || temporary_latin
+            || is_alt_pressed
+        {
+            return None;
+        }
+        let vkey = u16::try_from(vkey).ok()?;
+        kana_layout::kana_for_key(vkey, is_shift_pressed).map(UserAction::Kana)
+    }
+
+    /// Temporary Latin and full-width Latin compose typed text as is instead of
+    /// through the romaji table. Space still converts in full-width Latin.
+    #[inline]
+    fn composes_direct_text(
+        composition: &Composition,
+        mode: &InputMode,
+        start_temporary_latin: bool,
+        action: &UserAction,
+    ) -> bool {
+        let direct = composition.temporary_latin
+            || start_temporary_latin
+            || (*mode == InputMode::FullLatin && *action != UserAction::Space);
+        direct && Self::direct_text_for_action(action).is_some()
+    }
+
     #[inline]
     fn should_shrink_before_direct_append(
         composition: &Composition,
@@ -1427,6 +1524,7 @@ impl TextServiceFactory {
             hiragana: raw_hiragana.to_string(),
             corresponding_count: vec![corresponding_count],
             candidate_ids: vec![0],
+            metadata: Vec::new(),
         };
         FutureClauseSnapshot {
             clause_preview: clause_preview.to_string(),
@@ -1835,6 +1933,7 @@ impl TextServiceFactory {
             ClientAction::AppendText(_) => "AppendText",
             ClientAction::AppendTextRaw(_) => "AppendTextRaw",
             ClientAction::AppendTextDirect(_) => "AppendTextDirect",
+            ClientAction::AppendTextKana(_) => "AppendTextKana",
             ClientAction::CommitTextDirect(_) => "CommitTextDirect",
             ClientAction::RestoreReconversionOriginal => "RestoreReconversionOriginal",
             ClientAction::RemoveText => "RemoveText",
@@ -1843,8 +1942,13 @@ impl TextServiceFactory {
             ClientAction::MoveClause(_) => "MoveClause",
             ClientAction::AdjustBoundary(_) => "AdjustBoundary",
             ClientAction::SetIMEMode(_) => "SetIMEMode",
+            ClientAction::ToggleIncognito => "ToggleIncognito",
+            ClientAction::ApplyRefinedCandidates { .. } => "ApplyRefinedCandidates",
             ClientAction::SetSelection(_) => "SetSelection",
             ClientAction::CommitLearning { .. } => "CommitLearning",
+            ClientAction::ForgetSelectedCandidate => "ForgetSelectedCandidate",
+            ClientAction::UndoCommit => "UndoCommit",
+            ClientAction::SelectPrediction(_) => "SelectPrediction",
             ClientAction::ShrinkText(_) => "ShrinkText",
             ClientAction::ShrinkTextRaw(_) => "ShrinkTextRaw",
             ClientAction::ShrinkTextDirect(_) => "ShrinkTextDirect",
@@ -2030,13 +2134,53 @@ impl TextServiceFactory {
         Ok(())
     }
 
+    #[inline]
+    fn shows_reading_while_composing(
+        app_config: &AppConfig,
+        mode: &InputMode,
+        transition: &CompositionState,
+    ) -> bool {
+        *transition == CompositionState::Composing
+            && (app_config.general.conversion_mode == ConversionMode::Classic
+                || mode.shows_reading())
+    }
+
+    /// Text and subtext shown in the composition. Classic conversion and the
+    /// katakana and full-width modes keep the reading on screen until the user
+    /// converts, while the live conversion result is still tracked in
+    /// `preview`/`suffix`.
+    #[inline]
+    fn displayed_composing_text(
+        app_config: &AppConfig,
+        mode: &InputMode,
+        transition: &CompositionState,
+        fixed_prefix: &str,
+        raw_hiragana: &str,
+        preview: &str,
+        suffix: &str,
+    ) -> (String, String) {
+        if Self::shows_reading_while_composing(app_config, mode, transition) {
+            let reading = mode
+                .composing_text(raw_hiragana)
+                .unwrap_or_else(|| raw_hiragana.to_string());
+            (
+                Self::merge_preview_with_prefix(fixed_prefix, &reading),
+                String::new(),
+            )
+        } else {
+            (preview.to_string(), suffix.to_string())
+        }
+    }
+
     #[inline]
     fn live_conversion_reading<'a>(
         app_config: &AppConfig,
+        mode: &InputMode,
         candidates: &'a Candidates,
         transition: &CompositionState,
     ) -> Option<&'a str> {
         if app_config.general.show_live_conversion_reading
+            && !Self::shows_reading_while_composing(app_config, mode, transition)
             && *transition != CompositionState::None
             && !candidates.hiragana.is_empty()
         {
@@ -2049,10 +2193,11 @@ impl TextServiceFactory {
     #[inline]
     fn live_conversion_reading_update<'a>(
         app_config: &AppConfig,
+        mode: &InputMode,
         candidates: &'a Candidates,
         transition: &CompositionState,
     ) -> Option<&'a str> {
-        Self::live_conversion_reading(app_config, candidates, transition).or(Some(""))
+        Self::live_conversion_reading(app_config, mode, candidates, transition).or(Some(""))
     }
 
     #[inline]
@@ -2079,12 +2224,13 @@ impl TextServiceFactory {
     #[inline]
     fn live_conversion_reading_vertical_adjustment_for_update(
         app_config: &AppConfig,
+        mode: &InputMode,
         candidates: &Candidates,
         transition: &CompositionState,
     ) -> Option<i32> {
         Self::live_conversion_reading_vertical_adjustment_update(
             app_config,
-            Self::live_conversion_reading(app_config, candidates, transition),
+            Self::live_conversion_reading(app_config, mode, candidates, transition),
         )
     }
 
@@ -2144,7 +2290,7 @@ impl TextServiceFactory {
             let delivery = ipc_service.update_candidate_window_with_reading(
                 visible,
                 position,
-                Some(candidates.texts.clone()),
+                Some(candidates.window_list()),
                 Some(selection_index),
                 None,
                 reading,
@@ -2182,8 +2328,13 @@ impl TextServiceFactory {
 
     #[inline]
     fn hide_candidate_window_ui(&self, ipc_service: &mut IPCService) -> Result<WindowRpcDelivery> {
-        let delivery =
-            ipc_service.update_candidate_window(Some(false), None, Some(vec![]), Some(0), None)?;
+        let delivery = ipc_service.update_candidate_window(
+            Some(false),
+            None,
+            Some(shared::proto::CandidateList::default()),
+            Some(0),
+            None,
+        )?;
         self.remember_candidate_window_visibility_if_sent(delivery, Some(false));
         Ok(delivery)
     }
@@ -2251,18 +2402,21 @@ impl TextServiceFactory {
         candidates: &Candidates,
         selection_index: i32,
         app_config: &AppConfig,
+        mode: &InputMode,
         transition: &CompositionState,
     ) -> Result<()> {
-        let reading = Self::live_conversion_reading(app_config, candidates, transition);
+        let reading = Self::live_conversion_reading(app_config, mode, candidates, transition);
         let reading_update = reading.or(Some(""));
         let reading_vertical_adjustment =
             Self::live_conversion_reading_vertical_adjustment_update(app_config, reading);
-        let candidate_list_visible = if *transition != CompositionState::None {
+        let hides_window = *transition == CompositionState::None
+            || Self::shows_reading_while_composing(app_config, mode, transition);
+        let candidate_list_visible = if !hides_window {
             Some(!app_config.general.show_candidate_window_after_space)
         } else {
             Some(false)
         };
-        let visible = if *transition == CompositionState::None {
+        let visible = if hides_window {
             Some(false)
         } else if !app_config.general.show_candidate_window_after_space || reading.is_some() {
             Some(true)
@@ -2289,6 +2443,7 @@ impl TextServiceFactory {
             ClientAction::AppendText(_)
                 | ClientAction::AppendTextRaw(_)
                 | ClientAction::AppendTextDirect(_)
+                | ClientAction::AppendTextKana(_)
                 | ClientAction::ShrinkText(_)
                 | ClientAction::ShrinkTextRaw(_)
                 | ClientAction::ShrinkTextDirect(_)
@@ -4010,6 +4165,13 @@ impl TextServiceFactory {
                 continue;
             }
             hydrated.candidates.texts.push(candidate.text);
+            // Metadata stays parallel to the texts only while the snapshot's
+            // own candidates carry it.
+            if hydrated.candidates.metadata.len() + 1 == hydrated.candidates.texts.len() {
+                if let Some(metadata) = live_candidates.metadata_at(index) {
+                    hydrated.candidates.metadata.push(metadata.clone());
+                }
+            }
             hydrated.candidates.sub_texts.push(candidate.sub_text);
             hydrated
                 .candidates
@@ -4310,10 +4472,58 @@ impl TextServiceFactory {
         candidate_ids
     }
 
+    /// Reading/word pairs a whole-composition commit teaches for the first
+    /// time. Conversions that were already learned are left out, so forgetting
+    /// the pairs undoes exactly this commit.
+    fn collect_new_learned_entries(
+        selection_index: i32,
+        candidates: &Candidates,
+        clause_snapshots: &[ClauseSnapshot],
+        future_clause_snapshots: &[FutureClauseSnapshot],
+        temporary_latin: bool,
+    ) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut push_entry = |candidates: &Candidates, selection_index: i32| {
+            let Some(selection) = Self::select_candidate(candidates, selection_index) else {
+                return;
+            };
+            let already_learned = candidates
+                .metadata_at(selection.index as usize)
+                .is_some_and(|metadata| metadata.origin == shared::proto::CandidateOrigin::Learned);
+            if already_learned
+                || !Self::should_commit_learning_candidate(&selection, temporary_latin)
+            {
+                return;
+            }
+            let entry = (Self::learning_candidate_reading(&selection), selection.text);
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        };
+
+        for snapshot in clause_snapshots {
+            push_entry(&snapshot.candidates, snapshot.selection_index);
+        }
+        push_entry(candidates, selection_index);
+        for snapshot in future_clause_snapshots.iter().rev() {
+            push_entry(&snapshot.candidates, snapshot.selection_index);
+        }
+
+        entries
+    }
+
     fn flush_pending_learning_commits(
         ipc_service: &mut IPCService,
         pending_learning_commits: &mut Vec<PendingLearningCommit>,
     ) {
+        if ipc_service.learning_suspended() {
+            tracing::debug!(
+                candidate_count = pending_learning_commits.len(),
+                "Drop conversion learning while incognito"
+            );
+            pending_learning_commits.clear();
+            return;
+        }
         let commits = pending_learning_commits
             .drain(..)
             .map(|commit| (commit.candidate_id, commit.kind.proto_value()))
@@ -4327,12 +4537,7 @@ impl TextServiceFactory {
         }
     }
 
-    #[inline]
-    fn is_sensitive_input_scope(scope: InputScope) -> bool {
-        scope == IS_PASSWORD || scope == IS_NUMERIC_PASSWORD || scope == IS_PRIVATE
-    }
-
-    fn input_scope_list_contains_sensitive(input_scope: &ITfInputScope) -> bool {
+    fn input_scope_list_policy(input_scope: &ITfInputScope) -> InputScopePolicy {
         unsafe {
             let mut scopes_ptr: *mut InputScope = std::ptr::null_mut();
             let mut scope_count = 0;
@@ -4340,21 +4545,24 @@ impl TextServiceFactory {
                 .GetInputScopes(&mut scopes_ptr, &mut scope_count)
                 .is_err()
             {
-                return false;
+                return InputScopePolicy::default();
             }
             if scopes_ptr.is_null() {
-                return false;
+                return InputScopePolicy::default();
             }
 
             let scopes = std::slice::from_raw_parts(scopes_ptr, scope_count as usize);
-            let sensitive = scopes.iter().copied().any(Self::is_sensitive_input_scope);
+            let policy = input_scope_policy(scopes);
             CoTaskMemFree(Some(scopes_ptr.cast()));
-            sensitive
+            policy
         }
     }
 
-    fn context_has_sensitive_input_scope(tid: u32, context: ITfContext) -> Result<bool> {
-        read_edit_session::<bool>(
+    pub(crate) fn context_input_scope_policy(
+        tid: u32,
+        context: ITfContext,
+    ) -> Result<InputScopePolicy> {
+        read_edit_session::<InputScopePolicy>(
             tid,
             context.clone(),
             Rc::new({
@@ -4370,25 +4578,25 @@ impl TextServiceFactory {
                         )?;
                     }
                     if fetched == 0 {
-                        return Ok(false);
+                        return Ok(InputScopePolicy::default());
                     }
 
                     let range = match selection[0].range.as_ref() {
                         Some(range) => unsafe { range.Clone()? },
-                        None => return Ok(false),
+                        None => return Ok(InputScopePolicy::default()),
                     };
                     let input_scope_property =
                         unsafe { context.GetAppProperty(&GUID_PROP_INPUTSCOPE)? };
                     let value = unsafe { input_scope_property.GetValue(cookie, &range)? };
                     let unknown = IUnknown::try_from(&value)?;
                     let input_scope = unknown.cast::<ITfInputScope>()?;
-                    Ok(Self::input_scope_list_contains_sensitive(&input_scope))
+                    Ok(Self::input_scope_list_policy(&input_scope))
                 }
             }),
         )
     }
 
-    fn current_context_has_sensitive_input_scope(&self) -> bool {
+    fn current_context_input_scope_policy(&self) -> InputScopePolicy {
         let (tid, context) = match self.borrow() {
             Ok(text_service) => {
                 let tid = text_service.tid;
@@ -4399,7 +4607,7 @@ impl TextServiceFactory {
                             ?error,
                             "Skip learning input scope check because context is unavailable"
                         );
-                        return false;
+                        return InputScopePolicy::default();
                     }
                 }
             }
@@ -4408,18 +4616,18 @@ impl TextServiceFactory {
                     ?error,
                     "Skip learning input scope check because text service borrow failed"
                 );
-                return false;
+                return InputScopePolicy::default();
             }
         };
 
-        match Self::context_has_sensitive_input_scope(tid, context) {
-            Ok(value) => value,
+        match Self::context_input_scope_policy(tid, context) {
+            Ok(policy) => policy,
             Err(error) => {
                 tracing::debug!(
                     ?error,
                     "Skip learning input scope check because input scope could not be read"
                 );
-                false
+                InputScopePolicy::default()
             }
         }
     }
@@ -4588,7 +4796,7 @@ impl TextServiceFactory {
         app_config: &AppConfig,
         romaji_lookup: &RomajiLookup,
     ) -> bool {
-        if !app_config.general.punctuation_commit || *mode != InputMode::Kana {
+        if !app_config.general.punctuation_commit || !mode.uses_romaji() {
             return false;
         }
 
@@ -4634,6 +4842,51 @@ impl TextServiceFactory {
         actions
     }
 
+    /// First conversion out of classic composing. The candidate list follows
+    /// on the next Space unless it is configured to show while typing.
+    #[inline]
+    fn classic_convert_actions(app_config: &AppConfig) -> Vec<ClientAction> {
+        let mut actions = Vec::with_capacity(2);
+        if !app_config.general.show_candidate_window_after_space {
+            actions.push(ClientAction::ShowCandidateWindow);
+        }
+        actions.push(ClientAction::SetSelection(SetSelectionType::Number(0)));
+        actions
+    }
+
+    /// Classic conversion and the katakana and full-width modes show only the
+    /// reading while composing, so anything that would act on the hidden
+    /// conversion either converts first or commits the reading as is.
+    #[inline]
+    fn classic_composing_actions(
+        action: &UserAction,
+        app_config: &AppConfig,
+    ) -> Option<(CompositionState, Vec<ClientAction>)> {
+        match action {
+            UserAction::Enter | UserAction::CommitAndNextClause | UserAction::CommitFirstClause => {
+                Some((CompositionState::None, vec![ClientAction::EndComposition]))
+            }
+            UserAction::Navigation(Navigation::Left | Navigation::Right) => {
+                Some((CompositionState::Composing, vec![]))
+            }
+            UserAction::Navigation(Navigation::Up | Navigation::Down)
+            | UserAction::Space
+            | UserAction::Tab
+            | UserAction::Reconvert => Some((
+                CompositionState::Previewing,
+                Self::classic_convert_actions(app_config),
+            )),
+            UserAction::AdjustClauseBoundary(direction) => Some((
+                CompositionState::Previewing,
+                vec![
+                    ClientAction::SetSelection(SetSelectionType::Number(0)),
+                    ClientAction::AdjustBoundary(*direction),
+                ],
+            )),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn commit_preview_then_append_actions(
         append_action: ClientAction,
@@ -4755,10 +5008,47 @@ impl TextServiceFactory {
             // mode changes, and commit-then-append input remain safe.
             return Some((composition.state.clone(), Vec::new()));
         }
+        if let UserAction::SelectPrediction(direction) = action {
+            let picks_prediction = !composition.predictions.texts.is_empty()
+                && match composition.state {
+                    CompositionState::Composing => true,
+                    CompositionState::Previewing => {
+                        composition.candidates == composition.predictions
+                    }
+                    _ => false,
+                };
+            if picks_prediction {
+                return Some((
+                    CompositionState::Previewing,
+                    vec![ClientAction::SelectPrediction(*direction)],
+                ));
+            }
+            return Self::plan_actions_for_user_action_with_lookup(
+                composition,
+                &UserAction::Tab,
+                mode,
+                is_shift_pressed,
+                app_config,
+                romaji_lookup,
+                start_temporary_latin,
+            );
+        }
+        if *action == UserAction::ToggleIncognito {
+            return Some((
+                composition.state.clone(),
+                vec![ClientAction::ToggleIncognito],
+            ));
+        }
+        let shows_reading =
+            app_config.general.conversion_mode == ConversionMode::Classic || mode.shows_reading();
         let result = match composition.state {
             CompositionState::None => match action {
-                _ if (composition.temporary_latin || start_temporary_latin)
-                    && Self::direct_text_for_action(action).is_some() =>
+                _ if Self::composes_direct_text(
+                    composition,
+                    mode,
+                    start_temporary_latin,
+                    action,
+                ) =>
                 {
                     let text = Self::direct_text_for_action(action)?;
                     let mut actions = vec![ClientAction::StartComposition];
@@ -4768,7 +5058,7 @@ impl TextServiceFactory {
                     actions.push(ClientAction::AppendTextDirect(text));
                     Some((CompositionState::Composing, actions))
                 }
-                UserAction::NumpadSymbol(symbol) if *mode == InputMode::Kana => {
+                UserAction::NumpadSymbol(symbol) if mode.uses_romaji() => {
                     let text =
                         Self::numpad_text_for_mode(*symbol, app_config.general.numpad_input, true)?;
                     Some((
@@ -4779,17 +5069,24 @@ impl TextServiceFactory {
                         ],
                     ))
                 }
-                UserAction::Input(char) if *mode == InputMode::Kana => Some((
+                UserAction::Input(char) if mode.uses_romaji() => Some((
                     CompositionState::Composing,
                     vec![
                         ClientAction::StartComposition,
                         ClientAction::AppendText(Self::input_text_for_mode(*char, mode)),
                     ],
                 )),
+                UserAction::Kana(kana) if mode.uses_romaji() => Some((
+                    CompositionState::Composing,
+                    vec![
+                        ClientAction::StartComposition,
+                        ClientAction::AppendTextKana(kana.to_string()),
+                    ],
+                )),
                 UserAction::Number {
                     value,
                     is_numpad: true,
-                } if *mode == InputMode::Kana => {
+                } if mode.uses_romaji() => {
                     let digit = char::from_digit(*value as u32, 10).unwrap_or('0');
                     let text =
                         Self::numpad_text_for_mode(digit, app_config.general.numpad_input, true)?;
@@ -4805,14 +5102,14 @@ impl TextServiceFactory {
                 UserAction::Number {
                     value,
                     is_numpad: false,
-                } if *mode == InputMode::Kana => Some((
+                } if mode.uses_romaji() => Some((
                     CompositionState::Composing,
                     vec![
                         ClientAction::StartComposition,
                         ClientAction::AppendText(value.to_string()),
                     ],
                 )),
-                UserAction::Space if *mode == InputMode::Kana => {
+                UserAction::Space if *mode != InputMode::Latin => {
                     let mut use_halfwidth =
                         matches!(app_config.general.space_input, SpaceInputMode::AlwaysHalf);
                     if is_shift_pressed {
@@ -4831,8 +5128,8 @@ impl TextServiceFactory {
                 UserAction::ToggleInputMode => Some((
                     CompositionState::None,
                     vec![match mode {
-                        InputMode::Kana => ClientAction::SetIMEMode(InputMode::Latin),
                         InputMode::Latin => ClientAction::SetIMEMode(InputMode::Kana),
+                        _ => ClientAction::SetIMEMode(InputMode::Latin),
                     }],
                 )),
                 UserAction::InputModeOn => Some((
@@ -4843,10 +5140,24 @@ impl TextServiceFactory {
                     CompositionState::None,
                     vec![ClientAction::SetIMEMode(InputMode::Latin)],
                 )),
+                UserAction::SelectInputMode(target) => Some((
+                    CompositionState::None,
+                    vec![ClientAction::SetIMEMode(target.clone())],
+                )),
+                UserAction::UndoCommit => composition
+                    .last_commit
+                    .as_ref()
+                    .map(|last_commit| (last_commit.state.clone(), vec![ClientAction::UndoCommit])),
                 _ => None,
             },
             CompositionState::Composing => match action {
-                _ if !composition.temporary_latin
+                _ if shows_reading
+                    && Self::classic_composing_actions(action, app_config).is_some() =>
+                {
+                    Self::classic_composing_actions(action, app_config)
+                }
+                _ if !shows_reading
+                    && !composition.temporary_latin
                     && !start_temporary_latin
                     && Self::punctuation_commit_action_target_enabled(
                         action,
@@ -4865,8 +5176,12 @@ impl TextServiceFactory {
                     )?;
                     Some(Self::punctuation_commit_actions(text))
                 }
-                _ if (composition.temporary_latin || start_temporary_latin)
-                    && Self::direct_text_for_action(action).is_some() =>
+                _ if Self::composes_direct_text(
+                    composition,
+                    mode,
+                    start_temporary_latin,
+                    action,
+                ) =>
                 {
                     let text = Self::direct_text_for_action(action)?;
                     let mut actions = vec![];
@@ -4881,7 +5196,7 @@ impl TextServiceFactory {
                     }
                     Some((CompositionState::Composing, actions))
                 }
-                UserAction::NumpadSymbol(symbol) if *mode == InputMode::Kana => {
+                UserAction::NumpadSymbol(symbol) if mode.uses_romaji() => {
                     let text =
                         Self::numpad_text_for_mode(*symbol, app_config.general.numpad_input, false)
                             .unwrap_or_else(|| symbol.to_string());
@@ -4896,10 +5211,14 @@ impl TextServiceFactory {
                         *char, mode,
                     ))],
                 )),
+                UserAction::Kana(kana) => Some((
+                    CompositionState::Composing,
+                    vec![ClientAction::AppendTextKana(kana.to_string())],
+                )),
                 UserAction::Number {
                     value,
                     is_numpad: true,
-                } if *mode == InputMode::Kana => {
+                } if mode.uses_romaji() => {
                     let digit = char::from_digit(*value as u32, 10).unwrap_or('0');
                     let text =
                         Self::numpad_text_for_mode(digit, app_config.general.numpad_input, false)
@@ -4913,7 +5232,7 @@ impl TextServiceFactory {
                     CompositionState::Composing,
                     vec![ClientAction::AppendText(value.to_string())],
                 )),
-                UserAction::Backspace | UserAction::Delete => {
+                UserAction::Backspace | UserAction::Delete | UserAction::ForgetCandidate => {
                     if composition.raw_input.chars().count() <= 1 {
                         Some((
                             CompositionState::None,
@@ -4984,6 +5303,13 @@ impl TextServiceFactory {
                         ClientAction::SetIMEMode(InputMode::Latin),
                     ],
                 )),
+                UserAction::SelectInputMode(target) => Some((
+                    CompositionState::None,
+                    vec![
+                        ClientAction::EndComposition,
+                        ClientAction::SetIMEMode(target.clone()),
+                    ],
+                )),
                 UserAction::Space | UserAction::Tab | UserAction::Reconvert => Some((
                     CompositionState::Previewing,
                     Self::candidate_preview_actions(app_config),
@@ -5016,8 +5342,12 @@ impl TextServiceFactory {
                     )?;
                     Some(Self::punctuation_commit_actions(text))
                 }
-                _ if (composition.temporary_latin || start_temporary_latin)
-                    && Self::direct_text_for_action(action).is_some() =>
+                _ if Self::composes_direct_text(
+                    composition,
+                    mode,
+                    start_temporary_latin,
+                    action,
+                ) =>
                 {
                     let text = Self::direct_text_for_action(action)?;
                     Some(Self::commit_preview_then_append_actions(
@@ -5025,7 +5355,7 @@ impl TextServiceFactory {
                         composition.temporary_latin || start_temporary_latin,
                     ))
                 }
-                UserAction::NumpadSymbol(symbol) if *mode == InputMode::Kana => {
+                UserAction::NumpadSymbol(symbol) if mode.uses_romaji() => {
                     let text =
                         Self::numpad_text_for_mode(*symbol, app_config.general.numpad_input, false)
                             .unwrap_or_else(|| symbol.to_string());
@@ -5038,10 +5368,14 @@ impl TextServiceFactory {
                     ClientAction::AppendText(Self::input_text_for_mode(*char, mode)),
                     false,
                 )),
+                UserAction::Kana(kana) => Some(Self::commit_preview_then_append_actions(
+                    ClientAction::AppendTextKana(kana.to_string()),
+                    false,
+                )),
                 UserAction::Number {
                     value,
                     is_numpad: true,
-                } if *mode == InputMode::Kana => {
+                } if mode.uses_romaji() => {
                     let digit = char::from_digit(*value as u32, 10).unwrap_or('0');
                     let text =
                         Self::numpad_text_for_mode(digit, app_config.general.numpad_input, false)
@@ -5055,6 +5389,10 @@ impl TextServiceFactory {
                     ClientAction::AppendText(value.to_string()),
                     false,
                 )),
+                UserAction::ForgetCandidate => Some((
+                    CompositionState::Previewing,
+                    vec![ClientAction::ForgetSelectedCandidate],
+                )),
                 UserAction::Backspace | UserAction::Delete => {
                     if composition.raw_input.chars().count() <= 1 {
                         Some((
@@ -5126,6 +5464,13 @@ impl TextServiceFactory {
                         ClientAction::SetIMEMode(InputMode::Latin),
                     ],
                 )),
+                UserAction::SelectInputMode(target) => Some((
+                    CompositionState::None,
+                    vec![
+                        ClientAction::EndComposition,
+                        ClientAction::SetIMEMode(target.clone()),
+                    ],
+                )),
                 UserAction::Space | UserAction::Tab | UserAction::Reconvert => Some((
                     CompositionState::Previewing,
                     Self::candidate_preview_actions(app_config),
@@ -5141,7 +5486,18 @@ impl TextServiceFactory {
             CompositionState::Selecting => None,
         };
 
-        result
+        // Partial commits and clause moves keep the remaining text converted,
+        // which a reading-only composition can only show while previewing.
+        match result {
+            Some((CompositionState::Composing, actions))
+                if shows_reading
+                    && composition.state == CompositionState::Previewing
+                    && !matches!(action, UserAction::Backspace | UserAction::Delete) =>
+            {
+                Some((CompositionState::Previewing, actions))
+            }
+            result => result,
+        }
     }
 
     fn plan_deferred_user_action(
@@ -5151,9 +5507,8 @@ impl TextServiceFactory {
         app_config: &AppConfig,
         romaji_lookup: &RomajiLookup,
     ) -> Option<(CompositionState, Vec<ClientAction>)> {
-        let start_temporary_latin = !composition.temporary_latin
-            && *mode == InputMode::Kana
-            && deferred.shift_alphabet_shortcut;
+        let start_temporary_latin =
+            !composition.temporary_latin && mode.uses_romaji() && deferred.shift_alphabet_shortcut;
         let (transition, mut actions) = Self::plan_actions_for_user_action_with_lookup(
             composition,
             &deferred.action,
@@ -5208,6 +5563,10 @@ impl TextServiceFactory {
                 ClientAction::AppendText(text)
                 | ClientAction::AppendTextRaw(text)
                 | ClientAction::AppendTextDirect(text) => projection.raw_input.push_str(text),
+                ClientAction::AppendTextKana(text) => {
+                    text.chars()
+                        .for_each(|kana| kana_layout::push_kana(&mut projection.raw_input, kana));
+                }
                 ClientAction::RemoveText => {
                     projection.raw_input.pop();
                     // A mapped kana can consume more than one raw input element.
@@ -5224,7 +5583,9 @@ impl TextServiceFactory {
                 | ClientAction::MoveClause(_)
                 | ClientAction::AdjustBoundary(_)
                 | ClientAction::SetSelection(_)
-                | ClientAction::SetTextWithType(_) => projection.reliable = false,
+                | ClientAction::SetTextWithType(_)
+                | ClientAction::UndoCommit
+                | ClientAction::SelectPrediction(_) => projection.reliable = false,
                 ClientAction::SetIMEMode(mode) => {
                     projection.mode = mode.clone();
                     if projection.state == CompositionState::None {
@@ -5392,12 +5753,15 @@ impl TextServiceFactory {
             return Ok(ReconversionKeyTestResult::NotHandled);
         };
         // OnTest runs synchronously in the key hot path. Probe only the range
-        // shape here; the bounded text is read once in OnKeyDown.
+        // shape, or the short run around an empty caret; the reconverted text
+        // is read once in OnKeyDown.
         let has_selection = self.has_nonempty_selection(context)?;
         if has_selection {
             Ok(ReconversionKeyTestResult::Owned)
         } else if preset == ReconversionKey::Space {
             Ok(ReconversionKeyTestResult::MatchedEmpty)
+        } else if self.has_caret_phrase(context)? {
+            Ok(ReconversionKeyTestResult::Owned)
         } else {
             Ok(ReconversionKeyTestResult::NotHandled)
         }
@@ -5472,10 +5836,29 @@ impl TextServiceFactory {
         preset: ReconversionKey,
     ) -> Result<Option<bool>> {
         self.borrow_mut()?.context = Some(context.clone());
+        // Without a selection, take the phrase at the caret like MS-IME. Space
+        // keeps inserting a space there instead.
+        let caret_phrase = match range {
+            Some(range) if preset != ReconversionKey::Space => {
+                self.caret_phrase_range(context, range)?
+            }
+            _ => None,
+        };
+        let range = caret_phrase.as_ref().or(range);
         let selected_text = match range {
             Some(range) => self.reconversion_text_for_range(context, range)?,
             None => self.selected_text(context)?,
         };
+        let selected_text = match selected_text {
+            SelectedText::Empty
+                if range.is_none()
+                    && preset != ReconversionKey::Space
+                    && self.select_caret_phrase(context)? =>
+            {
+                self.selected_text(context)?
+            }
+            selected_text => selected_text,
+        };
         let original = match selected_text {
             SelectedText::Text(original) => original,
             SelectedText::Unsupported => return Ok(Some(true)),
@@ -5491,7 +5874,7 @@ impl TextServiceFactory {
         // A matched shortcut owns the selected text. Unsupported or sensitive text is
         // intentionally left untouched instead of falling back to Space and replacing it.
         if original.chars().count() > MAX_RECONVERSION_SURFACE_COUNT
-            || self.current_context_has_sensitive_input_scope()
+            || self.current_context_input_scope_policy().sensitive
         {
             return Ok(Some(true));
         }
@@ -5597,12 +5980,14 @@ impl TextServiceFactory {
             true,
             Self::live_conversion_reading_update(
                 app_config,
+                &InputMode::Kana,
                 &reconversion.candidates,
                 &CompositionState::Previewing,
             ),
             Some(true),
             Self::live_conversion_reading_vertical_adjustment_for_update(
                 app_config,
+                &InputMode::Kana,
                 &reconversion.candidates,
                 &CompositionState::Previewing,
             ),
@@ -5646,7 +6031,6 @@ impl TextServiceFactory {
             let is_win_pressed = Self::is_win_pressed();
             let shift_key_state = self.shift_key_state();
             let is_shift_pressed = shift_key_state.for_regular_key();
-            let is_ctrl_space = is_ctrl_pressed && wparam.0 == 0x20;
             let is_capslock_key = wparam.0 == VK_CAPITAL_KEY_CODE
                 || Self::is_translated_capslock_key(wparam.0, lparam);
             let is_eisu = if is_capslock_key {
@@ -5662,12 +6046,6 @@ impl TextServiceFactory {
             } else {
                 false
             };
-            let is_ctrl_enter = is_ctrl_pressed && wparam.0 == 0x0D;
-            let is_ctrl_down = is_ctrl_pressed && wparam.0 == 0x28;
-            let ctrl_conversion_function =
-                Self::ctrl_conversion_shortcut_function(wparam.0, is_ctrl_pressed, is_alt_pressed);
-            let is_shift_left = is_shift_pressed && wparam.0 == 0x25;
-            let is_shift_right = is_shift_pressed && wparam.0 == 0x27;
             let is_shift_key = Self::is_shift_key(wparam);
             let is_alt_backquote = Self::is_alt_backquote(wparam, lparam);
             let config_snapshot_start = trace_request_id.map(|_| Instant::now());
@@ -5698,43 +6076,6 @@ impl TextServiceFactory {
                 return Ok(None);
             }
 
-            // check shortcut keys
-            if is_ctrl_pressed
-                && !is_ctrl_space
-                && !is_alt_backquote
-                && !is_ctrl_enter
-                && !is_ctrl_down
-                && ctrl_conversion_function.is_none()
-            {
-                self.clear_temporary_latin_shift_pending_if_needed(!Self::is_shift_key(wparam))?;
-                return Ok(None);
-            }
-
-            if is_ctrl_space || is_alt_backquote || is_eisu {
-                let shortcuts = &app_config.shortcuts;
-
-                if is_ctrl_space && !shortcuts.ctrl_space_toggle {
-                    self.clear_temporary_latin_shift_pending_if_needed(!Self::is_shift_key(
-                        wparam,
-                    ))?;
-                    return Ok(None);
-                }
-
-                if is_alt_backquote && !shortcuts.alt_backquote_toggle {
-                    self.clear_temporary_latin_shift_pending_if_needed(!Self::is_shift_key(
-                        wparam,
-                    ))?;
-                    return Ok(None);
-                }
-
-                if is_eisu && !shortcuts.eisu_toggle {
-                    self.clear_temporary_latin_shift_pending_if_needed(!Self::is_shift_key(
-                        wparam,
-                    ))?;
-                    return Ok(None);
-                }
-            }
-
             #[allow(clippy::let_and_return)]
             let (mut composition, mut mode) = {
                 let text_service = self.borrow()?;
@@ -5764,11 +6105,47 @@ impl TextServiceFactory {
             }
             let projected =
                 has_deferred_input.then(|| Self::deferred_projection(&composition, mode.clone()));
+            let keymap_state = Self::keymap_state(
+                projected
+                    .as_ref()
+                    .map(|projection| &projection.state)
+                    .unwrap_or(&composition.state),
+            );
+            let chord = Self::keymap_chord(
+                wparam.0,
+                is_ctrl_pressed,
+                is_shift_pressed,
+                is_alt_pressed,
+                is_eisu,
+                is_alt_backquote,
+            );
+            let bound_action = config_snapshot
+                .keymap()
+                .lookup(keymap_state, &chord)
+                .map(UserAction::from_key_command);
+            // Only the key right after a commit can undo it; any other key may
+            // have moved the caret or edited the committed text.
+            if composition.last_commit.is_some()
+                && !Self::is_modifier_key(wparam)
+                && !matches!(bound_action, Some(Some(UserAction::UndoCommit)))
+            {
+                composition.last_commit = None;
+                let text_service = self.borrow()?;
+                text_service.borrow_mut_composition()?.last_commit = None;
+            }
+            // Ctrl chords, 英数 and Alt+` stay with the application unless bound.
+            if matches!(bound_action, Some(None))
+                || (bound_action.is_none() && (is_ctrl_pressed || is_eisu || is_alt_backquote))
+            {
+                self.clear_temporary_latin_shift_pending_if_needed(!is_shift_key)?;
+                return Ok(None);
+            }
+
             let temporary_latin = projected
                 .as_ref()
                 .map(|projection| projection.temporary_latin)
                 .unwrap_or(composition.temporary_latin);
-            if temporary_latin && is_shift_key && !is_shift_left && !is_shift_right {
+            if temporary_latin && is_shift_key {
                 if let Some(projection) = projected {
                     self.enqueue_deferred_actions(
                         &composition,
@@ -5793,20 +6170,22 @@ impl TextServiceFactory {
             let should_clear_shift_pending =
                 composition.temporary_latin_shift_pending && !is_shift_key;
 
-            let action = if is_alt_backquote || is_eisu {
-                UserAction::ToggleInputMode
-            } else if is_ctrl_enter {
-                UserAction::CommitFirstClause
-            } else if is_ctrl_down {
-                UserAction::CommitAndNextClause
-            } else if let Some(function) = ctrl_conversion_function {
-                UserAction::Function(function)
-            } else if is_shift_left {
-                UserAction::AdjustClauseBoundary(-1)
-            } else if is_shift_right {
-                UserAction::AdjustClauseBoundary(1)
-            } else {
-                UserAction::try_from(wparam.0)?
+            let action = match bound_action.flatten() {
+                Some(action) => action,
+                None => match Self::kana_input_action(
+                    wparam.0,
+                    is_shift_pressed,
+                    is_alt_pressed,
+                    projected
+                        .as_ref()
+                        .map(|projection| &projection.mode)
+                        .unwrap_or(&mode),
+                    temporary_latin,
+                    app_config,
+                ) {
+                    Some(action) => action,
+                    None => UserAction::try_from(wparam.0)?,
+                },
             };
 
             let deferred_user_action = DeferredUserAction {
@@ -6182,9 +6561,6 @@ impl TextServiceFactory {
 
             let config_snapshot = IMEState::app_config_snapshot()?;
             let app_config = config_snapshot.app_config();
-            if !app_config.shortcuts.eisu_toggle {
-                return Ok(false);
-            }
 
             #[allow(clippy::let_and_return)]
             let (composition, mode) = {
@@ -6193,14 +6569,31 @@ impl TextServiceFactory {
                 let mode = IMEState::input_mode()?;
                 (composition, mode)
             };
+            let has_deferred_input =
+                !composition.deferred_actions.is_empty() || !composition.deferred_inputs.is_empty();
+            let state = if has_deferred_input {
+                Self::deferred_projection(&composition, mode.clone()).state
+            } else {
+                composition.state.clone()
+            };
+            let Some(action) = config_snapshot
+                .keymap()
+                .lookup(
+                    Self::keymap_state(&state),
+                    &KeyChord::new(VK_EISU, false, false, false),
+                )
+                .and_then(UserAction::from_key_command)
+            else {
+                return Ok(false);
+            };
 
             let deferred = DeferredUserAction {
-                action: UserAction::ToggleInputMode,
+                action,
                 is_shift_pressed: shift_key_state.for_regular_key(),
                 is_shift_key: false,
                 shift_alphabet_shortcut: false,
             };
-            if !composition.deferred_actions.is_empty() || !composition.deferred_inputs.is_empty() {
+            if has_deferred_input {
                 if !self.enqueue_deferred_user_action(
                     &composition,
                     deferred,
@@ -6287,9 +6680,13 @@ impl TextServiceFactory {
 
         if let Ok(Some(mut ipc_service)) = IMEState::ipc_service() {
             ipc_service.discard_input_ledger();
-            if let Ok(delivery) =
-                ipc_service.update_candidate_window(Some(false), None, Some(vec![]), Some(0), None)
-            {
+            if let Ok(delivery) = ipc_service.update_candidate_window(
+                Some(false),
+                None,
+                Some(shared::proto::CandidateList::default()),
+                Some(0),
+                None,
+            ) {
                 self.remember_candidate_window_visibility_if_sent(delivery, Some(false));
             }
             let _ = ipc_service.clear_text();
@@ -6320,9 +6717,13 @@ impl TextServiceFactory {
         let ipc_service = IMEState::ipc_service().ok().flatten();
 
         if let Some(mut ipc_service) = ipc_service {
-            if let Ok(delivery) =
-                ipc_service.update_candidate_window(Some(false), None, Some(vec![]), Some(0), None)
-            {
+            if let Ok(delivery) = ipc_service.update_candidate_window(
+                Some(false),
+                None,
+                Some(shared::proto::CandidateList::default()),
+                Some(0),
+                None,
+            ) {
                 self.remember_candidate_window_visibility_if_sent(delivery, Some(false));
             }
             let _ = ipc_service.clear_text();
@@ -6343,9 +6744,13 @@ impl TextServiceFactory {
 
         if let Ok(Some(mut ipc_service)) = IMEState::ipc_service() {
             ipc_service.discard_input_ledger();
-            if let Ok(delivery) =
-                ipc_service.update_candidate_window(Some(false), None, Some(vec![]), Some(0), None)
-            {
+            if let Ok(delivery) = ipc_service.update_candidate_window(
+                Some(false),
+                None,
+                Some(shared::proto::CandidateList::default()),
+                Some(0),
+                None,
+            ) {
                 self.remember_candidate_window_visibility_if_sent(delivery, Some(false));
             }
             let _ = ipc_service.clear_text();
@@ -6379,17 +6784,14 @@ impl TextServiceFactory {
             tracing::warn!(?error, "Failed to update language bar after mode switch");
         }
 
-        let mode_label = match mode {
-            InputMode::Latin => "A",
-            InputMode::Kana => "あ",
-        };
+        let mode_label = mode.indicator_text(IMEState::incognito().unwrap_or(false));
         if let Ok(Some(mut ipc_service)) = IMEState::ipc_service() {
             if let Err(error) = ipc_service.update_candidate_window_with_reading(
                 None,
                 position,
                 None,
                 None,
-                Some(mode_label),
+                Some(&mode_label),
                 Some(""),
                 Some(false),
                 None,
@@ -6544,7 +6946,43 @@ impl TextServiceFactory {
         result
     }
 
+    /// Shows Zenzai-refined candidates that arrived after the dictionary
+    /// candidates for the same input, unless the user has moved on since.
+    pub(crate) fn apply_refined_candidates(&self, refinement: RefinedCandidates) -> Result<()> {
+        let is_current = {
+            let text_service = self.borrow()?;
+            let composition = text_service.borrow_composition()?;
+            composition.state == CompositionState::Composing
+                && composition.tip_composition.is_some()
+                && composition.deferred_actions.is_empty()
+                && composition.deferred_inputs.is_empty()
+                && composition.candidates == refinement.base
+        };
+        if !is_current {
+            tracing::debug!("Skip Zenzai refinement for a changed composition");
+            return Ok(());
+        }
+
+        self.handle_action(
+            &[ClientAction::ApplyRefinedCandidates {
+                base: refinement.base,
+                refined: refinement.refined,
+            }],
+            CompositionState::Composing,
+        )
+    }
+
     pub(crate) fn request_language_bar_input_mode_toggle(&self, mode: InputMode) -> Result<()> {
+        self.request_language_bar_input_mode(mode, false)
+    }
+
+    /// Switches to a mode picked from the language bar menu. Unlike a click,
+    /// the target is absolute even when queued behind pending input.
+    pub(crate) fn request_language_bar_input_mode_selection(&self, mode: InputMode) -> Result<()> {
+        self.request_language_bar_input_mode(mode, true)
+    }
+
+    fn request_language_bar_input_mode(&self, mode: InputMode, selected: bool) -> Result<()> {
         let (composition, replaces_pending_mode_switch) = {
             let text_service = self.borrow()?;
             let composition = text_service.borrow_composition()?.clone();
@@ -6560,7 +6998,7 @@ impl TextServiceFactory {
         // the request after the replacement has been queued successfully.
         let config_snapshot = IMEState::app_config_snapshot()?;
         let deferred = DeferredUserAction {
-            action: language_bar_deferred_action(&mode, replaces_pending_mode_switch),
+            action: language_bar_deferred_action(&mode, selected || replaces_pending_mode_switch),
             is_shift_pressed: false,
             is_shift_key: false,
             shift_alphabet_shortcut: false,
@@ -6674,17 +7112,22 @@ impl TextServiceFactory {
             let mut temporary_latin = composition.temporary_latin;
             let mut temporary_latin_shift_pending = composition.temporary_latin_shift_pending;
             let mut reconversion_original = composition.reconversion_original.clone();
+            let mut predictions = composition.predictions.clone();
             let mut ipc_service;
             let mut transition = transition;
             let mut deferred_clause_navigation_ready_ui_sync = None;
             let mut pending_learning_commits = Vec::new();
+            let mut new_learned_entries = Vec::new();
+            let mut last_commit = None;
             let mut terminal_ui_cleanup_sent_at = None;
             let has_learning_action = actions
                 .iter()
                 .any(|deferred| matches!(deferred.action, ClientAction::CommitLearning { .. }));
             let learning_blocked = has_learning_action
-                && (IMEState::keyboard_disabled().unwrap_or(true)
-                    || self.current_context_has_sensitive_input_scope());
+                && (app_config.learning.mode != LearningMode::Enabled
+                    || IMEState::incognito().unwrap_or(true)
+                    || IMEState::keyboard_disabled().unwrap_or(true)
+                    || self.current_context_input_scope_policy().suppress_learning);
 
             macro_rules! persist_local_state {
                 () => {{
@@ -6721,6 +7164,8 @@ impl TextServiceFactory {
                     persisted.temporary_latin = temporary_latin;
                     persisted.temporary_latin_shift_pending = temporary_latin_shift_pending;
                     persisted.reconversion_original = reconversion_original.clone();
+                    persisted.last_commit = last_commit.clone();
+                    persisted.predictions = predictions.clone();
                 }};
             }
 
@@ -6810,6 +7255,12 @@ impl TextServiceFactory {
 
             ipc_service = IMEState::ipc_service()?.context("ipc_service is None")?;
             failed_ledger_snapshot = Some(ipc_service.input_ledger_snapshot());
+            let refinement_sender = self
+                .borrow()?
+                .refinement_window
+                .as_ref()
+                .map(RefinementWindow::sender);
+            ipc_service.set_defer_refinement(refinement_sender.is_some());
 
             if ipc_service.recovery_pending() {
                 ipc_service.ensure_server_restart_requested();
@@ -6844,12 +7295,22 @@ impl TextServiceFactory {
                     suffix = selected.sub_text.clone();
                     raw_hiragana = selected.hiragana;
                     if composition.tip_composition.is_some() {
-                        self.set_text(&preview, &suffix)?;
+                        let (display_text, display_sub_text) = Self::displayed_composing_text(
+                            app_config,
+                            &mode,
+                            &transition,
+                            &fixed_prefix,
+                            &raw_hiragana,
+                            &preview,
+                            &suffix,
+                        );
+                        self.set_text(&display_text, &display_sub_text)?;
                         self.sync_candidate_window_after_text_update(
                             &mut ipc_service,
                             &candidates,
                             selection_index,
                             app_config,
+                            &mode,
                             &transition,
                         )?;
                     }
@@ -6863,7 +7324,12 @@ impl TextServiceFactory {
                 let action = &deferred.action;
                 if Self::action_needs_context_update(action) {
                     IMEState::set_ipc_service(ipc_service.clone())?;
-                    self.update_context(&preview)?;
+                    let (displayed_text, displayed_sub_text) = self.displayed_composition()?;
+                    self.update_context(
+                        &displayed_text,
+                        &displayed_sub_text,
+                        !app_config.zenzai.enable,
+                    )?;
                     ipc_service = IMEState::ipc_service()?.context("ipc_service is None")?;
                 }
 
@@ -6897,12 +7363,14 @@ impl TextServiceFactory {
                             None,
                             Self::live_conversion_reading_update(
                                 app_config,
+                                &mode,
                                 &candidates,
                                 &transition,
                             ),
                             Some(true),
                             Self::live_conversion_reading_vertical_adjustment_for_update(
                                 app_config,
+                                &mode,
                                 &candidates,
                                 &transition,
                             ),
@@ -6940,6 +7408,15 @@ impl TextServiceFactory {
                                 }
                             }),
                         );
+                        if *scope == LearningCommitScope::Composition {
+                            new_learned_entries = Self::collect_new_learned_entries(
+                                selection_index,
+                                &candidates,
+                                &clause_snapshots,
+                                &future_clause_snapshots,
+                                *was_temporary_latin,
+                            );
+                        }
                     }
                     ClientAction::EndComposition => {
                         // Let TSF commit the document before waiting for the synchronous UI
@@ -6954,11 +7431,55 @@ impl TextServiceFactory {
                             terminal_ui_cleanup_sent_at,
                             action_index,
                         ) || followup_owns_ui_cleanup;
+                        let committed_text = if has_learning_action
+                            && !learning_blocked
+                            && reconversion_original.is_none()
+                            && !raw_input.is_empty()
+                        {
+                            self.composition_text().unwrap_or_else(|error| {
+                                tracing::debug!(?error, "Could not read the committed text");
+                                None
+                            })
+                        } else {
+                            None
+                        };
                         Self::run_terminal_action_cleanup(
                             skip_ui_cleanup,
                             || self.end_composition(),
                             || self.hide_candidate_window_ui(&mut ipc_service),
                         )?;
+                        last_commit = committed_text.filter(|text| !text.is_empty()).map(|text| {
+                            let selection = Self::select_candidate(&candidates, selection_index);
+                            let (full_raw_input, full_raw_hiragana) = clause_snapshots
+                                .first()
+                                .map(|snapshot| {
+                                    (snapshot.raw_input.clone(), snapshot.raw_hiragana.clone())
+                                })
+                                .unwrap_or_else(|| (raw_input.clone(), raw_hiragana.clone()));
+                            Arc::new(CommittedComposition {
+                                text,
+                                raw_input: full_raw_input,
+                                raw_hiragana: full_raw_hiragana,
+                                state: if composition.state == CompositionState::Composing {
+                                    CompositionState::Composing
+                                } else {
+                                    CompositionState::Previewing
+                                },
+                                single_clause: clause_snapshots.is_empty()
+                                    && future_clause_snapshots.is_empty(),
+                                selection_index,
+                                corresponding_count,
+                                selected_text: selection
+                                    .as_ref()
+                                    .map(|selection| selection.text.clone())
+                                    .unwrap_or_default(),
+                                selected_sub_text: selection
+                                    .map(|selection| selection.sub_text)
+                                    .unwrap_or_default(),
+                                input_ledger: ipc_service.input_ledger_snapshot(),
+                                learned_entries: std::mem::take(&mut new_learned_entries),
+                            })
+                        });
                         selection_index = 0;
                         corresponding_count = 0;
                         temporary_latin = false;
@@ -6995,21 +7516,17 @@ impl TextServiceFactory {
                             &mut ipc_service,
                             &candidates,
                         )?;
-                        let resolved_symbol_text = match mode {
-                            InputMode::Kana => Self::resolve_symbol_input_text_with_lookup(
+                        let resolved_symbol_text = if mode.uses_romaji() {
+                            Self::resolve_symbol_input_text_with_lookup(
                                 &raw_input,
                                 text,
                                 app_config,
                                 romaji_lookup,
-                            ),
-                            InputMode::Latin => None,
-                        };
-                        let text = match mode {
-                            InputMode::Kana => {
-                                resolved_symbol_text.unwrap_or_else(|| text.to_string())
-                            }
-                            InputMode::Latin => text.to_string(),
+                            )
+                        } else {
+                            None
                         };
+                        let text = resolved_symbol_text.unwrap_or_else(|| text.to_string());
 
                         current_clause_is_split_derived = false;
                         current_clause_is_direct_split_remainder = false;
@@ -7054,12 +7571,22 @@ impl TextServiceFactory {
                             suffix = selected.sub_text.clone();
                             raw_hiragana = selected.hiragana;
 
-                            self.set_text(&preview, &suffix)?;
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
                             self.sync_candidate_window_after_text_update(
                                 &mut ipc_service,
                                 &candidates,
                                 selection_index,
                                 app_config,
+                                &mode,
                                 &transition,
                             )?;
                         } else if candidates.is_empty_composition() {
@@ -7118,12 +7645,22 @@ impl TextServiceFactory {
                             suffix = selected.sub_text.clone();
                             raw_hiragana = selected.hiragana;
 
-                            self.set_text(&preview, &suffix)?;
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
                             self.sync_candidate_window_after_text_update(
                                 &mut ipc_service,
                                 &candidates,
                                 selection_index,
                                 app_config,
+                                &mode,
                                 &transition,
                             )?;
                         } else if candidates.is_empty_composition() {
@@ -7132,7 +7669,7 @@ impl TextServiceFactory {
                             );
                         }
                     }
-                    ClientAction::AppendTextDirect(text) => {
+                    ClientAction::AppendTextDirect(text) | ClientAction::AppendTextKana(text) => {
                         Self::clear_clause_caches(
                             &mut clause_snapshots,
                             &mut future_clause_snapshots,
@@ -7148,8 +7685,13 @@ impl TextServiceFactory {
                         current_clause_split_group_id = None;
                         current_clause_consumed_prefix_restore = None;
                         current_clause_remainder_origin = None;
-                        let appended_candidates = ipc_service
-                            .append_text_direct_with_context(text.clone(), &candidates)?;
+                        let kana_input = matches!(action, ClientAction::AppendTextKana(_));
+                        let appended_candidates = if kana_input {
+                            ipc_service.append_text_kana_with_context(text.clone(), &candidates)?
+                        } else {
+                            ipc_service
+                                .append_text_direct_with_context(text.clone(), &candidates)?
+                        };
                         let session_changed = ipc_service.take_server_reset_recovered()
                             && Self::has_client_composition_state(
                                 &raw_input,
@@ -7172,7 +7714,12 @@ impl TextServiceFactory {
                             );
                             selection_index = 0;
                         }
-                        raw_input.push_str(text);
+                        if kana_input {
+                            text.chars()
+                                .for_each(|kana| kana_layout::push_kana(&mut raw_input, kana));
+                        } else {
+                            raw_input.push_str(text);
+                        }
                         if let Some(selected) = Self::select_candidate(&candidates, selection_index)
                         {
                             selection_index = selected.index;
@@ -7182,12 +7729,22 @@ impl TextServiceFactory {
                             suffix = selected.sub_text.clone();
                             raw_hiragana = selected.hiragana;
 
-                            self.set_text(&preview, &suffix)?;
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
                             self.sync_candidate_window_after_text_update(
                                 &mut ipc_service,
                                 &candidates,
                                 selection_index,
                                 app_config,
+                                &mode,
                                 &transition,
                             )?;
                         } else if candidates.is_empty_composition() {
@@ -7284,21 +7841,33 @@ impl TextServiceFactory {
                             suffix = selected.sub_text.clone();
                             raw_hiragana = selected.hiragana;
 
-                            self.set_text(&preview, &suffix)?;
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
                             self.sync_candidate_window_update(
                                 &mut ipc_service,
                                 &candidates,
                                 selection_index,
-                                None,
+                                Self::shows_reading_while_composing(app_config, &mode, &transition)
+                                    .then_some(false),
                                 false,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7397,21 +7966,33 @@ impl TextServiceFactory {
                             suffix = selected.sub_text.clone();
                             raw_hiragana = selected.hiragana;
 
-                            self.set_text(&preview, &suffix)?;
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
                             self.sync_candidate_window_update(
                                 &mut ipc_service,
                                 &candidates,
                                 selection_index,
-                                None,
+                                Self::shows_reading_while_composing(app_config, &mode, &transition)
+                                    .then_some(false),
                                 true,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7531,12 +8112,14 @@ impl TextServiceFactory {
                                 effect.update_pos,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 ready_ui_sync.and_then(|sync| sync.visible),
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7665,12 +8248,14 @@ impl TextServiceFactory {
                                 effect.update_pos,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 deferred_ready_ui_sync.and_then(|sync| sync.visible),
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7700,12 +8285,14 @@ impl TextServiceFactory {
                                 sync.update_pos,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 sync.visible,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7829,12 +8416,14 @@ impl TextServiceFactory {
                                 effect.update_pos,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -7889,17 +8478,14 @@ impl TextServiceFactory {
                         // update the language bar
                         self.update_lang_bar()?;
 
-                        let mode = match mode {
-                            InputMode::Latin => "A",
-                            InputMode::Kana => "あ",
-                        };
+                        let mode = mode.indicator_text(ipc_service.learning_suspended());
 
                         ipc_service.update_candidate_window_with_reading(
                             None,
                             position,
                             None,
                             None,
-                            Some(mode),
+                            Some(&mode),
                             Some(""),
                             Some(false),
                             None,
@@ -7986,12 +8572,14 @@ impl TextServiceFactory {
                                 effect.update_pos,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -8046,24 +8634,20 @@ impl TextServiceFactory {
                         current_clause_remainder_origin = None;
                         let shrunk_raw_input =
                             Self::current_raw_input_suffix(&raw_input, corresponding_count);
-                        let resolved_symbol_text = match mode {
-                            InputMode::Kana => Self::resolve_symbol_input_text_with_lookup(
+                        let resolved_symbol_text = if mode.uses_romaji() {
+                            Self::resolve_symbol_input_text_with_lookup(
                                 &shrunk_raw_input,
                                 text,
                                 app_config,
                                 romaji_lookup,
-                            ),
-                            InputMode::Latin => None,
+                            )
+                        } else {
+                            None
                         };
                         let mut updated_raw_input = shrunk_raw_input.clone();
                         updated_raw_input.push_str(text);
 
-                        let text = match mode {
-                            InputMode::Kana => {
-                                resolved_symbol_text.unwrap_or_else(|| text.to_string())
-                            }
-                            InputMode::Latin => text.to_string(),
-                        };
+                        let text = resolved_symbol_text.unwrap_or_else(|| text.to_string());
                         let session_changed_before_shrink = ipc_service
                             .take_server_reset_recovered()
                             && Self::has_client_composition_state(
@@ -8129,12 +8713,14 @@ impl TextServiceFactory {
                                 true,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -8243,12 +8829,14 @@ impl TextServiceFactory {
                                 true,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -8357,12 +8945,14 @@ impl TextServiceFactory {
                                 true,
                                 Self::live_conversion_reading_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
                                 None,
                                 Self::live_conversion_reading_vertical_adjustment_for_update(
                                     app_config,
+                                    &mode,
                                     &candidates,
                                     &transition,
                                 ),
@@ -8394,6 +8984,225 @@ impl TextServiceFactory {
                     ClientAction::SetTemporaryLatinShiftPending(is_shift_pending) => {
                         temporary_latin_shift_pending = *is_shift_pending;
                     }
+                    ClientAction::ToggleIncognito => {
+                        let incognito = IMEState::toggle_incognito()?;
+                        ipc_service.set_learning_suspended(incognito);
+                        self.update_lang_bar()?;
+                        let position = if transition == CompositionState::None {
+                            self.caret_window_position()?
+                        } else {
+                            self.candidate_window_position()?
+                        };
+                        ipc_service.update_candidate_window_with_reading(
+                            None,
+                            position,
+                            None,
+                            None,
+                            Some(&mode.indicator_text(incognito)),
+                            None,
+                            None,
+                            None,
+                        )?;
+                    }
+                    ClientAction::ForgetSelectedCandidate => {
+                        let index = usize::try_from(selection_index).unwrap_or_default();
+                        let candidate_id =
+                            candidates.candidate_ids.get(index).copied().unwrap_or(0);
+                        if candidate_id != 0
+                            && ipc_service.forget_learning_candidate(candidate_id)?
+                        {
+                            candidates.mark_forgotten(index);
+                            self.sync_candidate_window_after_text_update(
+                                &mut ipc_service,
+                                &candidates,
+                                selection_index,
+                                app_config,
+                                &mode,
+                                &transition,
+                            )?;
+                        }
+                    }
+                    ClientAction::UndoCommit => {
+                        let context = {
+                            let text_service = self.borrow()?;
+                            text_service.context::<ITfContext>()?
+                        };
+                        let undone = match composition.last_commit.as_deref() {
+                            Some(undone)
+                                if self
+                                    .start_composition_before_caret(&context, &undone.text)? =>
+                            {
+                                Some(undone)
+                            }
+                            _ => None,
+                        };
+                        let Some(undone) = undone else {
+                            // The committed text was edited or the caret moved away.
+                            transition = CompositionState::None;
+                            persist_local_state!();
+                            continue;
+                        };
+                        for (reading, word) in &undone.learned_entries {
+                            if let Err(error) = ipc_service.forget_learned_entry(reading, word) {
+                                tracing::warn!(
+                                    ?error,
+                                    "Failed to forget an undone commit's learning"
+                                );
+                            }
+                        }
+                        let mut restored = match ipc_service.restore_composition(
+                            &undone.input_ledger,
+                            &undone.raw_input,
+                            &undone.raw_hiragana,
+                        ) {
+                            Ok(restored) => restored,
+                            Err(error) => {
+                                // Leave the committed text as it was.
+                                tracing::warn!(?error, "Failed to restore the undone composition");
+                                self.end_composition()?;
+                                transition = CompositionState::None;
+                                persist_local_state!();
+                                continue;
+                            }
+                        };
+
+                        // Several clauses were joined by the client, so the whole text
+                        // stands in for the selection when they reopen as one.
+                        let (wanted_text, wanted_sub_text, wanted_count) = if undone.single_clause {
+                            (
+                                undone.selected_text.as_str(),
+                                undone.selected_sub_text.as_str(),
+                                undone.corresponding_count,
+                            )
+                        } else {
+                            (
+                                undone.text.as_str(),
+                                "",
+                                restored.hiragana.chars().count() as i32,
+                            )
+                        };
+                        let is_wanted = |candidates: &Candidates, index: usize| {
+                            candidates.texts.get(index).map(String::as_str) == Some(wanted_text)
+                                && candidates.sub_texts.get(index).map(String::as_str)
+                                    == Some(wanted_sub_text)
+                        };
+                        let preferred = usize::try_from(undone.selection_index).unwrap_or_default();
+                        let index = if is_wanted(&restored, preferred) {
+                            preferred
+                        } else if let Some(index) =
+                            (0..restored.texts.len()).find(|index| is_wanted(&restored, *index))
+                        {
+                            index
+                        } else {
+                            restored.texts.push(wanted_text.to_string());
+                            restored.sub_texts.push(wanted_sub_text.to_string());
+                            restored.corresponding_count.push(wanted_count);
+                            restored.candidate_ids.push(0);
+                            restored.texts.len() - 1
+                        };
+                        let Some(selected) = Self::select_candidate(&restored, index as i32) else {
+                            self.end_composition()?;
+                            ipc_service.clear_text()?;
+                            transition = CompositionState::None;
+                            persist_local_state!();
+                            continue;
+                        };
+
+                        candidates = restored;
+                        selection_index = selected.index;
+                        corresponding_count = selected.corresponding_count;
+                        preview = selected.text.clone();
+                        suffix = selected.sub_text.clone();
+                        raw_input = undone.raw_input.clone();
+                        raw_hiragana = selected.hiragana;
+                        fixed_prefix.clear();
+                        temporary_latin = false;
+                        transition = undone.state.clone();
+
+                        let (display_text, display_sub_text) = Self::displayed_composing_text(
+                            app_config,
+                            &mode,
+                            &transition,
+                            &fixed_prefix,
+                            &raw_hiragana,
+                            &preview,
+                            &suffix,
+                        );
+                        self.set_text(&display_text, &display_sub_text)?;
+                        self.sync_candidate_window_after_text_update(
+                            &mut ipc_service,
+                            &candidates,
+                            selection_index,
+                            app_config,
+                            &mode,
+                            &transition,
+                        )?;
+                    }
+                    ClientAction::SelectPrediction(direction) => {
+                        // The first Tab enters the predictions; later ones cycle them.
+                        let index = if candidates == predictions {
+                            (selection_index + direction)
+                                .rem_euclid(predictions.texts.len().max(1) as i32)
+                        } else if *direction < 0 {
+                            predictions.texts.len() as i32 - 1
+                        } else {
+                            0
+                        };
+                        if let Some(selected) = Self::select_candidate(&predictions, index) {
+                            candidates = predictions.clone();
+                            selection_index = selected.index;
+                            corresponding_count = selected.corresponding_count;
+                            preview =
+                                Self::merge_preview_with_prefix(&fixed_prefix, &selected.text);
+                            suffix = selected.sub_text.clone();
+                            raw_hiragana = selected.hiragana;
+                            self.set_text(&preview, &suffix)?;
+
+                            let position = self.candidate_window_position()?;
+                            let delivery = ipc_service.update_suggest_window(
+                                position,
+                                &predictions,
+                                Some(selection_index),
+                            )?;
+                            self.remember_candidate_window_visibility_if_sent(delivery, Some(true));
+                        }
+                    }
+                    ClientAction::ApplyRefinedCandidates { base, refined } => {
+                        // Any edit since `base` was shown means the refinement
+                        // describes a composition the user no longer sees.
+                        if candidates != *base || !clause_snapshots.is_empty() {
+                            tracing::debug!("Skip stale Zenzai refinement");
+                        } else if let Some(selected) =
+                            Self::select_candidate(refined, selection_index)
+                        {
+                            candidates = refined.clone();
+                            selection_index = selected.index;
+                            corresponding_count = selected.corresponding_count;
+                            preview =
+                                Self::merge_preview_with_prefix(&fixed_prefix, &selected.text);
+                            suffix = selected.sub_text.clone();
+                            raw_hiragana = selected.hiragana;
+
+                            let (display_text, display_sub_text) = Self::displayed_composing_text(
+                                app_config,
+                                &mode,
+                                &transition,
+                                &fixed_prefix,
+                                &raw_hiragana,
+                                &preview,
+                                &suffix,
+                            );
+                            self.set_text(&display_text, &display_sub_text)?;
+                            self.sync_candidate_window_after_text_update(
+                                &mut ipc_service,
+                                &candidates,
+                                selection_index,
+                                app_config,
+                                &mode,
+                                &transition,
+                            )?;
+                        }
+                    }
                     ClientAction::SetTextWithType(set_type) => {
                         let clause_raw_input = Self::current_clause_raw_input_preview(
                             &raw_input,
@@ -8432,6 +9241,49 @@ impl TextServiceFactory {
                 persist_local_state!();
             }
 
+            let edits_reading = action_values.iter().any(|action| {
+                Self::action_needs_context_update(action) || *action == ClientAction::RemoveText
+            });
+            if transition == CompositionState::Composing && edits_reading {
+                let had_predictions = !predictions.texts.is_empty();
+                predictions = if app_config.general.predictive_suggestions
+                    && reconversion_original.is_none()
+                {
+                    ipc_service.predict().unwrap_or_else(|error| {
+                        tracing::warn!(?error, "Failed to fetch predictions");
+                        Candidates::default()
+                    })
+                } else {
+                    Candidates::default()
+                };
+                if !predictions.texts.is_empty() {
+                    let position = self.candidate_window_position()?;
+                    let delivery =
+                        ipc_service.update_suggest_window(position, &predictions, None)?;
+                    self.remember_candidate_window_visibility_if_sent(delivery, Some(true));
+                } else if had_predictions {
+                    self.sync_candidate_window_after_text_update(
+                        &mut ipc_service,
+                        &candidates,
+                        selection_index,
+                        app_config,
+                        &mode,
+                        &transition,
+                    )?;
+                }
+            } else if transition == CompositionState::None {
+                predictions = Candidates::default();
+            }
+
+            if let Some(request_id) = ipc_service.take_pending_refinement() {
+                if let Some(sender) = refinement_sender {
+                    let base = candidates.clone();
+                    ipc_service.request_refined_candidates(request_id, move |refined| {
+                        sender.post(RefinedCandidates { base, refined });
+                    });
+                }
+            }
+
             persist_local_state!();
             self.borrow()?
                 .borrow_mut_composition()?
@@ -5,23 +5,30 @@ use super::{
     requires_action_recovery, requires_server_resynchronization, standard_reconversion_available,
     Candidates, CapsLockKeyboardLayout, ClauseActionBackend, ClauseActionEffect,
     ClauseActionStateMut, ClauseAdvance, ClauseAdvanceRawInput, ClauseBoundaryAdjustment,
-    ClauseBoundarySync, ClauseNavigationReadyUiSync, ClauseSnapshot, ClauseState, Composition,
-    CompositionReducer, CompositionState, ConsumedPrefixRestore, DeferredClientAction,
-    DeferredInputEvent, DeferredProjection, DeferredUserAction, FutureClauseSnapshot,
-    ModifierState, ShiftKeyState, TextServiceFactory,
+    ClauseBoundarySync, ClauseNavigationReadyUiSync, ClauseSnapshot, ClauseState,
+    CommittedComposition, Composition, CompositionReducer, CompositionState, ConsumedPrefixRestore,
+    DeferredClientAction, DeferredInputEvent, DeferredProjection, DeferredUserAction,
+    FutureClauseSnapshot, ModifierState, ShiftKeyState, TextServiceFactory,
 };
 use crate::engine::{
     client_action::{
         ClientAction, LearningCommitKind, LearningCommitScope, SetSelectionType, SetTextType,
     },
     input_mode::InputMode,
-    ipc_service::{ClauseSnapshotOperation, WindowRpcDelivery},
+    ipc_service::{CandidateMetadata, ClauseSnapshotOperation, WindowRpcDelivery},
     user_action::{Function, Navigation, UserAction},
 };
-use crate::tsf::edit_session::EditSessionFailure;
+use crate::tsf::{
+    edit_session::EditSessionFailure,
+    surrounded_text::{text_after_composition, text_before_composition},
+};
 use shared::{
-    get_default_romaji_rows, AppConfig, PunctuationStyle, ReconversionKey, RomajiRule, WidthMode,
+    get_default_romaji_rows,
+    keymap::{KeyCommand, KeymapEntry, KeymapState},
+    AppConfig, ConversionMode, KanaInputMethod, PunctuationStyle, ReconversionKey, RomajiRule,
+    WidthMode,
 };
+use std::sync::Arc;
 use windows::Win32::{
     Foundation::{LPARAM, WPARAM},
     UI::TextServices::TF_E_LOCKED,
@@ -729,6 +736,7 @@ pub(super) fn candidates(
         hiragana: hiragana.to_string(),
         corresponding_count: corresponding_count.to_vec(),
         candidate_ids: (1..=texts.len() as u64).collect(),
+        metadata: Vec::new(),
     }
 }
 
@@ -951,6 +959,529 @@ fn convert_key_cycles_candidates_in_an_active_composition() {
     assert_eq!(reconvert, space);
 }
 
+#[test]
+fn tab_picks_a_prediction_only_while_predictions_are_shown() {
+    let app_config = AppConfig::default();
+    let plan = |composition: &Composition, action: &UserAction| {
+        TextServiceFactory::plan_actions_for_user_action(
+            composition,
+            action,
+            &InputMode::Kana,
+            false,
+            &app_config,
+            false,
+        )
+    };
+    let composing = Composition {
+        state: CompositionState::Composing,
+        raw_input: "arigato".to_string(),
+        ..Composition::default()
+    };
+
+    // Without predictions Tab keeps converting as before.
+    assert_eq!(
+        plan(&composing, &UserAction::SelectPrediction(1)),
+        plan(&composing, &UserAction::Tab)
+    );
+
+    let predictions = candidates(
+        &["ありがとう", "ありがとうございます"],
+        &["", ""],
+        "ありがと",
+        &[7, 7],
+    );
+    let suggesting = Composition {
+        predictions: predictions.clone(),
+        ..composing
+    };
+    assert_eq!(
+        plan(&suggesting, &UserAction::SelectPrediction(-1)),
+        Some((
+            CompositionState::Previewing,
+            vec![ClientAction::SelectPrediction(-1)]
+        ))
+    );
+
+    let picked = Composition {
+        state: CompositionState::Previewing,
+        candidates: predictions,
+        ..suggesting.clone()
+    };
+    assert_eq!(
+        plan(&picked, &UserAction::SelectPrediction(1)),
+        Some((
+            CompositionState::Previewing,
+            vec![ClientAction::SelectPrediction(1)]
+        ))
+    );
+
+    // Once converted normally, Tab steps through the conversion candidates.
+    let converted = Composition {
+        state: CompositionState::Previewing,
+        ..suggesting
+    };
+    assert_eq!(
+        plan(&converted, &UserAction::SelectPrediction(1)),
+        plan(&converted, &UserAction::Tab)
+    );
+}
+
+fn committed_composition(text: &str) -> CommittedComposition {
+    CommittedComposition {
+        text: text.to_string(),
+        raw_input: "kanji".to_string(),
+        raw_hiragana: "かんじ".to_string(),
+        state: CompositionState::Previewing,
+        single_clause: true,
+        selection_index: 0,
+        corresponding_count: 3,
+        selected_text: text.to_string(),
+        selected_sub_text: String::new(),
+        input_ledger: Default::default(),
+        learned_entries: Vec::new(),
+    }
+}
+
+#[test]
+fn undo_commit_reopens_only_a_remembered_commit() {
+    let app_config = AppConfig::default();
+    let plan = |composition: &Composition| {
+        TextServiceFactory::plan_actions_for_user_action(
+            composition,
+            &UserAction::UndoCommit,
+            &InputMode::Kana,
+            false,
+            &app_config,
+            false,
+        )
+    };
+
+    assert_eq!(plan(&Composition::default()), None);
+    let committed = Composition {
+        last_commit: Some(Arc::new(committed_composition("漢字"))),
+        ..Composition::default()
+    };
+    assert_eq!(
+        plan(&committed),
+        Some((CompositionState::Previewing, vec![ClientAction::UndoCommit]))
+    );
+}
+
+#[test]
+fn incognito_toggles_in_every_state_without_leaving_it() {
+    let app_config = AppConfig::default();
+    for state in [
+        CompositionState::None,
+        CompositionState::Composing,
+        CompositionState::Previewing,
+    ] {
+        let composition = Composition {
+            state: state.clone(),
+            ..Composition::default()
+        };
+        assert_eq!(
+            TextServiceFactory::plan_actions_for_user_action(
+                &composition,
+                &UserAction::ToggleIncognito,
+                &InputMode::Kana,
+                false,
+                &app_config,
+                false,
+            ),
+            Some((state, vec![ClientAction::ToggleIncognito]))
+        );
+    }
+}
+
+#[test]
+fn undo_commit_forgets_only_conversions_learned_by_that_commit() {
+    let metadata = |origin| CandidateMetadata {
+        origin,
+        annotation: None,
+        lcid: 0,
+        rcid: 0,
+    };
+    let candidates = Candidates {
+        texts: vec!["漢字".to_string(), "感じ".to_string()],
+        sub_texts: vec![String::new(), String::new()],
+        hiragana: "かんじ".to_string(),
+        corresponding_count: vec![3, 3],
+        candidate_ids: vec![11, 12],
+        metadata: vec![
+            metadata(shared::proto::CandidateOrigin::SystemDictionary),
+            metadata(shared::proto::CandidateOrigin::Learned),
+        ],
+    };
+
+    assert_eq!(
+        TextServiceFactory::collect_new_learned_entries(0, &candidates, &[], &[], false),
+        vec![("かんじ".to_string(), "漢字".to_string())]
+    );
+    assert!(
+        TextServiceFactory::collect_new_learned_entries(1, &candidates, &[], &[], false).is_empty()
+    );
+    assert!(
+        TextServiceFactory::collect_new_learned_entries(0, &candidates, &[], &[], true).is_empty()
+    );
+}
+
+fn classic_app_config() -> AppConfig {
+    let mut app_config = AppConfig::default();
+    app_config.general.conversion_mode = ConversionMode::Classic;
+    app_config
+}
+
+#[test]
+fn classic_composing_shows_the_reading_instead_of_the_conversion() {
+    let app_config = classic_app_config();
+
+    assert_eq!(
+        TextServiceFactory::displayed_composing_text(
+            &app_config,
+            &InputMode::Kana,
+            &CompositionState::Composing,
+            "今日",
+            "はいい",
+            "今日は良い",
+            "天気",
+        ),
+        ("今日はいい".to_string(), String::new())
+    );
+    assert_eq!(
+        TextServiceFactory::displayed_composing_text(
+            &app_config,
+            &InputMode::Kana,
+            &CompositionState::Previewing,
+            "",
+            "はいい",
+            "は良い",
+            "天気",
+        ),
+        ("は良い".to_string(), "天気".to_string())
+    );
+    assert_eq!(
+        TextServiceFactory::displayed_composing_text(
+            &AppConfig::default(),
+            &InputMode::Kana,
+            &CompositionState::Composing,
+            "",
+            "はいい",
+            "は良い",
+            "天気",
+        ),
+        ("は良い".to_string(), "天気".to_string())
+    );
+}
+
+#[test]
+fn classic_composing_sends_only_the_text_before_the_composition() {
+    let (text, sub_text) = TextServiceFactory::displayed_composing_text(
+        &classic_app_config(),
+        &InputMode::Kana,
+        &CompositionState::Composing,
+        "",
+        "かんじを",
+        "漢字を",
+        "",
+    );
+
+    assert!(sub_text.is_empty());
+    assert_eq!(
+        text_before_composition("今日は、かんじを", &text),
+        "今日は、"
+    );
+}
+
+#[test]
+fn classic_composing_sends_the_text_right_after_the_composition() {
+    let (_, sub_text) = TextServiceFactory::displayed_composing_text(
+        &classic_app_config(),
+        &InputMode::Kana,
+        &CompositionState::Composing,
+        "",
+        "かんじ",
+        "漢字",
+        "を",
+    );
+
+    assert_eq!(text_after_composition("を書く。", &sub_text), "を書く。");
+}
+
+#[test]
+fn classic_space_converts_from_the_top_candidate() {
+    let composition = Composition {
+        state: CompositionState::Composing,
+        raw_input: "kanji".to_string(),
+        ..Composition::default()
+    };
+    let mut app_config = classic_app_config();
+    app_config.general.show_candidate_window_after_space = true;
+
+    let plan = TextServiceFactory::plan_actions_for_user_action(
+        &composition,
+        &UserAction::Space,
+        &InputMode::Kana,
+        false,
+        &app_config,
+        false,
+    );
+    assert_eq!(
+        plan,
+        Some((
+            CompositionState::Previewing,
+            vec![ClientAction::SetSelection(SetSelectionType::Number(0))],
+        ))
+    );
+
+    let previewing = Composition {
+        state: CompositionState::Previewing,
+        ..composition
+    };
+    let next = TextServiceFactory::plan_actions_for_user_action(
+        &previewing,
+        &UserAction::Space,
+        &InputMode::Kana,
+        false,
+        &app_config,
+        false,
+    );
+    assert_eq!(
+        next,
+        Some((
+            CompositionState::Previewing,
+            vec![
+                ClientAction::ShowCandidateWindow,
+                ClientAction::SetSelection(SetSelectionType::Down),
+            ],
+        ))
+    );
+}
+
+#[test]
+fn classic_enter_commits_the_reading_without_learning() {
+    let composition = Composition {
+        state: CompositionState::Composing,
+        raw_input: "kanji".to_string(),
+        suffix: "字".to_string(),
+        ..Composition::default()
+    };
+
+    let plan = TextServiceFactory::plan_actions_for_user_action(
+        &composition,
+        &UserAction::Enter,
+        &InputMode::Kana,
+        false,
+        &classic_app_config(),
+        false,
+    );
+
+    assert_eq!(
+        plan,
+        Some((CompositionState::None, vec![ClientAction::EndComposition]))
+    );
+}
+
+#[test]
+fn classic_composing_ignores_clause_navigation() {
+    let composition = Composition {
+        state: CompositionState::Composing,
+        raw_input: "kanji".to_string(),
+        ..Composition::default()
+    };
+
+    for direction in [Navigation::Left, Navigation::Right] {
+        assert_eq!(
+            TextServiceFactory::plan_actions_for_user_action(
+                &composition,
+                &UserAction::Navigation(direction),
+                &InputMode::Kana,
+                false,
+                &classic_app_config(),
+                false,
+            ),
+            Some((CompositionState::Composing, vec![]))
+        );
+    }
+}
+
+#[test]
+fn classic_clause_actions_stay_converted_until_backspace() {
+    let composition = Composition {
+        state: CompositionState::Previewing,
+        raw_input: "kanji".to_string(),
+        suffix: "字".to_string(),
+        ..Composition::default()
+    };
+    let app_config = classic_app_config();
+    let plan = |action: UserAction| {
+        TextServiceFactory::plan_actions_for_user_action(
+            &composition,
+            &action,
+            &InputMode::Kana,
+            false,
+            &app_config,
+            false,
+        )
+        .map(|(state, _)| state)
+    };
+
+    assert_eq!(
+        plan(UserAction::Navigation(Navigation::Right)),
+        Some(CompositionState::Previewing)
+    );
+    assert_eq!(plan(UserAction::Enter), Some(CompositionState::Previewing));
+    assert_eq!(
+        plan(UserAction::Backspace),
+        Some(CompositionState::Composing)
+    );
+}
+
+#[test]
+fn katakana_modes_compose_the_reading_in_their_script() {
+    let app_config = AppConfig::default();
+    let display = |mode: InputMode| {
+        TextServiceFactory::displayed_composing_text(
+            &app_config,
+            &mode,
+            &CompositionState::Composing,
+            "",
+            "かな",
+            "仮名",
+            "",
+        )
+    };
+
+    assert_eq!(
+        display(InputMode::Katakana),
+        ("カナ".to_string(), String::new())
+    );
+    assert_eq!(
+        display(InputMode::HalfKatakana),
+        ("ｶﾅ".to_string(), String::new())
+    );
+    assert_eq!(
+        display(InputMode::Kana),
+        ("仮名".to_string(), String::new())
+    );
+
+    let enter = TextServiceFactory::plan_actions_for_user_action(
+        &Composition {
+            state: CompositionState::Composing,
+            raw_input: "kana".to_string(),
+            ..Composition::default()
+        },
+        &UserAction::Enter,
+        &InputMode::Katakana,
+        false,
+        &app_config,
+        false,
+    );
+    assert_eq!(
+        enter,
+        Some((CompositionState::None, vec![ClientAction::EndComposition]))
+    );
+}
+
+#[test]
+fn reading_modes_send_only_the_text_before_the_composition() {
+    let app_config = AppConfig::default();
+    let before_composition = |mode: InputMode, reading: &str, document: &str| {
+        let (text, sub_text) = TextServiceFactory::displayed_composing_text(
+            &app_config,
+            &mode,
+            &CompositionState::Composing,
+            "",
+            reading,
+            "仮名",
+            "です",
+        );
+        assert!(sub_text.is_empty());
+        text_before_composition(document, &text)
+    };
+
+    assert_eq!(
+        before_composition(InputMode::Katakana, "かな", "前の文カナ"),
+        "前の文"
+    );
+    assert_eq!(
+        before_composition(InputMode::HalfKatakana, "がな", "前の文ｶﾞﾅ"),
+        "前の文"
+    );
+    assert_eq!(
+        before_composition(InputMode::FullLatin, "abc", "前の文ａｂｃ"),
+        "前の文"
+    );
+}
+
+#[test]
+fn full_latin_mode_composes_typed_text_directly() {
+    let app_config = AppConfig::default();
+
+    let plan = TextServiceFactory::plan_actions_for_user_action(
+        &Composition::default(),
+        &UserAction::Input('a'),
+        &InputMode::FullLatin,
+        false,
+        &app_config,
+        false,
+    );
+    assert_eq!(
+        plan,
+        Some((
+            CompositionState::Composing,
+            vec![
+                ClientAction::StartComposition,
+                ClientAction::AppendTextDirect("a".to_string()),
+            ],
+        ))
+    );
+    assert_eq!(
+        TextServiceFactory::displayed_composing_text(
+            &app_config,
+            &InputMode::FullLatin,
+            &CompositionState::Composing,
+            "",
+            "ab1",
+            "ab1",
+            "",
+        ),
+        ("ａｂ１".to_string(), String::new())
+    );
+}
+
+#[test]
+fn selecting_an_input_mode_commits_the_composition_first() {
+    let composition = Composition {
+        state: CompositionState::Composing,
+        raw_input: "kana".to_string(),
+        ..Composition::default()
+    };
+
+    let plan = TextServiceFactory::plan_actions_for_user_action(
+        &composition,
+        &UserAction::SelectInputMode(InputMode::HalfKatakana),
+        &InputMode::Kana,
+        false,
+        &AppConfig::default(),
+        false,
+    );
+
+    assert_eq!(
+        plan,
+        Some((
+            CompositionState::None,
+            vec![
+                ClientAction::EndComposition,
+                ClientAction::SetIMEMode(InputMode::HalfKatakana),
+            ],
+        ))
+    );
+    assert_eq!(
+        language_bar_deferred_action(&InputMode::Katakana, true),
+        UserAction::SelectInputMode(InputMode::Katakana)
+    );
+}
+
 #[test]
 fn reconversion_consumes_edits_that_require_an_ambiguous_server_reading() {
     let composition = Composition {
@@ -1310,6 +1841,89 @@ fn temporary_latin_after_space_conversion_starts_new_direct_composition() {
     );
 }
 
+#[test]
+fn kana_input_reads_keys_by_their_jis_kana_layout_position() {
+    let mut app_config = AppConfig::default();
+    app_config.general.input_method = KanaInputMethod::Kana;
+
+    let kana = |vkey, shift, mode: &InputMode, temporary_latin| {
+        TextServiceFactory::kana_input_action(
+            vkey,
+            shift,
+            false,
+            mode,
+            temporary_latin,
+            &app_config,
+        )
+    };
+    assert_eq!(
+        kana(0x31, false, &InputMode::Kana, false),
+        Some(UserAction::Kana('ぬ'))
+    );
+    assert_eq!(
+        kana(0x30, true, &InputMode::Kana, false),
+        Some(UserAction::Kana('を'))
+    );
+    assert_eq!(
+        kana(0xC0, false, &InputMode::Katakana, false),
+        Some(UserAction::Kana('゛'))
+    );
+    assert_eq!(kana(0x31, false, &InputMode::Latin, false), None);
+    assert_eq!(kana(0x31, false, &InputMode::Kana, true), None);
+    assert_eq!(
+        TextServiceFactory::kana_input_action(
+            0x31,
+            false,
+            false,
+            &InputMode::Kana,
+            false,
+            &AppConfig::default(),
+        ),
+        None
+    );
+}
+
+#[test]
+fn kana_input_appends_through_the_kana_input_style() {
+    let (transition, actions) = TextServiceFactory::plan_actions_for_user_action(
+        &Composition::default(),
+        &UserAction::Kana('か'),
+        &InputMode::Kana,
+        false,
+        &AppConfig::default(),
+        false,
+    )
+    .expect("kana should start a composition");
+    assert_eq!(transition, CompositionState::Composing);
+    assert_eq!(
+        actions,
+        vec![
+            ClientAction::StartComposition,
+            ClientAction::AppendTextKana("か".to_string())
+        ]
+    );
+
+    let composing = Composition {
+        state: CompositionState::Composing,
+        raw_input: "か".to_string(),
+        raw_hiragana: "か".to_string(),
+        ..Composition::default()
+    };
+    let (_, actions) = TextServiceFactory::plan_actions_for_user_action(
+        &composing,
+        &UserAction::Kana('゛'),
+        &InputMode::Kana,
+        false,
+        &AppConfig::default(),
+        false,
+    )
+    .expect("a voicing mark should append to the composition");
+    assert_eq!(
+        actions,
+        vec![ClientAction::AppendTextKana("゛".to_string())]
+    );
+}
+
 #[test]
 fn existing_temporary_latin_after_space_conversion_preserves_direct_composition() {
     let composition = Composition {
@@ -1391,6 +2005,38 @@ fn delete_ends_composition_after_last_character() {
     );
 }
 
+#[test]
+fn ctrl_delete_forgets_the_highlighted_candidate_only_while_previewing() {
+    let plan = |state| {
+        let composition = Composition {
+            state,
+            raw_input: "ab".to_string(),
+            ..Composition::default()
+        };
+        TextServiceFactory::plan_actions_for_user_action(
+            &composition,
+            &UserAction::ForgetCandidate,
+            &InputMode::Kana,
+            false,
+            &AppConfig::default(),
+            false,
+        )
+    };
+
+    assert_eq!(
+        plan(CompositionState::Previewing),
+        Some((
+            CompositionState::Previewing,
+            vec![ClientAction::ForgetSelectedCandidate]
+        ))
+    );
+    assert_eq!(
+        plan(CompositionState::Composing),
+        Some((CompositionState::Composing, vec![ClientAction::RemoveText]))
+    );
+    assert_eq!(plan(CompositionState::None), None);
+}
+
 #[test]
 fn live_conversion_reading_respects_setting_and_composition_state() {
     let mut app_config = AppConfig::default();
@@ -1399,6 +2045,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
     assert_eq!(
         TextServiceFactory::live_conversion_reading(
             &app_config,
+            &InputMode::Kana,
             &candidates,
             &CompositionState::Composing,
         ),
@@ -1409,6 +2056,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
     assert_eq!(
         TextServiceFactory::live_conversion_reading(
             &app_config,
+            &InputMode::Kana,
             &candidates,
             &CompositionState::Composing,
         ),
@@ -1417,6 +2065,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
     assert_eq!(
         TextServiceFactory::live_conversion_reading_update(
             &app_config,
+            &InputMode::Kana,
             &candidates,
             &CompositionState::Composing,
         ),
@@ -1427,6 +2076,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
     assert_eq!(
         TextServiceFactory::live_conversion_reading(
             &app_config,
+            &InputMode::Kana,
             &candidates,
             &CompositionState::None,
         ),
@@ -1435,6 +2085,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
     assert_eq!(
         TextServiceFactory::live_conversion_reading(
             &app_config,
+            &InputMode::Kana,
             &Candidates::default(),
             &CompositionState::Composing,
         ),
@@ -1444,6 +2095,7 @@ fn live_conversion_reading_respects_setting_and_composition_state() {
 
 #[test]
 fn ctrl_conversion_shortcuts_are_handled_as_function_keys() {
+    let keymap = AppConfig::default().keymap.resolve();
     let cases = [
         (0x55, Function::Six, SetTextType::Hiragana),
         (0x49, Function::Seven, SetTextType::Katakana),
@@ -1453,10 +2105,14 @@ fn ctrl_conversion_shortcuts_are_handled_as_function_keys() {
     ];
 
     for (key_code, function, set_text_type) in cases {
-        assert_eq!(
-            TextServiceFactory::ctrl_conversion_shortcut_function(key_code, true, false),
-            Some(function)
-        );
+        let chord = TextServiceFactory::keymap_chord(key_code, true, false, false, false, false);
+        for state in [CompositionState::Composing, CompositionState::Selecting] {
+            let command = keymap.lookup(TextServiceFactory::keymap_state(&state), &chord);
+            assert_eq!(
+                command.and_then(UserAction::from_key_command),
+                Some(UserAction::Function(function))
+            );
+        }
         assert_eq!(
             TextServiceFactory::set_text_type_for_function(function),
             set_text_type
@@ -1466,17 +2122,46 @@ fn ctrl_conversion_shortcuts_are_handled_as_function_keys() {
 
 #[test]
 fn ctrl_conversion_shortcuts_do_not_capture_non_ctrl_or_alt_modified_keys() {
+    let keymap = AppConfig::default().keymap.resolve();
+    let state = TextServiceFactory::keymap_state(&CompositionState::Composing);
+    for (key_code, ctrl, alt) in [
+        (0x55, false, false),
+        (0x55, true, true),
+        (0x41, true, false),
+    ] {
+        let chord = TextServiceFactory::keymap_chord(key_code, ctrl, false, alt, false, false);
+        assert_eq!(keymap.lookup(state, &chord), None);
+    }
+}
+
+#[test]
+fn eisu_and_alt_backquote_are_looked_up_without_their_modifiers() {
+    let mut app_config = AppConfig::default();
+    app_config.keymap.overrides.push(KeymapEntry {
+        state: KeymapState::Precomposition,
+        key: "Eisu".to_string(),
+        command: KeyCommand::ToggleInputMode,
+    });
+    let keymap = app_config.keymap.resolve();
+    let state = TextServiceFactory::keymap_state(&CompositionState::None);
+
+    let eisu = TextServiceFactory::keymap_chord(0x14, false, true, false, true, false);
     assert_eq!(
-        TextServiceFactory::ctrl_conversion_shortcut_function(0x55, false, false),
-        None
+        keymap.lookup(state, &eisu),
+        Some(KeyCommand::ToggleInputMode)
     );
+    let alt_backquote = TextServiceFactory::keymap_chord(0xC0, true, false, true, false, true);
     assert_eq!(
-        TextServiceFactory::ctrl_conversion_shortcut_function(0x55, true, true),
-        None
+        keymap.lookup(state, &alt_backquote),
+        Some(KeyCommand::ToggleInputMode)
     );
+    let forget = TextServiceFactory::keymap_chord(0x2E, true, false, false, false, false);
     assert_eq!(
-        TextServiceFactory::ctrl_conversion_shortcut_function(0x41, true, false),
-        None
+        keymap.lookup(
+            TextServiceFactory::keymap_state(&CompositionState::Selecting),
+            &forget
+        ),
+        Some(KeyCommand::ForgetCandidate)
     );
 }
 
@@ -452,6 +452,7 @@ fn candidates_owned(
         hiragana,
         corresponding_count,
         candidate_ids,
+        metadata: Vec::new(),
     }
 }
 
@@ -1,17 +1,110 @@
 use crate::tsf::factory::TextServiceFactory;
 
+use super::{
+    full_width::to_fullwidth,
+    text_util::{to_half_katakana, to_katakana},
+};
+
 use windows::{
     core::Interface,
     Win32::UI::TextServices::{ITfLangBarItemButton, ITfLangBarItemMgr},
 };
 
 use anyhow::Result;
+use shared::AppInputMode;
 
 #[derive(Default, Clone, PartialEq, Debug)]
 pub enum InputMode {
+    /// 半角英数: keys reach the application untouched.
     #[default]
     Latin,
+    /// ひらがな
     Kana,
+    /// 全角英数
+    FullLatin,
+    /// 全角カタカナ
+    Katakana,
+    /// 半角カタカナ
+    HalfKatakana,
+}
+
+impl InputMode {
+    /// Every mode in the order the language bar menu lists them.
+    pub const ALL: [InputMode; 5] = [
+        InputMode::Kana,
+        InputMode::Katakana,
+        InputMode::FullLatin,
+        InputMode::HalfKatakana,
+        InputMode::Latin,
+    ];
+
+    /// Whether typed letters go through the romaji table.
+    pub fn uses_romaji(&self) -> bool {
+        matches!(
+            self,
+            InputMode::Kana | InputMode::Katakana | InputMode::HalfKatakana
+        )
+    }
+
+    /// Glyph shown by the mode indicator.
+    pub fn indicator_label(&self) -> &'static str {
+        match self {
+            InputMode::Latin => "A",
+            InputMode::Kana => "あ",
+            InputMode::FullLatin => "Ａ",
+            InputMode::Katakana => "カ",
+            InputMode::HalfKatakana => "ｶ",
+        }
+    }
+
+    /// Text shown by the mode indicator, marked while incognito.
+    pub fn indicator_text(&self, incognito: bool) -> String {
+        if incognito {
+            format!("{}🔒", self.indicator_label())
+        } else {
+            self.indicator_label().to_string()
+        }
+    }
+
+    /// Name shown in the language bar menu.
+    pub fn menu_label(&self) -> &'static str {
+        match self {
+            InputMode::Latin => "半角英数",
+            InputMode::Kana => "ひらがな",
+            InputMode::FullLatin => "全角英数",
+            InputMode::Katakana => "全角カタカナ",
+            InputMode::HalfKatakana => "半角カタカナ",
+        }
+    }
+
+    /// Whether the composition shows the reading in this mode's script rather
+    /// than the live conversion.
+    pub fn shows_reading(&self) -> bool {
+        !matches!(self, InputMode::Latin | InputMode::Kana)
+    }
+
+    /// The reading as this mode writes it while composing, or `None` when the
+    /// mode shows the conversion instead.
+    pub fn composing_text(&self, reading: &str) -> Option<String> {
+        match self {
+            InputMode::Katakana => Some(to_katakana(reading)),
+            InputMode::HalfKatakana => Some(to_half_katakana(reading)),
+            InputMode::FullLatin => Some(to_fullwidth(reading, true)),
+            InputMode::Latin | InputMode::Kana => None,
+        }
+    }
+}
+
+impl From<AppInputMode> for InputMode {
+    fn from(mode: AppInputMode) -> Self {
+        match mode {
+            AppInputMode::Latin => InputMode::Latin,
+            AppInputMode::Kana => InputMode::Kana,
+            AppInputMode::FullLatin => InputMode::FullLatin,
+            AppInputMode::Katakana => InputMode::Katakana,
+            AppInputMode::HalfKatakana => InputMode::HalfKatakana,
+        }
+    }
 }
 
 impl TextServiceFactory {
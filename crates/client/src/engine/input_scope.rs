@@ -0,0 +1,151 @@
+use windows::Win32::UI::TextServices::{
+    InputScope, IS_ALPHANUMERIC_FULLWIDTH, IS_ALPHANUMERIC_HALFWIDTH, IS_DIGITS,
+    IS_EMAILNAME_OR_ADDRESS, IS_EMAIL_SMTPEMAILADDRESS, IS_EMAIL_USERNAME, IS_HIRAGANA,
+    IS_KATAKANA_FULLWIDTH, IS_KATAKANA_HALFWIDTH, IS_NUMBER, IS_NUMBER_FULLWIDTH,
+    IS_NUMERIC_PASSWORD, IS_PASSWORD, IS_PRIVATE, IS_TELEPHONE_AREACODE, IS_TELEPHONE_COUNTRYCODE,
+    IS_TELEPHONE_FULLTELEPHONENUMBER, IS_TELEPHONE_LOCALNUMBER, IS_URL,
+};
+
+use super::input_mode::InputMode;
+
+/// How the IME treats a field, derived from the input scopes it advertises.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct InputScopePolicy {
+    /// Mode the field starts in; `None` keeps the window's mode.
+    pub input_mode: Option<InputMode>,
+    /// Committed text is not learned.
+    pub suppress_learning: bool,
+    /// The field holds secrets: no learning and no reconversion of its text.
+    pub sensitive: bool,
+}
+
+struct ScopeRule {
+    scope: InputScope,
+    input_mode: Option<InputMode>,
+    suppress_learning: bool,
+    sensitive: bool,
+}
+
+const fn rule(scope: InputScope, input_mode: Option<InputMode>) -> ScopeRule {
+    ScopeRule {
+        scope,
+        input_mode,
+        suppress_learning: false,
+        sensitive: false,
+    }
+}
+
+const fn unlearned(scope: InputScope, input_mode: Option<InputMode>) -> ScopeRule {
+    ScopeRule {
+        scope,
+        input_mode,
+        suppress_learning: true,
+        sensitive: false,
+    }
+}
+
+const fn sensitive(scope: InputScope) -> ScopeRule {
+    ScopeRule {
+        scope,
+        input_mode: None,
+        suppress_learning: true,
+        sensitive: true,
+    }
+}
+
+const SCOPE_RULES: &[ScopeRule] = &[
+    sensitive(IS_PASSWORD),
+    sensitive(IS_NUMERIC_PASSWORD),
+    sensitive(IS_PRIVATE),
+    unlearned(IS_URL, Some(InputMode::Latin)),
+    unlearned(IS_EMAIL_USERNAME, Some(InputMode::Latin)),
+    unlearned(IS_EMAIL_SMTPEMAILADDRESS, Some(InputMode::Latin)),
+    unlearned(IS_EMAILNAME_OR_ADDRESS, Some(InputMode::Latin)),
+    rule(IS_DIGITS, Some(InputMode::Latin)),
+    rule(IS_NUMBER, Some(InputMode::Latin)),
+    rule(IS_NUMBER_FULLWIDTH, Some(InputMode::FullLatin)),
+    rule(IS_TELEPHONE_FULLTELEPHONENUMBER, Some(InputMode::Latin)),
+    rule(IS_TELEPHONE_COUNTRYCODE, Some(InputMode::Latin)),
+    rule(IS_TELEPHONE_AREACODE, Some(InputMode::Latin)),
+    rule(IS_TELEPHONE_LOCALNUMBER, Some(InputMode::Latin)),
+    rule(IS_ALPHANUMERIC_HALFWIDTH, Some(InputMode::Latin)),
+    rule(IS_ALPHANUMERIC_FULLWIDTH, Some(InputMode::FullLatin)),
+    rule(IS_HIRAGANA, Some(InputMode::Kana)),
+    rule(IS_KATAKANA_FULLWIDTH, Some(InputMode::Katakana)),
+    rule(IS_KATAKANA_HALFWIDTH, Some(InputMode::HalfKatakana)),
+];
+
+/// Combines the rules for every scope a field advertises. The first scope
+/// that names a mode picks it; learning restrictions from any scope apply.
+pub(crate) fn input_scope_policy(scopes: &[InputScope]) -> InputScopePolicy {
+    let mut policy = InputScopePolicy::default();
+    for rule in scopes
+        .iter()
+        .filter_map(|scope| SCOPE_RULES.iter().find(|rule| rule.scope == *scope))
+    {
+        if policy.input_mode.is_none() {
+            policy.input_mode = rule.input_mode.clone();
+        }
+        policy.suppress_learning |= rule.suppress_learning;
+        policy.sensitive |= rule.sensitive;
+    }
+    policy
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::UI::TextServices::{
+        IS_DEFAULT, IS_EMAIL_USERNAME, IS_HIRAGANA, IS_KATAKANA_FULLWIDTH, IS_NUMBER, IS_PASSWORD,
+        IS_TELEPHONE_LOCALNUMBER, IS_URL,
+    };
+
+    use super::{input_scope_policy, InputScopePolicy};
+    use crate::engine::input_mode::InputMode;
+
+    #[test]
+    fn unknown_scopes_keep_the_current_mode_and_learning() {
+        assert_eq!(input_scope_policy(&[]), InputScopePolicy::default());
+        assert_eq!(
+            input_scope_policy(&[IS_DEFAULT]),
+            InputScopePolicy::default()
+        );
+    }
+
+    #[test]
+    fn address_fields_start_in_latin_without_learning() {
+        for scope in [IS_URL, IS_EMAIL_USERNAME] {
+            let policy = input_scope_policy(&[scope]);
+            assert_eq!(policy.input_mode, Some(InputMode::Latin));
+            assert!(policy.suppress_learning);
+            assert!(!policy.sensitive);
+        }
+    }
+
+    #[test]
+    fn number_and_kana_fields_start_in_their_mode() {
+        assert_eq!(
+            input_scope_policy(&[IS_NUMBER]).input_mode,
+            Some(InputMode::Latin)
+        );
+        assert_eq!(
+            input_scope_policy(&[IS_TELEPHONE_LOCALNUMBER]).input_mode,
+            Some(InputMode::Latin)
+        );
+        assert_eq!(
+            input_scope_policy(&[IS_HIRAGANA]).input_mode,
+            Some(InputMode::Kana)
+        );
+        let katakana = input_scope_policy(&[IS_KATAKANA_FULLWIDTH]);
+        assert_eq!(katakana.input_mode, Some(InputMode::Katakana));
+        assert!(!katakana.suppress_learning);
+    }
+
+    #[test]
+    fn first_mode_wins_and_restrictions_accumulate() {
+        let policy = input_scope_policy(&[IS_DEFAULT, IS_HIRAGANA, IS_URL, IS_PASSWORD]);
+
+        assert_eq!(policy.input_mode, Some(InputMode::Kana));
+        assert!(policy.suppress_learning);
+        assert!(policy.sensitive);
+    }
+}
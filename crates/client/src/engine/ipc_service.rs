@@ -5,27 +5,28 @@ use shared::{
         azookey_service_client::AzookeyServiceClient, window_service_client::WindowServiceClient,
         PerformanceLogRequest, StartReconversionRequest,
     },
+    transport::{self, ConnectFailure, IpcEndpoint},
     AppConfig,
 };
 use std::{
     cell::Cell,
+    collections::HashMap,
     error::Error as StdError,
     fmt,
     future::Future,
-    os::windows::io::IntoRawHandle,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex, OnceLock,
     },
     time::{Duration, Instant},
 };
-use tokio::{net::windows::named_pipe::NamedPipeClient, time};
+use tokio::time;
 use tonic::transport::{channel::Channel, Endpoint};
 use tower::service_fn;
-use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, ERROR_PIPE_BUSY};
 
 const INPUT_STYLE_ROMAN2KANA: i32 = 0;
 const INPUT_STYLE_DIRECT: i32 = 1;
+const INPUT_STYLE_KANA: i32 = 2;
 const CLIENT_LOG_CONFIG_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
 const PIPE_BUSY_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 const SERVER_PIPE_BUSY_TIMEOUT: Duration = Duration::from_millis(750);
@@ -36,6 +37,8 @@ const STATE_RPC_DEADLINE: Duration = Duration::from_secs(1);
 const LEARNING_RPC_DEADLINE: Duration = Duration::from_secs(1);
 const UI_RPC_DEADLINE: Duration = Duration::from_millis(250);
 const PERFORMANCE_RPC_DEADLINE: Duration = Duration::from_millis(100);
+// The server bounds its own wait; this only covers a stalled transport.
+const REFINEMENT_RPC_DEADLINE: Duration = Duration::from_secs(6);
 
 static CLIENT_REQUEST_SEQUENCE: AtomicU64 = AtomicU64::new(1);
 static IPC_CONNECTION_SEQUENCE: AtomicU64 = AtomicU64::new(1);
@@ -45,11 +48,6 @@ thread_local! {
     static CLIENT_INPUT_TRACE_REQUEST_ID: Cell<Option<u64>> = const { Cell::new(None) };
 }
 
-fn open_named_pipe_client(pipe_name: &str) -> std::io::Result<NamedPipeClient> {
-    let handle = shared::open_named_pipe_client_handle(pipe_name)?;
-    unsafe { NamedPipeClient::from_raw_handle(handle.into_raw_handle()) }
-}
-
 #[derive(Debug, Default)]
 struct ClientLogConfigCache {
     last_checked: Option<Instant>,
@@ -67,7 +65,22 @@ pub struct IPCService {
     runtime: Arc<tokio::runtime::Runtime>,
     performance_log_tx: tokio::sync::mpsc::Sender<PerformanceLogRequest>,
     server_session_id: Option<u64>,
+    // Server-side composition session requests go to. 0 is the shared
+    // session used with servers that predate composition sessions.
+    composition_session_id: u64,
+    // TSF context that owns `composition_session_id`. None while the session
+    // is not bound to a context yet; the next new context takes it.
+    composition_context: Option<isize>,
+    // Composition session of each TSF context seen, keyed by context identity.
+    composition_sessions: HashMap<isize, u64>,
     server_reset_recovered: bool,
+    // Ask the server to return dictionary candidates first and rerank with
+    // Zenzai in the background. Only set while someone can apply the result.
+    defer_refinement: bool,
+    // request_id of the last append whose Zenzai refinement is still pending.
+    pending_refinement: Option<u64>,
+    // Incognito: learning commits are dropped here and flagged for the server.
+    learning_suspended: bool,
     recovery: Arc<ServerRecoveryState>,
 }
 
@@ -77,6 +90,9 @@ struct ServerRecoveryState {
     generation: AtomicU64,
     restart_completed_generation: AtomicU64,
     restart_request_in_flight: AtomicBool,
+    // Set when the server no longer knows our composition session, e.g.
+    // after it evicted the session as idle.
+    composition_session_lost: AtomicBool,
     input_ledger: Mutex<InputLedger>,
 }
 
@@ -87,6 +103,7 @@ impl Default for ServerRecoveryState {
             generation: AtomicU64::new(0),
             restart_completed_generation: AtomicU64::new(0),
             restart_request_in_flight: AtomicBool::new(false),
+            composition_session_lost: AtomicBool::new(false),
             input_ledger: Mutex::new(InputLedger {
                 operations: Vec::new(),
                 complete: true,
@@ -131,7 +148,7 @@ struct CursorMove {
     raw_input: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct InputLedgerSnapshot(InputLedger);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -184,6 +201,12 @@ pub(crate) fn is_non_destructive_ipc_error(error: &anyhow::Error) -> bool {
         })
 }
 
+fn is_composition_session_lost(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<tonic::Status>()
+        .is_some_and(|status| status.code() == tonic::Code::NotFound)
+}
+
 pub(crate) fn requires_ipc_recovery(error: &anyhow::Error) -> bool {
     is_ipc_deadline(error) || error.downcast_ref::<IpcRecoveryPending>().is_some()
 }
@@ -288,6 +311,51 @@ pub struct Candidates {
     pub hiragana: String,
     pub corresponding_count: Vec<i32>,
     pub candidate_ids: Vec<u64>,
+    /// Parallel to `texts`. May be shorter for locally built candidates, so
+    /// look entries up with [`Candidates::metadata_at`].
+    pub metadata: Vec<CandidateMetadata>,
+}
+
+/// Where a candidate came from and what the candidate window shows under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateMetadata {
+    pub origin: shared::proto::CandidateOrigin,
+    pub annotation: Option<String>,
+    pub lcid: u32,
+    pub rcid: u32,
+}
+
+impl CandidateMetadata {
+    fn from_suggestion(suggestion: &shared::proto::Suggestion) -> Self {
+        Self {
+            origin: suggestion.origin(),
+            annotation: suggestion.annotation.clone(),
+            lcid: suggestion.lcid,
+            rcid: suggestion.rcid,
+        }
+    }
+
+    /// Secondary line for the candidate window, e.g. "ユーザー辞書・環境依存".
+    /// Plain system dictionary candidates without an annotation get none.
+    pub(crate) fn window_annotation(&self) -> String {
+        use shared::proto::CandidateOrigin;
+
+        let origin = match self.origin {
+            CandidateOrigin::UserDictionary => Some("ユーザー辞書"),
+            CandidateOrigin::Learned => Some("学習"),
+            CandidateOrigin::TypoCorrection => Some("入力ミス補正"),
+            CandidateOrigin::Unspecified | CandidateOrigin::SystemDictionary => None,
+        };
+        origin
+            .into_iter()
+            .chain(
+                self.annotation
+                    .as_deref()
+                    .filter(|annotation| !annotation.is_empty()),
+            )
+            .collect::<Vec<_>>()
+            .join("・")
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -318,6 +386,36 @@ impl Candidates {
             && self.candidate_ids.is_empty()
     }
 
+    pub(crate) fn metadata_at(&self, index: usize) -> Option<&CandidateMetadata> {
+        self.metadata.get(index)
+    }
+
+    /// Drops the "learned" origin of the candidate at `index` after its
+    /// learning was forgotten, so the window stops labelling it.
+    pub(crate) fn mark_forgotten(&mut self, index: usize) {
+        if let Some(metadata) = self
+            .metadata
+            .get_mut(index)
+            .filter(|metadata| metadata.origin == shared::proto::CandidateOrigin::Learned)
+        {
+            metadata.origin = shared::proto::CandidateOrigin::SystemDictionary;
+        }
+    }
+
+    /// Candidate texts and their secondary lines for the candidate window.
+    pub(crate) fn window_list(&self) -> shared::proto::CandidateList {
+        shared::proto::CandidateList {
+            candidates: self.texts.clone(),
+            annotations: (0..self.texts.len())
+                .map(|index| {
+                    self.metadata_at(index)
+                        .map(CandidateMetadata::window_annotation)
+                        .unwrap_or_default()
+                })
+                .collect(),
+        }
+    }
+
     #[inline]
     fn has_same_composition(&self, other: &Self) -> bool {
         self.texts == other.texts
@@ -448,21 +546,22 @@ impl Drop for ClientInputTraceGuard {
 
 impl IPCService {
     pub fn new() -> Result<Self> {
+        let mut service = Self::connect()?;
+        service.composition_session_id = service.create_composition_session()?;
+        Ok(service)
+    }
+
+    fn connect() -> Result<Self> {
         let runtime = Arc::new(tokio::runtime::Runtime::new()?);
         let connection_id = IPC_CONNECTION_SEQUENCE.fetch_add(1, Ordering::Relaxed);
 
-        let server_channel = Self::connect_named_pipe_channel(
+        let server_channel = Self::connect_ipc_channel(
             &runtime,
             "http://[::]:50051",
-            shared::SERVER_PIPE_PATH,
+            IpcEndpoint::server()?,
             SERVER_PIPE_BUSY_TIMEOUT,
         )?;
-        let window_client = match Self::connect_named_pipe_channel(
-            &runtime,
-            "http://[::]:50052",
-            shared::UI_PIPE_PATH,
-            UI_PIPE_BUSY_TIMEOUT,
-        ) {
+        let window_client = match Self::connect_window_channel(&runtime) {
             Ok(ui_channel) => Some(WindowServiceClient::new(ui_channel)),
             Err(error) => {
                 tracing::warn!(
@@ -501,54 +600,102 @@ impl IPCService {
             runtime,
             performance_log_tx,
             server_session_id: None,
+            composition_session_id: 0,
+            composition_context: None,
+            composition_sessions: HashMap::new(),
             server_reset_recovered: false,
+            defer_refinement: false,
+            pending_refinement: None,
+            learning_suspended: false,
             recovery: Arc::new(ServerRecoveryState::default()),
         })
     }
 
-    fn connect_named_pipe_channel(
+    fn create_composition_session(&mut self) -> anyhow::Result<u64> {
+        let mut request = tonic::Request::new(shared::proto::CreateCompositionSessionRequest {
+            request_id: current_or_next_request_id(),
+        });
+        request.set_timeout(STATE_RPC_DEADLINE);
+        let response = self.runtime.block_on(await_rpc_with_deadline(
+            "create_composition_session",
+            STATE_RPC_DEADLINE,
+            self.azookey_client.create_composition_session(request),
+        ));
+        match response {
+            Ok(response) => {
+                let response = response.into_inner();
+                self.observe_server_session(
+                    "create_composition_session",
+                    response.server_session_id,
+                );
+                tracing::debug!(
+                    composition_session_id = response.composition_session_id,
+                    "Created composition session"
+                );
+                Ok(response.composition_session_id)
+            }
+            Err(error)
+                if error
+                    .downcast_ref::<tonic::Status>()
+                    .is_some_and(|status| status.code() == tonic::Code::Unimplemented) =>
+            {
+                tracing::info!("Server has no composition sessions; using the shared session");
+                Ok(0)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn connect_window_channel(runtime: &tokio::runtime::Runtime) -> Result<Channel> {
+        Self::connect_ipc_channel(
+            runtime,
+            "http://[::]:50052",
+            IpcEndpoint::ui()?,
+            UI_PIPE_BUSY_TIMEOUT,
+        )
+    }
+
+    fn connect_ipc_channel(
         runtime: &tokio::runtime::Runtime,
-        endpoint: &'static str,
-        pipe_name: &'static str,
+        uri: &'static str,
+        ipc_endpoint: IpcEndpoint,
         busy_timeout: Duration,
     ) -> Result<Channel> {
-        let endpoint = Endpoint::try_from(endpoint)?;
-        let connect = endpoint.connect_with_connector(service_fn(move |_| async move {
-            let busy_started_at = Instant::now();
-            let client = loop {
-                match open_named_pipe_client(pipe_name) {
-                    Ok(client) => break client,
-                    Err(e)
-                        if matches!(
-                            e.raw_os_error(),
-                            Some(code)
-                                if code == ERROR_PIPE_BUSY.0 as i32
-                                    || code == ERROR_FILE_NOT_FOUND.0 as i32
-                                    || code == ERROR_PATH_NOT_FOUND.0 as i32
-                        ) =>
-                    {
-                        if busy_started_at.elapsed() >= busy_timeout {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::TimedOut,
-                                format!(
-                                    "{pipe_name} remained unavailable for at least {busy_timeout:?}"
-                                ),
-                            ));
+        let endpoint = Endpoint::try_from(uri)?;
+        let connect = endpoint.connect_with_connector(service_fn(move |_| {
+            let ipc_endpoint = ipc_endpoint.clone();
+            async move {
+                let busy_started_at = Instant::now();
+                let client = loop {
+                    match transport::connect(&ipc_endpoint).await {
+                        Ok(client) => break client,
+                        Err(e)
+                            if transport::classify_connect_error(&e)
+                                != ConnectFailure::Fatal =>
+                        {
+                            if busy_started_at.elapsed() >= busy_timeout {
+                                return Err(std::io::Error::new(
+                                    std::io::ErrorKind::TimedOut,
+                                    format!(
+                                        "{ipc_endpoint} remained unavailable for at least {busy_timeout:?}"
+                                    ),
+                                ));
+                            }
                         }
+                        Err(e) => return Err(e),
                     }
-                    Err(e) => return Err(e),
-                }
 
-                time::sleep(PIPE_BUSY_RETRY_INTERVAL).await;
-            };
+                    time::sleep(PIPE_BUSY_RETRY_INTERVAL).await;
+                };
 
-            Ok::<_, std::io::Error>(TokioIo::new(client))
+                Ok::<_, std::io::Error>(TokioIo::new(client))
+            }
         }));
         let channel = runtime.block_on(async {
             time::timeout(IPC_CONNECT_DEADLINE, connect)
                 .await
                 .map_err(|_| IpcDeadlineExceeded {
-                    operation: "connect_named_pipe",
+                    operation: "connect_ipc",
                     deadline: IPC_CONNECT_DEADLINE,
                 })?
                 .map_err(anyhow::Error::from)
@@ -586,6 +733,11 @@ impl IPCService {
                     .iter()
                     .map(|s| s.candidate_id)
                     .collect(),
+                metadata: composing_text
+                    .suggestions
+                    .iter()
+                    .map(CandidateMetadata::from_suggestion)
+                    .collect(),
             })
         } else {
             anyhow::bail!("composing_text is None");
@@ -593,12 +745,38 @@ impl IPCService {
     }
 
     fn reconnect(&mut self) -> anyhow::Result<()> {
-        let refreshed = Self::new()?;
+        let refreshed = Self::connect()?;
         self.connection_id = refreshed.connection_id;
         self.azookey_client = refreshed.azookey_client;
         self.window_client = refreshed.window_client;
         self.runtime = refreshed.runtime;
         self.performance_log_tx = refreshed.performance_log_tx;
+
+        // A transport reconnect to the same server keeps the session. A
+        // restarted server or an evicted session needs a new, empty one.
+        let session_lost = self
+            .recovery
+            .composition_session_lost
+            .swap(false, Ordering::AcqRel);
+        let restarted = self.recovery.pending.load(Ordering::Acquire);
+        if session_lost || restarted {
+            self.composition_session_id = self.create_composition_session()?;
+            if restarted {
+                // The new server knows none of the other contexts' sessions.
+                self.composition_sessions.clear();
+            }
+            if let Some(context) = self.composition_context {
+                self.composition_sessions
+                    .insert(context, self.composition_session_id);
+            }
+            if session_lost {
+                tracing::warn!(
+                    composition_session_id = self.composition_session_id,
+                    "Server evicted the composition session; continuing in a new one"
+                );
+                self.server_reset_recovered = true;
+            }
+        }
         Ok(())
     }
 
@@ -782,6 +960,11 @@ impl IPCService {
         if result.as_ref().is_err_and(is_ipc_deadline) {
             Self::mark_server_recovery_required(recovery, operation);
         }
+        if result.as_ref().is_err_and(is_composition_session_lost) {
+            recovery
+                .composition_session_lost
+                .store(true, Ordering::Release);
+        }
         result
     }
 
@@ -822,12 +1005,153 @@ impl IPCService {
             && previous_session_id.is_some_and(|previous| previous != server_session_id)
     }
 
+    /// Sends further requests to the composition session of the TSF context
+    /// identified by `context`, creating one for a context seen for the first
+    /// time.
+    pub(crate) fn switch_composition_session(&mut self, context: isize) -> anyhow::Result<()> {
+        if self.composition_context == Some(context) {
+            return Ok(());
+        }
+        let session_id = match self.composition_sessions.get(&context) {
+            Some(&session_id) => session_id,
+            None if self.composition_context.is_none() => self.composition_session_id,
+            None => self.create_composition_session()?,
+        };
+        self.composition_sessions.insert(context, session_id);
+        self.composition_context = Some(context);
+        self.composition_session_id = session_id;
+        self.pending_refinement = None;
+        tracing::debug!(
+            composition_session_id = session_id,
+            "Switched composition session"
+        );
+        Ok(())
+    }
+
+    /// Destroys the composition session of a context TSF has popped. The
+    /// current session is kept for the next new context instead, so requests
+    /// in flight never name a destroyed session.
+    pub(crate) fn release_composition_session(&mut self, context: isize) {
+        let Some(session_id) = self.composition_sessions.remove(&context) else {
+            return;
+        };
+        if self.composition_context == Some(context) {
+            self.composition_context = None;
+        } else {
+            self.destroy_composition_session(session_id);
+        }
+    }
+
+    /// Destroys the composition sessions of every context when the text
+    /// service deactivates, except the current one, which the next new
+    /// context takes.
+    pub(crate) fn release_composition_sessions(&mut self) {
+        self.composition_context = None;
+        for (_, session_id) in std::mem::take(&mut self.composition_sessions) {
+            if session_id != self.composition_session_id {
+                self.destroy_composition_session(session_id);
+            }
+        }
+    }
+
+    fn destroy_composition_session(&self, composition_session_id: u64) {
+        // The shared session of an older server cannot be destroyed.
+        if composition_session_id == 0 {
+            return;
+        }
+        let mut client = self.azookey_client.clone();
+        let request_id = current_or_next_request_id();
+        self.runtime.spawn(async move {
+            let mut request =
+                tonic::Request::new(shared::proto::DestroyCompositionSessionRequest {
+                    request_id,
+                    composition_session_id,
+                });
+            request.set_timeout(STATE_RPC_DEADLINE);
+            if let Err(error) = await_rpc_with_deadline(
+                "destroy_composition_session",
+                STATE_RPC_DEADLINE,
+                client.destroy_composition_session(request),
+            )
+            .await
+            {
+                tracing::debug!(
+                    composition_session_id,
+                    "Failed to destroy composition session: {error:?}"
+                );
+            }
+        });
+    }
+
     pub(crate) fn take_server_reset_recovered(&mut self) -> bool {
         let recovered = self.server_reset_recovered;
         self.server_reset_recovered = false;
         recovered
     }
 
+    pub(crate) fn set_defer_refinement(&mut self, defer_refinement: bool) {
+        self.defer_refinement = defer_refinement;
+    }
+
+    pub(crate) fn set_learning_suspended(&mut self, learning_suspended: bool) {
+        self.learning_suspended = learning_suspended;
+    }
+
+    pub(crate) fn learning_suspended(&self) -> bool {
+        self.learning_suspended
+    }
+
+    pub(crate) fn take_pending_refinement(&mut self) -> Option<u64> {
+        self.pending_refinement.take()
+    }
+
+    /// Waits for the Zenzai refinement scheduled by append `request_id` on the
+    /// IPC runtime and hands the refined candidates to `deliver` from there.
+    /// Stale or unavailable refinements are dropped silently; the dictionary
+    /// candidates already shown stay in place.
+    pub(crate) fn request_refined_candidates(
+        &self,
+        request_id: u64,
+        deliver: impl FnOnce(Candidates) + Send + 'static,
+    ) {
+        let mut client = self.azookey_client.clone();
+        let composition_session_id = self.composition_session_id;
+        self.runtime.spawn(async move {
+            let mut request = tonic::Request::new(shared::proto::GetRefinedCandidatesRequest {
+                request_id,
+                composition_session_id,
+            });
+            request.set_timeout(REFINEMENT_RPC_DEADLINE);
+            let response = match await_rpc_with_deadline(
+                "get_refined_candidates",
+                REFINEMENT_RPC_DEADLINE,
+                client.get_refined_candidates(request),
+            )
+            .await
+            {
+                Ok(response) => response.into_inner(),
+                Err(error) => {
+                    tracing::debug!(request_id, "Zenzai refinement request failed: {error:?}");
+                    return;
+                }
+            };
+            if response.status() != shared::proto::RefinementStatus::Ready {
+                tracing::debug!(
+                    request_id,
+                    status = ?response.status(),
+                    "Dropping Zenzai refinement"
+                );
+                return;
+            }
+            match Self::candidates_from_composing_text(response.composing_text) {
+                Ok(candidates) => deliver(candidates),
+                Err(error) => {
+                    tracing::debug!(request_id, "Malformed Zenzai refinement: {error:?}");
+                }
+            }
+        });
+    }
+
     fn run_rpc_with_reconnect<T>(
         &mut self,
         operation: &str,
@@ -1014,6 +1338,7 @@ impl IPCService {
                 | tonic::Code::Cancelled
                 | tonic::Code::DataLoss
                 | tonic::Code::Internal
+                | tonic::Code::NotFound
                 | tonic::Code::Unavailable
                 | tonic::Code::Unknown
         )
@@ -1029,9 +1354,12 @@ impl IPCService {
             text_to_append: text.to_string(),
             input_style,
             request_id,
+            composition_session_id: self.composition_session_id,
+            defer_refinement: self.defer_refinement,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
 
+        self.pending_refinement = None;
         let response = Self::block_on_server_rpc(
             self.runtime.as_ref(),
             &self.recovery,
@@ -1046,11 +1374,17 @@ impl IPCService {
         let response = response.into_inner();
         self.observe_server_session("append_text", response.server_session_id);
         self.record_successful_append(text, input_style);
+        if response.refinement_pending {
+            self.pending_refinement = Some(request_id);
+        }
         Ok(response)
     }
 
     fn send_remove_text(&mut self, request_id: u64) -> anyhow::Result<TextRemoval> {
-        let mut request = tonic::Request::new(shared::proto::RemoveTextRequest { request_id });
+        let mut request = tonic::Request::new(shared::proto::RemoveTextRequest {
+            request_id,
+            composition_session_id: self.composition_session_id,
+        });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
             self.runtime.as_ref(),
@@ -1079,7 +1413,10 @@ impl IPCService {
     }
 
     fn send_clear_text(&mut self, request_id: u64) -> anyhow::Result<()> {
-        let mut request = tonic::Request::new(shared::proto::ClearTextRequest { request_id });
+        let mut request = tonic::Request::new(shared::proto::ClearTextRequest {
+            request_id,
+            composition_session_id: self.composition_session_id,
+        });
         request.set_timeout(STATE_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
             self.runtime.as_ref(),
@@ -1102,6 +1439,7 @@ impl IPCService {
         let mut request = tonic::Request::new(StartReconversionRequest {
             surface: surface.to_string(),
             request_id,
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1138,6 +1476,8 @@ impl IPCService {
             candidate_id,
             commit_kind,
             request_id,
+            composition_session_id: self.composition_session_id,
+            do_not_learn: self.learning_suspended,
         });
         request.set_timeout(LEARNING_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1152,6 +1492,71 @@ impl IPCService {
         Ok(())
     }
 
+    fn send_forget_learning_candidate(
+        &mut self,
+        candidate_id: u64,
+        request_id: u64,
+    ) -> anyhow::Result<bool> {
+        let mut request = tonic::Request::new(shared::proto::ForgetLearningCandidateRequest {
+            request_id,
+            candidate_id,
+            composition_session_id: self.composition_session_id,
+        });
+        request.set_timeout(LEARNING_RPC_DEADLINE);
+        let response = Self::block_on_server_rpc(
+            self.runtime.as_ref(),
+            &self.recovery,
+            "forget_learning_candidate",
+            LEARNING_RPC_DEADLINE,
+            self.azookey_client.forget_learning_candidate(request),
+        )?
+        .into_inner();
+        self.observe_server_session("forget_learning_candidate", response.server_session_id);
+        Ok(response.forgotten)
+    }
+
+    fn send_forget_learned_entry(
+        &mut self,
+        reading: &str,
+        word: &str,
+        request_id: u64,
+    ) -> anyhow::Result<bool> {
+        let mut request = tonic::Request::new(shared::proto::ForgetLearnedEntryRequest {
+            request_id,
+            reading: reading.to_string(),
+            word: word.to_string(),
+        });
+        request.set_timeout(LEARNING_RPC_DEADLINE);
+        let response = Self::block_on_server_rpc(
+            self.runtime.as_ref(),
+            &self.recovery,
+            "forget_learned_entry",
+            LEARNING_RPC_DEADLINE,
+            self.azookey_client.forget_learned_entry(request),
+        )?
+        .into_inner();
+        self.observe_server_session("forget_learned_entry", response.server_session_id);
+        Ok(response.forgotten)
+    }
+
+    fn send_predict(&mut self, request_id: u64) -> anyhow::Result<Candidates> {
+        let mut request = tonic::Request::new(shared::proto::PredictRequest {
+            request_id,
+            composition_session_id: self.composition_session_id,
+        });
+        request.set_timeout(INPUT_RPC_DEADLINE);
+        let response = Self::block_on_server_rpc(
+            self.runtime.as_ref(),
+            &self.recovery,
+            "predict",
+            INPUT_RPC_DEADLINE,
+            self.azookey_client.predict(request),
+        )?
+        .into_inner();
+        self.observe_server_session("predict", response.server_session_id);
+        Self::candidates_from_composing_text(response.composing_text)
+    }
+
     fn send_commit_learning_candidates(
         &mut self,
         commits: &[(u64, i32)],
@@ -1168,6 +1573,8 @@ impl IPCService {
                 )
                 .collect(),
             request_id,
+            composition_session_id: self.composition_session_id,
+            do_not_learn: self.learning_suspended,
         });
         request.set_timeout(LEARNING_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1183,8 +1590,11 @@ impl IPCService {
     }
 
     fn send_shrink_text(&mut self, offset: i32, request_id: u64) -> anyhow::Result<Candidates> {
-        let mut request =
-            tonic::Request::new(shared::proto::ShrinkTextRequest { offset, request_id });
+        let mut request = tonic::Request::new(shared::proto::ShrinkTextRequest {
+            offset,
+            request_id,
+            composition_session_id: self.composition_session_id,
+        });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
             self.runtime.as_ref(),
@@ -1209,6 +1619,7 @@ impl IPCService {
             offset,
             request_id,
             selected_candidate_id,
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1247,6 +1658,7 @@ impl IPCService {
             request_id,
             leave_at_last,
             initial_selected_candidate_id,
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1280,8 +1692,11 @@ impl IPCService {
         offset: i32,
         request_id: u64,
     ) -> anyhow::Result<CursorMove> {
-        let mut request =
-            tonic::Request::new(shared::proto::MoveCursorRequest { offset, request_id });
+        let mut request = tonic::Request::new(shared::proto::MoveCursorRequest {
+            offset,
+            request_id,
+            composition_session_id: self.composition_session_id,
+        });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
             self.runtime.as_ref(),
@@ -1316,6 +1731,7 @@ impl IPCService {
             direction,
             request_id,
             expected_raw_input: expected_raw_input.to_string(),
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1363,6 +1779,7 @@ impl IPCService {
             operation: operation.proto_value(),
             request_id,
             selected_candidate_id,
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1377,10 +1794,19 @@ impl IPCService {
         Ok(())
     }
 
-    fn send_set_context(&mut self, context: &str, request_id: u64) -> anyhow::Result<()> {
+    fn send_set_context(
+        &mut self,
+        context: &str,
+        right_context: &str,
+        zenzai_disabled: bool,
+        request_id: u64,
+    ) -> anyhow::Result<()> {
         let mut request = tonic::Request::new(shared::proto::SetContextRequest {
             context: context.to_string(),
             request_id,
+            composition_session_id: self.composition_session_id,
+            zenzai_disabled,
+            right_context: right_context.to_string(),
         });
         request.set_timeout(STATE_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1431,6 +1857,7 @@ impl IPCService {
         let mut request = tonic::Request::new(shared::proto::ReplaceCompositionRequest {
             operations,
             request_id,
+            composition_session_id: self.composition_session_id,
         });
         request.set_timeout(INPUT_RPC_DEADLINE);
         let response = Self::block_on_server_rpc(
@@ -1584,6 +2011,15 @@ impl IPCService {
         self.append_text_with_style_and_context(text, INPUT_STYLE_DIRECT, Some(previous_candidates))
     }
 
+    #[tracing::instrument]
+    pub fn append_text_kana_with_context(
+        &mut self,
+        text: String,
+        previous_candidates: &Candidates,
+    ) -> anyhow::Result<Candidates> {
+        self.append_text_with_style_and_context(text, INPUT_STYLE_KANA, Some(previous_candidates))
+    }
+
     #[tracing::instrument]
     fn append_text_with_style(
         &mut self,
@@ -1883,6 +2319,94 @@ impl IPCService {
     }
 
     #[tracing::instrument]
+    /// Forgets the learned conversion behind a shown candidate. Returns
+    /// whether the server had anything to forget.
+    pub fn forget_learning_candidate(&mut self, candidate_id: u64) -> anyhow::Result<bool> {
+        let request_id = current_or_next_request_id();
+        let performance_start = client_performance_start();
+        let result = self.send_forget_learning_candidate(candidate_id, request_id);
+        self.log_client_performance_from_start(
+            performance_start,
+            request_id,
+            "forget_learning_candidate",
+            "rpc_total",
+            || match &result {
+                Ok(forgotten) => {
+                    format!("status=success;candidate_id={candidate_id};forgotten={forgotten}")
+                }
+                Err(error) => {
+                    format!("status=error;candidate_id={candidate_id};error={error:?}")
+                }
+            },
+        );
+        result
+    }
+
+    /// Forgets a learned reading/word pair. Unlike
+    /// [`Self::forget_learning_candidate`] this still works once the
+    /// candidate has been committed and dropped from the server's cache.
+    pub(crate) fn forget_learned_entry(
+        &mut self,
+        reading: &str,
+        word: &str,
+    ) -> anyhow::Result<bool> {
+        let request_id = current_or_next_request_id();
+        let performance_start = client_performance_start();
+        let result = self.send_forget_learned_entry(reading, word, request_id);
+        self.log_client_performance_from_start(
+            performance_start,
+            request_id,
+            "forget_learned_entry",
+            "rpc_total",
+            || match &result {
+                Ok(forgotten) => format!("status=success;forgotten={forgotten}"),
+                Err(error) => format!("status=error;error={error:?}"),
+            },
+        );
+        result
+    }
+
+    /// Completions of the current reading. They cover the whole composition
+    /// and leave the server's composition untouched.
+    pub(crate) fn predict(&mut self) -> anyhow::Result<Candidates> {
+        let request_id = current_or_next_request_id();
+        let performance_start = client_performance_start();
+        let result = self.send_predict(request_id);
+        self.log_client_performance_from_start(
+            performance_start,
+            request_id,
+            "predict",
+            "rpc_total",
+            || match &result {
+                Ok(predictions) => {
+                    format!("status=success;predictions={}", predictions.texts.len())
+                }
+                Err(error) => format!("status=error;error={error:?}"),
+            },
+        );
+        result
+    }
+
+    /// Rebuilds a composition that was committed and cleared, replaying
+    /// `input_ledger` when it is complete and the raw input otherwise.
+    pub(crate) fn restore_composition(
+        &mut self,
+        input_ledger: &InputLedgerSnapshot,
+        raw_input: &str,
+        raw_hiragana: &str,
+    ) -> anyhow::Result<Candidates> {
+        let input_ledger = if input_ledger.0.complete {
+            input_ledger.0.clone()
+        } else {
+            fallback_input_ledger(raw_input, raw_hiragana)
+        };
+        let request_id = current_or_next_request_id();
+        self.run_rpc_with_reconnect("restore_composition", |this| {
+            this.send_replace_composition(&input_ledger, request_id)
+        })
+        .map(|(candidates, _)| candidates)
+    }
+
     pub fn commit_learning_candidate(
         &mut self,
         candidate_id: u64,
@@ -2323,12 +2847,17 @@ impl IPCService {
         result.map(|_| ())
     }
 
-    pub fn set_context(&mut self, context: String) -> anyhow::Result<()> {
+    pub fn set_context(
+        &mut self,
+        context: String,
+        right_context: String,
+        zenzai_disabled: bool,
+    ) -> anyhow::Result<()> {
         let request_id = current_or_next_request_id();
         let performance_start = client_performance_start();
         let context_len = performance_start.map(|_| context.chars().count());
         let result = self.run_rpc_with_reconnect("set_context", |this| {
-            this.send_set_context(&context, request_id)
+            this.send_set_context(&context, &right_context, zenzai_disabled, request_id)
         });
         self.log_client_performance_from_start(
             performance_start,
@@ -2359,12 +2888,7 @@ impl IPCService {
         operation: &str,
     ) -> Option<&mut WindowServiceClient<Channel>> {
         if self.window_client.is_none() {
-            match Self::connect_named_pipe_channel(
-                self.runtime.as_ref(),
-                "http://[::]:50052",
-                shared::UI_PIPE_PATH,
-                UI_PIPE_BUSY_TIMEOUT,
-            ) {
+            match Self::connect_window_channel(self.runtime.as_ref()) {
                 Ok(ui_channel) => {
                     tracing::info!(
                         operation,
@@ -2652,7 +3176,7 @@ impl IPCService {
         &mut self,
         visible: Option<bool>,
         position: Option<shared::proto::WindowPosition>,
-        candidates: Option<Vec<String>>,
+        candidates: Option<shared::proto::CandidateList>,
         selected_index: Option<i32>,
         input_mode: Option<&str>,
     ) -> anyhow::Result<WindowRpcDelivery> {
@@ -2675,32 +3199,73 @@ impl IPCService {
         &mut self,
         visible: Option<bool>,
         position: Option<shared::proto::WindowPosition>,
-        candidates: Option<Vec<String>>,
+        candidates: Option<shared::proto::CandidateList>,
         selected_index: Option<i32>,
         input_mode: Option<&str>,
         reading: Option<&str>,
         candidate_list_visible: Option<bool>,
         reading_vertical_adjustment: Option<i32>,
+    ) -> anyhow::Result<WindowRpcDelivery> {
+        self.send_candidate_window_update(shared::proto::UpdateCandidateWindowRequest {
+            visible,
+            position,
+            candidates,
+            selected_index,
+            input_mode: input_mode.map(ToString::to_string),
+            reading: reading.map(ToString::to_string),
+            candidate_list_visible,
+            reading_vertical_adjustment,
+            suggest: None,
+        })
+    }
+
+    /// Shows `predictions` in the window's suggest style under the
+    /// composition. `selected_index` is the picked prediction, if any.
+    #[tracing::instrument(skip(predictions))]
+    pub(crate) fn update_suggest_window(
+        &mut self,
+        position: Option<shared::proto::WindowPosition>,
+        predictions: &Candidates,
+        selected_index: Option<i32>,
+    ) -> anyhow::Result<WindowRpcDelivery> {
+        self.send_candidate_window_update(shared::proto::UpdateCandidateWindowRequest {
+            visible: Some(true),
+            position,
+            candidates: Some(predictions.window_list()),
+            selected_index: Some(selected_index.unwrap_or(-1)),
+            candidate_list_visible: Some(true),
+            suggest: Some(true),
+            ..Default::default()
+        })
+    }
+
+    fn send_candidate_window_update(
+        &mut self,
+        update: shared::proto::UpdateCandidateWindowRequest,
     ) -> anyhow::Result<WindowRpcDelivery> {
         let request_id = current_or_next_request_id();
         let performance_start = client_performance_start();
-        let position_present = performance_start.map(|_| position.is_some());
-        let candidate_count = performance_start.map(|_| candidates.as_ref().map(Vec::len));
-        let input_mode_present = performance_start.map(|_| input_mode.is_some());
-        let reading_present =
-            performance_start.map(|_| reading.is_some_and(|value| !value.is_empty()));
+        let visible = update.visible;
+        let selected_index = update.selected_index;
+        let candidate_list_visible = update.candidate_list_visible;
+        let reading_vertical_adjustment = update.reading_vertical_adjustment;
+        let suggest = update.suggest;
+        let position_present = performance_start.map(|_| update.position.is_some());
+        let candidate_count = performance_start.map(|_| {
+            update
+                .candidates
+                .as_ref()
+                .map(|candidates| candidates.candidates.len())
+        });
+        let input_mode_present = performance_start.map(|_| update.input_mode.is_some());
+        let reading_present = performance_start.map(|_| {
+            update
+                .reading
+                .as_deref()
+                .is_some_and(|value| !value.is_empty())
+        });
         let result: anyhow::Result<WindowRpcDelivery> = {
-            let mut request = tonic::Request::new(shared::proto::UpdateCandidateWindowRequest {
-                visible,
-                position,
-                candidates: candidates
-                    .map(|candidates| shared::proto::CandidateList { candidates }),
-                selected_index,
-                input_mode: input_mode.map(ToString::to_string),
-                reading: reading.map(ToString::to_string),
-                candidate_list_visible,
-                reading_vertical_adjustment,
-            });
+            let mut request = tonic::Request::new(update);
             request.set_timeout(UI_RPC_DEADLINE);
             self.with_window_client_delivery(
                 "ui_update_candidate_window",
@@ -2726,11 +3291,11 @@ impl IPCService {
                 let reading_present = reading_present.unwrap_or_default();
                 match &result {
                     Ok(delivery) => format!(
-                        "status={};visible={visible:?};position_present={position_present};candidate_count={candidate_count:?};selected_index={selected_index:?};input_mode_present={input_mode_present};reading_present={reading_present};candidate_list_visible={candidate_list_visible:?};reading_vertical_adjustment={reading_vertical_adjustment:?}",
+                        "status={};visible={visible:?};position_present={position_present};candidate_count={candidate_count:?};selected_index={selected_index:?};input_mode_present={input_mode_present};reading_present={reading_present};candidate_list_visible={candidate_list_visible:?};reading_vertical_adjustment={reading_vertical_adjustment:?};suggest={suggest:?}",
                         delivery.log_status()
                     ),
                     Err(error) => format!(
-                        "status=error;visible={visible:?};position_present={position_present};candidate_count={candidate_count:?};selected_index={selected_index:?};input_mode_present={input_mode_present};reading_present={reading_present};candidate_list_visible={candidate_list_visible:?};reading_vertical_adjustment={reading_vertical_adjustment:?};error={error:?}"
+                        "status=error;visible={visible:?};position_present={position_present};candidate_count={candidate_count:?};selected_index={selected_index:?};input_mode_present={input_mode_present};reading_present={reading_present};candidate_list_visible={candidate_list_visible:?};reading_vertical_adjustment={reading_vertical_adjustment:?};suggest={suggest:?};error={error:?}"
                     ),
                 }
             },
@@ -2743,12 +3308,12 @@ impl IPCService {
 mod tests {
     use super::{
         append_input_segment, await_rpc_with_deadline, fallback_input_ledger,
-        is_non_destructive_ipc_error, mark_input_ledger_incomplete, move_input_cursor,
-        pop_input_segment_character, preserve_recovery_error, recovery_generation_is_current,
-        requires_ipc_recovery, restart_generation_ready, restart_request_needed, Candidates,
-        ClauseSnapshotOperation, CompositionOperation, IPCService, InputLedger,
-        IpcDeadlineExceeded, NonIdempotentEditAttempt, ServerRecoveryState, INPUT_STYLE_DIRECT,
-        INPUT_STYLE_ROMAN2KANA,
+        is_composition_session_lost, is_non_destructive_ipc_error, mark_input_ledger_incomplete,
+        move_input_cursor, pop_input_segment_character, preserve_recovery_error,
+        recovery_generation_is_current, requires_ipc_recovery, restart_generation_ready,
+        restart_request_needed, CandidateMetadata, Candidates, ClauseSnapshotOperation,
+        CompositionOperation, IPCService, InputLedger, IpcDeadlineExceeded,
+        NonIdempotentEditAttempt, ServerRecoveryState, INPUT_STYLE_DIRECT, INPUT_STYLE_ROMAN2KANA,
     };
     use std::{
         future::Future,
@@ -2967,6 +3532,35 @@ mod tests {
         assert!(error.to_string().contains("recovery is still pending"));
     }
 
+    #[test]
+    fn window_list_pairs_every_candidate_with_its_secondary_line() {
+        use shared::proto::CandidateOrigin;
+
+        let candidates = Candidates {
+            texts: vec!["漢字".into(), "髙橋".into(), "幹事".into()],
+            metadata: vec![
+                CandidateMetadata {
+                    origin: CandidateOrigin::SystemDictionary,
+                    annotation: None,
+                    lcid: 0,
+                    rcid: 0,
+                },
+                CandidateMetadata {
+                    origin: CandidateOrigin::UserDictionary,
+                    annotation: Some("環境依存".into()),
+                    lcid: 0,
+                    rcid: 0,
+                },
+            ],
+            ..Candidates::default()
+        };
+
+        let list = candidates.window_list();
+
+        assert_eq!(list.candidates, candidates.texts);
+        assert_eq!(list.annotations, ["", "ユーザー辞書・環境依存", ""]);
+    }
+
     #[test]
     fn deadline_never_uses_immediate_retry_policy() {
         let error = anyhow::Error::new(IpcDeadlineExceeded {
@@ -3003,6 +3597,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(IPCService::should_retry_append_after_refresh(
@@ -3019,9 +3614,11 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
         let refreshed = Candidates {
             candidate_ids: vec![2],
+            metadata: Vec::new(),
             ..previous.clone()
         };
 
@@ -3040,6 +3637,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(!IPCService::should_retry_append_after_refresh(
@@ -3056,6 +3654,7 @@ mod tests {
             hiragana: "かんじ".to_string(),
             corresponding_count: vec![5],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(IPCService::should_retry_append_after_refresh(
@@ -3072,6 +3671,7 @@ mod tests {
             hiragana: "かんじ".to_string(),
             corresponding_count: vec![5],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(
@@ -3090,6 +3690,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(
@@ -3108,6 +3709,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(IPCService::should_retry_non_idempotent_edit_after_refresh(
@@ -3126,6 +3728,7 @@ mod tests {
             hiragana: "ん".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(IPCService::should_retry_non_idempotent_edit_after_refresh(
@@ -3166,9 +3769,11 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
         let refreshed = Candidates {
             candidate_ids: vec![2],
+            metadata: Vec::new(),
             ..previous.clone()
         };
 
@@ -3188,6 +3793,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
         let refreshed = Candidates {
             texts: vec!["".to_string()],
@@ -3195,6 +3801,7 @@ mod tests {
             hiragana: String::new(),
             corresponding_count: vec![0],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(!IPCService::should_retry_non_idempotent_edit_after_refresh(
@@ -3213,6 +3820,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(!IPCService::should_retry_non_idempotent_edit_after_refresh(
@@ -3259,6 +3867,15 @@ mod tests {
         assert!(!requires_ipc_recovery(&error));
     }
 
+    #[test]
+    fn reconnect_retry_replaces_an_evicted_composition_session() {
+        let error = anyhow::Error::new(tonic::Status::not_found("composition session 7"));
+
+        assert!(is_composition_session_lost(&error));
+        assert!(IPCService::should_reconnect_rpc_error(&error));
+        assert!(!requires_ipc_recovery(&error));
+    }
+
     #[test]
     fn reconnect_retry_is_enabled_for_non_status_error() {
         let error = anyhow::anyhow!("named pipe disconnected");
@@ -3274,9 +3891,11 @@ mod tests {
             hiragana: "いいかげんとういつ".to_string(),
             corresponding_count: vec![7],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
         let refreshed = Candidates {
             candidate_ids: vec![2],
+            metadata: Vec::new(),
             ..candidates.clone()
         };
 
@@ -3294,6 +3913,7 @@ mod tests {
             hiragana: "いいかげんとういつ".to_string(),
             corresponding_count: vec![7],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         assert!(
@@ -3312,6 +3932,7 @@ mod tests {
             hiragana: "か".to_string(),
             corresponding_count: vec![1],
             candidate_ids: vec![1],
+            metadata: Vec::new(),
         };
 
         let attempt =
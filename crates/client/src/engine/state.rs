@@ -1,11 +1,12 @@
 use std::{
+    collections::HashMap,
     fs, io,
     path::PathBuf,
     sync::{Arc, LazyLock, Mutex, MutexGuard},
     time::SystemTime,
 };
 
-use shared::AppConfig;
+use shared::{keymap::Keymap, AppConfig};
 use windows::{
     core::Interface as _,
     Win32::UI::TextServices::{ITfCompartmentMgr, ITfContext, GUID_COMPARTMENT_KEYBOARD_DISABLED},
@@ -57,7 +58,8 @@ mod tests {
         time::{SystemTime, UNIX_EPOCH},
     };
 
-    use super::{AppConfigCacheKey, IMEState};
+    use super::{AppConfigCacheKey, IMEState, WindowInputModes, REMEMBERED_WINDOW_LIMIT};
+    use crate::engine::input_mode::InputMode;
 
     fn env_lock() -> MutexGuard<'static, ()> {
         static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
@@ -133,6 +135,73 @@ mod tests {
         fs::remove_dir_all(root).ok();
     }
 
+    #[test]
+    fn window_input_modes_restore_the_mode_each_window_last_used() {
+        let mut modes = WindowInputModes::default();
+
+        assert_eq!(modes.focus(1, &InputMode::Kana, None, |_| true), None);
+        assert_eq!(
+            modes.focus(2, &InputMode::Kana, Some(InputMode::Latin), |_| true),
+            Some(InputMode::Latin)
+        );
+        assert_eq!(
+            modes.focus(1, &InputMode::Latin, None, |_| true),
+            Some(InputMode::Kana)
+        );
+        assert_eq!(modes.focus(1, &InputMode::Kana, None, |_| true), None);
+        assert_eq!(
+            modes.focus(2, &InputMode::Kana, Some(InputMode::Kana), |_| true),
+            Some(InputMode::Latin)
+        );
+    }
+
+    #[test]
+    fn input_scope_mode_is_undone_when_leaving_the_field() {
+        let mut modes = WindowInputModes::default();
+        modes.focus(1, &InputMode::Kana, None, |_| true);
+
+        assert_eq!(
+            modes.focus_scope(Some(InputMode::Latin), &InputMode::Kana),
+            Some(InputMode::Latin)
+        );
+        assert_eq!(
+            modes.focus_scope(Some(InputMode::Latin), &InputMode::Latin),
+            None
+        );
+        assert_eq!(
+            modes.focus_scope(None, &InputMode::Latin),
+            Some(InputMode::Kana)
+        );
+        assert_eq!(modes.focus_scope(None, &InputMode::Kana), None);
+    }
+
+    #[test]
+    fn window_remembers_its_own_mode_rather_than_the_scope_mode() {
+        let mut modes = WindowInputModes::default();
+        modes.focus(1, &InputMode::Kana, None, |_| true);
+        modes.focus_scope(Some(InputMode::Latin), &InputMode::Kana);
+
+        assert_eq!(
+            modes.focus(2, &InputMode::Latin, None, |_| true),
+            Some(InputMode::Kana)
+        );
+        assert_eq!(
+            modes.focus(1, &InputMode::Latin, None, |_| true),
+            Some(InputMode::Kana)
+        );
+    }
+
+    #[test]
+    fn window_input_modes_drop_closed_windows_past_the_limit() {
+        let mut modes = WindowInputModes::default();
+        for window in 0..=REMEMBERED_WINDOW_LIMIT as isize + 1 {
+            modes.focus(window, &InputMode::Kana, None, |window| window % 2 == 0);
+        }
+
+        assert!(modes.modes.len() <= REMEMBERED_WINDOW_LIMIT);
+        assert!(modes.modes.keys().all(|window| window % 2 == 0));
+    }
+
     #[test]
     fn app_config_snapshot_does_not_cache_default_after_read_error() {
         let root = unique_test_dir("config-read-error");
@@ -187,19 +256,41 @@ mod tests {
     }
 }
 
+/// File name of the executable this DLL was loaded into, used to pick the
+/// matching entry of `AppConfig::app_profiles`.
+static CURRENT_EXECUTABLE: LazyLock<String> = LazyLock::new(|| {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+});
+
 #[derive(Clone, Debug)]
 pub(crate) struct AppConfigSnapshot {
     app_config: Arc<AppConfig>,
     romaji_lookup: Arc<RomajiLookup>,
+    keymap: Arc<Keymap>,
+    default_input_mode: Option<InputMode>,
     cache_key: AppConfigCacheKey,
 }
 
 impl AppConfigSnapshot {
     fn new(app_config: AppConfig, cache_key: AppConfigCacheKey) -> Self {
+        let default_input_mode = app_config
+            .app_profile(&CURRENT_EXECUTABLE)
+            .and_then(|profile| profile.default_input_mode)
+            .map(InputMode::from);
+        let app_config = app_config.for_app(&CURRENT_EXECUTABLE);
         let romaji_lookup = RomajiLookup::from_rows(&app_config.romaji_table.rows);
+        let keymap = app_config.keymap.resolve();
         Self {
             app_config: Arc::new(app_config),
             romaji_lookup: Arc::new(romaji_lookup),
+            keymap: Arc::new(keymap),
+            default_input_mode,
             cache_key,
         }
     }
@@ -211,6 +302,82 @@ impl AppConfigSnapshot {
     pub(super) fn romaji_lookup(&self) -> &RomajiLookup {
         self.romaji_lookup.as_ref()
     }
+
+    pub(super) fn keymap(&self) -> &Keymap {
+        self.keymap.as_ref()
+    }
+
+    /// Mode a window of this application starts in before the user picks one.
+    pub(crate) fn default_input_mode(&self) -> Option<InputMode> {
+        self.default_input_mode.clone()
+    }
+}
+
+/// Most windows remembered at once; closed ones are dropped first.
+const REMEMBERED_WINDOW_LIMIT: usize = 64;
+
+/// Input mode last used in each top-level window of this process, keyed by
+/// root `HWND`.
+#[derive(Debug, Default)]
+pub(crate) struct WindowInputModes {
+    focused: Option<isize>,
+    modes: HashMap<isize, InputMode>,
+    /// Window mode to return to once focus leaves a field whose input scope
+    /// picked another mode.
+    scope_return: Option<InputMode>,
+}
+
+impl WindowInputModes {
+    /// Records `current` for the window losing focus and returns the mode
+    /// `window` should switch to, if it differs from `current`. A window with
+    /// no remembered mode and no default keeps the user's mode, never one an
+    /// input scope picked.
+    fn focus(
+        &mut self,
+        window: isize,
+        current: &InputMode,
+        default: Option<InputMode>,
+        is_alive: impl Fn(isize) -> bool,
+    ) -> Option<InputMode> {
+        if self.focused == Some(window) {
+            return None;
+        }
+        let left_mode = self.scope_return.take().unwrap_or_else(|| current.clone());
+        if let Some(previous) = self.focused.replace(window) {
+            self.modes.insert(previous, left_mode.clone());
+        }
+        if self.modes.len() > REMEMBERED_WINDOW_LIMIT {
+            self.modes.retain(|window, _| is_alive(*window));
+        }
+        if self.modes.len() > REMEMBERED_WINDOW_LIMIT {
+            self.modes.clear();
+        }
+
+        let mode = self
+            .modes
+            .get(&window)
+            .cloned()
+            .or(default)
+            .unwrap_or(left_mode);
+        (mode != *current).then_some(mode)
+    }
+
+    /// Returns the mode to switch to for a field whose input scope asks for
+    /// `scope_mode`, or the window's own mode when the field asks for none.
+    fn focus_scope(
+        &mut self,
+        scope_mode: Option<InputMode>,
+        current: &InputMode,
+    ) -> Option<InputMode> {
+        let mode = match scope_mode {
+            Some(mode) => {
+                self.scope_return.get_or_insert_with(|| current.clone());
+                mode
+            }
+            None => self.scope_return.take()?,
+        };
+        (mode != *current).then_some(mode)
+    }
 }
 
 #[derive(Debug)]
@@ -218,6 +385,9 @@ pub struct IMEState {
     pub ipc_service: Option<IPCService>,
     pub input_mode: InputMode,
     pub keyboard_disabled: bool,
+    /// Learning and context upload are suspended until toggled back.
+    pub incognito: bool,
+    window_input_modes: WindowInputModes,
     app_config_snapshot: Option<AppConfigSnapshot>,
 }
 
@@ -227,6 +397,8 @@ pub static IME_STATE: LazyLock<Mutex<IMEState>> = LazyLock::new(|| {
         ipc_service: None,
         input_mode: InputMode::default(),
         keyboard_disabled: false,
+        incognito: false,
+        window_input_modes: WindowInputModes::default(),
         app_config_snapshot: None,
     })
 });
@@ -243,8 +415,10 @@ impl IMEState {
         Ok(Self::get()?.ipc_service.clone())
     }
 
-    pub fn set_ipc_service(ipc_service: IPCService) -> anyhow::Result<()> {
-        Self::get()?.ipc_service = Some(ipc_service);
+    pub fn set_ipc_service(mut ipc_service: IPCService) -> anyhow::Result<()> {
+        let mut state = Self::get()?;
+        ipc_service.set_learning_suspended(state.incognito);
+        state.ipc_service = Some(ipc_service);
         Ok(())
     }
 
@@ -269,6 +443,43 @@ impl IMEState {
         Ok(())
     }
 
+    /// Switches the remembered window to `window` and returns the input mode
+    /// to restore there, if it differs from the current one.
+    pub fn focus_window(
+        window: isize,
+        is_alive: impl Fn(isize) -> bool,
+    ) -> anyhow::Result<Option<InputMode>> {
+        let default = Self::app_config_snapshot()?.default_input_mode();
+        let mut state = Self::get()?;
+        let current = state.input_mode.clone();
+        Ok(state
+            .window_input_modes
+            .focus(window, &current, default, is_alive))
+    }
+
+    /// Applies the mode a field's input scope asks for, returning the input
+    /// mode to switch to, if it differs from the current one.
+    pub fn focus_input_scope(scope_mode: Option<InputMode>) -> anyhow::Result<Option<InputMode>> {
+        let mut state = Self::get()?;
+        let current = state.input_mode.clone();
+        Ok(state.window_input_modes.focus_scope(scope_mode, &current))
+    }
+
+    pub fn incognito() -> anyhow::Result<bool> {
+        Ok(Self::get()?.incognito)
+    }
+
+    /// Flips incognito and returns the new value.
+    pub fn toggle_incognito() -> anyhow::Result<bool> {
+        let mut state = Self::get()?;
+        state.incognito = !state.incognito;
+        let incognito = state.incognito;
+        if let Some(ipc_service) = state.ipc_service.as_mut() {
+            ipc_service.set_learning_suspended(incognito);
+        }
+        Ok(incognito)
+    }
+
     pub fn keyboard_disabled() -> anyhow::Result<bool> {
         Ok(Self::get()?.keyboard_disabled)
     }
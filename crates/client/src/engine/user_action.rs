@@ -1,12 +1,13 @@
-use crate::extension::VKeyExt;
+use crate::{engine::input_mode::InputMode, extension::VKeyExt};
 use anyhow::{Context, Result};
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    GetKeyboardState, ToUnicode, VK_CONTROL, VK_LCONTROL, VK_RCONTROL, VK_SHIFT,
-};
+use shared::keymap::KeyCommand;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyboardState, ToUnicode, VK_SHIFT};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum UserAction {
     Input(char),
+    /// A kana key on the JIS kana layout (かな入力).
+    Kana(char),
     Backspace,
     Delete,
     Enter,
@@ -20,11 +21,24 @@ pub enum UserAction {
     AdjustClauseBoundary(i32),
     Function(Function),
     NumpadSymbol(char),
-    Number { value: i8, is_numpad: bool },
+    Number {
+        value: i8,
+        is_numpad: bool,
+    },
     ToggleInputMode,
     InputModeOn,
     InputModeOff,
+    /// Switch to a mode other than ひらがな or 半角英数.
+    SelectInputMode(InputMode),
     Reconvert,
+    /// Forget the learned conversion of the highlighted candidate.
+    ForgetCandidate,
+    /// Reopen the text committed last as an editable composition.
+    UndoCommit,
+    /// Step through the shown predictions; a plain Tab when there are none.
+    SelectPrediction(i32),
+    /// Suspend or resume learning and context upload.
+    ToggleIncognito,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -44,24 +58,49 @@ pub enum Function {
     Ten,
 }
 
-impl Function {
-    pub(crate) fn from_ctrl_shortcut_key_code(key_code: usize) -> Option<Self> {
-        match key_code {
-            0x55 => Some(Self::Six),   // Ctrl+U
-            0x49 => Some(Self::Seven), // Ctrl+I
-            0x4F => Some(Self::Eight), // Ctrl+O
-            0x50 => Some(Self::Nine),  // Ctrl+P
-            0x54 => Some(Self::Ten),   // Ctrl+T
-            _ => None,
-        }
+impl UserAction {
+    /// The action a key map command stands for, or `None` when the key should
+    /// reach the application.
+    pub(crate) fn from_key_command(command: KeyCommand) -> Option<Self> {
+        let action = match command {
+            KeyCommand::PassThrough => return None,
+            KeyCommand::Commit => UserAction::Enter,
+            KeyCommand::CommitFirstClause => UserAction::CommitFirstClause,
+            KeyCommand::CommitAndNextClause => UserAction::CommitAndNextClause,
+            KeyCommand::Convert => UserAction::Space,
+            KeyCommand::Cancel => UserAction::Escape,
+            KeyCommand::Backspace => UserAction::Backspace,
+            KeyCommand::Delete => UserAction::Delete,
+            KeyCommand::ForgetCandidate => UserAction::ForgetCandidate,
+            KeyCommand::MoveLeft => UserAction::Navigation(Navigation::Left),
+            KeyCommand::MoveRight => UserAction::Navigation(Navigation::Right),
+            KeyCommand::MoveUp => UserAction::Navigation(Navigation::Up),
+            KeyCommand::MoveDown => UserAction::Navigation(Navigation::Down),
+            KeyCommand::ShrinkClause => UserAction::AdjustClauseBoundary(-1),
+            KeyCommand::ExpandClause => UserAction::AdjustClauseBoundary(1),
+            KeyCommand::ToHiragana => UserAction::Function(Function::Six),
+            KeyCommand::ToKatakana => UserAction::Function(Function::Seven),
+            KeyCommand::ToHalfKatakana => UserAction::Function(Function::Eight),
+            KeyCommand::ToFullLatin => UserAction::Function(Function::Nine),
+            KeyCommand::ToHalfLatin => UserAction::Function(Function::Ten),
+            KeyCommand::ToggleInputMode => UserAction::ToggleInputMode,
+            KeyCommand::InputModeOn => UserAction::InputModeOn,
+            KeyCommand::InputModeOff => UserAction::InputModeOff,
+            KeyCommand::InputModeKatakana => UserAction::SelectInputMode(InputMode::Katakana),
+            KeyCommand::InputModeHalfKatakana => {
+                UserAction::SelectInputMode(InputMode::HalfKatakana)
+            }
+            KeyCommand::InputModeFullLatin => UserAction::SelectInputMode(InputMode::FullLatin),
+            KeyCommand::Reconvert => UserAction::Reconvert,
+            KeyCommand::UndoCommit => UserAction::UndoCommit,
+            KeyCommand::NextPrediction => UserAction::SelectPrediction(1),
+            KeyCommand::PreviousPrediction => UserAction::SelectPrediction(-1),
+            KeyCommand::ToggleIncognito => UserAction::ToggleIncognito,
+        };
+        Some(action)
     }
 }
 
-#[inline]
-fn is_ctrl_pressed() -> bool {
-    VK_CONTROL.is_pressed() || VK_LCONTROL.is_pressed() || VK_RCONTROL.is_pressed()
-}
-
 fn clear_dead_key_state(key_state: &[u8; 256]) {
     let mut unicode = [0u16; 8];
     // Use VK_SPACE to clear dead-key state left by ToUnicode.
@@ -75,13 +114,7 @@ impl TryFrom<usize> for UserAction {
             0x08 => UserAction::Backspace, // VK_BACK
             0x09 => UserAction::Tab,       // VK_TAB
             0x0D => UserAction::Enter,     // VK_RETURN
-            0x20 => {
-                if is_ctrl_pressed() {
-                    UserAction::ToggleInputMode
-                } else {
-                    UserAction::Space
-                }
-            } // VK_SPACE
+            0x20 => UserAction::Space,     // VK_SPACE
             0x1B => UserAction::Escape,    // VK_ESCAPE
             0x2E => UserAction::Delete,    // VK_DELETE
 
@@ -225,31 +258,44 @@ impl TryFrom<usize> for UserAction {
 
 #[cfg(test)]
 mod tests {
-    use super::{Function, UserAction};
+    use super::{Function, InputMode, Navigation, UserAction};
+    use shared::keymap::KeyCommand;
 
     #[test]
-    fn ctrl_shortcut_keys_map_to_conversion_functions() {
+    fn key_commands_map_to_user_actions() {
+        assert_eq!(UserAction::from_key_command(KeyCommand::PassThrough), None);
+        assert_eq!(
+            UserAction::from_key_command(KeyCommand::Convert),
+            Some(UserAction::Space)
+        );
+        assert_eq!(
+            UserAction::from_key_command(KeyCommand::ShrinkClause),
+            Some(UserAction::AdjustClauseBoundary(-1))
+        );
+        assert_eq!(
+            UserAction::from_key_command(KeyCommand::MoveDown),
+            Some(UserAction::Navigation(Navigation::Down))
+        );
         assert_eq!(
-            Function::from_ctrl_shortcut_key_code(0x55),
-            Some(Function::Six)
+            UserAction::from_key_command(KeyCommand::ToHalfLatin),
+            Some(UserAction::Function(Function::Ten))
         );
         assert_eq!(
-            Function::from_ctrl_shortcut_key_code(0x49),
-            Some(Function::Seven)
+            UserAction::from_key_command(KeyCommand::InputModeHalfKatakana),
+            Some(UserAction::SelectInputMode(InputMode::HalfKatakana))
         );
         assert_eq!(
-            Function::from_ctrl_shortcut_key_code(0x4F),
-            Some(Function::Eight)
+            UserAction::from_key_command(KeyCommand::UndoCommit),
+            Some(UserAction::UndoCommit)
         );
         assert_eq!(
-            Function::from_ctrl_shortcut_key_code(0x50),
-            Some(Function::Nine)
+            UserAction::from_key_command(KeyCommand::PreviousPrediction),
+            Some(UserAction::SelectPrediction(-1))
         );
         assert_eq!(
-            Function::from_ctrl_shortcut_key_code(0x54),
-            Some(Function::Ten)
+            UserAction::from_key_command(KeyCommand::ToggleIncognito),
+            Some(UserAction::ToggleIncognito)
         );
-        assert_eq!(Function::from_ctrl_shortcut_key_code(0x41), None);
     }
 
     #[test]
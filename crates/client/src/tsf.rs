@@ -1,3 +1,4 @@
+pub(super) mod candidate_refinement;
 pub(super) mod display_attribute;
 pub(super) mod edit_session;
 pub(super) mod factory;
@@ -0,0 +1,226 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
+};
+
+use windows::{
+    core::{w, AsImpl as _, PCWSTR},
+    Win32::{
+        Foundation::{
+            GetLastError, ERROR_CLASS_ALREADY_EXISTS, HINSTANCE, HWND, LPARAM, LRESULT, WPARAM,
+        },
+        UI::{
+            TextServices::ITfTextInputProcessor,
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, PostMessageW, RegisterClassW,
+                UnregisterClassW, HMENU, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP,
+                WNDCLASSW,
+            },
+        },
+    },
+};
+
+use anyhow::{Context as _, Result};
+
+use crate::{engine::ipc_service::Candidates, extension::StringExt as _, globals::DllModule};
+
+const WM_REFINED_CANDIDATES: u32 = WM_APP + 1;
+
+static REFINEMENT_WINDOW_CLASS_SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    // Text service that owns each refinement window created on this thread.
+    static REFINEMENT_TARGETS: RefCell<HashMap<isize, RefinementTarget>> =
+        RefCell::new(HashMap::new());
+}
+
+/// The text service a refinement window delivers to, and the refinements
+/// posted to the window. Dropping the receiver with the window frees the
+/// refinements that were never delivered.
+struct RefinementTarget {
+    this: ITfTextInputProcessor,
+    refinements: mpsc::Receiver<RefinedCandidates>,
+}
+
+/// Zenzai-refined candidates together with the dictionary candidates they
+/// replace. The refinement only applies while the composition still shows
+/// `base`.
+#[derive(Debug)]
+pub(crate) struct RefinedCandidates {
+    pub base: Candidates,
+    pub refined: Candidates,
+}
+
+/// Message-only window that carries refined candidates from the IPC runtime
+/// back to the TSF thread, which is the only thread allowed to edit the
+/// composition. Created in `Activate` and destroyed in `Deactivate`.
+///
+/// The candidates travel through a channel; the window message only wakes the
+/// TSF thread up to take them.
+#[derive(Debug)]
+pub(crate) struct RefinementWindow {
+    hwnd: HWND,
+    class_name: Vec<u16>,
+    hinstance: HINSTANCE,
+    refinements: mpsc::Sender<RefinedCandidates>,
+}
+
+impl RefinementWindow {
+    pub(crate) fn create(this: ITfTextInputProcessor) -> Result<Self> {
+        let hmodule = DllModule::module_handle()?;
+        let hinstance = HINSTANCE(hmodule.0);
+
+        unsafe {
+            for _ in 0..32 {
+                let sequence = REFINEMENT_WINDOW_CLASS_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+                let class_name = format!(
+                    "AzookeyCandidateRefinement-{}-{:#x}-{}",
+                    std::process::id(),
+                    hmodule.0 as usize,
+                    sequence
+                )
+                .as_str()
+                .to_wide_16();
+                let window_class = WNDCLASSW {
+                    lpfnWndProc: Some(refinement_window_proc),
+                    hInstance: hinstance,
+                    lpszClassName: PCWSTR(class_name.as_ptr()),
+                    ..Default::default()
+                };
+
+                if RegisterClassW(&window_class) == 0 {
+                    let error = GetLastError();
+                    if error == ERROR_CLASS_ALREADY_EXISTS {
+                        continue;
+                    }
+                    anyhow::bail!("Failed to register refinement window class: {:?}", error);
+                }
+
+                let hwnd = match CreateWindowExW(
+                    WINDOW_EX_STYLE::default(),
+                    PCWSTR(class_name.as_ptr()),
+                    w!(""),
+                    WINDOW_STYLE::default(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    HWND_MESSAGE,
+                    HMENU::default(),
+                    hinstance,
+                    None,
+                ) {
+                    Ok(hwnd) => hwnd,
+                    Err(error) => {
+                        let _ = UnregisterClassW(PCWSTR(class_name.as_ptr()), hinstance);
+                        return Err(error).context("Failed to create refinement window");
+                    }
+                };
+
+                let (sender, receiver) = mpsc::channel();
+                REFINEMENT_TARGETS.with(|targets| {
+                    targets.borrow_mut().insert(
+                        hwnd.0 as isize,
+                        RefinementTarget {
+                            this,
+                            refinements: receiver,
+                        },
+                    );
+                });
+                return Ok(Self {
+                    hwnd,
+                    class_name,
+                    hinstance,
+                    refinements: sender,
+                });
+            }
+        }
+
+        anyhow::bail!("Failed to register unique refinement window class")
+    }
+
+    pub(crate) fn sender(&self) -> RefinementSender {
+        RefinementSender {
+            hwnd: self.hwnd.0 as isize,
+            refinements: self.refinements.clone(),
+        }
+    }
+}
+
+impl Drop for RefinementWindow {
+    fn drop(&mut self) {
+        REFINEMENT_TARGETS.with(|targets| {
+            targets.borrow_mut().remove(&(self.hwnd.0 as isize));
+        });
+        unsafe {
+            let _ = DestroyWindow(self.hwnd);
+            let _ = UnregisterClassW(PCWSTR(self.class_name.as_ptr()), self.hinstance);
+        }
+    }
+}
+
+/// Thread-safe handle for posting to a [`RefinementWindow`]. `HWND` itself is
+/// not `Send`, so the handle is carried as an integer.
+#[derive(Clone, Debug)]
+pub(crate) struct RefinementSender {
+    hwnd: isize,
+    refinements: mpsc::Sender<RefinedCandidates>,
+}
+
+impl RefinementSender {
+    pub(crate) fn post(self, refinement: RefinedCandidates) {
+        if self.refinements.send(refinement).is_err() {
+            tracing::debug!("Dropping Zenzai refinement for a closed window");
+            return;
+        }
+        let result = unsafe {
+            PostMessageW(
+                HWND(self.hwnd as _),
+                WM_REFINED_CANDIDATES,
+                WPARAM(0),
+                LPARAM(0),
+            )
+        };
+        if let Err(error) = result {
+            // The window is gone (the text service was deactivated); the
+            // refinement is freed with the window's receiver.
+            tracing::debug!(?error, "Dropping Zenzai refinement for a closed window");
+        }
+    }
+}
+
+unsafe extern "system" fn refinement_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_REFINED_CANDIDATES {
+        return unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) };
+    }
+
+    // Take the refinements before applying them, so that applying one never
+    // runs with the targets borrowed.
+    let target = REFINEMENT_TARGETS.with(|targets| {
+        targets.borrow().get(&(hwnd.0 as isize)).map(|target| {
+            (
+                target.this.clone(),
+                target.refinements.try_iter().collect::<Vec<_>>(),
+            )
+        })
+    });
+    let Some((this, refinements)) = target else {
+        return LRESULT(0);
+    };
+    let factory = unsafe { this.as_impl() };
+    for refinement in refinements {
+        if let Err(error) = factory.apply_refined_candidates(refinement) {
+            tracing::warn!(?error, "Failed to apply Zenzai refinement");
+        }
+    }
+    LRESULT(0)
+}
@@ -591,6 +591,7 @@ impl TextServiceFactory {
         let context = context.clone();
         let context_composition = context.cast::<ITfContextComposition>()?;
         let expected_text = expected_text.to_string();
+        let displayed_text = expected_text.clone();
         let composition = write_edit_session(
             tid,
             context.clone(),
@@ -628,7 +629,83 @@ impl TextServiceFactory {
             return Ok(false);
         };
         let mut text_service = self.borrow_mut()?;
-        text_service.borrow_mut_composition()?.tip_composition = Some(composition);
+        {
+            let mut current = text_service.borrow_mut_composition()?;
+            current.tip_composition = Some(composition);
+            current.displayed_text = displayed_text;
+            current.displayed_sub_text.clear();
+        }
+        text_service.invalidate_mode_switch_requests();
+        Ok(true)
+    }
+
+    /// Starts a composition over `expected_text` when it sits right before an
+    /// empty selection, as it does straight after that text was committed.
+    pub(crate) fn start_composition_before_caret(
+        &self,
+        context: &ITfContext,
+        expected_text: &str,
+    ) -> Result<bool> {
+        let (tid, sink) = {
+            let text_service = self.borrow()?;
+            (text_service.tid, text_service.this::<ITfCompositionSink>()?)
+        };
+        let context = context.clone();
+        let context_composition = context.cast::<ITfContextComposition>()?;
+        let expected_text = expected_text.to_string();
+        let displayed_text = expected_text.clone();
+        let text_len = utf16_code_unit_len(&expected_text)?;
+        let composition = write_edit_session(
+            tid,
+            context.clone(),
+            Rc::new(move |cookie| {
+                let mut selection: [TF_SELECTION; 1] = [TF_SELECTION::default()];
+                let mut fetched = 0;
+                unsafe {
+                    context.GetSelection(
+                        cookie,
+                        TF_DEFAULT_SELECTION,
+                        &mut selection,
+                        &mut fetched,
+                    )?;
+                }
+                let Some(caret) = (fetched > 0).then(|| selection[0].range.as_ref()).flatten()
+                else {
+                    return Ok(None);
+                };
+                if text_len == 0 || !unsafe { caret.IsEmpty(cookie)? }.as_bool() {
+                    return Ok(None);
+                }
+                let range = unsafe { caret.Clone()? };
+                let mut shifted = 0;
+                unsafe {
+                    range.Collapse(cookie, TF_ANCHOR_START)?;
+                    range.ShiftStart(cookie, -text_len, &mut shifted, std::ptr::null())?;
+                }
+                if shifted != -text_len {
+                    return Ok(None);
+                }
+                let readable_range = unsafe { range.Clone()? };
+                let current_text = read_all_range_text(cookie, &readable_range)?;
+                if String::from_utf16(&current_text).ok().as_deref() != Some(&expected_text) {
+                    return Ok(None);
+                }
+                let composition =
+                    unsafe { context_composition.StartComposition(cookie, &range, &sink)? };
+                Ok(Some(composition))
+            }),
+        )?;
+
+        let Some(composition) = composition else {
+            return Ok(false);
+        };
+        let mut text_service = self.borrow_mut()?;
+        {
+            let mut current = text_service.borrow_mut_composition()?;
+            current.tip_composition = Some(composition);
+            current.displayed_text = displayed_text;
+            current.displayed_sub_text.clear();
+        }
         text_service.invalidate_mode_switch_requests();
         Ok(true)
     }
@@ -651,6 +728,7 @@ impl TextServiceFactory {
         let context_composition = context.cast::<ITfContextComposition>()?;
         let range = unsafe { range.Clone()? };
         let expected_text = expected_text.to_string();
+        let displayed_text = expected_text.clone();
         let composition = write_edit_session(
             tid,
             context,
@@ -674,7 +752,12 @@ impl TextServiceFactory {
             return Ok(false);
         };
         let mut text_service = self.borrow_mut()?;
-        text_service.borrow_mut_composition()?.tip_composition = Some(composition);
+        {
+            let mut current = text_service.borrow_mut_composition()?;
+            current.tip_composition = Some(composition);
+            current.displayed_text = displayed_text;
+            current.displayed_sub_text.clear();
+        }
         text_service.invalidate_mode_switch_requests();
         Ok(true)
     }
@@ -845,7 +928,12 @@ impl TextServiceFactory {
         )?;
 
         tracing::debug!("Composition started {composition:?}");
-        text_service.borrow_mut_composition()?.tip_composition = Some(composition);
+        {
+            let mut current = text_service.borrow_mut_composition()?;
+            current.tip_composition = Some(composition);
+            current.displayed_text.clear();
+            current.displayed_sub_text.clear();
+        }
         // An idle mode-switch request may still be waiting for its asynchronous caret read.
         // Starting a composition makes that request stale even if this composition ends before
         // the callback arrives.
@@ -876,7 +964,8 @@ impl TextServiceFactory {
     pub fn set_text(&self, text: &str, subtext: &str) -> Result<()> {
         let text_service = self.borrow()?;
 
-        if let Some(composition) = text_service.borrow_composition()?.tip_composition.clone() {
+        let tip_composition = text_service.borrow_composition()?.tip_composition.clone();
+        if let Some(composition) = tip_composition {
             write_edit_session(
                 text_service.tid,
                 text_service.context()?,
@@ -932,6 +1021,9 @@ impl TextServiceFactory {
                     }
                 }),
             )?;
+            let mut current = text_service.borrow_mut_composition()?;
+            current.displayed_text = text.to_string();
+            current.displayed_sub_text = subtext.to_string();
         } else {
             tracing::warn!("Composition is not started");
         }
@@ -939,11 +1031,43 @@ impl TextServiceFactory {
         Ok(())
     }
 
+    /// Text and subtext last written under the current composition, empty
+    /// without one.
+    pub(crate) fn displayed_composition(&self) -> Result<(String, String)> {
+        let text_service = self.borrow()?;
+        let composition = text_service.borrow_composition()?;
+        if composition.tip_composition.is_none() {
+            return Ok(Default::default());
+        }
+        Ok((
+            composition.displayed_text.clone(),
+            composition.displayed_sub_text.clone(),
+        ))
+    }
+
+    /// The document text under the current composition, or `None` without one.
+    pub(crate) fn composition_text(&self) -> Result<Option<String>> {
+        let text_service = self.borrow()?;
+        let Some(composition) = text_service.borrow_composition()?.tip_composition.clone() else {
+            return Ok(None);
+        };
+        let text = read_edit_session(
+            text_service.tid,
+            text_service.context()?,
+            Rc::new(move |cookie| {
+                let range = unsafe { composition.GetRange()? };
+                read_all_range_text(cookie, &range)
+            }),
+        )?;
+        Ok(String::from_utf16(&text).ok())
+    }
+
     #[tracing::instrument]
     pub fn shift_start(&self, text: &str, subtext: &str) -> Result<()> {
         let text_service = self.borrow()?;
 
-        if let Some(composition) = text_service.borrow_composition()?.tip_composition.clone() {
+        let tip_composition = text_service.borrow_composition()?.tip_composition.clone();
+        if let Some(composition) = tip_composition {
             write_edit_session(
                 text_service.tid,
                 text_service.context()?,
@@ -1012,6 +1136,9 @@ impl TextServiceFactory {
                     }
                 }),
             )?;
+            let mut current = text_service.borrow_mut_composition()?;
+            current.displayed_text = subtext.to_string();
+            current.displayed_sub_text.clear();
         } else {
             tracing::warn!("Composition is not started");
         }
@@ -86,8 +86,16 @@ impl ITfFnReconversion_Impl for TextServiceFactory_Impl {
             return Ok(());
         }
 
+        // An empty range stands for the caret; offer the phrase around it.
+        let caret_phrase = unsafe { range.GetContext() }
+            .ok()
+            .and_then(|context| self.caret_phrase_range(&context, range).ok().flatten());
+        let new_range = match caret_phrase {
+            Some(phrase) => phrase,
+            None => unsafe { range.Clone()? },
+        };
         if !ppnewrange.is_null() {
-            unsafe { ppnewrange.write(Some(range.Clone()?)) };
+            unsafe { ppnewrange.write(Some(new_range)) };
         }
         unsafe { pfconvertable.write(true.into()) };
         Ok(())
@@ -30,8 +30,8 @@ use windows::{
                 AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu,
                 DestroyWindow, LoadImageW, PostMessageW, RegisterClassW, SetForegroundWindow,
                 TrackPopupMenu, UnregisterClassW, HICON, HMENU, IMAGE_ICON, LR_DEFAULTCOLOR,
-                MF_STRING, SW_SHOWNORMAL, TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_NULL,
-                WNDCLASSW, WS_EX_TOOLWINDOW, WS_POPUP,
+                MF_CHECKED, MF_SEPARATOR, MF_STRING, SW_SHOWNORMAL, TPM_NONOTIFY, TPM_RETURNCMD,
+                TPM_RIGHTBUTTON, WM_NULL, WNDCLASSW, WS_EX_TOOLWINDOW, WS_POPUP,
             },
         },
     },
@@ -58,6 +58,8 @@ const INFO: TF_LANGBARITEMINFO = TF_LANGBARITEMINFO {
 
 const SETTINGS_MENU_ID: usize = 1;
 const RESTART_SERVER_MENU_ID: usize = 2;
+const INCOGNITO_MENU_ID: usize = 3;
+const INPUT_MODE_MENU_ID_BASE: usize = 16;
 const SETTINGS_APP_DIRNAME: &str = "Azookey";
 const SETTINGS_APP_FILENAME: &str = "frontend.exe";
 const SETTINGS_APP_INNO_UNINSTALL_SUBKEY: PCWSTR = w!(
@@ -73,10 +75,21 @@ static MENU_OWNER_WINDOW_CLASS_SEQUENCE: AtomicU32 = AtomicU32::new(0);
 fn toggled_input_mode(mode: InputMode) -> InputMode {
     match mode {
         InputMode::Latin => InputMode::Kana,
-        InputMode::Kana => InputMode::Latin,
+        _ => InputMode::Latin,
     }
 }
 
+fn input_mode_menu_id(index: usize) -> usize {
+    INPUT_MODE_MENU_ID_BASE + index
+}
+
+fn input_mode_for_menu_id(command: u32) -> Option<InputMode> {
+    (command as usize)
+        .checked_sub(INPUT_MODE_MENU_ID_BASE)
+        .and_then(|index| InputMode::ALL.get(index))
+        .cloned()
+}
+
 // you need to implement these three interfaces to create a language bar item
 // if not, you will get E_FAIL error in ITfLangBarItemMgr::AddItem
 
@@ -114,14 +127,57 @@ impl TextServiceFactory_Impl {
         self.request_language_bar_input_mode_toggle(mode)
     }
 
+    fn select_input_mode(&self, mode: InputMode) -> Result<()> {
+        if !Self::ensure_ipc_service_for_language_bar_event("select_input_mode") {
+            return Ok(());
+        }
+
+        self.request_language_bar_input_mode_selection(mode)
+    }
+
+    fn toggle_incognito(&self) -> Result<()> {
+        let incognito = IMEState::toggle_incognito()?;
+        self.update_lang_bar()?;
+
+        if let Some(mut ipc_service) = IMEState::ipc_service()? {
+            let label = IMEState::input_mode()?.indicator_text(incognito);
+            ipc_service.update_candidate_window_with_reading(
+                None,
+                None,
+                None,
+                None,
+                Some(&label),
+                None,
+                None,
+                None,
+            )?;
+            IMEState::set_ipc_service(ipc_service)?;
+        }
+
+        Ok(())
+    }
+
     fn handle_right_click(&self, pt: &POINT) -> Result<()> {
-        match show_settings_menu(pt) {
+        let pending_mode = { self.borrow()?.pending_mode_switch() };
+        let current_mode = match pending_mode {
+            Some(mode) => mode,
+            None => IMEState::input_mode()?,
+        };
+        match show_settings_menu(pt, &current_mode, IMEState::incognito()?) {
+            Ok(Some(command)) if input_mode_for_menu_id(command).is_some() => {
+                if let Some(mode) = input_mode_for_menu_id(command) {
+                    self.select_input_mode(mode)?;
+                }
+            }
             Ok(Some(command)) if command == SETTINGS_MENU_ID as u32 => {
                 launch_settings_app_with_logging();
             }
             Ok(Some(command)) if command == RESTART_SERVER_MENU_ID as u32 => {
                 restart_server_with_logging();
             }
+            Ok(Some(command)) if command == INCOGNITO_MENU_ID as u32 => {
+                self.toggle_incognito()?;
+            }
             Ok(_) => {}
             Err(error) => {
                 tracing::warn!(?error, "Failed to show settings menu");
@@ -199,18 +255,18 @@ impl ITfLangBarItemButton_Impl for TextServiceFactory_Impl {
         let theme = get_theme()?;
 
         let icon_id = match input_mode {
-            InputMode::Kana => {
+            InputMode::Latin => {
                 if theme {
-                    102
+                    103
                 } else {
-                    104
+                    105
                 }
             }
-            InputMode::Latin => {
+            _ => {
                 if theme {
-                    103
+                    102
                 } else {
-                    105
+                    104
                 }
             }
         };
@@ -262,7 +318,11 @@ impl Drop for MenuOwnerWindow {
     }
 }
 
-fn show_settings_menu(pt: &POINT) -> Result<Option<u32>> {
+fn show_settings_menu(
+    pt: &POINT,
+    current_mode: &InputMode,
+    incognito: bool,
+) -> Result<Option<u32>> {
     struct PopupMenu(HMENU);
 
     impl Drop for PopupMenu {
@@ -276,6 +336,31 @@ fn show_settings_menu(pt: &POINT) -> Result<Option<u32>> {
     unsafe {
         let owner = create_menu_owner_window()?;
         let menu = PopupMenu(CreatePopupMenu()?);
+        for (index, mode) in InputMode::ALL.iter().enumerate() {
+            let label = mode.menu_label().to_wide_16();
+            let flags = if mode == current_mode {
+                MF_STRING | MF_CHECKED
+            } else {
+                MF_STRING
+            };
+            AppendMenuW(
+                menu.0,
+                flags,
+                input_mode_menu_id(index),
+                PCWSTR(label.as_ptr()),
+            )?;
+        }
+        AppendMenuW(menu.0, MF_SEPARATOR, 0, PCWSTR::null())?;
+        AppendMenuW(
+            menu.0,
+            if incognito {
+                MF_STRING | MF_CHECKED
+            } else {
+                MF_STRING
+            },
+            INCOGNITO_MENU_ID,
+            w!("シークレットモード（学習しない）"),
+        )?;
         AppendMenuW(menu.0, MF_STRING, SETTINGS_MENU_ID, w!("設定"))?;
         AppendMenuW(
             menu.0,
@@ -719,8 +804,9 @@ impl ITfSource_Impl for TextServiceFactory_Impl {
 #[cfg(test)]
 mod tests {
     use super::{
+        input_mode_for_menu_id, input_mode_menu_id,
         resolve_settings_app_path_from_install_location, select_existing_settings_app_path,
-        toggled_input_mode, trim_registry_string, SettingsAppPath,
+        toggled_input_mode, trim_registry_string, SettingsAppPath, SETTINGS_MENU_ID,
     };
     use crate::engine::input_mode::InputMode;
     use crate::launcher_control::parse_launcher_response;
@@ -829,4 +915,25 @@ mod tests {
 
         assert_eq!(second_target, InputMode::Latin);
     }
+
+    #[test]
+    fn toggling_from_any_japanese_mode_turns_the_ime_off() {
+        assert_eq!(toggled_input_mode(InputMode::Katakana), InputMode::Latin);
+        assert_eq!(toggled_input_mode(InputMode::FullLatin), InputMode::Latin);
+    }
+
+    #[test]
+    fn input_mode_menu_ids_round_trip() {
+        for (index, mode) in InputMode::ALL.iter().enumerate() {
+            assert_eq!(
+                input_mode_for_menu_id(input_mode_menu_id(index) as u32).as_ref(),
+                Some(mode)
+            );
+        }
+        assert_eq!(input_mode_for_menu_id(SETTINGS_MENU_ID as u32), None);
+        assert_eq!(
+            input_mode_for_menu_id(input_mode_menu_id(InputMode::ALL.len()) as u32),
+            None
+        );
+    }
 }
@@ -7,21 +7,147 @@ use anyhow::Result;
 use windows::{
     core::{IUnknown, Interface},
     Win32::UI::TextServices::{
-        ITfCompartmentMgr, ITfContext, ITfDocumentMgr, GUID_COMPARTMENT_TRANSITORYEXTENSION_PARENT,
-        TF_ANCHOR_START, TF_DEFAULT_SELECTION, TF_HALTCOND, TF_HF_OBJECT, TF_SELECTION,
+        ITfCompartmentMgr, ITfContext, ITfDocumentMgr, ITfRange,
+        GUID_COMPARTMENT_TRANSITORYEXTENSION_PARENT, TF_AE_NONE, TF_ANCHOR_END, TF_ANCHOR_START,
+        TF_DEFAULT_SELECTION, TF_HALTCOND, TF_HF_OBJECT, TF_SELECTION, TF_SELECTIONSTYLE,
         TF_TF_MOVESTART, TS_SS_TRANSITORY,
     },
 };
 
 use crate::{
-    engine::{ipc_service::current_input_trace_request_id, state::IMEState},
+    engine::{
+        composition::MAX_RECONVERSION_SURFACE_COUNT, ipc_service::current_input_trace_request_id,
+        state::IMEState,
+    },
     extension::utf16_code_unit_len,
 };
 
-use super::{edit_session::read_edit_session, factory::TextServiceFactory};
+use super::{
+    edit_session::{read_edit_session, write_edit_session},
+    factory::TextServiceFactory,
+};
+
+/// UTF-16 code units of document text sent on each side of the composition.
+const SURROUNDING_CONTEXT_CODE_UNITS: i32 = 30;
+
+/// `before_caret` without the composition's displayed `text`, which ends at
+/// the caret. Measured in UTF-16 code units, as TSF ranges are.
+pub(crate) fn text_before_composition(before_caret: &str, text: &str) -> String {
+    let mut units = before_caret.encode_utf16().collect::<Vec<_>>();
+    units.truncate(units.len().saturating_sub(text.encode_utf16().count()));
+    String::from_utf16_lossy(&units)
+}
+
+/// `after_caret` without the composition's displayed `sub_text`, which starts
+/// at the caret. Measured in UTF-16 code units, as TSF ranges are.
+pub(crate) fn text_after_composition(after_caret: &str, sub_text: &str) -> String {
+    let units = after_caret.encode_utf16().collect::<Vec<_>>();
+    let start = sub_text.encode_utf16().count().min(units.len());
+    String::from_utf16_lossy(&units[start..])
+}
+
+/// Kanji and kana, the characters a phrase at the caret is made of.
+fn is_phrase_character(character: char) -> bool {
+    matches!(
+        character,
+        '\u{3041}'..='\u{3096}'
+            | '\u{309D}'..='\u{309F}'
+            | '\u{30A1}'..='\u{30FA}'
+            | '\u{30FC}'..='\u{30FF}'
+            | '々'
+            | '〆'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{3134F}'
+    )
+}
+
+/// The part of `before` and `after` the caret that forms the kanji/kana run
+/// around it, at most `max_chars` long and favouring the text before the
+/// caret. `None` when the caret does not touch such a run.
+fn caret_phrase_extent<'a>(
+    before: &'a str,
+    after: &'a str,
+    max_chars: usize,
+) -> Option<(&'a str, &'a str)> {
+    let before_start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, character)| is_phrase_character(*character))
+        .take(max_chars)
+        .last()
+        .map_or(before.len(), |(index, _)| index);
+    let before = &before[before_start..];
+    let after_end = after
+        .char_indices()
+        .take_while(|(_, character)| is_phrase_character(*character))
+        .take(max_chars - before.chars().count())
+        .last()
+        .map_or(0, |(index, character)| index + character.len_utf8());
+    let after = &after[..after_end];
+    (!before.is_empty() || !after.is_empty()).then_some((before, after))
+}
 
-fn preview_end_shift(preview: &str) -> anyhow::Result<i32> {
-    Ok(-utf16_code_unit_len(preview)?)
+fn read_range_text(cookie: u32, range: &ITfRange, max_code_units: usize) -> Result<String> {
+    let range = unsafe { range.Clone()? };
+    let mut text = vec![0u16; max_code_units];
+    let mut length = 0;
+    unsafe { range.GetText(cookie, TF_TF_MOVESTART, &mut text, &mut length)? };
+    text.truncate(length as usize);
+    Ok(String::from_utf16_lossy(&text))
+}
+
+/// The kanji/kana run around an empty `caret` range.
+fn caret_phrase_range_for_cookie(cookie: u32, caret: &ITfRange) -> Result<Option<ITfRange>> {
+    if !unsafe { caret.IsEmpty(cookie)? }.as_bool() {
+        return Ok(None);
+    }
+    // Surrogate pairs take two code units per character.
+    let window = MAX_RECONVERSION_SURFACE_COUNT * 2;
+    let halt_cond = TF_HALTCOND {
+        pHaltRange: ManuallyDrop::new(None),
+        aHaltPos: TF_ANCHOR_START,
+        dwFlags: TF_HF_OBJECT,
+    };
+    let mut shifted = 0;
+    let preceding_range = unsafe { caret.Clone()? };
+    unsafe { preceding_range.ShiftStart(cookie, -(window as i32), &mut shifted, &halt_cond)? };
+    let following_range = unsafe { caret.Clone()? };
+    unsafe { following_range.ShiftEnd(cookie, window as i32, &mut shifted, &halt_cond)? };
+    let before = read_range_text(cookie, &preceding_range, window)?;
+    let after = read_range_text(cookie, &following_range, window)?;
+
+    let Some((before, after)) =
+        caret_phrase_extent(&before, &after, MAX_RECONVERSION_SURFACE_COUNT)
+    else {
+        return Ok(None);
+    };
+    let phrase = unsafe { caret.Clone()? };
+    unsafe {
+        phrase.ShiftStart(
+            cookie,
+            -utf16_code_unit_len(before)?,
+            &mut shifted,
+            std::ptr::null(),
+        )?;
+        phrase.ShiftEnd(
+            cookie,
+            utf16_code_unit_len(after)?,
+            &mut shifted,
+            std::ptr::null(),
+        )?;
+    }
+    Ok(Some(phrase))
+}
+
+fn default_selection(cookie: u32, context: &ITfContext) -> Result<Option<ITfRange>> {
+    let mut selection: [TF_SELECTION; 1] = [TF_SELECTION::default()];
+    let mut fetched = 0;
+    unsafe { context.GetSelection(cookie, TF_DEFAULT_SELECTION, &mut selection, &mut fetched)? };
+    Ok((fetched > 0)
+        .then(|| selection[0].range.as_ref().cloned())
+        .flatten())
 }
 
 impl TextServiceFactory {
@@ -129,7 +255,70 @@ impl TextServiceFactory {
         }
     }
 
-    pub fn update_context(&self, preview: &str) -> Result<()> {
+    /// The phrase reconversion would take for an empty `caret` range.
+    pub(crate) fn caret_phrase_range(
+        &self,
+        context: &ITfContext,
+        caret: &ITfRange,
+    ) -> Result<Option<ITfRange>> {
+        let tid = self.borrow()?.tid;
+        let caret = unsafe { caret.Clone()? };
+        read_edit_session(
+            tid,
+            context.clone(),
+            Rc::new(move |cookie| caret_phrase_range_for_cookie(cookie, &caret)),
+        )
+    }
+
+    /// Whether the caret, with nothing selected, touches a kanji/kana run.
+    pub(crate) fn has_caret_phrase(&self, context: &ITfContext) -> Result<bool> {
+        let tid = self.borrow()?.tid;
+        let context = context.clone();
+        read_edit_session(
+            tid,
+            context.clone(),
+            Rc::new(move |cookie| {
+                let Some(caret) = default_selection(cookie, &context)? else {
+                    return Ok(false);
+                };
+                Ok(caret_phrase_range_for_cookie(cookie, &caret)?.is_some())
+            }),
+        )
+    }
+
+    /// Selects the kanji/kana run at the caret, as MS-IME does before
+    /// reconverting without a selection. Returns whether anything was selected.
+    pub(crate) fn select_caret_phrase(&self, context: &ITfContext) -> Result<bool> {
+        let tid = self.borrow()?.tid;
+        let context = context.clone();
+        write_edit_session(
+            tid,
+            context.clone(),
+            Rc::new(move |cookie| {
+                let Some(caret) = default_selection(cookie, &context)? else {
+                    return Ok(false);
+                };
+                let Some(phrase) = caret_phrase_range_for_cookie(cookie, &caret)? else {
+                    return Ok(false);
+                };
+                let selection = TF_SELECTION {
+                    range: ManuallyDrop::new(Some(phrase)),
+                    style: TF_SELECTIONSTYLE {
+                        ase: TF_AE_NONE,
+                        fInterimChar: false.into(),
+                    },
+                };
+                unsafe { context.SetSelection(cookie, &[selection])? };
+                Ok(true)
+            }),
+        )
+    }
+
+    /// Sends the text before and after the composition to the server,
+    /// together with whether the application's profile turned Zenzai off.
+    /// `text` and `sub_text` are the composition text in the document before
+    /// and after the caret. Incognito keeps the text on this side.
+    pub fn update_context(&self, text: &str, sub_text: &str, zenzai_disabled: bool) -> Result<()> {
         let trace_request_id = current_input_trace_request_id();
         let total_start = trace_request_id.map(|_| Instant::now());
         let result: Result<()> = (|| unsafe {
@@ -141,8 +330,11 @@ impl TextServiceFactory {
             };
 
             let edit_session_start = trace_request_id.map(|_| Instant::now());
-            let preview_end_shift = preview_end_shift(preview)?;
-            let preceding_text = read_edit_session::<String>(
+            let text = text.to_string();
+            let text_len = utf16_code_unit_len(&text)?;
+            let sub_text = sub_text.to_string();
+            let sub_text_len = utf16_code_unit_len(&sub_text)?;
+            let (preceding_text, following_text) = read_edit_session::<(String, String)>(
                 tid,
                 parent_context.clone(),
                 Rc::new({
@@ -158,12 +350,12 @@ impl TextServiceFactory {
                         )?;
 
                         if pfetched == 0 {
-                            return Ok(String::new());
+                            return Ok(Default::default());
                         }
 
                         let range = match pselection[0].range.as_ref() {
                             Some(range) => range.Clone()?,
-                            None => return Ok(String::new()),
+                            None => return Ok(Default::default()),
                         };
 
                         let mut preceding_range_shifted = 0;
@@ -178,28 +370,35 @@ impl TextServiceFactory {
                         preceding_range.Collapse(cookie, TF_ANCHOR_START)?;
                         preceding_range.ShiftStart(
                             cookie,
-                            -30,
+                            -(text_len + SURROUNDING_CONTEXT_CODE_UNITS),
                             &mut preceding_range_shifted,
                             &halt_cond,
                         )?;
+                        let before_caret = read_range_text(
+                            cookie,
+                            &preceding_range,
+                            (text_len + SURROUNDING_CONTEXT_CODE_UNITS) as usize,
+                        )?;
+                        let preceding_text = text_before_composition(&before_caret, &text);
 
-                        preceding_range.ShiftEnd(
+                        // The text after the caret, past the rest of the composition.
+                        let mut following_range_shifted = 0;
+                        let following_range = range.Clone()?;
+                        following_range.Collapse(cookie, TF_ANCHOR_END)?;
+                        following_range.ShiftEnd(
                             cookie,
-                            preview_end_shift,
-                            &mut preceding_range_shifted,
+                            sub_text_len + SURROUNDING_CONTEXT_CODE_UNITS,
+                            &mut following_range_shifted,
                             &halt_cond,
                         )?;
-
-                        let mut pchtext = [0u16; 64];
-                        let mut pcch = 0;
-                        preceding_range.GetText(
+                        let after_caret = read_range_text(
                             cookie,
-                            TF_TF_MOVESTART,
-                            &mut pchtext,
-                            &mut pcch,
+                            &following_range,
+                            (sub_text_len + SURROUNDING_CONTEXT_CODE_UNITS) as usize,
                         )?;
+                        let following_text = text_after_composition(&after_caret, &sub_text);
 
-                        Ok(String::from_utf16_lossy(&pchtext[..pcch as usize]))
+                        Ok((preceding_text, following_text))
                     }
                 }),
             )?;
@@ -211,12 +410,20 @@ impl TextServiceFactory {
                     "edit_session",
                     edit_session_start,
                     format!(
-                        "status=success;preview_len={};preceding_text_present=true",
-                        preview.chars().count()
+                        "status=success;text_len={};preceding_text_present=true",
+                        text.chars().count()
                     ),
                 );
             }
 
+            // Incognito sends an empty context once, which also clears what
+            // the server kept from before.
+            let (preceding_text, following_text) = if IMEState::incognito()? {
+                Default::default()
+            } else {
+                (preceding_text, following_text)
+            };
+
             let Some(mut ipc_service) = IMEState::ipc_service()? else {
                 return Ok(());
             };
@@ -224,9 +431,12 @@ impl TextServiceFactory {
             let connection_id = ipc_service.connection_id();
             let should_set_context = {
                 let text_service = self.borrow_mut()?;
-                text_service
-                    .surrounding_text_context_state
-                    .should_send(connection_id, &preceding_text)
+                text_service.surrounding_text_context_state.should_send(
+                    connection_id,
+                    &preceding_text,
+                    &following_text,
+                    zenzai_disabled,
+                )
             };
             if !should_set_context {
                 if let Some(request_id) = trace_request_id {
@@ -235,22 +445,30 @@ impl TextServiceFactory {
                         "set_context",
                         Instant::now(),
                         format!(
-                            "status=skipped;reason=unchanged;preview_len={};context_len={};connection_id={connection_id}",
-                            preview.chars().count(),
-                            preceding_text.chars().count()
+                            "status=skipped;reason=unchanged;text_len={};context_len={};right_context_len={};connection_id={connection_id}",
+                            text.chars().count(),
+                            preceding_text.chars().count(),
+                            following_text.chars().count()
                         ),
                     );
                 }
                 return Ok(());
             }
 
-            ipc_service.set_context(preceding_text.clone())?;
+            ipc_service.set_context(
+                preceding_text.clone(),
+                following_text.clone(),
+                zenzai_disabled,
+            )?;
             let connection_id = ipc_service.connection_id();
             {
                 let mut text_service = self.borrow_mut()?;
-                text_service
-                    .surrounding_text_context_state
-                    .remember(connection_id, &preceding_text);
+                text_service.surrounding_text_context_state.remember(
+                    connection_id,
+                    &preceding_text,
+                    &following_text,
+                    zenzai_disabled,
+                );
             }
             IMEState::set_ipc_service(ipc_service)?;
 
@@ -259,10 +477,10 @@ impl TextServiceFactory {
 
         if let (Some(request_id), Some(total_start)) = (trace_request_id, total_start) {
             let details = match &result {
-                Ok(()) => format!("status=success;preview_len={}", preview.chars().count()),
+                Ok(()) => format!("status=success;text_len={}", text.chars().count()),
                 Err(error) => format!(
-                    "status=error;preview_len={};error={error:?}",
-                    preview.chars().count()
+                    "status=error;text_len={};error={error:?}",
+                    text.chars().count()
                 ),
             };
             Self::log_update_context_performance(request_id, "total", total_start, details);
@@ -278,12 +496,49 @@ impl TextServiceFactory {
 
 #[cfg(test)]
 mod tests {
-    use super::preview_end_shift;
+    use super::{caret_phrase_extent, text_after_composition, text_before_composition};
+
+    #[test]
+    fn utf16_tsf_boundary_surrounded_text_excludes_the_composition_by_code_units() {
+        assert_eq!(text_before_composition("前😀かな", "😀かな"), "前");
+        assert_eq!(text_before_composition("前か😀な", "か😀な"), "前");
+        assert_eq!(text_before_composition("前かな𠮷", "かな𠮷"), "前");
+        assert_eq!(text_before_composition("かな", "前かな"), "");
+    }
+
+    #[test]
+    fn right_context_skips_only_the_displayed_sub_text() {
+        assert_eq!(text_after_composition("天気です。", "天気"), "です。");
+        assert_eq!(text_after_composition("😀です。", "😀"), "です。");
+        // The reading is shown without a sub-text, so the caret is followed
+        // by document text straight away.
+        assert_eq!(text_after_composition("を書く。", ""), "を書く。");
+        assert_eq!(text_after_composition("天", "天気"), "");
+    }
+
+    #[test]
+    fn caret_phrase_is_the_kanji_and_kana_run_around_the_caret() {
+        assert_eq!(
+            caret_phrase_extent("今日は、漢字を", "変換する。", 128),
+            Some(("漢字を", "変換する"))
+        );
+        assert_eq!(
+            caret_phrase_extent("ABC ", "コーヒー々", 128),
+            Some(("", "コーヒー々"))
+        );
+        assert_eq!(caret_phrase_extent("abc", " def", 128), None);
+        assert_eq!(caret_phrase_extent("𠮷野家", "", 128), Some(("𠮷野家", "")));
+    }
 
     #[test]
-    fn utf16_tsf_boundary_surrounded_text_excludes_preview_by_code_units() {
-        assert_eq!(preview_end_shift("😀かな").unwrap(), -4);
-        assert_eq!(preview_end_shift("か😀な").unwrap(), -4);
-        assert_eq!(preview_end_shift("かな𠮷").unwrap(), -4);
+    fn caret_phrase_prefers_text_before_the_caret_when_too_long() {
+        assert_eq!(
+            caret_phrase_extent("あいうえお", "かきくけこ", 7),
+            Some(("あいうえお", "かき"))
+        );
+        assert_eq!(
+            caret_phrase_extent("あいうえお", "かきくけこ", 3),
+            Some(("うえお", ""))
+        );
     }
 }
@@ -6,7 +6,10 @@ use crate::{
     trace,
 };
 
-use super::factory::{TextServiceFactory, TextServiceFactory_Impl};
+use super::{
+    candidate_refinement::RefinementWindow,
+    factory::{TextServiceFactory, TextServiceFactory_Impl},
+};
 use windows::{
     core::Interface as _,
     Win32::{
@@ -15,9 +18,9 @@ use windows::{
         UI::TextServices::{
             CLSID_TF_CategoryMgr, ITfCategoryMgr, ITfFunctionProvider, ITfKeyEventSink,
             ITfKeystrokeMgr, ITfLangBarItemButton, ITfLangBarItemMgr, ITfSource, ITfSourceSingle,
-            ITfTextInputProcessorEx_Impl, ITfTextInputProcessor_Impl, ITfThreadFocusSink,
-            ITfThreadMgr, ITfThreadMgrEventSink, TF_MOD_IGNORE_ALL_MODIFIER, TF_MOD_SHIFT,
-            TF_PRESERVEDKEY,
+            ITfTextInputProcessor, ITfTextInputProcessorEx_Impl, ITfTextInputProcessor_Impl,
+            ITfThreadFocusSink, ITfThreadMgr, ITfThreadMgrEventSink, TF_MOD_IGNORE_ALL_MODIFIER,
+            TF_MOD_SHIFT, TF_PRESERVEDKEY,
         },
     },
 };
@@ -118,6 +121,15 @@ impl ITfTextInputProcessor_Impl for TextServiceFactory_Impl {
                 .cast::<ITfLangBarItemMgr>()?
                 .AddItem(&text_service.this::<ITfLangBarItemButton>()?)?;
         };
+
+        // Late Zenzai candidates are optional: without the window the server
+        // is simply asked to rerank before answering, as before.
+        match RefinementWindow::create(text_service.this::<ITfTextInputProcessor>()?) {
+            Ok(window) => text_service.refinement_window = Some(window),
+            Err(error) => {
+                tracing::warn!(?error, "Failed to create candidate refinement window");
+            }
+        }
         drop(text_service);
         self.set_keyboard_disabled_for_document_mgr(doc_mgr.as_ref())?;
         match IMEState::ensure_ipc_service() {
@@ -134,6 +146,8 @@ impl ITfTextInputProcessor_Impl for TextServiceFactory_Impl {
             }
         }
 
+        self.switch_composition_session_for_document_mgr(doc_mgr.as_ref())?;
+
         tracing::debug!("Activate success");
 
         Ok(())
@@ -152,6 +166,10 @@ impl ITfTextInputProcessor_Impl for TextServiceFactory_Impl {
         // End composition before borrowing TextService for sink teardown. The event helper
         // needs a mutable borrow to invalidate pending mode switches and clear local state.
         self.end_composition_for_tsf_event();
+        if let Some(mut ipc_service) = IMEState::ipc_service()? {
+            ipc_service.release_composition_sessions();
+            IMEState::set_ipc_service(ipc_service)?;
+        }
 
         {
             let text_service = self.borrow()?;
@@ -207,6 +225,8 @@ impl ITfTextInputProcessor_Impl for TextServiceFactory_Impl {
         // clear display attribute
         text_service.display_attribute_atom.clear();
 
+        text_service.refinement_window = None;
+
         text_service.tid = 0;
         text_service.thread_mgr = None;
 
@@ -13,6 +13,8 @@ use anyhow::{Context, Result};
 
 use crate::engine::{composition::Composition, input_mode::InputMode};
 
+use super::candidate_refinement::RefinementWindow;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum UpdatePosState {
     #[default]
@@ -112,17 +114,37 @@ impl CandidateWindowVisibilityState {
 pub struct SurroundingTextContextState {
     connection_id: Option<u64>,
     context: String,
+    right_context: String,
+    zenzai_disabled: bool,
 }
 
 impl SurroundingTextContextState {
-    pub fn should_send(&self, connection_id: u64, context: &str) -> bool {
-        self.connection_id != Some(connection_id) || self.context != context
+    pub fn should_send(
+        &self,
+        connection_id: u64,
+        context: &str,
+        right_context: &str,
+        zenzai_disabled: bool,
+    ) -> bool {
+        self.connection_id != Some(connection_id)
+            || self.context != context
+            || self.right_context != right_context
+            || self.zenzai_disabled != zenzai_disabled
     }
 
-    pub fn remember(&mut self, connection_id: u64, context: &str) {
+    pub fn remember(
+        &mut self,
+        connection_id: u64,
+        context: &str,
+        right_context: &str,
+        zenzai_disabled: bool,
+    ) {
         self.connection_id = Some(connection_id);
         self.context.clear();
         self.context.push_str(context);
+        self.right_context.clear();
+        self.right_context.push_str(right_context);
+        self.zenzai_disabled = zenzai_disabled;
     }
 }
 
@@ -144,6 +166,7 @@ pub struct TextService {
     pub display_attribute_atom: HashMap<GUID, u32>,
     pub mode: InputMode,
     pub this: Option<ITfTextInputProcessor>,
+    pub refinement_window: Option<RefinementWindow>,
     pub shift_key_down: bool,
     // (virtual key, selection was non-empty). A matched empty Space skips a
     // second selection edit session in OnKeyDown and follows the normal path.
@@ -218,12 +241,14 @@ mod tests {
     fn surrounding_text_context_state_resends_after_connection_change() {
         let mut state = SurroundingTextContextState::default();
 
-        assert!(state.should_send(1, "context"));
-        state.remember(1, "context");
+        assert!(state.should_send(1, "context", "after", false));
+        state.remember(1, "context", "after", false);
 
-        assert!(!state.should_send(1, "context"));
-        assert!(state.should_send(2, "context"));
-        assert!(state.should_send(1, "changed"));
+        assert!(!state.should_send(1, "context", "after", false));
+        assert!(state.should_send(2, "context", "after", false));
+        assert!(state.should_send(1, "changed", "after", false));
+        assert!(state.should_send(1, "context", "changed", false));
+        assert!(state.should_send(1, "context", "after", true));
     }
 
     #[test]
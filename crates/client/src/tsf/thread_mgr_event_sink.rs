@@ -1,5 +1,14 @@
-use windows::Win32::UI::TextServices::{
-    ITfContext, ITfDocumentMgr, ITfThreadFocusSink_Impl, ITfThreadMgrEventSink_Impl,
+use windows::{
+    core::{IUnknown, Interface as _},
+    Win32::{
+        Foundation::HWND,
+        UI::{
+            TextServices::{
+                ITfContext, ITfDocumentMgr, ITfThreadFocusSink_Impl, ITfThreadMgrEventSink_Impl,
+            },
+            WindowsAndMessaging::{GetAncestor, IsWindow, GA_ROOT},
+        },
+    },
 };
 
 use anyhow::Result;
@@ -16,14 +25,26 @@ fn ensure_ipc_service_for_tsf_event(event: &str) {
     }
 }
 
+/// Identity of `context`, which keys its composition session.
+fn composition_context_key(context: &ITfContext) -> Option<isize> {
+    context
+        .cast::<IUnknown>()
+        .ok()
+        .map(|unknown| unknown.as_raw() as isize)
+}
+
 impl TextServiceFactory {
     pub fn set_keyboard_disabled_state(&self, disabled: bool) -> Result<()> {
         let (changed, ipc_service) = IMEState::set_keyboard_disabled_and_clone_ipc(disabled)?;
 
         if let Some(mut ipc_service) = ipc_service {
-            if let Ok(delivery) =
-                ipc_service.update_candidate_window(Some(false), None, Some(vec![]), Some(0), None)
-            {
+            if let Ok(delivery) = ipc_service.update_candidate_window(
+                Some(false),
+                None,
+                Some(shared::proto::CandidateList::default()),
+                Some(0),
+                None,
+            ) {
                 self.remember_candidate_window_visibility_if_sent(delivery, Some(false));
             }
 
@@ -53,6 +74,90 @@ impl TextServiceFactory {
 
         self.set_keyboard_disabled_state(disabled)
     }
+
+    /// Sends further composition requests to the server session of the
+    /// context on top of `focus`, so every context composes on its own.
+    pub(crate) fn switch_composition_session_for_document_mgr(
+        &self,
+        focus: Option<&ITfDocumentMgr>,
+    ) -> Result<()> {
+        let Some(context) = focus.and_then(|focus| unsafe { focus.GetTop().ok() }) else {
+            return Ok(());
+        };
+        let (Some(context), Some(mut ipc_service)) =
+            (composition_context_key(&context), IMEState::ipc_service()?)
+        else {
+            return Ok(());
+        };
+        if let Err(error) = ipc_service.switch_composition_session(context) {
+            tracing::warn!(?error, "Failed to switch composition session");
+        }
+        IMEState::set_ipc_service(ipc_service)
+    }
+
+    /// Restores the input mode last used in the top-level window that owns
+    /// `focus`, or the application profile's default for a new window, then
+    /// applies the mode the focused field's input scope asks for.
+    pub(crate) fn restore_input_mode_for_document_mgr(
+        &self,
+        focus: Option<&ITfDocumentMgr>,
+    ) -> Result<()> {
+        let Some(context) = focus.and_then(|focus| unsafe { focus.GetTop().ok() }) else {
+            return Ok(());
+        };
+        let mut changed = false;
+
+        let window = unsafe {
+            context
+                .GetActiveView()
+                .and_then(|view| view.GetWnd())
+                .map(|window| GetAncestor(window, GA_ROOT))
+                .unwrap_or_default()
+        };
+        if !window.is_invalid() {
+            let is_alive = |window: isize| unsafe { IsWindow(HWND(window as _)).as_bool() };
+            if let Some(mode) = IMEState::focus_window(window.0 as isize, is_alive)? {
+                IMEState::set_input_mode(mode)?;
+                changed = true;
+            }
+        }
+
+        let tid = self.borrow()?.tid;
+        match Self::context_input_scope_policy(tid, context) {
+            Ok(policy) => {
+                if let Some(mode) = IMEState::focus_input_scope(policy.input_mode)? {
+                    IMEState::set_input_mode(mode)?;
+                    changed = true;
+                }
+            }
+            Err(error) => tracing::debug!(?error, "Skip input scope mode on focus"),
+        }
+
+        if changed {
+            self.update_lang_bar()?;
+
+            // The floating indicator would otherwise keep showing the mode
+            // of the window focused before until the next key.
+            let label = IMEState::input_mode()?.indicator_text(IMEState::incognito()?);
+            if let Some(mut ipc_service) = IMEState::ipc_service()? {
+                if let Err(error) = ipc_service.update_candidate_window_with_reading(
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(&label),
+                    None,
+                    None,
+                    None,
+                ) {
+                    tracing::warn!(?error, "Failed to update mode indicator after focus");
+                }
+                IMEState::set_ipc_service(ipc_service)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl ITfThreadMgrEventSink_Impl for TextServiceFactory_Impl {
@@ -84,6 +189,8 @@ impl ITfThreadMgrEventSink_Impl for TextServiceFactory_Impl {
         ensure_ipc_service_for_tsf_event("OnSetFocus");
 
         self.end_composition_for_tsf_event();
+        self.switch_composition_session_for_document_mgr(focus)?;
+        self.restore_input_mode_for_document_mgr(focus)?;
 
         if focus.is_none() {
             let mut text_service = self.borrow_mut()?;
@@ -100,8 +207,15 @@ impl ITfThreadMgrEventSink_Impl for TextServiceFactory_Impl {
     }
 
     #[macros::anyhow]
-    fn OnPopContext(&self, _pic: Option<&ITfContext>) -> Result<()> {
-        Ok(())
+    fn OnPopContext(&self, pic: Option<&ITfContext>) -> Result<()> {
+        let (Some(context), Some(mut ipc_service)) = (
+            pic.and_then(composition_context_key),
+            IMEState::ipc_service()?,
+        ) else {
+            return Ok(());
+        };
+        ipc_service.release_composition_session(context);
+        IMEState::set_ipc_service(ipc_service)
     }
 }
 
@@ -116,6 +230,8 @@ impl ITfThreadFocusSink_Impl for TextServiceFactory_Impl {
         };
         ensure_ipc_service_for_tsf_event("OnSetThreadFocus");
         self.set_keyboard_disabled_for_document_mgr(focus.as_ref())?;
+        self.switch_composition_session_for_document_mgr(focus.as_ref())?;
+        self.restore_input_mode_for_document_mgr(focus.as_ref())?;
 
         Ok(())
     }
@@ -1,6 +1,12 @@
 use std::env;
 
 fn main() {
+    // The Swift converter is only built for Windows. Other targets fall back to
+    // the in-process Rust converter and must not try to link the Swift library.
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+        return;
+    }
+
     // link dll
     let project_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     println!("cargo:rustc-link-search={}/target/", project_dir);
@@ -0,0 +1,163 @@
+use shared::{
+    proto::{LearnedEntry, LearningCandidateCommit, Suggestion},
+    user_dictionary::UserWord,
+};
+
+mod fake;
+#[cfg(windows)]
+mod swift;
+
+pub(crate) use fake::FakeConverter;
+#[cfg(windows)]
+pub(crate) use swift::SwiftConverter;
+
+pub(crate) const INPUT_STYLE_DIRECT: i32 = 1;
+pub(crate) const INPUT_STYLE_KANA: i32 = 2;
+
+pub(crate) struct RawComposingText {
+    pub(crate) text: String,
+    pub(crate) cursor: i32,
+}
+
+pub(crate) struct ComposedText {
+    pub(crate) hiragana: Option<String>,
+    pub(crate) suggestions: Vec<Suggestion>,
+}
+
+pub(crate) struct RawClauseBoundaryAdjustment {
+    pub(crate) text: String,
+    pub(crate) applied: bool,
+    pub(crate) adjusted_input_count: i32,
+    pub(crate) cursor_offset: i32,
+}
+
+/// Selects which candidate list the converter materializes for the current
+/// composition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ComposedTextQuery {
+    /// Candidates for the whole composition.
+    Full,
+    /// Candidates for the whole composition without Zenzai reranking; the
+    /// fast first phase of a deferred conversion.
+    WithoutZenzai,
+    /// Candidates for the text before the cursor. When an input count is
+    /// required, only candidates covering exactly that many input elements
+    /// are useful to the caller.
+    CursorPrefix { required_input_count: Option<i32> },
+    /// Candidates for a composition restored from a committed surface.
+    Reconversion,
+    /// Completions whose reading extends the whole composition's reading,
+    /// from learning history, the user dictionary and prediction mode.
+    Prediction,
+}
+
+/// The kana-kanji converter behind `AzookeyService`.
+///
+/// Every method maps onto one operation of the Swift FFI surface. The service
+/// serializes calls through its mutation lock, so implementations only need
+/// interior mutability, not their own transaction handling.
+pub(crate) trait ConverterBackend: Send + Sync {
+    fn initialize(&self, path: &str) -> Result<(), String>;
+    fn set_request_id(&self, request_id: u64);
+    /// Sets the document text before and after the composition.
+    fn set_context(&self, context: &str, right_context: &str) -> Result<(), String>;
+    fn load_config(&self);
+    fn warmup(&self) -> bool;
+    fn has_active_composition(&self) -> bool;
+    /// Whether a `Full` query for the current composition would run Zenzai.
+    fn would_use_zenzai(&self) -> bool;
+
+    /// Makes `session_id` the composition session that subsequent calls act
+    /// on, creating an empty session on first use. Session 0 is the default.
+    fn select_session(&self, session_id: u64);
+    /// Drops an inactive session's composition, snapshots and candidate cache.
+    fn destroy_session(&self, session_id: u64);
+    /// Whether the selected session's `Full` queries may run Zenzai when it
+    /// is configured. Sessions start out allowing it.
+    fn set_session_zenzai_allowed(&self, allowed: bool);
+
+    fn append_text(&self, input: &str) -> Result<RawComposingText, String>;
+    fn append_text_direct(&self, input: &str) -> Result<RawComposingText, String>;
+    fn remove_text(&self) -> Result<RawComposingText, String>;
+    fn move_cursor(&self, offset: i32) -> Result<RawComposingText, String>;
+    fn shrink_text(&self, offset: i32) -> Result<RawComposingText, String>;
+    fn clear_text(&self);
+    fn cursor_position(&self) -> i32;
+    fn raw_input(&self) -> Result<String, String>;
+    fn adjust_clause_boundary(
+        &self,
+        current_input_count: i32,
+        direction: i32,
+        expected_raw_input: &str,
+    ) -> Result<RawClauseBoundaryAdjustment, String>;
+
+    fn clear_snapshots(&self);
+    fn push_snapshot(&self, selected_candidate_id: u64);
+    fn pop_snapshot(&self, selected_candidate_id: u64);
+
+    fn infer_reconversion_readings(&self, surface: &str) -> Result<Vec<String>, String>;
+    fn composed_text(
+        &self,
+        query: ComposedTextQuery,
+        request_id: u64,
+    ) -> Result<ComposedText, String>;
+
+    fn pin_learning_candidate(&self, candidate_id: u64) -> bool;
+    fn commit_learning_candidate(&self, candidate_id: u64, commit_kind: i32) -> bool;
+    fn commit_learning_candidates(&self, commits: &[LearningCandidateCommit]) -> usize;
+    fn reset_learning_memory(&self) -> bool;
+    /// Lists learned reading/surface pairs, most recently used first.
+    fn learned_entries(&self) -> Result<Vec<LearnedEntry>, String>;
+    fn forget_learned_entry(&self, reading: &str, word: &str) -> bool;
+    /// Forgets the learned conversion behind a cached candidate.
+    fn forget_learning_candidate(&self, candidate_id: u64) -> bool;
+
+    /// Replaces the converter's user dictionary with `words`, which are in
+    /// priority order.
+    fn set_user_words(&self, words: &[UserWord]) -> Result<(), String>;
+    /// Appends one word at the lowest user dictionary priority.
+    fn add_user_word(&self, word: &UserWord) -> Result<(), String>;
+    fn remove_user_word(&self, word: &UserWord) -> Result<(), String>;
+}
+
+#[cfg(any(windows, test))]
+pub(crate) fn decode_reconversion_readings(encoded: &str) -> Result<Vec<String>, String> {
+    let readings: Vec<String> = serde_json::from_str(encoded)
+        .map_err(|error| format!("InferReconversionReadings returned invalid JSON: {error}"))?;
+    if readings.iter().any(String::is_empty) {
+        return Err("InferReconversionReadings returned an empty reading".to_string());
+    }
+    Ok(readings)
+}
+
+#[cfg(any(windows, test))]
+pub(crate) fn decode_learned_entries(encoded: &str) -> Result<Vec<LearnedEntry>, String> {
+    let invalid = |detail: String| format!("ListLearnedEntries returned invalid JSON: {detail}");
+    let entries: Vec<serde_json::Map<String, serde_json::Value>> =
+        serde_json::from_str(encoded).map_err(|error| invalid(error.to_string()))?;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let text = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(serde_json::Value::as_str)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+                    .ok_or_else(|| invalid(format!("missing {key}")))
+            };
+            let number = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(serde_json::Value::as_i64)
+                    .ok_or_else(|| invalid(format!("missing {key}")))
+            };
+            Ok(LearnedEntry {
+                reading: text("reading")?,
+                word: text("word")?,
+                count: u32::try_from(number("count")?).unwrap_or_default(),
+                last_used_unix_ms: number("last_used_unix_ms")?,
+            })
+        })
+        .collect()
+}
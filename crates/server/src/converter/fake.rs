@@ -0,0 +1,896 @@
+use super::{
+    ComposedText, ComposedTextQuery, ConverterBackend, RawClauseBoundaryAdjustment,
+    RawComposingText,
+};
+use shared::{
+    proto::{CandidateOrigin, LearnedEntry, LearningCandidateCommit, Suggestion},
+    user_dictionary::UserWord,
+    RomajiRule,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+/// Same threshold as the Swift converter's `minInputCountForZenzaiCandidates`.
+const MIN_INPUT_COUNT_FOR_ZENZAI: usize = 4;
+
+const DEFAULT_DICTIONARY: &[(&str, &str)] = &[
+    ("かんじ", "漢字"),
+    ("かんじ", "感じ"),
+    ("かんじ", "幹事"),
+    ("へんかん", "変換"),
+    ("にほん", "日本"),
+    ("にほんご", "日本語"),
+    ("きょう", "今日"),
+    ("きょう", "京"),
+    ("てんき", "天気"),
+    ("わたし", "私"),
+    ("なまえ", "名前"),
+    ("がっこう", "学校"),
+    ("いく", "行く"),
+    ("は", "は"),
+    ("は", "葉"),
+    ("いい", "良い"),
+    ("です", "です"),
+    ("ありがとう", "ありがとう"),
+    ("ありがとうございます", "ありがとうございます"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InputElement {
+    character: char,
+    direct: bool,
+}
+
+/// A run of input elements that converts to one piece of kana. Cursor and
+/// clause boundaries only fall between segments, as with the Swift
+/// converter's independent input segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    input_count: usize,
+    kana: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Composition {
+    input: Vec<InputElement>,
+    cursor: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CachedCandidate {
+    reading: String,
+    surface: String,
+}
+
+/// Composition state of a session that is not currently selected.
+#[derive(Debug, Default)]
+struct InactiveSession {
+    composition: Composition,
+    snapshots: Vec<Composition>,
+    context: String,
+    right_context: String,
+    candidates: HashMap<u64, CachedCandidate>,
+    zenzai_suppressed: bool,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    composition: Composition,
+    snapshots: Vec<Composition>,
+    context: String,
+    right_context: String,
+    /// The selected session has Zenzai turned off by its application.
+    zenzai_suppressed: bool,
+    next_candidate_id: u64,
+    candidates: HashMap<u64, CachedCandidate>,
+    learned: HashMap<String, Vec<String>>,
+    /// `(count, last use)` per learned `(reading, surface)`; the fake clock
+    /// is the learning commit count, so entries order deterministically.
+    learned_uses: HashMap<(String, String), (u32, i64)>,
+    learning_clock: i64,
+    /// `(hiragana reading, surface)` in registration order.
+    user_words: Vec<(String, String)>,
+    active_session_id: u64,
+    inactive_sessions: HashMap<u64, InactiveSession>,
+}
+
+/// A pure-Rust stand-in for the Swift converter.
+///
+/// It converts romaji with the shipped default table and resolves kanji from a
+/// small reading-to-surface dictionary. Conversion quality is not the point;
+/// it exists so the service's transaction logic can run under `cargo test`
+/// without the Swift toolchain. With Zenzai enabled, "reranking" swaps the
+/// first two `Full` candidates so tests can tell the two phases apart.
+pub(crate) struct FakeConverter {
+    romaji_rows: Vec<RomajiRule>,
+    dictionary: Vec<(String, String)>,
+    zenzai_enabled: AtomicBool,
+    state: Mutex<FakeState>,
+}
+
+impl Default for FakeConverter {
+    fn default() -> Self {
+        Self::with_dictionary(
+            DEFAULT_DICTIONARY
+                .iter()
+                .map(|(reading, surface)| (reading.to_string(), surface.to_string())),
+        )
+    }
+}
+
+impl FakeConverter {
+    pub(crate) fn with_dictionary(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            romaji_rows: shared::get_default_romaji_rows(),
+            dictionary: entries.into_iter().collect(),
+            zenzai_enabled: AtomicBool::new(false),
+            state: Mutex::new(FakeState {
+                next_candidate_id: 1,
+                ..FakeState::default()
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_zenzai_enabled(&self, enabled: bool) {
+        self.zenzai_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn context(&self) -> String {
+        self.state().context.clone()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn right_context(&self) -> String {
+        self.state().right_context.clone()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn snapshot_count(&self) -> usize {
+        self.state().snapshots.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn session_count(&self) -> usize {
+        self.state().inactive_sessions.len() + 1
+    }
+
+    fn segments(&self, input: &[InputElement]) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut index = 0;
+        while index < input.len() {
+            if input[index].direct {
+                segments.push(Segment {
+                    input_count: 1,
+                    kana: input[index].character.to_string(),
+                });
+                index += 1;
+                continue;
+            }
+
+            let rest = input[index..]
+                .iter()
+                .take_while(|element| !element.direct)
+                .map(|element| element.character)
+                .collect::<String>();
+            let rule = self
+                .romaji_rows
+                .iter()
+                .filter(|row| !row.input.is_empty() && rest.starts_with(row.input.as_str()))
+                .max_by_key(|row| row.input.chars().count());
+            match rule {
+                Some(rule) => {
+                    let input_count = rule.input.chars().count();
+                    let carried = if rule.input.ends_with(rule.next_input.as_str()) {
+                        rule.next_input.chars().count()
+                    } else {
+                        0
+                    };
+                    let consumed = input_count.saturating_sub(carried).max(1);
+                    segments.push(Segment {
+                        input_count: consumed,
+                        kana: rule.output.clone(),
+                    });
+                    index += consumed;
+                }
+                None => {
+                    segments.push(Segment {
+                        input_count: 1,
+                        kana: input[index].character.to_string(),
+                    });
+                    index += 1;
+                }
+            }
+        }
+        segments
+    }
+
+    fn hiragana(&self, composition: &Composition) -> String {
+        self.segments(&composition.input)
+            .into_iter()
+            .map(|segment| segment.kana)
+            .collect()
+    }
+
+    fn surface_cursor(&self, composition: &Composition) -> usize {
+        let mut input_index = 0;
+        let mut surface_index = 0;
+        for segment in self.segments(&composition.input) {
+            if input_index + segment.input_count > composition.cursor {
+                break;
+            }
+            input_index += segment.input_count;
+            surface_index += segment.kana.chars().count();
+        }
+        surface_index
+    }
+
+    fn raw_composing_text(&self, composition: &Composition) -> RawComposingText {
+        RawComposingText {
+            text: self.hiragana(composition),
+            cursor: i32::try_from(self.surface_cursor(composition)).unwrap_or(i32::MAX),
+        }
+    }
+
+    fn insert(&self, input: &str, direct: bool) -> RawComposingText {
+        let mut state = self.state();
+        let composition = &mut state.composition;
+        let elements = input
+            .chars()
+            .map(|character| InputElement { character, direct })
+            .collect::<Vec<_>>();
+        let cursor = composition.cursor;
+        composition
+            .input
+            .splice(cursor..cursor, elements.iter().copied());
+        composition.cursor += elements.len();
+        self.raw_composing_text(composition)
+    }
+
+    fn surfaces_for(&self, state: &FakeState, reading: &str) -> Vec<String> {
+        let mut surfaces = state.learned.get(reading).cloned().unwrap_or_default();
+        for (entry_reading, surface) in state.user_words.iter().chain(&self.dictionary) {
+            if entry_reading == reading && !surfaces.contains(surface) {
+                surfaces.push(surface.clone());
+            }
+        }
+        surfaces
+    }
+
+    /// Splits `segments` into clauses by greedy longest dictionary match and
+    /// returns `(reading, input_count)` for each clause.
+    fn clauses(&self, state: &FakeState, segments: &[Segment]) -> Vec<(String, usize)> {
+        let mut clauses = Vec::new();
+        let mut start = 0;
+        while start < segments.len() {
+            let mut best_end = start + 1;
+            let mut reading = String::new();
+            for end in start + 1..=segments.len() {
+                reading.push_str(&segments[end - 1].kana);
+                if state
+                    .user_words
+                    .iter()
+                    .chain(&self.dictionary)
+                    .any(|(entry_reading, _)| *entry_reading == reading)
+                {
+                    best_end = end;
+                }
+            }
+            let clause = &segments[start..best_end];
+            clauses.push((
+                clause.iter().map(|segment| segment.kana.as_str()).collect(),
+                clause.iter().map(|segment| segment.input_count).sum(),
+            ));
+            start = best_end;
+        }
+        clauses
+    }
+
+    fn candidates(
+        &self,
+        state: &mut FakeState,
+        segments: &[Segment],
+        suffix: &str,
+    ) -> Vec<Suggestion> {
+        let reading = segments
+            .iter()
+            .map(|segment| segment.kana.as_str())
+            .collect::<String>();
+        let input_count = segments.iter().map(|segment| segment.input_count).sum();
+        let clauses = self.clauses(state, segments);
+        let mut candidates: Vec<(String, String, String, usize)> = Vec::new();
+
+        let best = clauses
+            .iter()
+            .map(|(clause_reading, _)| {
+                self.surfaces_for(state, clause_reading)
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| clause_reading.clone())
+            })
+            .collect::<String>();
+        candidates.push((reading.clone(), best, suffix.to_string(), input_count));
+        for surface in self.surfaces_for(state, &reading) {
+            candidates.push((reading.clone(), surface, suffix.to_string(), input_count));
+        }
+        if let [(first_reading, first_input_count), _, ..] = clauses.as_slice() {
+            let remaining = format!("{}{suffix}", &reading[first_reading.len()..]);
+            for surface in self.surfaces_for(state, first_reading) {
+                candidates.push((
+                    first_reading.clone(),
+                    surface,
+                    remaining.clone(),
+                    *first_input_count,
+                ));
+            }
+            candidates.push((
+                first_reading.clone(),
+                first_reading.clone(),
+                remaining,
+                *first_input_count,
+            ));
+        }
+        candidates.push((
+            reading.clone(),
+            reading.clone(),
+            suffix.to_string(),
+            input_count,
+        ));
+        candidates.push((
+            reading.clone(),
+            to_katakana(&reading),
+            suffix.to_string(),
+            input_count,
+        ));
+
+        let mut seen = HashSet::new();
+        candidates
+            .into_iter()
+            .filter(|(_, surface, _, _)| seen.insert(surface.clone()))
+            .map(|(reading, surface, subtext, corresponding_count)| {
+                Self::suggestion(state, reading, surface, subtext, corresponding_count)
+            })
+            .collect()
+    }
+
+    /// Completions for the whole reading of `segments`: learned conversions
+    /// (most recent first), then user words, then dictionary entries whose
+    /// reading is longer than and starts with it.
+    fn predictions(&self, state: &mut FakeState, segments: &[Segment]) -> Vec<Suggestion> {
+        let reading = segments
+            .iter()
+            .map(|segment| segment.kana.as_str())
+            .collect::<String>();
+        let input_count = segments.iter().map(|segment| segment.input_count).sum();
+        let completes = |entry_reading: &str| {
+            entry_reading.len() > reading.len() && entry_reading.starts_with(reading.as_str())
+        };
+
+        let mut learned = state
+            .learned_uses
+            .iter()
+            .filter(|((entry_reading, _), _)| completes(entry_reading))
+            .map(|(entry, (_, last_used))| (entry.clone(), *last_used))
+            .collect::<Vec<_>>();
+        learned.sort_by_key(|(_, last_used)| std::cmp::Reverse(*last_used));
+        let mut predictions = learned
+            .into_iter()
+            .map(|(entry, _)| entry)
+            .collect::<Vec<_>>();
+        predictions.extend(
+            state
+                .user_words
+                .iter()
+                .chain(&self.dictionary)
+                .filter(|(entry_reading, _)| completes(entry_reading))
+                .cloned(),
+        );
+
+        let mut seen = HashSet::new();
+        predictions
+            .into_iter()
+            .filter(|(_, surface)| seen.insert(surface.clone()))
+            .map(|(reading, surface)| {
+                Self::suggestion(state, reading, surface, String::new(), input_count)
+            })
+            .collect()
+    }
+
+    /// Caches a candidate for learning and tags it with where it came from.
+    fn suggestion(
+        state: &mut FakeState,
+        reading: String,
+        surface: String,
+        subtext: String,
+        corresponding_count: usize,
+    ) -> Suggestion {
+        let origin = if state
+            .learned
+            .get(&reading)
+            .is_some_and(|surfaces| surfaces.contains(&surface))
+        {
+            CandidateOrigin::Learned
+        } else if state.user_words.iter().any(|(user_reading, user_surface)| {
+            *user_reading == reading && *user_surface == surface
+        }) {
+            CandidateOrigin::UserDictionary
+        } else {
+            CandidateOrigin::SystemDictionary
+        };
+        let candidate_id = state.next_candidate_id;
+        state.next_candidate_id += 1;
+        state.candidates.insert(
+            candidate_id,
+            CachedCandidate {
+                reading,
+                surface: surface.clone(),
+            },
+        );
+        Suggestion {
+            text: surface,
+            subtext,
+            corresponding_count: i32::try_from(corresponding_count).unwrap_or(i32::MAX),
+            candidate_id,
+            origin: origin.into(),
+            ..Suggestion::default()
+        }
+    }
+
+    fn learn(state: &mut FakeState, candidate_id: u64) -> bool {
+        let Some(candidate) = state.candidates.get(&candidate_id).cloned() else {
+            return false;
+        };
+        state.learning_clock += 1;
+        let clock = state.learning_clock;
+        let uses = state
+            .learned_uses
+            .entry((candidate.reading.clone(), candidate.surface.clone()))
+            .or_default();
+        *uses = (uses.0 + 1, clock);
+        let surfaces = state.learned.entry(candidate.reading).or_default();
+        surfaces.retain(|surface| *surface != candidate.surface);
+        surfaces.insert(0, candidate.surface);
+        true
+    }
+
+    fn forget(state: &mut FakeState, reading: &str, word: &str) -> bool {
+        let Some(surfaces) = state.learned.get_mut(reading) else {
+            return false;
+        };
+        let learned_count = surfaces.len();
+        surfaces.retain(|surface| surface != word);
+        if surfaces.len() == learned_count {
+            return false;
+        }
+        if surfaces.is_empty() {
+            state.learned.remove(reading);
+        }
+        state
+            .learned_uses
+            .remove(&(reading.to_string(), word.to_string()));
+        true
+    }
+}
+
+fn to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|character| match character {
+            'ぁ'..='ゖ' => char::from_u32(character as u32 + 0x60).unwrap_or(character),
+            _ => character,
+        })
+        .collect()
+}
+
+fn to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|character| match character {
+            'ァ'..='ヶ' => char::from_u32(character as u32 - 0x60).unwrap_or(character),
+            _ => character,
+        })
+        .collect()
+}
+
+fn is_kana(text: &str) -> bool {
+    text.chars()
+        .all(|character| matches!(character, 'ぁ'..='ゖ' | 'ァ'..='ヶ' | 'ー'))
+}
+
+impl ConverterBackend for FakeConverter {
+    fn initialize(&self, _path: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn set_request_id(&self, _request_id: u64) {}
+
+    fn set_context(&self, context: &str, right_context: &str) -> Result<(), String> {
+        let mut state = self.state();
+        state.context = context.to_string();
+        state.right_context = right_context.to_string();
+        Ok(())
+    }
+
+    fn load_config(&self) {}
+
+    fn warmup(&self) -> bool {
+        true
+    }
+
+    fn has_active_composition(&self) -> bool {
+        !self.state().composition.input.is_empty()
+    }
+
+    fn would_use_zenzai(&self) -> bool {
+        let state = self.state();
+        self.zenzai_enabled.load(Ordering::Relaxed)
+            && !state.zenzai_suppressed
+            && state.composition.input.len() >= MIN_INPUT_COUNT_FOR_ZENZAI
+    }
+
+    fn select_session(&self, session_id: u64) {
+        let mut state = self.state();
+        if state.active_session_id == session_id {
+            return;
+        }
+        let previous = InactiveSession {
+            composition: std::mem::take(&mut state.composition),
+            snapshots: std::mem::take(&mut state.snapshots),
+            context: std::mem::take(&mut state.context),
+            right_context: std::mem::take(&mut state.right_context),
+            candidates: std::mem::take(&mut state.candidates),
+            zenzai_suppressed: state.zenzai_suppressed,
+        };
+        let previous_id = state.active_session_id;
+        state.inactive_sessions.insert(previous_id, previous);
+        let next = state
+            .inactive_sessions
+            .remove(&session_id)
+            .unwrap_or_default();
+        state.composition = next.composition;
+        state.snapshots = next.snapshots;
+        state.context = next.context;
+        state.right_context = next.right_context;
+        state.candidates = next.candidates;
+        state.zenzai_suppressed = next.zenzai_suppressed;
+        state.active_session_id = session_id;
+    }
+
+    fn destroy_session(&self, session_id: u64) {
+        let mut state = self.state();
+        if state.active_session_id != session_id {
+            state.inactive_sessions.remove(&session_id);
+        }
+    }
+
+    fn set_session_zenzai_allowed(&self, allowed: bool) {
+        self.state().zenzai_suppressed = !allowed;
+    }
+
+    fn append_text(&self, input: &str) -> Result<RawComposingText, String> {
+        Ok(self.insert(input, false))
+    }
+
+    fn append_text_direct(&self, input: &str) -> Result<RawComposingText, String> {
+        Ok(self.insert(input, true))
+    }
+
+    fn remove_text(&self) -> Result<RawComposingText, String> {
+        let mut state = self.state();
+        let composition = &mut state.composition;
+        let segments = self.segments(&composition.input);
+        let mut start = 0;
+        for segment in segments {
+            let end = start + segment.input_count;
+            if end == composition.cursor {
+                let mut kana = segment.kana.chars().collect::<Vec<_>>();
+                kana.pop();
+                let replacement = kana
+                    .into_iter()
+                    .map(|character| InputElement {
+                        character,
+                        direct: true,
+                    })
+                    .collect::<Vec<_>>();
+                composition.cursor = start + replacement.len();
+                composition.input.splice(start..end, replacement);
+                break;
+            }
+            start = end;
+        }
+        Ok(self.raw_composing_text(composition))
+    }
+
+    fn move_cursor(&self, offset: i32) -> Result<RawComposingText, String> {
+        let mut state = self.state();
+        let composition = &mut state.composition;
+        let current = self.surface_cursor(composition) as i64;
+        let target = current.saturating_add(i64::from(offset)).max(0) as usize;
+        // (input index, surface index) for every segment boundary.
+        let mut boundaries = vec![(0usize, 0usize)];
+        for segment in self.segments(&composition.input) {
+            let (input_index, surface_index) = boundaries.last().copied().unwrap_or_default();
+            boundaries.push((
+                input_index + segment.input_count,
+                surface_index + segment.kana.chars().count(),
+            ));
+        }
+        let boundary = if offset < 0 {
+            boundaries
+                .iter()
+                .rev()
+                .find(|(_, surface_index)| *surface_index <= target)
+        } else {
+            boundaries
+                .iter()
+                .find(|(_, surface_index)| *surface_index >= target)
+                .or(boundaries.last())
+        };
+        composition.cursor = boundary.map(|(input_index, _)| *input_index).unwrap_or(0);
+        Ok(self.raw_composing_text(composition))
+    }
+
+    fn shrink_text(&self, offset: i32) -> Result<RawComposingText, String> {
+        let mut state = self.state();
+        let composition = &mut state.composition;
+        let offset = usize::try_from(offset)
+            .unwrap_or_default()
+            .min(composition.input.len());
+        composition.input.drain(..offset);
+        composition.cursor = composition.input.len();
+        Ok(RawComposingText {
+            text: self.hiragana(composition),
+            cursor: 0,
+        })
+    }
+
+    fn clear_text(&self) {
+        let mut state = self.state();
+        state.composition = Composition::default();
+        state.snapshots.clear();
+        state.candidates.clear();
+    }
+
+    fn cursor_position(&self) -> i32 {
+        let state = self.state();
+        i32::try_from(self.surface_cursor(&state.composition)).unwrap_or(i32::MAX)
+    }
+
+    fn raw_input(&self) -> Result<String, String> {
+        Ok(self
+            .state()
+            .composition
+            .input
+            .iter()
+            .map(|element| element.character)
+            .collect())
+    }
+
+    fn adjust_clause_boundary(
+        &self,
+        current_input_count: i32,
+        direction: i32,
+        expected_raw_input: &str,
+    ) -> Result<RawClauseBoundaryAdjustment, String> {
+        let mut state = self.state();
+        let composition = &mut state.composition;
+        let text = self.hiragana(composition);
+        let skipped = RawClauseBoundaryAdjustment {
+            text: text.clone(),
+            applied: false,
+            adjusted_input_count: current_input_count,
+            cursor_offset: 0,
+        };
+        let raw_input = composition
+            .input
+            .iter()
+            .map(|element| element.character)
+            .collect::<String>();
+        if raw_input != expected_raw_input || direction == 0 {
+            return Ok(skipped);
+        }
+
+        let mut boundaries = vec![0usize];
+        for segment in self.segments(&composition.input) {
+            boundaries.push(boundaries.last().copied().unwrap_or_default() + segment.input_count);
+        }
+        let Some(current_index) = usize::try_from(current_input_count)
+            .ok()
+            .and_then(|count| boundaries.iter().position(|boundary| *boundary == count))
+        else {
+            return Ok(skipped);
+        };
+        let target_index = if direction < 0 {
+            current_index.checked_sub(1)
+        } else {
+            Some(current_index + 1)
+        };
+        let Some(adjusted) = target_index
+            .and_then(|index| boundaries.get(index))
+            .copied()
+            .filter(|adjusted| *adjusted > 0)
+        else {
+            return Ok(skipped);
+        };
+
+        let before = self.surface_cursor(composition) as i32;
+        composition.cursor = adjusted;
+        let after = self.surface_cursor(composition) as i32;
+        Ok(RawClauseBoundaryAdjustment {
+            text,
+            applied: true,
+            adjusted_input_count: i32::try_from(adjusted).unwrap_or(i32::MAX),
+            cursor_offset: after - before,
+        })
+    }
+
+    fn clear_snapshots(&self) {
+        self.state().snapshots.clear();
+    }
+
+    fn push_snapshot(&self, _selected_candidate_id: u64) {
+        let mut state = self.state();
+        let composition = state.composition.clone();
+        state.snapshots.push(composition);
+    }
+
+    fn pop_snapshot(&self, _selected_candidate_id: u64) {
+        let mut state = self.state();
+        if let Some(restored) = state.snapshots.pop() {
+            state.composition = restored;
+        }
+    }
+
+    fn infer_reconversion_readings(&self, surface: &str) -> Result<Vec<String>, String> {
+        let mut readings = self
+            .dictionary
+            .iter()
+            .filter(|(_, entry_surface)| entry_surface == surface)
+            .map(|(reading, _)| reading.clone())
+            .collect::<Vec<_>>();
+        if readings.is_empty() && is_kana(surface) {
+            readings.push(to_hiragana(surface));
+        }
+        readings.dedup();
+        Ok(readings)
+    }
+
+    fn composed_text(
+        &self,
+        query: ComposedTextQuery,
+        _request_id: u64,
+    ) -> Result<ComposedText, String> {
+        let rerank = query == ComposedTextQuery::Full && self.would_use_zenzai();
+        let mut state = self.state();
+        let composition = state.composition.clone();
+        let segments = self.segments(&composition.input);
+        let hiragana = self.hiragana(&composition);
+        if segments.is_empty() {
+            return Ok(ComposedText {
+                hiragana: None,
+                suggestions: Vec::new(),
+            });
+        }
+
+        let suggestions = match query {
+            ComposedTextQuery::Full
+            | ComposedTextQuery::WithoutZenzai
+            | ComposedTextQuery::Reconversion => {
+                let mut suggestions = self.candidates(&mut state, &segments, "");
+                if rerank && suggestions.len() >= 2 {
+                    suggestions.swap(0, 1);
+                }
+                suggestions
+            }
+            ComposedTextQuery::CursorPrefix {
+                required_input_count,
+            } => {
+                let mut input_index = 0;
+                let prefix_len = segments
+                    .iter()
+                    .take_while(|segment| {
+                        input_index += segment.input_count;
+                        input_index <= composition.cursor
+                    })
+                    .count();
+                let (prefix, suffix) = segments.split_at(prefix_len.max(1));
+                let suffix = suffix
+                    .iter()
+                    .map(|segment| segment.kana.as_str())
+                    .collect::<String>();
+                let mut suggestions = self.candidates(&mut state, prefix, &suffix);
+                if let Some(required) = required_input_count.filter(|count| *count >= 0) {
+                    suggestions.retain(|suggestion| suggestion.corresponding_count == required);
+                }
+                suggestions
+            }
+            ComposedTextQuery::Prediction => self.predictions(&mut state, &segments),
+        };
+
+        Ok(ComposedText {
+            hiragana: Some(hiragana),
+            suggestions,
+        })
+    }
+
+    fn pin_learning_candidate(&self, candidate_id: u64) -> bool {
+        self.state().candidates.contains_key(&candidate_id)
+    }
+
+    fn commit_learning_candidate(&self, candidate_id: u64, _commit_kind: i32) -> bool {
+        Self::learn(&mut self.state(), candidate_id)
+    }
+
+    fn commit_learning_candidates(&self, commits: &[LearningCandidateCommit]) -> usize {
+        let mut state = self.state();
+        commits
+            .iter()
+            .filter(|commit| Self::learn(&mut state, commit.candidate_id))
+            .count()
+    }
+
+    fn reset_learning_memory(&self) -> bool {
+        let mut state = self.state();
+        state.learned.clear();
+        state.learned_uses.clear();
+        true
+    }
+
+    fn learned_entries(&self) -> Result<Vec<LearnedEntry>, String> {
+        let state = self.state();
+        let mut entries = state
+            .learned_uses
+            .iter()
+            .map(|((reading, word), (count, last_used))| LearnedEntry {
+                reading: reading.clone(),
+                word: word.clone(),
+                count: *count,
+                last_used_unix_ms: *last_used,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used_unix_ms));
+        Ok(entries)
+    }
+
+    fn forget_learned_entry(&self, reading: &str, word: &str) -> bool {
+        Self::forget(&mut self.state(), reading, word)
+    }
+
+    fn forget_learning_candidate(&self, candidate_id: u64) -> bool {
+        let mut state = self.state();
+        let Some(candidate) = state.candidates.get(&candidate_id).cloned() else {
+            return false;
+        };
+        Self::forget(&mut state, &candidate.reading, &candidate.surface)
+    }
+
+    fn set_user_words(&self, words: &[UserWord]) -> Result<(), String> {
+        self.state().user_words = words.iter().map(user_word_entry).collect();
+        Ok(())
+    }
+
+    fn add_user_word(&self, word: &UserWord) -> Result<(), String> {
+        self.state().user_words.push(user_word_entry(word));
+        Ok(())
+    }
+
+    fn remove_user_word(&self, word: &UserWord) -> Result<(), String> {
+        let entry = user_word_entry(word);
+        self.state()
+            .user_words
+            .retain(|existing| *existing != entry);
+        Ok(())
+    }
+}
+
+fn user_word_entry(word: &UserWord) -> (String, String) {
+    (to_hiragana(&word.reading), word.word.clone())
+}
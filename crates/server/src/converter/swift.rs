@@ -0,0 +1,664 @@
+use super::{
+    decode_learned_entries, decode_reconversion_readings, ComposedText, ComposedTextQuery,
+    ConverterBackend, RawClauseBoundaryAdjustment, RawComposingText,
+};
+use crate::{
+    elapsed_ms, log_event, log_performance_event, should_log, should_log_performance,
+    AzookeyServerCrashTraceEnabled, AzookeyServerCrashTraceFromSwift, AzookeyServerLogEnabled,
+    AzookeyServerLogFlushFromSwift, AzookeyServerLogFromSwift, AzookeyServerLogLevelEnabled,
+    AzookeyServerPerformanceLogEnabled, AzookeyServerPerformanceLogFromSwift, ServerLogLevel,
+};
+use shared::{
+    proto::{LearnedEntry, LearningCandidateCommit, Suggestion},
+    user_dictionary::UserWord,
+};
+use std::{
+    collections::HashSet,
+    ffi::{c_char, c_int, c_uint, CStr, CString},
+    time::Instant,
+};
+
+const USE_ZENZAI: bool = true;
+
+fn performance_instant(enabled: bool) -> Option<Instant> {
+    enabled.then(Instant::now)
+}
+
+fn add_elapsed_ms(total: &mut u128, start: Option<Instant>) {
+    if let Some(start) = start {
+        *total += elapsed_ms(start);
+    }
+}
+
+fn query_operation(query: ComposedTextQuery) -> &'static str {
+    match query {
+        ComposedTextQuery::Full => "get_composed_text",
+        ComposedTextQuery::WithoutZenzai => "get_composed_text_without_zenzai",
+        ComposedTextQuery::CursorPrefix { .. } => "get_composed_text_for_cursor_prefix",
+        ComposedTextQuery::Reconversion => "get_composed_text_for_reconversion",
+        ComposedTextQuery::Prediction => "get_composed_text_for_prediction",
+    }
+}
+
+#[derive(Debug, Clone)]
+#[repr(C)]
+struct FFICandidate {
+    text: *mut c_char,
+    subtext: *mut c_char,
+    hiragana: *mut c_char,
+    corresponding_count: c_int,
+    candidate_id: u64,
+    origin: c_int,
+    lcid: c_uint,
+    rcid: c_uint,
+    annotation: *mut c_char,
+}
+
+unsafe extern "C" {
+    fn Initialize(path: *const c_char, use_zenzai: bool);
+    fn SetContext(context: *const c_char, rightContext: *const c_char);
+    fn AppendText(input: *const c_char, cursorPtr: *mut c_int) -> *mut c_char;
+    fn AppendTextDirect(input: *const c_char, cursorPtr: *mut c_int) -> *mut c_char;
+    fn RemoveText(cursorPtr: *mut c_int) -> *mut c_char;
+    fn MoveCursor(offset: c_int, cursorPtr: *mut c_int) -> *mut c_char;
+    fn GetCursorPosition() -> c_int;
+    fn GetRawInput() -> *mut c_char;
+    fn AdjustClauseBoundary(
+        currentInputCount: c_int,
+        direction: c_int,
+        expectedRawInput: *const c_char,
+        appliedPtr: *mut c_int,
+        adjustedInputCountPtr: *mut c_int,
+        cursorOffsetPtr: *mut c_int,
+    ) -> *mut c_char;
+    fn ClearComposingTextSnapshots();
+    fn PushComposingTextSnapshot(selectedCandidateId: u64);
+    fn PopComposingTextSnapshot(selectedCandidateId: u64);
+    fn PinLearningCandidate(candidateId: u64) -> bool;
+    fn ShrinkText(offset: c_int) -> *mut c_char;
+    fn ClearText();
+    fn Warmup() -> bool;
+    fn HasActiveComposition() -> bool;
+    fn ComposedTextWouldUseZenzai() -> bool;
+    fn SelectCompositionSession(sessionId: u64);
+    fn DestroyCompositionSession(sessionId: u64);
+    fn SetSessionZenzaiAllowed(allowed: bool);
+    fn InferReconversionReadings(surface: *const c_char) -> *mut c_char;
+    fn GetComposedText(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
+    fn GetComposedTextWithoutZenzai(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
+    fn GetComposedTextForReconversion(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
+    fn GetComposedTextForPrediction(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
+    fn GetComposedTextForCursorPrefix(
+        requiredInputCount: c_int,
+        lengthPtr: *mut c_int,
+    ) -> *mut *mut FFICandidate;
+    fn FreeCString(ptr: *mut c_char);
+    fn FreeCandidateList(ptr: *mut *mut FFICandidate, length: c_int);
+    fn CommitLearningCandidate(candidateId: u64, commitKind: c_int) -> bool;
+    fn CommitLearningCandidates(
+        candidateIds: *const u64,
+        commitKinds: *const c_int,
+        count: c_int,
+    ) -> c_int;
+    fn ResetLearningMemory() -> bool;
+    fn ListLearnedEntries() -> *mut c_char;
+    fn ForgetLearnedEntry(reading: *const c_char, word: *const c_char) -> bool;
+    fn ForgetLearningCandidate(candidateId: u64) -> bool;
+    fn SetUserDictionary(wordsJson: *const c_char) -> bool;
+    fn AddUserDictionaryWord(wordsJson: *const c_char) -> bool;
+    fn RemoveUserDictionaryWord(wordsJson: *const c_char) -> bool;
+    fn LoadConfig();
+    fn SetRequestId(request_id: u64);
+    fn SetServerLogCallbacks(
+        log_enabled: extern "C" fn() -> bool,
+        log_level_enabled: extern "C" fn(*const c_char) -> bool,
+        performance_log_enabled: extern "C" fn() -> bool,
+        write_log: extern "C" fn(*const c_char, *const c_char),
+        write_performance_log: extern "C" fn(u64, *const c_char, *const c_char, u64, *const c_char),
+        flush_log: extern "C" fn(),
+        crash_trace_enabled: extern "C" fn() -> bool,
+        write_crash_trace: extern "C" fn(
+            *const c_char,
+            *const c_char,
+            *const c_char,
+            *const c_char,
+        ),
+    );
+}
+
+struct OwnedFfiString {
+    ptr: *mut c_char,
+}
+
+impl OwnedFfiString {
+    unsafe fn from_raw(scope: &str, ptr: *mut c_char) -> Result<Self, String> {
+        if ptr.is_null() {
+            return Err(format!("[{scope}] Swift FFI returned null pointer"));
+        }
+
+        Ok(Self { ptr })
+    }
+
+    fn to_string_lossy(&self) -> String {
+        unsafe { CStr::from_ptr(self.ptr as *const c_char) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for OwnedFfiString {
+    fn drop(&mut self) {
+        unsafe {
+            FreeCString(self.ptr);
+        }
+    }
+}
+
+struct OwnedFfiCandidates {
+    ptr: *mut *mut FFICandidate,
+    length: c_int,
+}
+
+impl OwnedFfiCandidates {
+    unsafe fn from_raw(
+        scope: &str,
+        ptr: *mut *mut FFICandidate,
+        length: c_int,
+    ) -> Result<Self, String> {
+        if length < 0 {
+            if !ptr.is_null() {
+                FreeCandidateList(ptr, 0);
+            }
+            return Err(format!("[{scope}] invalid negative length: {length}"));
+        }
+
+        if length > 0 && ptr.is_null() {
+            return Err(format!(
+                "[{scope}] null candidate list pointer (length={length})"
+            ));
+        }
+
+        Ok(Self { ptr, length })
+    }
+
+    fn len(&self) -> usize {
+        self.length as usize
+    }
+
+    unsafe fn candidate_ptr(&self, index: usize) -> *mut FFICandidate {
+        *self.ptr.add(index)
+    }
+
+    fn free_with_performance(mut self, request_id: u64, operation: &str) {
+        let ptr = self.ptr;
+        let length = self.length;
+        self.ptr = std::ptr::null_mut();
+        self.length = 0;
+
+        let free_start = Instant::now();
+        unsafe {
+            FreeCandidateList(ptr, length);
+        }
+        performance_event_lazy!(
+            request_id,
+            operation,
+            "free_candidate_list",
+            elapsed_ms(free_start),
+            "candidate_count={}",
+            length.max(0)
+        );
+    }
+}
+
+impl Drop for OwnedFfiCandidates {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                FreeCandidateList(self.ptr, self.length);
+            }
+        }
+    }
+}
+
+fn cstring_from_input(scope: &str, value: &str) -> Result<CString, String> {
+    CString::new(value).map_err(|error| format!("[{scope}] CString::new failed: {error}"))
+}
+
+fn ffi_text_result(scope: &str, result: *mut c_char) -> Result<String, String> {
+    let result = unsafe { OwnedFfiString::from_raw(scope, result)? };
+    Ok(result.to_string_lossy())
+}
+
+fn register_server_log_callbacks() {
+    unsafe {
+        SetServerLogCallbacks(
+            AzookeyServerLogEnabled,
+            AzookeyServerLogLevelEnabled,
+            AzookeyServerPerformanceLogEnabled,
+            AzookeyServerLogFromSwift,
+            AzookeyServerPerformanceLogFromSwift,
+            AzookeyServerLogFlushFromSwift,
+            AzookeyServerCrashTraceEnabled,
+            AzookeyServerCrashTraceFromSwift,
+        );
+    }
+}
+
+/// The AzooKeyKanaKanjiConverter binding built from `server-swift`.
+///
+/// The Swift side keeps one global composition, so this type carries no state
+/// of its own.
+#[derive(Debug, Default)]
+pub(crate) struct SwiftConverter;
+
+impl SwiftConverter {
+    pub(crate) fn new() -> Self {
+        register_server_log_callbacks();
+        Self
+    }
+}
+
+impl ConverterBackend for SwiftConverter {
+    fn initialize(&self, path: &str) -> Result<(), String> {
+        let path = cstring_from_input("Initialize.path", path)?;
+        unsafe {
+            Initialize(path.as_ptr(), USE_ZENZAI);
+        }
+        Ok(())
+    }
+
+    fn set_request_id(&self, request_id: u64) {
+        unsafe {
+            SetRequestId(request_id);
+        }
+    }
+
+    fn set_context(&self, context: &str, right_context: &str) -> Result<(), String> {
+        let context = cstring_from_input("SetContext.context", context)?;
+        let right_context = cstring_from_input("SetContext.rightContext", right_context)?;
+        unsafe { SetContext(context.as_ptr(), right_context.as_ptr()) };
+        Ok(())
+    }
+
+    fn load_config(&self) {
+        unsafe { LoadConfig() };
+    }
+
+    fn warmup(&self) -> bool {
+        unsafe { Warmup() }
+    }
+
+    fn has_active_composition(&self) -> bool {
+        unsafe { HasActiveComposition() }
+    }
+
+    fn would_use_zenzai(&self) -> bool {
+        unsafe { ComposedTextWouldUseZenzai() }
+    }
+
+    fn select_session(&self, session_id: u64) {
+        unsafe { SelectCompositionSession(session_id) }
+    }
+
+    fn destroy_session(&self, session_id: u64) {
+        unsafe { DestroyCompositionSession(session_id) }
+    }
+
+    fn set_session_zenzai_allowed(&self, allowed: bool) {
+        unsafe { SetSessionZenzaiAllowed(allowed) }
+    }
+
+    fn append_text(&self, input: &str) -> Result<RawComposingText, String> {
+        let input = cstring_from_input("AppendText.input", input)?;
+
+        unsafe {
+            let mut cursor: c_int = 0;
+            let result = AppendText(input.as_ptr(), &mut cursor);
+            let text = ffi_text_result("AppendText", result)?;
+
+            Ok(RawComposingText { text, cursor })
+        }
+    }
+
+    fn append_text_direct(&self, input: &str) -> Result<RawComposingText, String> {
+        let input = cstring_from_input("AppendTextDirect.input", input)?;
+
+        unsafe {
+            let mut cursor: c_int = 0;
+            let result = AppendTextDirect(input.as_ptr(), &mut cursor);
+            let text = ffi_text_result("AppendTextDirect", result)?;
+
+            Ok(RawComposingText { text, cursor })
+        }
+    }
+
+    fn remove_text(&self) -> Result<RawComposingText, String> {
+        unsafe {
+            let mut cursor: c_int = 0;
+            let result = RemoveText(&mut cursor);
+            let text = ffi_text_result("RemoveText", result)?;
+
+            Ok(RawComposingText { text, cursor })
+        }
+    }
+
+    fn move_cursor(&self, offset: i32) -> Result<RawComposingText, String> {
+        unsafe {
+            let mut cursor: c_int = 0;
+            let result = MoveCursor(offset, &mut cursor);
+            let text = ffi_text_result("MoveCursor", result)?;
+
+            Ok(RawComposingText { text, cursor })
+        }
+    }
+
+    fn shrink_text(&self, offset: i32) -> Result<RawComposingText, String> {
+        unsafe {
+            let result = ShrinkText(offset);
+            let text = ffi_text_result("ShrinkText", result)?;
+
+            Ok(RawComposingText { text, cursor: 0 })
+        }
+    }
+
+    fn clear_text(&self) {
+        unsafe {
+            ClearText();
+        }
+    }
+
+    fn cursor_position(&self) -> i32 {
+        unsafe { GetCursorPosition() }
+    }
+
+    fn raw_input(&self) -> Result<String, String> {
+        unsafe { ffi_text_result("GetRawInput", GetRawInput()) }
+    }
+
+    fn adjust_clause_boundary(
+        &self,
+        current_input_count: i32,
+        direction: i32,
+        expected_raw_input: &str,
+    ) -> Result<RawClauseBoundaryAdjustment, String> {
+        let expected_raw_input =
+            cstring_from_input("AdjustClauseBoundary.expectedRawInput", expected_raw_input)?;
+        unsafe {
+            let mut applied: c_int = 0;
+            let mut adjusted_input_count: c_int = current_input_count;
+            let mut cursor_offset: c_int = 0;
+            let result = AdjustClauseBoundary(
+                current_input_count,
+                direction,
+                expected_raw_input.as_ptr(),
+                &mut applied,
+                &mut adjusted_input_count,
+                &mut cursor_offset,
+            );
+            let text = ffi_text_result("AdjustClauseBoundary", result)?;
+
+            Ok(RawClauseBoundaryAdjustment {
+                text,
+                applied: applied != 0,
+                adjusted_input_count,
+                cursor_offset,
+            })
+        }
+    }
+
+    fn clear_snapshots(&self) {
+        unsafe { ClearComposingTextSnapshots() }
+    }
+
+    fn push_snapshot(&self, selected_candidate_id: u64) {
+        unsafe { PushComposingTextSnapshot(selected_candidate_id) }
+    }
+
+    fn pop_snapshot(&self, selected_candidate_id: u64) {
+        unsafe { PopComposingTextSnapshot(selected_candidate_id) }
+    }
+
+    fn infer_reconversion_readings(&self, surface: &str) -> Result<Vec<String>, String> {
+        let surface = cstring_from_input("InferReconversionReadings.surface", surface)?;
+        let result = unsafe { InferReconversionReadings(surface.as_ptr()) };
+        if result.is_null() {
+            return Ok(Vec::new());
+        }
+        let result = unsafe { OwnedFfiString::from_raw("InferReconversionReadings", result)? };
+        decode_reconversion_readings(&result.to_string_lossy())
+    }
+
+    fn composed_text(
+        &self,
+        query: ComposedTextQuery,
+        request_id: u64,
+    ) -> Result<ComposedText, String> {
+        get_composed_text_from_ffi(query, request_id)
+    }
+
+    fn pin_learning_candidate(&self, candidate_id: u64) -> bool {
+        unsafe { PinLearningCandidate(candidate_id) }
+    }
+
+    fn commit_learning_candidate(&self, candidate_id: u64, commit_kind: i32) -> bool {
+        unsafe { CommitLearningCandidate(candidate_id, commit_kind as c_int) }
+    }
+
+    fn commit_learning_candidates(&self, commits: &[LearningCandidateCommit]) -> usize {
+        let Ok(count) = c_int::try_from(commits.len()) else {
+            return 0;
+        };
+        let candidate_ids = commits
+            .iter()
+            .map(|commit| commit.candidate_id)
+            .collect::<Vec<_>>();
+        let commit_kinds = commits
+            .iter()
+            .map(|commit| commit.commit_kind as c_int)
+            .collect::<Vec<_>>();
+        let committed = unsafe {
+            CommitLearningCandidates(candidate_ids.as_ptr(), commit_kinds.as_ptr(), count)
+        };
+        usize::try_from(committed).unwrap_or_default()
+    }
+
+    fn reset_learning_memory(&self) -> bool {
+        unsafe { ResetLearningMemory() }
+    }
+
+    fn learned_entries(&self) -> Result<Vec<LearnedEntry>, String> {
+        let result = unsafe { ListLearnedEntries() };
+        if result.is_null() {
+            return Err("ListLearnedEntries returned null".to_string());
+        }
+        let result = unsafe { OwnedFfiString::from_raw("ListLearnedEntries", result)? };
+        decode_learned_entries(&result.to_string_lossy())
+    }
+
+    fn forget_learned_entry(&self, reading: &str, word: &str) -> bool {
+        let (Ok(reading), Ok(word)) = (
+            cstring_from_input("ForgetLearnedEntry.reading", reading),
+            cstring_from_input("ForgetLearnedEntry.word", word),
+        ) else {
+            return false;
+        };
+        unsafe { ForgetLearnedEntry(reading.as_ptr(), word.as_ptr()) }
+    }
+
+    fn forget_learning_candidate(&self, candidate_id: u64) -> bool {
+        unsafe { ForgetLearningCandidate(candidate_id) }
+    }
+
+    fn set_user_words(&self, words: &[UserWord]) -> Result<(), String> {
+        let words = user_dictionary_json("SetUserDictionary", words)?;
+        if !unsafe { SetUserDictionary(words.as_ptr()) } {
+            return Err("[SetUserDictionary] Swift rejected the user dictionary".to_string());
+        }
+        Ok(())
+    }
+
+    fn add_user_word(&self, word: &UserWord) -> Result<(), String> {
+        let word = user_dictionary_json("AddUserDictionaryWord", std::slice::from_ref(word))?;
+        if !unsafe { AddUserDictionaryWord(word.as_ptr()) } {
+            return Err("[AddUserDictionaryWord] Swift rejected the word".to_string());
+        }
+        Ok(())
+    }
+
+    fn remove_user_word(&self, word: &UserWord) -> Result<(), String> {
+        let word = user_dictionary_json("RemoveUserDictionaryWord", std::slice::from_ref(word))?;
+        if !unsafe { RemoveUserDictionaryWord(word.as_ptr()) } {
+            return Err("[RemoveUserDictionaryWord] Swift rejected the word".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Encodes words as the JSON array every user dictionary FFI call takes.
+fn user_dictionary_json(scope: &str, words: &[UserWord]) -> Result<CString, String> {
+    let json = serde_json::to_string(words).map_err(|error| format!("[{scope}] {error}"))?;
+    cstring_from_input(scope, &json)
+}
+
+fn get_composed_text_from_ffi(
+    query: ComposedTextQuery,
+    request_id: u64,
+) -> Result<ComposedText, String> {
+    let mut length: c_int = 0;
+    let operation = query_operation(query);
+    let use_cursor_prefix = matches!(query, ComposedTextQuery::CursorPrefix { .. });
+    let reconversion = query == ComposedTextQuery::Reconversion;
+    let ffi_call_start = Instant::now();
+    let result = unsafe {
+        match query {
+            ComposedTextQuery::Full => GetComposedText(&mut length),
+            ComposedTextQuery::WithoutZenzai => GetComposedTextWithoutZenzai(&mut length),
+            ComposedTextQuery::CursorPrefix {
+                required_input_count,
+            } => GetComposedTextForCursorPrefix(required_input_count.unwrap_or(-1), &mut length),
+            ComposedTextQuery::Reconversion => GetComposedTextForReconversion(&mut length),
+            ComposedTextQuery::Prediction => GetComposedTextForPrediction(&mut length),
+        }
+    };
+    let call_name = match query {
+        ComposedTextQuery::Full => "GetComposedText",
+        ComposedTextQuery::WithoutZenzai => "GetComposedTextWithoutZenzai",
+        ComposedTextQuery::CursorPrefix { .. } => "GetComposedTextForCursorPrefix",
+        ComposedTextQuery::Reconversion => "GetComposedTextForReconversion",
+        ComposedTextQuery::Prediction => "GetComposedTextForPrediction",
+    };
+    let candidates = unsafe { OwnedFfiCandidates::from_raw(call_name, result, length)? };
+    let length = candidates.len();
+    performance_event_lazy!(
+        request_id,
+        operation,
+        "ffi_call",
+        elapsed_ms(ffi_call_start),
+        "candidate_count={length};use_cursor_prefix={use_cursor_prefix};reconversion={reconversion}"
+    );
+
+    let mut suggestions = Vec::with_capacity(length);
+    let mut hiragana = None;
+    let mut seen_texts = HashSet::with_capacity(length);
+    let performance_enabled = should_log_performance();
+    let mut cstr_decode_ms = 0;
+    let mut dedup_ms = 0;
+    let mut duplicate_count = 0usize;
+    log_event_lazy!(
+        ServerLogLevel::Debug,
+        "[{call_name}] candidate_count={length}"
+    );
+
+    for index in 0..length {
+        let candidate_ptr = unsafe { candidates.candidate_ptr(index) };
+        if candidate_ptr.is_null() {
+            log_event(
+                ServerLogLevel::Warn,
+                &format!("[{call_name}] candidate[{index}] is null and skipped"),
+            );
+            continue;
+        }
+
+        let candidate = unsafe { (*candidate_ptr).clone() };
+        if candidate.text.is_null() || candidate.subtext.is_null() {
+            log_event(
+                ServerLogLevel::Warn,
+                &format!(
+                    "[{call_name}] candidate[{index}] has null text/subtext pointer and was skipped"
+                ),
+            );
+            continue;
+        }
+
+        if hiragana.is_none() && !candidate.hiragana.is_null() {
+            let decode_start = performance_instant(performance_enabled);
+            hiragana = Some(
+                unsafe { CStr::from_ptr(candidate.hiragana) }
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+            add_elapsed_ms(&mut cstr_decode_ms, decode_start);
+        }
+
+        let text_decode_start = performance_instant(performance_enabled);
+        let text = unsafe { CStr::from_ptr(candidate.text) }
+            .to_string_lossy()
+            .into_owned();
+        add_elapsed_ms(&mut cstr_decode_ms, text_decode_start);
+
+        let dedup_start = performance_instant(performance_enabled);
+        if !seen_texts.insert(text.clone()) {
+            duplicate_count += 1;
+            add_elapsed_ms(&mut dedup_ms, dedup_start);
+            continue;
+        }
+        add_elapsed_ms(&mut dedup_ms, dedup_start);
+
+        let subtext_decode_start = performance_instant(performance_enabled);
+        let subtext = unsafe { CStr::from_ptr(candidate.subtext) }
+            .to_string_lossy()
+            .into_owned();
+        let annotation = (!candidate.annotation.is_null()).then(|| {
+            unsafe { CStr::from_ptr(candidate.annotation) }
+                .to_string_lossy()
+                .into_owned()
+        });
+        add_elapsed_ms(&mut cstr_decode_ms, subtext_decode_start);
+        let corresponding_count = candidate.corresponding_count;
+
+        let suggestion = Suggestion {
+            text,
+            subtext,
+            corresponding_count,
+            candidate_id: candidate.candidate_id,
+            origin: candidate.origin,
+            annotation,
+            lcid: candidate.lcid,
+            rcid: candidate.rcid,
+        };
+
+        suggestions.push(suggestion);
+    }
+    performance_event_lazy!(
+        request_id,
+        operation,
+        "cstr_decode",
+        cstr_decode_ms,
+        "candidate_count={length};unique_candidate_count={};duplicate_count={duplicate_count}",
+        suggestions.len()
+    );
+    performance_event_lazy!(
+        request_id,
+        operation,
+        "dedup",
+        dedup_ms,
+        "candidate_count={length};unique_candidate_count={};duplicate_count={duplicate_count}",
+        suggestions.len()
+    );
+    candidates.free_with_performance(request_id, operation);
+
+    Ok(ComposedText {
+        hiragana,
+        suggestions,
+    })
+}
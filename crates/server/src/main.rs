@@ -1,39 +1,53 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use azookey_server::TonicNamedPipeServer;
 use tonic::{transport::Server, Request, Response, Status};
 use tonic_reflection::server::Builder as ReflectionBuilder;
+#[cfg(windows)]
 use windows::Win32::System::Threading::{GetCurrentProcess, SetPriorityClass, HIGH_PRIORITY_CLASS};
 
 use shared::proto::azookey_service_server::{AzookeyService, AzookeyServiceServer};
 use shared::proto::{
-    AdjustClauseBoundaryRequest, AdjustClauseBoundaryResponse, AdvanceClauseRequest,
-    AdvanceClauseResponse, AppendTextRequest, AppendTextResponse, ClearTextRequest,
-    ClearTextResponse, ComposingText, CompositionOperationKind, CompositionSnapshotOperation,
+    AddUserWordRequest, AddUserWordResponse, AdjustClauseBoundaryRequest,
+    AdjustClauseBoundaryResponse, AdvanceClauseRequest, AdvanceClauseResponse, AppendTextRequest,
+    AppendTextResponse, ClearTextRequest, ClearTextResponse, ComposingText,
+    CompositionOperationKind, CompositionSnapshotOperation, CreateCompositionSessionRequest,
+    CreateCompositionSessionResponse, DestroyCompositionSessionRequest,
+    DestroyCompositionSessionResponse, GetRefinedCandidatesRequest, GetRefinedCandidatesResponse,
+    ImportUserWordsRequest, ImportUserWordsResponse, ListUserWordsRequest, ListUserWordsResponse,
     MoveCursorRequest, MoveCursorResponse, PerformanceLogRequest, PerformanceLogResponse,
-    PrepareFutureClausesRequest, PrepareFutureClausesResponse, PreparedClauseAdvance,
-    RemoveTextRequest, RemoveTextResponse, ReplaceCompositionRequest, ReplaceCompositionResponse,
-    ShrinkTextRequest, ShrinkTextResponse, StartReconversionRequest, StartReconversionResponse,
-    Suggestion, UpdateCompositionSnapshotRequest, UpdateCompositionSnapshotResponse,
+    PredictRequest, PredictResponse, PrepareFutureClausesRequest, PrepareFutureClausesResponse,
+    PreparedClauseAdvance, RefinementStatus, RemoveTextRequest, RemoveTextResponse,
+    RemoveUserWordRequest, RemoveUserWordResponse, ReplaceCompositionRequest,
+    ReplaceCompositionResponse, SearchUserWordsRequest, SearchUserWordsResponse, ShrinkTextRequest,
+    ShrinkTextResponse, StartReconversionRequest, StartReconversionResponse, Suggestion,
+    UpdateCompositionSnapshotRequest, UpdateCompositionSnapshotResponse,
+};
+use shared::{
+    kana_layout,
+    transport::{IpcEndpoint, IpcListener},
+    user_dictionary::{
+        shared_dictionaries::SharedDictionaries, PartOfSpeech, UserDictionaryStore, UserWord,
+        UserWordError,
+    },
+    AppConfig, RewriterConfig, UserDictionaryConfig,
 };
-use shared::{AppConfig, SERVER_PIPE_PATH};
 
+use refinement::{RefinementOutcome, Refinements, MAX_REFINEMENT_WAIT};
+use session::CompositionSessions;
 use std::{
     backtrace::Backtrace,
     collections::HashSet,
-    ffi::{c_char, c_int, CStr, CString, OsStr},
+    ffi::{c_char, CStr, OsStr},
     fs::{self, File, OpenOptions},
     io::{BufWriter, Write},
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
-        mpsc, Arc, OnceLock,
+        mpsc, Arc, Mutex, MutexGuard, OnceLock,
     },
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-const USE_ZENZAI: bool = true;
-const INPUT_STYLE_DIRECT: i32 = 1;
 const SERVER_LOG_FILE_NAME: &str = "server.log";
 const SERVER_PERFORMANCE_LOG_FILE_NAME: &str = "server-performance.tsv";
 const SERVER_CRASH_TRACE_FILE_NAME: &str = "server-crash-trace.json";
@@ -47,6 +61,7 @@ const LOG_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
 const LOG_FLUSH_ACK_TIMEOUT: Duration = Duration::from_secs(2);
 const WARMUP_INTERVAL_SECS: u64 = 30;
 const WARMUP_RECENT_INPUT_SKIP_MS: u64 = 2_000;
+const SHARED_DICTIONARY_POLL_INTERVAL: Duration = Duration::from_secs(10);
 const MAX_RECONVERSION_SURFACE_COUNT: usize = 128;
 const MAX_RECONVERSION_READINGS: usize = 4;
 const MAX_RECONVERSION_SUGGESTIONS: usize = 100;
@@ -386,179 +401,17 @@ macro_rules! performance_event_lazy {
     }};
 }
 
-struct RawComposingText {
-    text: String,
-    cursor: i32,
-}
-
-struct ComposedText {
-    hiragana: Option<String>,
-    suggestions: Vec<Suggestion>,
-}
-
-#[derive(Debug, Clone)]
-#[repr(C)]
-struct FFICandidate {
-    text: *mut c_char,
-    subtext: *mut c_char,
-    hiragana: *mut c_char,
-    corresponding_count: c_int,
-    candidate_id: u64,
-}
-
-unsafe extern "C" {
-    fn Initialize(path: *const c_char, use_zenzai: bool);
-    fn SetContext(context: *const c_char);
-    fn AppendText(input: *const c_char, cursorPtr: *mut c_int) -> *mut c_char;
-    fn AppendTextDirect(input: *const c_char, cursorPtr: *mut c_int) -> *mut c_char;
-    fn RemoveText(cursorPtr: *mut c_int) -> *mut c_char;
-    fn MoveCursor(offset: c_int, cursorPtr: *mut c_int) -> *mut c_char;
-    fn GetCursorPosition() -> c_int;
-    fn GetRawInput() -> *mut c_char;
-    fn AdjustClauseBoundary(
-        currentInputCount: c_int,
-        direction: c_int,
-        expectedRawInput: *const c_char,
-        appliedPtr: *mut c_int,
-        adjustedInputCountPtr: *mut c_int,
-        cursorOffsetPtr: *mut c_int,
-    ) -> *mut c_char;
-    fn ClearComposingTextSnapshots();
-    fn PushComposingTextSnapshot(selectedCandidateId: u64);
-    fn PopComposingTextSnapshot(selectedCandidateId: u64);
-    fn PinLearningCandidate(candidateId: u64) -> bool;
-    fn ShrinkText(offset: c_int) -> *mut c_char;
-    fn ClearText();
-    fn Warmup() -> bool;
-    fn HasActiveComposition() -> bool;
-    fn InferReconversionReadings(surface: *const c_char) -> *mut c_char;
-    fn GetComposedText(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
-    fn GetComposedTextForReconversion(lengthPtr: *mut c_int) -> *mut *mut FFICandidate;
-    fn GetComposedTextForCursorPrefix(
-        requiredInputCount: c_int,
-        lengthPtr: *mut c_int,
-    ) -> *mut *mut FFICandidate;
-    fn FreeCString(ptr: *mut c_char);
-    fn FreeCandidateList(ptr: *mut *mut FFICandidate, length: c_int);
-    fn CommitLearningCandidate(candidateId: u64, commitKind: c_int) -> bool;
-    fn CommitLearningCandidates(
-        candidateIds: *const u64,
-        commitKinds: *const c_int,
-        count: c_int,
-    ) -> c_int;
-    fn ResetLearningMemory() -> bool;
-    fn LoadConfig();
-    fn SetRequestId(request_id: u64);
-    fn SetServerLogCallbacks(
-        log_enabled: extern "C" fn() -> bool,
-        log_level_enabled: extern "C" fn(*const c_char) -> bool,
-        performance_log_enabled: extern "C" fn() -> bool,
-        write_log: extern "C" fn(*const c_char, *const c_char),
-        write_performance_log: extern "C" fn(u64, *const c_char, *const c_char, u64, *const c_char),
-        flush_log: extern "C" fn(),
-        crash_trace_enabled: extern "C" fn() -> bool,
-        write_crash_trace: extern "C" fn(
-            *const c_char,
-            *const c_char,
-            *const c_char,
-            *const c_char,
-        ),
-    );
-}
-
-struct OwnedFfiString {
-    ptr: *mut c_char,
-}
-
-impl OwnedFfiString {
-    unsafe fn from_raw(scope: &str, ptr: *mut c_char) -> Result<Self, String> {
-        if ptr.is_null() {
-            return Err(format!("[{scope}] Swift FFI returned null pointer"));
-        }
-
-        Ok(Self { ptr })
-    }
-
-    fn to_string_lossy(&self) -> String {
-        unsafe { CStr::from_ptr(self.ptr as *const c_char) }
-            .to_string_lossy()
-            .into_owned()
-    }
-}
-
-impl Drop for OwnedFfiString {
-    fn drop(&mut self) {
-        unsafe {
-            FreeCString(self.ptr);
-        }
-    }
-}
-
-struct OwnedFfiCandidates {
-    ptr: *mut *mut FFICandidate,
-    length: c_int,
-}
-
-impl OwnedFfiCandidates {
-    unsafe fn from_raw(
-        scope: &str,
-        ptr: *mut *mut FFICandidate,
-        length: c_int,
-    ) -> Result<Self, String> {
-        if length < 0 {
-            if !ptr.is_null() {
-                FreeCandidateList(ptr, 0);
-            }
-            return Err(format!("[{scope}] invalid negative length: {length}"));
-        }
-
-        if length > 0 && ptr.is_null() {
-            return Err(format!(
-                "[{scope}] null candidate list pointer (length={length})"
-            ));
-        }
-
-        Ok(Self { ptr, length })
-    }
-
-    fn len(&self) -> usize {
-        self.length as usize
-    }
-
-    unsafe fn candidate_ptr(&self, index: usize) -> *mut FFICandidate {
-        *self.ptr.add(index)
-    }
-
-    fn free_with_performance(mut self, request_id: u64, operation: &str) {
-        let ptr = self.ptr;
-        let length = self.length;
-        self.ptr = std::ptr::null_mut();
-        self.length = 0;
-
-        let free_start = Instant::now();
-        unsafe {
-            FreeCandidateList(ptr, length);
-        }
-        performance_event_lazy!(
-            request_id,
-            operation,
-            "free_candidate_list",
-            elapsed_ms(free_start),
-            "candidate_count={}",
-            length.max(0)
-        );
-    }
-}
+mod converter;
+mod refinement;
+mod rewriter;
+#[cfg(test)]
+mod service_tests;
+mod session;
 
-impl Drop for OwnedFfiCandidates {
-    fn drop(&mut self) {
-        if !self.ptr.is_null() {
-            unsafe {
-                FreeCandidateList(self.ptr, self.length);
-            }
-        }
-    }
-}
+use converter::{
+    ComposedText, ComposedTextQuery, ConverterBackend, RawComposingText, INPUT_STYLE_DIRECT,
+    INPUT_STYLE_KANA,
+};
 
 fn next_request_id() -> u64 {
     SERVER_GENERATED_REQUEST_ID_PREFIX | REQUEST_SEQUENCE.fetch_add(1, Ordering::Relaxed)
@@ -579,41 +432,10 @@ fn server_session_id() -> u64 {
     })
 }
 
-fn set_request_id(request_id: u64) {
-    unsafe {
-        SetRequestId(request_id);
-    }
-}
-
-fn register_server_log_callbacks() {
-    unsafe {
-        SetServerLogCallbacks(
-            AzookeyServerLogEnabled,
-            AzookeyServerLogLevelEnabled,
-            AzookeyServerPerformanceLogEnabled,
-            AzookeyServerLogFromSwift,
-            AzookeyServerPerformanceLogFromSwift,
-            AzookeyServerLogFlushFromSwift,
-            AzookeyServerCrashTraceEnabled,
-            AzookeyServerCrashTraceFromSwift,
-        );
-    }
-}
-
 fn elapsed_ms(start: Instant) -> u128 {
     start.elapsed().as_millis()
 }
 
-fn performance_instant(enabled: bool) -> Option<Instant> {
-    enabled.then(Instant::now)
-}
-
-fn add_elapsed_ms(total: &mut u128, start: Option<Instant>) {
-    if let Some(start) = start {
-        *total += elapsed_ms(start);
-    }
-}
-
 fn monotonic_millis() -> u64 {
     let elapsed = MONOTONIC_START
         .get_or_init(Instant::now)
@@ -1116,20 +938,20 @@ fn install_panic_hook() {
     }));
 }
 
-fn cstring_from_input(scope: &str, value: &str) -> Result<CString, String> {
-    CString::new(value).map_err(|error| format!("[{scope}] CString::new failed: {error}"))
-}
-
-fn ffi_text_result(scope: &str, result: *mut c_char) -> Result<String, String> {
-    let result = unsafe { OwnedFfiString::from_raw(scope, result)? };
-    Ok(result.to_string_lossy())
-}
-
 fn status_from_error(scope: &str, error: String) -> Status {
     log_event(ServerLogLevel::Error, &error);
     Status::internal(format!("{scope} failed"))
 }
 
+fn status_from_user_word_error(error: UserWordError) -> Status {
+    match error {
+        UserWordError::EmptyReading | UserWordError::EmptyWord => {
+            Status::invalid_argument(error.to_string())
+        }
+        UserWordError::Duplicate { .. } => Status::already_exists(error.to_string()),
+    }
+}
+
 #[allow(clippy::result_large_err)]
 fn validate_shrink_offset(offset: i32) -> Result<i32, Status> {
     if offset < 0 {
@@ -1173,6 +995,7 @@ fn merge_reconversion_suggestions(
         subtext: String::new(),
         corresponding_count,
         candidate_id: 0,
+        ..Suggestion::default()
     });
 
     let max_group_len = suggestion_groups.iter().map(Vec::len).max().unwrap_or(0);
@@ -1213,82 +1036,6 @@ fn retained_prepared_snapshot_count(
     }
 }
 
-fn initialize(path: &str) -> Result<(), String> {
-    let path = cstring_from_input("Initialize.path", path)?;
-    unsafe {
-        Initialize(path.as_ptr(), USE_ZENZAI);
-    }
-    Ok(())
-}
-
-fn add_text(input: &str) -> Result<RawComposingText, String> {
-    let input = cstring_from_input("AppendText.input", input)?;
-
-    unsafe {
-        let mut cursor: c_int = 0;
-        let result = AppendText(input.as_ptr(), &mut cursor);
-        let text = ffi_text_result("AppendText", result)?;
-
-        Ok(RawComposingText { text, cursor })
-    }
-}
-
-fn add_text_direct(input: &str) -> Result<RawComposingText, String> {
-    let input = cstring_from_input("AppendTextDirect.input", input)?;
-
-    unsafe {
-        let mut cursor: c_int = 0;
-        let result = AppendTextDirect(input.as_ptr(), &mut cursor);
-        let text = ffi_text_result("AppendTextDirect", result)?;
-
-        Ok(RawComposingText { text, cursor })
-    }
-}
-
-fn infer_reconversion_readings(surface: &str) -> Result<Vec<String>, String> {
-    let surface = cstring_from_input("InferReconversionReadings.surface", surface)?;
-    let result = unsafe { InferReconversionReadings(surface.as_ptr()) };
-    if result.is_null() {
-        return Ok(Vec::new());
-    }
-    let result = unsafe { OwnedFfiString::from_raw("InferReconversionReadings", result)? };
-    decode_reconversion_readings(&result.to_string_lossy())
-}
-
-fn decode_reconversion_readings(encoded: &str) -> Result<Vec<String>, String> {
-    let readings: Vec<String> = serde_json::from_str(encoded)
-        .map_err(|error| format!("InferReconversionReadings returned invalid JSON: {error}"))?;
-    if readings.iter().any(String::is_empty) {
-        return Err("InferReconversionReadings returned an empty reading".to_string());
-    }
-    Ok(readings)
-}
-
-fn move_cursor(offset: i32) -> Result<RawComposingText, String> {
-    unsafe {
-        let mut cursor: c_int = 0;
-        let result = MoveCursor(offset, &mut cursor);
-        let text = ffi_text_result("MoveCursor", result)?;
-
-        Ok(RawComposingText { text, cursor })
-    }
-}
-
-fn get_cursor_position() -> i32 {
-    unsafe { GetCursorPosition() }
-}
-
-fn get_raw_input() -> Result<String, String> {
-    unsafe { ffi_text_result("GetRawInput", GetRawInput()) }
-}
-
-struct RawClauseBoundaryAdjustment {
-    text: String,
-    applied: bool,
-    adjusted_input_count: i32,
-    cursor_offset: i32,
-}
-
 fn hiragana_boundary_fallback(
     hiragana: &str,
     cursor: i32,
@@ -1315,47 +1062,20 @@ fn hiragana_boundary_fallback(
             subtext: suffix,
             corresponding_count,
             candidate_id: 0,
+            ..Suggestion::default()
         }],
     })
 }
 
-fn adjust_clause_boundary(
-    current_input_count: i32,
-    direction: i32,
-    expected_raw_input: &str,
-) -> Result<RawClauseBoundaryAdjustment, String> {
-    let expected_raw_input =
-        cstring_from_input("AdjustClauseBoundary.expectedRawInput", expected_raw_input)?;
-    unsafe {
-        let mut applied: c_int = 0;
-        let mut adjusted_input_count: c_int = current_input_count;
-        let mut cursor_offset: c_int = 0;
-        let result = AdjustClauseBoundary(
-            current_input_count,
-            direction,
-            expected_raw_input.as_ptr(),
-            &mut applied,
-            &mut adjusted_input_count,
-            &mut cursor_offset,
-        );
-        let text = ffi_text_result("AdjustClauseBoundary", result)?;
-
-        Ok(RawClauseBoundaryAdjustment {
-            text,
-            applied: applied != 0,
-            adjusted_input_count,
-            cursor_offset,
-        })
-    }
-}
-
 fn rollback_clause_boundary(
+    converter: &dyn ConverterBackend,
     adjusted_input_count: i32,
     original_input_count: i32,
     direction: i32,
     expected_raw_input: &str,
 ) -> Result<(), String> {
-    let rollback = adjust_clause_boundary(adjusted_input_count, -direction, expected_raw_input)?;
+    let rollback =
+        converter.adjust_clause_boundary(adjusted_input_count, -direction, expected_raw_input)?;
     if rollback.applied && rollback.adjusted_input_count == original_input_count {
         Ok(())
     } else {
@@ -1366,55 +1086,6 @@ fn rollback_clause_boundary(
     }
 }
 
-fn remove_text() -> Result<RawComposingText, String> {
-    unsafe {
-        let mut cursor: c_int = 0;
-        let result = RemoveText(&mut cursor);
-        let text = ffi_text_result("RemoveText", result)?;
-
-        Ok(RawComposingText { text, cursor })
-    }
-}
-
-fn clear_text() {
-    unsafe {
-        ClearText();
-    }
-}
-
-fn swift_commit_learning_candidate(candidate_id: u64, commit_kind: i32) -> bool {
-    unsafe { CommitLearningCandidate(candidate_id, commit_kind as c_int) }
-}
-
-fn swift_commit_learning_candidates(commits: &[shared::proto::LearningCandidateCommit]) -> usize {
-    let Ok(count) = c_int::try_from(commits.len()) else {
-        return 0;
-    };
-    let candidate_ids = commits
-        .iter()
-        .map(|commit| commit.candidate_id)
-        .collect::<Vec<_>>();
-    let commit_kinds = commits
-        .iter()
-        .map(|commit| commit.commit_kind as c_int)
-        .collect::<Vec<_>>();
-    let committed =
-        unsafe { CommitLearningCandidates(candidate_ids.as_ptr(), commit_kinds.as_ptr(), count) };
-    usize::try_from(committed).unwrap_or_default()
-}
-
-fn swift_reset_learning_memory() -> bool {
-    unsafe { ResetLearningMemory() }
-}
-
-fn warmup() -> bool {
-    unsafe { Warmup() }
-}
-
-fn query_active_composition_state() -> bool {
-    unsafe { HasActiveComposition() }
-}
-
 fn has_active_composition() -> bool {
     HAS_ACTIVE_COMPOSITION.load(Ordering::Relaxed)
 }
@@ -1423,226 +1094,446 @@ fn update_active_composition_state(text: &str) {
     HAS_ACTIVE_COMPOSITION.store(!text.is_empty(), Ordering::Relaxed);
 }
 
-fn get_composed_text(
-    use_cursor_prefix: bool,
-    required_cursor_prefix_input_count: Option<i32>,
-    request_id: u64,
-) -> Result<ComposedText, String> {
-    get_composed_text_from_ffi(
-        use_cursor_prefix,
-        required_cursor_prefix_input_count,
-        false,
-        request_id,
-    )
+fn append_with_style(
+    converter: &dyn ConverterBackend,
+    input: &str,
+    input_style: i32,
+) -> Result<RawComposingText, String> {
+    match input_style {
+        INPUT_STYLE_DIRECT => converter.append_text_direct(input),
+        INPUT_STYLE_KANA => append_kana(converter, input),
+        _ => converter.append_text(input),
+    }
+}
+
+/// Appends kana typed on the JIS kana layout. A dakuten or handakuten replaces
+/// the kana before the cursor with its voiced form, so backspace removes が as
+/// one character and ledger replay rebuilds the same composition.
+fn append_kana(converter: &dyn ConverterBackend, input: &str) -> Result<RawComposingText, String> {
+    let mut composing = None;
+    for kana in input.chars() {
+        let mut appended = converter.append_text_direct(kana.encode_utf8(&mut [0; 4]))?;
+        if kana_layout::is_voicing_mark(kana) {
+            let before_cursor = usize::try_from(appended.cursor)
+                .unwrap_or_default()
+                .checked_sub(2)
+                .and_then(|index| appended.text.chars().nth(index));
+            if let Some(voiced) =
+                before_cursor.and_then(|base| kana_layout::voiced_kana(base, kana))
+            {
+                converter.remove_text()?;
+                converter.remove_text()?;
+                appended = converter.append_text_direct(voiced.encode_utf8(&mut [0; 4]))?;
+            }
+        }
+        composing = Some(appended);
+    }
+    match composing {
+        Some(composing) => Ok(composing),
+        None => converter.append_text_direct(input),
+    }
 }
 
-fn get_reconversion_composed_text(request_id: u64) -> Result<ComposedText, String> {
-    get_composed_text_from_ffi(false, None, true, request_id)
+struct CompositionSnapshotRollback<'a> {
+    converter: &'a dyn ConverterBackend,
+    armed: bool,
 }
 
-fn get_composed_text_from_ffi(
-    use_cursor_prefix: bool,
-    required_cursor_prefix_input_count: Option<i32>,
-    reconversion: bool,
-    request_id: u64,
-) -> Result<ComposedText, String> {
-    let mut length: c_int = 0;
-    let operation = if reconversion {
-        "get_composed_text_for_reconversion"
-    } else if use_cursor_prefix {
-        "get_composed_text_for_cursor_prefix"
-    } else {
-        "get_composed_text"
-    };
-    let ffi_call_start = Instant::now();
-    let result = unsafe {
-        if reconversion {
-            GetComposedTextForReconversion(&mut length)
-        } else if use_cursor_prefix {
-            GetComposedTextForCursorPrefix(
-                required_cursor_prefix_input_count.unwrap_or(-1),
-                &mut length,
-            )
-        } else {
-            GetComposedText(&mut length)
+impl<'a> CompositionSnapshotRollback<'a> {
+    fn push(converter: &'a dyn ConverterBackend, selected_candidate_id: u64) -> Self {
+        converter.push_snapshot(selected_candidate_id);
+        Self {
+            converter,
+            armed: true,
         }
-    };
-    let call_name = if reconversion {
-        "GetComposedTextForReconversion"
-    } else if use_cursor_prefix {
-        "GetComposedTextForCursorPrefix"
-    } else {
-        "GetComposedText"
-    };
-    let candidates = unsafe { OwnedFfiCandidates::from_raw(call_name, result, length)? };
-    let length = candidates.len();
-    performance_event_lazy!(
-        request_id,
-        operation,
-        "ffi_call",
-        elapsed_ms(ffi_call_start),
-        "candidate_count={length};use_cursor_prefix={use_cursor_prefix};reconversion={reconversion}"
-    );
+    }
 
-    let mut suggestions = Vec::with_capacity(length);
-    let mut hiragana = None;
-    let mut seen_texts = HashSet::with_capacity(length);
-    let performance_enabled = should_log_performance();
-    let mut cstr_decode_ms = 0;
-    let mut dedup_ms = 0;
-    let mut duplicate_count = 0usize;
-    log_event_lazy!(
-        ServerLogLevel::Debug,
-        "[{call_name}] candidate_count={length}"
-    );
+    fn commit(mut self) {
+        self.armed = false;
+    }
+}
 
-    for index in 0..length {
-        let candidate_ptr = unsafe { candidates.candidate_ptr(index) };
-        if candidate_ptr.is_null() {
-            log_event(
-                ServerLogLevel::Warn,
-                &format!("[{call_name}] candidate[{index}] is null and skipped"),
-            );
-            continue;
+impl Drop for CompositionSnapshotRollback<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.converter.pop_snapshot(0);
         }
+    }
+}
 
-        let candidate = unsafe { (*candidate_ptr).clone() };
-        if candidate.text.is_null() || candidate.subtext.is_null() {
-            log_event(
-                ServerLogLevel::Warn,
-                &format!(
-                    "[{call_name}] candidate[{index}] has null text/subtext pointer and was skipped"
-                ),
-            );
-            continue;
+#[derive(Clone)]
+pub struct MyAzookeyService {
+    mutation_lock: Arc<tokio::sync::Mutex<CompositionSessions>>,
+    refinements: Arc<Refinements>,
+    converter: Arc<dyn ConverterBackend>,
+    user_dictionary: Arc<Mutex<UserDictionaryStore>>,
+    /// Whether user dictionary changes are written back to disk. Off for the
+    /// in-memory store a service starts with, so tests never touch the
+    /// user's configuration.
+    persist_user_dictionary: bool,
+    /// Read-only dictionaries merged with `user_dictionary` for the converter.
+    /// Reloads read the files into a copy and only swap it in here.
+    shared_dictionaries: Arc<Mutex<SharedDictionaries>>,
+    /// Held for a whole reload, so an older copy never replaces a newer one.
+    shared_dictionary_reload: Arc<tokio::sync::Mutex<()>>,
+    rewriters: Arc<Mutex<RewriterConfig>>,
+}
+
+impl MyAzookeyService {
+    fn new(converter: Arc<dyn ConverterBackend>) -> Self {
+        Self {
+            mutation_lock: Arc::new(tokio::sync::Mutex::new(CompositionSessions::new(
+                server_session_id(),
+            ))),
+            refinements: Arc::default(),
+            converter,
+            user_dictionary: Arc::default(),
+            persist_user_dictionary: false,
+            shared_dictionaries: Arc::default(),
+            shared_dictionary_reload: Arc::default(),
+            rewriters: Arc::default(),
         }
+    }
 
-        if hiragana.is_none() && !candidate.hiragana.is_null() {
-            let decode_start = performance_instant(performance_enabled);
-            hiragana = Some(
-                unsafe { CStr::from_ptr(candidate.hiragana) }
-                    .to_string_lossy()
-                    .into_owned(),
-            );
-            add_elapsed_ms(&mut cstr_decode_ms, decode_start);
-        }
+    /// Serves `store` and saves every change to it. The converter gets its
+    /// words from [`Self::with_user_dictionary_config`].
+    fn with_persisted_user_dictionary(mut self, store: UserDictionaryStore) -> Self {
+        self.user_dictionary = Arc::new(Mutex::new(store));
+        self.persist_user_dictionary = true;
+        self
+    }
 
-        let text_decode_start = performance_instant(performance_enabled);
-        let text = unsafe { CStr::from_ptr(candidate.text) }
-            .to_string_lossy()
-            .into_owned();
-        add_elapsed_ms(&mut cstr_decode_ms, text_decode_start);
-
-        let dedup_start = performance_instant(performance_enabled);
-        if !seen_texts.insert(text.clone()) {
-            duplicate_count += 1;
-            add_elapsed_ms(&mut dedup_ms, dedup_start);
-            continue;
-        }
-        add_elapsed_ms(&mut dedup_ms, dedup_start);
-
-        let subtext_decode_start = performance_instant(performance_enabled);
-        let subtext = unsafe { CStr::from_ptr(candidate.subtext) }
-            .to_string_lossy()
-            .into_owned();
-        add_elapsed_ms(&mut cstr_decode_ms, subtext_decode_start);
-        let corresponding_count = candidate.corresponding_count;
-
-        let suggestion = Suggestion {
-            text,
-            subtext,
-            corresponding_count,
-            candidate_id: candidate.candidate_id,
-        };
+    /// Loads the shared dictionaries `config` names and gives the converter
+    /// the merged user words.
+    fn with_user_dictionary_config(self, config: &UserDictionaryConfig) -> Self {
+        self.shared_dictionaries().configure(config);
+        self.set_merged_user_words();
+        self
+    }
 
-        suggestions.push(suggestion);
+    fn with_rewriters(self, config: RewriterConfig) -> Self {
+        *self
+            .rewriters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = config;
+        self
     }
-    performance_event_lazy!(
-        request_id,
-        operation,
-        "cstr_decode",
-        cstr_decode_ms,
-        "candidate_count={length};unique_candidate_count={};duplicate_count={duplicate_count}",
-        suggestions.len()
-    );
-    performance_event_lazy!(
-        request_id,
-        operation,
-        "dedup",
-        dedup_ms,
-        "candidate_count={length};unique_candidate_count={};duplicate_count={duplicate_count}",
-        suggestions.len()
-    );
-    candidates.free_with_performance(request_id, operation);
 
-    Ok(ComposedText {
-        hiragana,
-        suggestions,
-    })
-}
+    /// The converter's result for `query`, with the enabled rewriters applied
+    /// when it covers the whole composition.
+    fn composed_text(
+        &self,
+        query: ComposedTextQuery,
+        request_id: u64,
+    ) -> Result<ComposedText, String> {
+        let mut composed = self.converter.composed_text(query, request_id)?;
+        if matches!(
+            query,
+            ComposedTextQuery::Full | ComposedTextQuery::WithoutZenzai
+        ) {
+            let config = *self
+                .rewriters
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            rewriter::rewrite(&mut composed, &config, chrono::Local::now().naive_local());
+        }
+        Ok(composed)
+    }
 
-fn shrink_text(offset: i32) -> Result<RawComposingText, String> {
-    unsafe {
-        let result = ShrinkText(offset);
-        let text = ffi_text_result("ShrinkText", result)?;
+    fn user_dictionary(&self) -> MutexGuard<'_, UserDictionaryStore> {
+        self.user_dictionary
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 
-        Ok(RawComposingText { text, cursor: 0 })
+    fn shared_dictionaries(&self) -> MutexGuard<'_, SharedDictionaries> {
+        self.shared_dictionaries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
-}
 
-struct CompositionSnapshotRollback {
-    armed: bool,
-}
+    /// The converter's user words with `personal` as the personal dictionary.
+    fn merged_user_words(&self, personal: &UserDictionaryStore) -> Vec<UserWord> {
+        self.shared_dictionaries().merge(personal.words())
+    }
 
-impl CompositionSnapshotRollback {
-    fn push(selected_candidate_id: u64) -> Self {
-        unsafe {
-            PushComposingTextSnapshot(selected_candidate_id);
+    /// Gives the converter the merged user words of the served store.
+    fn set_merged_user_words(&self) {
+        let personal = self.user_dictionary().clone();
+        let words = self.merged_user_words(&personal);
+        match self.converter.set_user_words(&words) {
+            Ok(()) => log_event_lazy!(
+                ServerLogLevel::Info,
+                "user dictionary merged: personal={} total={}",
+                personal.words().len(),
+                words.len()
+            ),
+            Err(error) => log_event(ServerLogLevel::Error, &error),
         }
-        Self { armed: true }
     }
 
-    fn commit(mut self) {
-        self.armed = false;
+    /// Brings the converter up to date with `updated` personal words.
+    /// `change` applies just the difference, which is enough while the
+    /// converter holds the personal words alone.
+    fn apply_personal_words(
+        &self,
+        updated: &UserDictionaryStore,
+        change: impl FnOnce(&dyn ConverterBackend) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if self.shared_dictionaries().serves_personal_only() {
+            change(self.converter.as_ref())
+        } else {
+            self.converter
+                .set_user_words(&self.merged_user_words(updated))
+        }
     }
-}
 
-impl Drop for CompositionSnapshotRollback {
-    fn drop(&mut self) {
-        if self.armed {
-            unsafe {
-                PopComposingTextSnapshot(0);
+    /// Rereads the shared dictionary folder off the async runtime, after
+    /// applying `config` when given, and when a dictionary changed gives the
+    /// converter the merged words again. The files are read into a copy, so
+    /// the served dictionaries stay usable meanwhile.
+    async fn reload_shared_dictionaries(&self, config: Option<UserDictionaryConfig>) {
+        let _reload_guard = self.shared_dictionary_reload.lock().await;
+        let mut reloaded = self.shared_dictionaries().clone();
+        let reloaded = tokio::task::spawn_blocking(move || {
+            let changed = match config {
+                Some(config) => reloaded.configure(&config),
+                None => reloaded.refresh(),
+            };
+            changed.then_some(reloaded)
+        })
+        .await;
+        let Ok(Some(reloaded)) = reloaded else {
+            return;
+        };
+        let _mutation_guard = self.mutation_lock.lock().await;
+        *self.shared_dictionaries() = reloaded;
+        self.set_merged_user_words();
+    }
+
+    /// Saves `updated` and makes it the served store. The converter already
+    /// reflects `updated`; when saving fails it is put back to `store`.
+    #[allow(clippy::result_large_err)]
+    fn commit_user_dictionary(
+        &self,
+        scope: &str,
+        store: &mut UserDictionaryStore,
+        updated: UserDictionaryStore,
+    ) -> Result<(), Status> {
+        if self.persist_user_dictionary {
+            if let Err(error) = updated.write() {
+                if let Err(restore_error) = self
+                    .converter
+                    .set_user_words(&self.merged_user_words(store))
+                {
+                    log_event(ServerLogLevel::Error, &restore_error);
+                }
+                return Err(status_from_error(
+                    scope,
+                    format!("[{scope}] failed to save user dictionary: {error}"),
+                ));
             }
         }
+        *store = updated;
+        Ok(())
     }
-}
 
-#[derive(Debug, Clone, Default)]
-pub struct MyAzookeyService {
-    mutation_lock: Arc<tokio::sync::Mutex<()>>,
-}
+    /// Takes the mutation lock and selects `session_id` in the converter for
+    /// the rest of the transaction.
+    async fn lock_session(
+        &self,
+        session_id: u64,
+    ) -> Result<tokio::sync::MutexGuard<'_, CompositionSessions>, Status> {
+        let mut sessions = self.mutation_lock.lock().await;
+        let switched = sessions
+            .select(session_id, self.converter.as_ref())
+            .ok_or_else(|| {
+                Status::not_found(format!("composition session {session_id} not found"))
+            })?;
+        if switched {
+            HAS_ACTIVE_COMPOSITION
+                .store(self.converter.has_active_composition(), Ordering::Relaxed);
+        }
+        self.refinements.supersede(session_id);
+        Ok(sessions)
+    }
 
-#[tonic::async_trait]
-impl AzookeyService for MyAzookeyService {
-    async fn append_text(
+    /// Like [`Self::lock_session`] for requests that leave the composition
+    /// as it is: a pending refinement of the session stays valid.
+    async fn lock_session_for_read(
         &self,
-        request: Request<AppendTextRequest>,
-    ) -> Result<Response<AppendTextResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
-        let request = request.into_inner();
-        let _request_guard = ServerRequestGuard::begin(true);
-        let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
-        let handler_start = Instant::now();
-        let input_style = request.input_style;
-        let input = request.text_to_append;
-        let input_len = input.chars().count();
-        let append_start = Instant::now();
-        let composing_text = if input_style == INPUT_STYLE_DIRECT {
-            add_text_direct(&input).map_err(|error| status_from_error("append_text", error))?
-        } else {
-            add_text(&input).map_err(|error| status_from_error("append_text", error))?
+        session_id: u64,
+    ) -> Result<tokio::sync::MutexGuard<'_, CompositionSessions>, Status> {
+        let mut sessions = self.mutation_lock.lock().await;
+        let switched = sessions
+            .select_for_read(session_id, self.converter.as_ref())
+            .ok_or_else(|| {
+                Status::not_found(format!("composition session {session_id} not found"))
+            })?;
+        if switched {
+            HAS_ACTIVE_COMPOSITION
+                .store(self.converter.has_active_composition(), Ordering::Relaxed);
+        }
+        Ok(sessions)
+    }
+
+    /// Reruns the candidate query of a deferred `append_text` with Zenzai in
+    /// the background. The Zenzai pass holds the mutation lock like any other
+    /// converter call; it is skipped when a request reached the session first.
+    fn schedule_refinement(
+        &self,
+        sessions: &CompositionSessions,
+        session_id: u64,
+        request_id: u64,
+        hiragana: String,
+    ) {
+        let Some(revision) = sessions.revision(session_id) else {
+            return;
         };
+        self.refinements.begin(session_id, request_id);
+        let service = self.clone();
+        tokio::spawn(async move {
+            service
+                .refine_candidates(session_id, revision, request_id, hiragana)
+                .await;
+        });
+    }
+
+    async fn refine_candidates(
+        &self,
+        session_id: u64,
+        revision: u64,
+        request_id: u64,
+        hiragana: String,
+    ) {
+        let mut sessions = self.mutation_lock.lock().await;
+        let Some(switched) =
+            sessions.select_unchanged(session_id, revision, self.converter.as_ref())
+        else {
+            self.refinements
+                .finish(session_id, request_id, RefinementOutcome::Stale);
+            performance_event_lazy!(request_id, "refine_candidates", "total", 0, "status=stale");
+            return;
+        };
+        if switched {
+            HAS_ACTIVE_COMPOSITION
+                .store(self.converter.has_active_composition(), Ordering::Relaxed);
+        }
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+        let outcome = match self.composed_text(ComposedTextQuery::Full, request_id) {
+            Ok(composed_text) => {
+                performance_event_lazy!(
+                    request_id,
+                    "refine_candidates",
+                    "total",
+                    elapsed_ms(handler_start),
+                    "status=success;suggestions={}",
+                    composed_text.suggestions.len()
+                );
+                RefinementOutcome::Ready(ComposingText {
+                    hiragana: composed_text.hiragana.unwrap_or(hiragana),
+                    suggestions: composed_text.suggestions,
+                })
+            }
+            Err(error) => {
+                log_event_lazy!(
+                    ServerLogLevel::Warn,
+                    "[refine_candidates] request_id={request_id} session={session_id} failed: {error}"
+                );
+                RefinementOutcome::Unavailable
+            }
+        };
+        self.refinements.finish(session_id, request_id, outcome);
+    }
+}
+
+const CONVERTER_BACKEND_ENV: &str = "AZOOKEY_CONVERTER_BACKEND";
+
+/// Picks the converter backend. The Swift converter is the default on Windows;
+/// `AZOOKEY_CONVERTER_BACKEND=fake` selects the dictionary-backed stand-in,
+/// which is also the only backend on other platforms.
+fn create_converter_backend() -> Arc<dyn ConverterBackend> {
+    let requested = std::env::var(CONVERTER_BACKEND_ENV).unwrap_or_default();
+    if requested.eq_ignore_ascii_case("fake") || !cfg!(windows) {
+        log_event_lazy!(ServerLogLevel::Info, "converter backend=fake");
+        return Arc::new(converter::FakeConverter::default());
+    }
+
+    #[cfg(windows)]
+    {
+        Arc::new(converter::SwiftConverter::new())
+    }
+    #[cfg(not(windows))]
+    unreachable!("non-Windows targets always use the fake converter")
+}
+
+#[tonic::async_trait]
+impl AzookeyService for MyAzookeyService {
+    async fn create_composition_session(
+        &self,
+        request: Request<CreateCompositionSessionRequest>,
+    ) -> Result<Response<CreateCompositionSessionResponse>, Status> {
+        let mut sessions = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        let (composition_session_id, evicted) = sessions.create(self.converter.as_ref());
+        log_event_lazy!(
+            ServerLogLevel::Debug,
+            "[create_composition_session] request_id={request_id} session={composition_session_id} open={} evicted={evicted:?}",
+            sessions.len()
+        );
+
+        Ok(Response::new(CreateCompositionSessionResponse {
+            server_session_id: server_session_id(),
+            composition_session_id,
+        }))
+    }
+
+    async fn destroy_composition_session(
+        &self,
+        request: Request<DestroyCompositionSessionRequest>,
+    ) -> Result<Response<DestroyCompositionSessionResponse>, Status> {
+        let mut sessions = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        let destroyed = sessions.destroy(request.composition_session_id, self.converter.as_ref());
+        self.refinements.remove(request.composition_session_id);
+        if destroyed {
+            HAS_ACTIVE_COMPOSITION
+                .store(self.converter.has_active_composition(), Ordering::Relaxed);
+        }
+        log_event_lazy!(
+            ServerLogLevel::Debug,
+            "[destroy_composition_session] request_id={request_id} session={} destroyed={destroyed} open={}",
+            request.composition_session_id,
+            sessions.len()
+        );
+
+        Ok(Response::new(DestroyCompositionSessionResponse {
+            server_session_id: server_session_id(),
+            destroyed,
+        }))
+    }
+
+    async fn append_text(
+        &self,
+        request: Request<AppendTextRequest>,
+    ) -> Result<Response<AppendTextResponse>, Status> {
+        let request = request.into_inner();
+        let sessions = self.lock_session(request.composition_session_id).await?;
+        let _request_guard = ServerRequestGuard::begin(true);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+        let input_style = request.input_style;
+        let input = request.text_to_append;
+        let input_len = input.chars().count();
+        let append_start = Instant::now();
+        let composing_text = append_with_style(self.converter.as_ref(), &input, input_style)
+            .map_err(|error| status_from_error("append_text", error))?;
         performance_event_lazy!(
             request_id,
             "append_text",
@@ -1650,15 +1541,24 @@ impl AzookeyService for MyAzookeyService {
             elapsed_ms(append_start),
             "input_len={input_len};input_style={input_style}"
         );
+        // Deferring only pays off when Zenzai would actually run; otherwise the
+        // full query is already the fast dictionary lookup.
+        let refinement_pending = request.defer_refinement && self.converter.would_use_zenzai();
+        let query = if refinement_pending {
+            ComposedTextQuery::WithoutZenzai
+        } else {
+            ComposedTextQuery::Full
+        };
         let get_composed_start = Instant::now();
-        let composed_text = get_composed_text(false, None, request_id)
+        let composed_text = self
+            .composed_text(query, request_id)
             .map_err(|error| status_from_error("append_text", error))?;
         performance_event_lazy!(
             request_id,
             "append_text",
             "swift_get_composed_text",
             elapsed_ms(get_composed_start),
-            "suggestions={};hiragana_len={}",
+            "suggestions={};hiragana_len={};deferred={refinement_pending}",
             composed_text.suggestions.len(),
             composed_text
                 .hiragana
@@ -1679,12 +1579,94 @@ impl AzookeyService for MyAzookeyService {
             composed_text.suggestions.len()
         );
 
+        let hiragana = composed_text.hiragana.unwrap_or(composing_text.text);
+        if refinement_pending {
+            self.schedule_refinement(
+                &sessions,
+                request.composition_session_id,
+                request_id,
+                hiragana.clone(),
+            );
+        }
+        drop(sessions);
+
         Ok(Response::new(AppendTextResponse {
             composing_text: Some(ComposingText {
-                hiragana: composed_text.hiragana.unwrap_or(composing_text.text),
+                hiragana,
                 suggestions: composed_text.suggestions,
             }),
             server_session_id: server_session_id(),
+            refinement_pending,
+        }))
+    }
+
+    async fn get_refined_candidates(
+        &self,
+        request: Request<GetRefinedCandidatesRequest>,
+    ) -> Result<Response<GetRefinedCandidatesResponse>, Status> {
+        let request = request.into_inner();
+        let session_id = request.composition_session_id;
+        let handler_start = Instant::now();
+        let outcome = self
+            .refinements
+            .wait(session_id, request.request_id, MAX_REFINEMENT_WAIT)
+            .await;
+        let (status, composing_text) = match outcome {
+            RefinementOutcome::Ready(composing_text) => {
+                (RefinementStatus::Ready, Some(composing_text))
+            }
+            RefinementOutcome::Stale => (RefinementStatus::Stale, None),
+            RefinementOutcome::Pending | RefinementOutcome::Unavailable => {
+                (RefinementStatus::Unavailable, None)
+            }
+        };
+        performance_event_lazy!(
+            request.request_id,
+            "get_refined_candidates",
+            "total",
+            elapsed_ms(handler_start),
+            "status={}",
+            status.as_str_name()
+        );
+
+        Ok(Response::new(GetRefinedCandidatesResponse {
+            server_session_id: server_session_id(),
+            status: status.into(),
+            composing_text,
+        }))
+    }
+
+    async fn predict(
+        &self,
+        request: Request<PredictRequest>,
+    ) -> Result<Response<PredictResponse>, Status> {
+        let request = request.into_inner();
+        let _mutation_guard = self
+            .lock_session_for_read(request.composition_session_id)
+            .await?;
+        let _request_guard = ServerRequestGuard::begin(true);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+
+        let composed_text = self
+            .composed_text(ComposedTextQuery::Prediction, request_id)
+            .map_err(|error| status_from_error("predict", error))?;
+        performance_event_lazy!(
+            request_id,
+            "predict",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;suggestions={}",
+            composed_text.suggestions.len()
+        );
+
+        Ok(Response::new(PredictResponse {
+            server_session_id: server_session_id(),
+            composing_text: Some(ComposingText {
+                hiragana: composed_text.hiragana.unwrap_or_default(),
+                suggestions: composed_text.suggestions,
+            }),
         }))
     }
 
@@ -1692,17 +1674,17 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<ReplaceCompositionRequest>,
     ) -> Result<Response<ReplaceCompositionResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
 
         // This is an absolute-state operation: clear the old converter state and
         // replay the complete client-observed mutation log. It is safe to repeat after a
         // timeout whose server-side completion is unknown.
-        clear_text();
+        self.converter.clear_text();
         let mut composing_text = RawComposingText {
             text: String::new(),
             cursor: 0,
@@ -1712,20 +1694,23 @@ impl AzookeyService for MyAzookeyService {
                 .map_err(|_| Status::invalid_argument("unknown composition operation"))?
             {
                 CompositionOperationKind::Append => {
-                    composing_text = if operation.input_style == INPUT_STYLE_DIRECT {
-                        add_text_direct(&operation.text)
-                            .map_err(|error| status_from_error("replace_composition", error))?
-                    } else {
-                        add_text(&operation.text)
-                            .map_err(|error| status_from_error("replace_composition", error))?
-                    };
+                    composing_text = append_with_style(
+                        self.converter.as_ref(),
+                        &operation.text,
+                        operation.input_style,
+                    )
+                    .map_err(|error| status_from_error("replace_composition", error))?;
                 }
                 CompositionOperationKind::Remove => {
-                    composing_text = remove_text()
+                    composing_text = self
+                        .converter
+                        .remove_text()
                         .map_err(|error| status_from_error("replace_composition", error))?;
                 }
                 CompositionOperationKind::MoveCursor => {
-                    composing_text = move_cursor(operation.cursor_offset)
+                    composing_text = self
+                        .converter
+                        .move_cursor(operation.cursor_offset)
                         .map_err(|error| status_from_error("replace_composition", error))?;
                 }
             }
@@ -1737,7 +1722,7 @@ impl AzookeyService for MyAzookeyService {
                 suggestions: Vec::new(),
             }
         } else {
-            get_composed_text(false, None, request_id)
+            self.composed_text(ComposedTextQuery::Full, request_id)
                 .map_err(|error| status_from_error("replace_composition", error))?
         };
         update_active_composition_state(&composing_text.text);
@@ -1765,16 +1750,18 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<StartReconversionRequest>,
     ) -> Result<Response<StartReconversionResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let surface = request.surface;
         let surface_len = validate_reconversion_surface(&surface)?;
 
-        let mut readings = infer_reconversion_readings(&surface)
+        let mut readings = self
+            .converter
+            .infer_reconversion_readings(&surface)
             .map_err(|error| status_from_error("start_reconversion", error))?;
         readings.truncate(MAX_RECONVERSION_READINGS);
         if readings.is_empty() {
@@ -1800,19 +1787,19 @@ impl AzookeyService for MyAzookeyService {
         let mut conversions = Vec::with_capacity(readings.len());
         let mut last_composing_text = None;
         for (reading_index, reading) in readings.iter().enumerate() {
-            clear_text();
-            let composing = match add_text_direct(reading) {
+            self.converter.clear_text();
+            let composing = match self.converter.append_text_direct(reading) {
                 Ok(text) => text,
                 Err(error) => {
-                    clear_text();
+                    self.converter.clear_text();
                     update_active_composition_state("");
                     return Err(status_from_error("start_reconversion", error));
                 }
             };
-            let composed = match get_reconversion_composed_text(request_id) {
+            let composed = match self.composed_text(ComposedTextQuery::Reconversion, request_id) {
                 Ok(text) => text,
                 Err(error) => {
-                    clear_text();
+                    self.converter.clear_text();
                     update_active_composition_state("");
                     return Err(status_from_error("start_reconversion", error));
                 }
@@ -1836,17 +1823,22 @@ impl AzookeyService for MyAzookeyService {
                 composed,
             )
         } else {
-            clear_text();
-            let composing = add_text_direct(&chosen_reading).map_err(|error| {
-                clear_text();
-                update_active_composition_state("");
-                status_from_error("start_reconversion", error)
-            })?;
-            let composed = get_reconversion_composed_text(request_id).map_err(|error| {
-                clear_text();
-                update_active_composition_state("");
-                status_from_error("start_reconversion", error)
-            })?;
+            self.converter.clear_text();
+            let composing =
+                self.converter
+                    .append_text_direct(&chosen_reading)
+                    .map_err(|error| {
+                        self.converter.clear_text();
+                        update_active_composition_state("");
+                        status_from_error("start_reconversion", error)
+                    })?;
+            let composed = self
+                .composed_text(ComposedTextQuery::Reconversion, request_id)
+                .map_err(|error| {
+                    self.converter.clear_text();
+                    update_active_composition_state("");
+                    status_from_error("start_reconversion", error)
+                })?;
             (composing, composed)
         };
         let hiragana = composed_text
@@ -1891,17 +1883,22 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<RemoveTextRequest>,
     ) -> Result<Response<RemoveTextResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
 
         let remove_start = Instant::now();
-        let composing_text =
-            remove_text().map_err(|error| status_from_error("remove_text", error))?;
-        let raw_input = get_raw_input().map_err(|error| status_from_error("remove_text", error))?;
+        let composing_text = self
+            .converter
+            .remove_text()
+            .map_err(|error| status_from_error("remove_text", error))?;
+        let raw_input = self
+            .converter
+            .raw_input()
+            .map_err(|error| status_from_error("remove_text", error))?;
         performance_event_lazy!(
             request_id,
             "remove_text",
@@ -1911,7 +1908,8 @@ impl AzookeyService for MyAzookeyService {
             composing_text.text.chars().count()
         );
         let get_composed_start = Instant::now();
-        let composed_text = get_composed_text(false, None, request_id)
+        let composed_text = self
+            .composed_text(ComposedTextQuery::Full, request_id)
             .map_err(|error| status_from_error("remove_text", error))?;
         performance_event_lazy!(
             request_id,
@@ -1947,18 +1945,20 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<MoveCursorRequest>,
     ) -> Result<Response<MoveCursorResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let raw_offset = request.offset;
 
         let use_cursor_prefix = raw_offset == 0;
         let move_start = Instant::now();
-        let composing_text =
-            move_cursor(raw_offset).map_err(|error| status_from_error("move_cursor", error))?;
+        let composing_text = self
+            .converter
+            .move_cursor(raw_offset)
+            .map_err(|error| status_from_error("move_cursor", error))?;
         performance_event_lazy!(
             request_id,
             "move_cursor",
@@ -1968,10 +1968,18 @@ impl AzookeyService for MyAzookeyService {
             composing_text.text.chars().count()
         );
         let get_composed_start = Instant::now();
-        let composed_text = get_composed_text(use_cursor_prefix, None, request_id)
+        let query = if use_cursor_prefix {
+            ComposedTextQuery::CursorPrefix {
+                required_input_count: None,
+            }
+        } else {
+            ComposedTextQuery::Full
+        };
+        let composed_text = self
+            .composed_text(query, request_id)
             .map_err(|error| status_from_error("move_cursor", error))?;
         let raw_input = use_cursor_prefix
-            .then(get_raw_input)
+            .then(|| self.converter.raw_input())
             .transpose()
             .map_err(|error| status_from_error("move_cursor", error))?;
         performance_event_lazy!(
@@ -2008,11 +2016,11 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<AdjustClauseBoundaryRequest>,
     ) -> Result<Response<AdjustClauseBoundaryResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let current_input_count = validate_shrink_offset(request.current_input_count)?;
         let expected_raw_input = request.expected_raw_input;
@@ -2032,9 +2040,10 @@ impl AzookeyService for MyAzookeyService {
         };
 
         let adjust_start = Instant::now();
-        let adjustment =
-            adjust_clause_boundary(current_input_count, direction, &expected_raw_input)
-                .map_err(|error| status_from_error("adjust_clause_boundary", error))?;
+        let adjustment = self
+            .converter
+            .adjust_clause_boundary(current_input_count, direction, &expected_raw_input)
+            .map_err(|error| status_from_error("adjust_clause_boundary", error))?;
         performance_event_lazy!(
             request_id,
             "adjust_clause_boundary",
@@ -2066,25 +2075,30 @@ impl AzookeyService for MyAzookeyService {
         }
 
         let get_composed_start = Instant::now();
-        let mut composed_text =
-            match get_composed_text(true, Some(adjustment.adjusted_input_count), request_id) {
-                Ok(composed_text) => composed_text,
-                Err(error) => {
-                    rollback_clause_boundary(
-                        adjustment.adjusted_input_count,
-                        current_input_count,
-                        direction,
-                        &expected_raw_input,
+        let mut composed_text = match self.composed_text(
+            ComposedTextQuery::CursorPrefix {
+                required_input_count: Some(adjustment.adjusted_input_count),
+            },
+            request_id,
+        ) {
+            Ok(composed_text) => composed_text,
+            Err(error) => {
+                rollback_clause_boundary(
+                    &*self.converter,
+                    adjustment.adjusted_input_count,
+                    current_input_count,
+                    direction,
+                    &expected_raw_input,
+                )
+                .map_err(|rollback_error| {
+                    status_from_error(
+                        "adjust_clause_boundary",
+                        format!("{error}; {rollback_error}"),
                     )
-                    .map_err(|rollback_error| {
-                        status_from_error(
-                            "adjust_clause_boundary",
-                            format!("{error}; {rollback_error}"),
-                        )
-                    })?;
-                    return Err(status_from_error("adjust_clause_boundary", error));
-                }
-            };
+                })?;
+                return Err(status_from_error("adjust_clause_boundary", error));
+            }
+        };
         performance_event_lazy!(
             request_id,
             "adjust_clause_boundary",
@@ -2099,7 +2113,7 @@ impl AzookeyService for MyAzookeyService {
             .iter()
             .any(|candidate| candidate.corresponding_count == adjustment.adjusted_input_count)
         {
-            let cursor = get_cursor_position();
+            let cursor = self.converter.cursor_position();
             if let Some(fallback) = hiragana_boundary_fallback(
                 &adjustment.text,
                 cursor,
@@ -2117,6 +2131,7 @@ impl AzookeyService for MyAzookeyService {
                 );
             } else {
                 rollback_clause_boundary(
+                    &*self.converter,
                     adjustment.adjusted_input_count,
                     current_input_count,
                     direction,
@@ -2157,29 +2172,27 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<UpdateCompositionSnapshotRequest>,
     ) -> Result<Response<UpdateCompositionSnapshotResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let operation = CompositionSnapshotOperation::try_from(request.operation)
             .map_err(|_| Status::invalid_argument("unknown composition snapshot operation"))?;
 
-        unsafe {
-            match operation {
-                CompositionSnapshotOperation::Unspecified => {
-                    return Err(Status::invalid_argument(
-                        "composition snapshot operation is required",
-                    ));
-                }
-                CompositionSnapshotOperation::Clear => ClearComposingTextSnapshots(),
-                CompositionSnapshotOperation::Push => {
-                    PushComposingTextSnapshot(request.selected_candidate_id)
-                }
-                CompositionSnapshotOperation::Pop => {
-                    PopComposingTextSnapshot(request.selected_candidate_id)
-                }
+        match operation {
+            CompositionSnapshotOperation::Unspecified => {
+                return Err(Status::invalid_argument(
+                    "composition snapshot operation is required",
+                ));
+            }
+            CompositionSnapshotOperation::Clear => self.converter.clear_snapshots(),
+            CompositionSnapshotOperation::Push => {
+                self.converter.push_snapshot(request.selected_candidate_id)
+            }
+            CompositionSnapshotOperation::Pop => {
+                self.converter.pop_snapshot(request.selected_candidate_id)
             }
         }
         performance_event_lazy!(
@@ -2199,14 +2212,14 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<ClearTextRequest>,
     ) -> Result<Response<ClearTextResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let clear_start = Instant::now();
-        clear_text();
+        self.converter.clear_text();
         performance_event_lazy!(
             request_id,
             "clear_text",
@@ -2231,17 +2244,19 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<ShrinkTextRequest>,
     ) -> Result<Response<ShrinkTextResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let raw_offset = validate_shrink_offset(request.offset)?;
 
         let shrink_start = Instant::now();
-        let composing_text =
-            shrink_text(raw_offset).map_err(|error| status_from_error("shrink_text", error))?;
+        let composing_text = self
+            .converter
+            .shrink_text(raw_offset)
+            .map_err(|error| status_from_error("shrink_text", error))?;
         performance_event_lazy!(
             request_id,
             "shrink_text",
@@ -2251,7 +2266,8 @@ impl AzookeyService for MyAzookeyService {
             composing_text.text.chars().count()
         );
         let get_composed_start = Instant::now();
-        let composed_text = get_composed_text(false, None, request_id)
+        let composed_text = self
+            .composed_text(ComposedTextQuery::Full, request_id)
             .map_err(|error| status_from_error("shrink_text", error))?;
         performance_event_lazy!(
             request_id,
@@ -2285,21 +2301,26 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<AdvanceClauseRequest>,
     ) -> Result<Response<AdvanceClauseResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let raw_offset = validate_shrink_offset(request.offset)?;
 
-        let snapshot_rollback = CompositionSnapshotRollback::push(request.selected_candidate_id);
+        let snapshot_rollback =
+            CompositionSnapshotRollback::push(&*self.converter, request.selected_candidate_id);
 
         let shrink_start = Instant::now();
-        let shrunk_text =
-            shrink_text(raw_offset).map_err(|error| status_from_error("advance_clause", error))?;
-        let raw_input =
-            get_raw_input().map_err(|error| status_from_error("advance_clause", error))?;
+        let shrunk_text = self
+            .converter
+            .shrink_text(raw_offset)
+            .map_err(|error| status_from_error("advance_clause", error))?;
+        let raw_input = self
+            .converter
+            .raw_input()
+            .map_err(|error| status_from_error("advance_clause", error))?;
         performance_event_lazy!(
             request_id,
             "advance_clause",
@@ -2309,9 +2330,17 @@ impl AzookeyService for MyAzookeyService {
         );
 
         let move_start = Instant::now();
-        let navigation_text =
-            move_cursor(0).map_err(|error| status_from_error("advance_clause", error))?;
-        let navigation_composed = get_composed_text(true, None, request_id)
+        let navigation_text = self
+            .converter
+            .move_cursor(0)
+            .map_err(|error| status_from_error("advance_clause", error))?;
+        let navigation_composed = self
+            .composed_text(
+                ComposedTextQuery::CursorPrefix {
+                    required_input_count: None,
+                },
+                request_id,
+            )
             .map_err(|error| status_from_error("advance_clause", error))?;
         performance_event_lazy!(
             request_id,
@@ -2353,11 +2382,11 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<PrepareFutureClausesRequest>,
     ) -> Result<Response<PrepareFutureClausesResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let mut offset = validate_shrink_offset(request.initial_offset)?;
         let leave_at_last = request.leave_at_last;
@@ -2369,19 +2398,23 @@ impl AzookeyService for MyAzookeyService {
 
         let result = (|| -> Result<(), Box<Status>> {
             for _ in 0..shared::MAX_PREPARED_CLAUSE_ADVANCES {
-                unsafe {
-                    PushComposingTextSnapshot(selected_candidate_id);
-                }
+                self.converter.push_snapshot(selected_candidate_id);
                 snapshot_count += 1;
 
-                let shrunk_text = shrink_text(offset).map_err(|error| {
+                let shrunk_text = self.converter.shrink_text(offset).map_err(|error| {
                     Box::new(status_from_error("prepare_future_clauses", error))
                 })?;
-                let navigation_text = move_cursor(0).map_err(|error| {
+                let navigation_text = self.converter.move_cursor(0).map_err(|error| {
                     Box::new(status_from_error("prepare_future_clauses", error))
                 })?;
-                let navigation_composed =
-                    get_composed_text(true, None, request_id).map_err(|error| {
+                let navigation_composed = self
+                    .composed_text(
+                        ComposedTextQuery::CursorPrefix {
+                            required_input_count: None,
+                        },
+                        request_id,
+                    )
+                    .map_err(|error| {
                         Box::new(status_from_error("prepare_future_clauses", error))
                     })?;
 
@@ -2389,9 +2422,7 @@ impl AzookeyService for MyAzookeyService {
                     break;
                 };
                 selected_candidate_id = selected.candidate_id;
-                unsafe {
-                    PinLearningCandidate(selected_candidate_id);
-                }
+                self.converter.pin_learning_candidate(selected_candidate_id);
                 let is_last = selected.subtext.is_empty();
                 let signature = (
                     navigation_composed
@@ -2433,9 +2464,7 @@ impl AzookeyService for MyAzookeyService {
             advances.len(),
         );
         for _ in retained_snapshot_count..snapshot_count {
-            unsafe {
-                PopComposingTextSnapshot(0);
-            }
+            self.converter.pop_snapshot(0);
         }
         result.map_err(|status| *status)?;
 
@@ -2467,31 +2496,35 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<shared::proto::SetContextRequest>,
     ) -> Result<Response<shared::proto::SetContextResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(false);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let context = request.context;
         let trimmed_context = context
             .split('\r')
             .rfind(|s| !s.is_empty())
             .unwrap_or_default();
+        // Only the rest of the caret's line follows the composition.
+        let right_context = request.right_context.split('\r').next().unwrap_or_default();
         let original_len = context.chars().count();
         let trimmed_len = trimmed_context.chars().count();
-
-        let context = cstring_from_input("SetContext.context", trimmed_context)
-            .map_err(|error| status_from_error("set_context", error))?;
+        let right_len = right_context.chars().count();
 
         let set_context_start = Instant::now();
-        unsafe { SetContext(context.as_ptr()) };
+        self.converter
+            .set_session_zenzai_allowed(!request.zenzai_disabled);
+        self.converter
+            .set_context(trimmed_context, right_context)
+            .map_err(|error| status_from_error("set_context", error))?;
         performance_event_lazy!(
             request_id,
             "set_context",
             "swift_set_context",
             elapsed_ms(set_context_start),
-            "original_len={original_len};trimmed_len={trimmed_len}"
+            "original_len={original_len};trimmed_len={trimmed_len};right_len={right_len}"
         );
         performance_event_lazy!(
             request_id,
@@ -2509,16 +2542,21 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<shared::proto::UpdateConfigRequest>,
     ) -> Result<Response<shared::proto::UpdateConfigResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
+        let mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
         let _request_guard = ServerRequestGuard::begin(false);
         let request_id = request_id_or_next(request.request_id);
         let _log_paths = reload_server_logging_from_settings();
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let load_config_start = Instant::now();
-        unsafe { LoadConfig() };
-        let has_active_composition = query_active_composition_state();
+        self.converter.load_config();
+        let config = AppConfig::read().unwrap_or_default();
+        *self
+            .rewriters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = config.general.rewriters;
+        let has_active_composition = self.converter.has_active_composition();
         performance_event_lazy!(
             request_id,
             "update_config",
@@ -2527,6 +2565,9 @@ impl AzookeyService for MyAzookeyService {
             "active_composition={has_active_composition}"
         );
         HAS_ACTIVE_COMPOSITION.store(has_active_composition, Ordering::Relaxed);
+        drop(mutation_guard);
+        self.reload_shared_dictionaries(Some(config.user_dictionary))
+            .await;
         performance_event_lazy!(
             request_id,
             "update_config",
@@ -2543,18 +2584,21 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<shared::proto::CommitLearningCandidateRequest>,
     ) -> Result<Response<shared::proto::CommitLearningCandidateResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let candidate_id = request.candidate_id;
         let commit_kind = request.commit_kind;
 
         let commit_start = Instant::now();
-        let committed = swift_commit_learning_candidate(candidate_id, commit_kind);
-        if !committed {
+        let committed = !request.do_not_learn
+            && self
+                .converter
+                .commit_learning_candidate(candidate_id, commit_kind);
+        if !committed && !request.do_not_learn {
             log_event(
                 ServerLogLevel::Warn,
                 &format!(
@@ -2588,16 +2632,20 @@ impl AzookeyService for MyAzookeyService {
         &self,
         request: Request<shared::proto::CommitLearningCandidatesRequest>,
     ) -> Result<Response<shared::proto::CommitLearningCandidatesResponse>, Status> {
-        let _mutation_guard = self.mutation_lock.lock().await;
         let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
         let _request_guard = ServerRequestGuard::begin(true);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
         let requested_count = request.commits.len();
 
         let commit_start = Instant::now();
-        let committed_count = swift_commit_learning_candidates(&request.commits);
+        let committed_count = if request.do_not_learn {
+            0
+        } else {
+            self.converter.commit_learning_candidates(&request.commits)
+        };
         performance_event_lazy!(
             request_id,
             "commit_learning_candidates",
@@ -2629,11 +2677,11 @@ impl AzookeyService for MyAzookeyService {
         let request = request.into_inner();
         let _request_guard = ServerRequestGuard::begin(false);
         let request_id = request_id_or_next(request.request_id);
-        set_request_id(request_id);
+        self.converter.set_request_id(request_id);
         let handler_start = Instant::now();
 
         let reset_start = Instant::now();
-        let reset = swift_reset_learning_memory();
+        let reset = self.converter.reset_learning_memory();
         if !reset {
             log_event(
                 ServerLogLevel::Warn,
@@ -2661,6 +2709,269 @@ impl AzookeyService for MyAzookeyService {
         }))
     }
 
+    async fn list_learned_entries(
+        &self,
+        request: Request<shared::proto::ListLearnedEntriesRequest>,
+    ) -> Result<Response<shared::proto::ListLearnedEntriesResponse>, Status> {
+        let _mutation_guard = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+
+        let entries = self
+            .converter
+            .learned_entries()
+            .map_err(|error| status_from_error("list_learned_entries", error))?;
+        let entry_count = entries.len();
+        performance_event_lazy!(
+            request_id,
+            "list_learned_entries",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;entry_count={entry_count}"
+        );
+
+        Ok(Response::new(shared::proto::ListLearnedEntriesResponse {
+            server_session_id: server_session_id(),
+            entries,
+        }))
+    }
+
+    async fn forget_learned_entry(
+        &self,
+        request: Request<shared::proto::ForgetLearnedEntryRequest>,
+    ) -> Result<Response<shared::proto::ForgetLearnedEntryResponse>, Status> {
+        let _mutation_guard = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+
+        let forgotten = self
+            .converter
+            .forget_learned_entry(&request.reading, &request.word);
+        performance_event_lazy!(
+            request_id,
+            "forget_learned_entry",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;forgotten={forgotten}"
+        );
+
+        Ok(Response::new(shared::proto::ForgetLearnedEntryResponse {
+            server_session_id: server_session_id(),
+            forgotten,
+        }))
+    }
+
+    async fn forget_learning_candidate(
+        &self,
+        request: Request<shared::proto::ForgetLearningCandidateRequest>,
+    ) -> Result<Response<shared::proto::ForgetLearningCandidateResponse>, Status> {
+        let request = request.into_inner();
+        let _mutation_guard = self.lock_session(request.composition_session_id).await?;
+        let _request_guard = ServerRequestGuard::begin(true);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+        let candidate_id = request.candidate_id;
+
+        let forgotten = self.converter.forget_learning_candidate(candidate_id);
+        if !forgotten {
+            log_event(
+                ServerLogLevel::Warn,
+                &format!(
+                    "[forget_learning_candidate] candidate not forgotten candidate_id={candidate_id}"
+                ),
+            );
+        }
+        performance_event_lazy!(
+            request_id,
+            "forget_learning_candidate",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;candidate_id={candidate_id};forgotten={forgotten}"
+        );
+
+        Ok(Response::new(
+            shared::proto::ForgetLearningCandidateResponse {
+                server_session_id: server_session_id(),
+                forgotten,
+            },
+        ))
+    }
+
+    async fn add_user_word(
+        &self,
+        request: Request<AddUserWordRequest>,
+    ) -> Result<Response<AddUserWordResponse>, Status> {
+        let _mutation_guard = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+        let part_of_speech = PartOfSpeech::from(request.part_of_speech());
+
+        let mut store = self.user_dictionary();
+        let mut updated = store.clone();
+        let word = updated
+            .add(
+                &request.reading,
+                &request.word,
+                part_of_speech,
+                request.comment,
+            )
+            .map_err(status_from_user_word_error)?
+            .clone();
+        self.apply_personal_words(&updated, |converter| converter.add_user_word(&word))
+            .map_err(|error| status_from_error("add_user_word", error))?;
+        self.commit_user_dictionary("add_user_word", &mut store, updated)?;
+        let entry_count = store.words().len();
+        drop(store);
+
+        performance_event_lazy!(
+            request_id,
+            "add_user_word",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;entries={entry_count}"
+        );
+
+        Ok(Response::new(AddUserWordResponse {
+            server_session_id: server_session_id(),
+            word: Some((&word).into()),
+        }))
+    }
+
+    async fn remove_user_word(
+        &self,
+        request: Request<RemoveUserWordRequest>,
+    ) -> Result<Response<RemoveUserWordResponse>, Status> {
+        let _mutation_guard = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+
+        let mut store = self.user_dictionary();
+        let mut updated = store.clone();
+        let removed = match updated.remove(request.id) {
+            Some(word) => {
+                self.apply_personal_words(&updated, |converter| converter.remove_user_word(&word))
+                    .map_err(|error| status_from_error("remove_user_word", error))?;
+                self.commit_user_dictionary("remove_user_word", &mut store, updated)?;
+                true
+            }
+            None => false,
+        };
+        let entry_count = store.words().len();
+        drop(store);
+
+        performance_event_lazy!(
+            request_id,
+            "remove_user_word",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;removed={removed};entries={entry_count}"
+        );
+
+        Ok(Response::new(RemoveUserWordResponse {
+            server_session_id: server_session_id(),
+            removed,
+        }))
+    }
+
+    async fn import_user_words(
+        &self,
+        request: Request<ImportUserWordsRequest>,
+    ) -> Result<Response<ImportUserWordsResponse>, Status> {
+        let _mutation_guard = self.mutation_lock.lock().await;
+        let request = request.into_inner();
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request_id = request_id_or_next(request.request_id);
+        self.converter.set_request_id(request_id);
+        let handler_start = Instant::now();
+
+        let mut store = self.user_dictionary();
+        let mut updated = store.clone();
+        let mut added = Vec::new();
+        let mut skipped_indexes = Vec::new();
+        for (index, word) in request.words.into_iter().enumerate() {
+            let part_of_speech = PartOfSpeech::from(word.part_of_speech());
+            match updated.add(&word.reading, &word.word, part_of_speech, word.comment) {
+                Ok(word) => added.push(shared::proto::UserWord::from(word)),
+                Err(_) => skipped_indexes.push(index as u32),
+            }
+        }
+        if !added.is_empty() {
+            self.apply_personal_words(&updated, |converter| {
+                converter.set_user_words(updated.words())
+            })
+            .map_err(|error| status_from_error("import_user_words", error))?;
+            self.commit_user_dictionary("import_user_words", &mut store, updated)?;
+        }
+        let entry_count = store.words().len();
+        drop(store);
+
+        performance_event_lazy!(
+            request_id,
+            "import_user_words",
+            "total",
+            elapsed_ms(handler_start),
+            "status=success;added={};skipped={};entries={entry_count}",
+            added.len(),
+            skipped_indexes.len()
+        );
+
+        Ok(Response::new(ImportUserWordsResponse {
+            server_session_id: server_session_id(),
+            added,
+            skipped_indexes,
+        }))
+    }
+
+    async fn list_user_words(
+        &self,
+        _request: Request<ListUserWordsRequest>,
+    ) -> Result<Response<ListUserWordsResponse>, Status> {
+        let _request_guard = ServerRequestGuard::begin(false);
+        let words = self
+            .user_dictionary()
+            .words()
+            .iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Response::new(ListUserWordsResponse {
+            server_session_id: server_session_id(),
+            words,
+        }))
+    }
+
+    async fn search_user_words(
+        &self,
+        request: Request<SearchUserWordsRequest>,
+    ) -> Result<Response<SearchUserWordsResponse>, Status> {
+        let _request_guard = ServerRequestGuard::begin(false);
+        let request = request.into_inner();
+        let words = self
+            .user_dictionary()
+            .search(&request.query, request.limit as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Response::new(SearchUserWordsResponse {
+            server_session_id: server_session_id(),
+            words,
+        }))
+    }
+
     async fn log_performance(
         &self,
         request: Request<PerformanceLogRequest>,
@@ -2705,9 +3016,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             ),
         );
     }
-    register_server_log_callbacks();
-
     // Keep IME RPC latency predictable when other applications saturate the CPU.
+    #[cfg(windows)]
     unsafe {
         match SetPriorityClass(GetCurrentProcess(), HIGH_PRIORITY_CLASS) {
             Ok(()) => log_event_lazy!(ServerLogLevel::Info, "process priority set to HIGH"),
@@ -2722,11 +3032,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let parent_dir_str = parent_dir
         .to_str()
         .ok_or_else(|| std::io::Error::other("executable path is not valid UTF-8"))?;
-    initialize(parent_dir_str).map_err(std::io::Error::other)?;
+    let converter = create_converter_backend();
+    converter
+        .initialize(parent_dir_str)
+        .map_err(std::io::Error::other)?;
 
-    let service = MyAzookeyService::default();
+    let user_dictionary = UserDictionaryStore::read().unwrap_or_else(|error| {
+        log_event_lazy!(
+            ServerLogLevel::Error,
+            "failed to read user dictionary, starting with an empty one: {error}"
+        );
+        UserDictionaryStore::default()
+    });
+    let config = AppConfig::read().unwrap_or_default();
+    let service = MyAzookeyService::new(Arc::clone(&converter))
+        .with_persisted_user_dictionary(user_dictionary)
+        .with_user_dictionary_config(&config.user_dictionary)
+        .with_rewriters(config.general.rewriters);
+
+    tokio::spawn({
+        let service = service.clone();
+        async move {
+            let mut interval = tokio::time::interval(SHARED_DICTIONARY_POLL_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                service.reload_shared_dictionaries(None).await;
+            }
+        }
+    });
 
-    tokio::spawn(async {
+    tokio::spawn(async move {
         let mut interval =
             tokio::time::interval(std::time::Duration::from_secs(WARMUP_INTERVAL_SECS));
         interval.tick().await;
@@ -2750,9 +3086,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 ServerLogLevel::Debug,
                 "request_id={request_id} [warmup] schedule_start interval_secs={WARMUP_INTERVAL_SECS};recent_input_skip_ms={WARMUP_RECENT_INPUT_SKIP_MS}"
             );
-            set_request_id(request_id);
+            converter.set_request_id(request_id);
             let schedule_start = Instant::now();
-            let scheduled = warmup();
+            let scheduled = converter.warmup();
             let schedule_elapsed_ms = elapsed_ms(schedule_start);
             if scheduled {
                 performance_event_lazy!(
@@ -2782,37 +3118,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
+    serve(service).await
+}
+
+async fn serve(service: MyAzookeyService) -> Result<(), Box<dyn std::error::Error>> {
     let reflection_service = ReflectionBuilder::configure()
         .register_encoded_file_descriptor_set(shared::proto::FILE_DESCRIPTOR_SET)
         .build_v1()
         .map_err(std::io::Error::other)?;
 
-    let incoming = TonicNamedPipeServer::new_with_first_pipe_callback(SERVER_PIPE_PATH, || {
-        log_event_lazy!(ServerLogLevel::Info, "AzookeyServer listening");
-        write_server_crash_trace(
-            "rust",
-            "server_startup",
-            "listening",
-            "completed",
-            "pipe=LOCAL\\azookey_server",
-        );
-        write_crash_trace_file(
-            LAUNCHER_CRASH_TRACE_FILE_NAME,
-            "rust",
-            "server_startup",
-            "server_listening",
-            "completed",
-            &format!(
-                "server_pid={};pipe=LOCAL\\azookey_server",
-                std::process::id()
-            ),
-        );
-    })?;
+    let endpoint = IpcEndpoint::server()?;
+    let listener = IpcListener::bind(&endpoint)?;
+    log_event_lazy!(
+        ServerLogLevel::Info,
+        "AzookeyServer listening on {endpoint}"
+    );
+    write_server_crash_trace(
+        "rust",
+        "server_startup",
+        "listening",
+        "completed",
+        &format!("endpoint={endpoint}"),
+    );
+    write_crash_trace_file(
+        LAUNCHER_CRASH_TRACE_FILE_NAME,
+        "rust",
+        "server_startup",
+        "server_listening",
+        "completed",
+        &format!("server_pid={};endpoint={endpoint}", std::process::id()),
+    );
 
     Server::builder()
         .add_service(AzookeyServiceServer::new(service))
         .add_service(reflection_service)
-        .serve_with_incoming(incoming)
+        .serve_with_incoming(listener.incoming())
         .await
         .map_err(|error| {
             log_event(
@@ -2827,18 +3167,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod path_tests {
+    use super::converter::{decode_learned_entries, decode_reconversion_readings, FakeConverter};
     use super::{
-        decode_reconversion_readings, hiragana_boundary_fallback, merge_reconversion_suggestions,
-        resolve_log_path_from_roots, retained_prepared_snapshot_count,
-        validate_reconversion_surface, validate_shrink_offset, MyAzookeyService,
-        MAX_RECONVERSION_SUGGESTIONS,
+        hiragana_boundary_fallback, merge_reconversion_suggestions, resolve_log_path_from_roots,
+        retained_prepared_snapshot_count, validate_reconversion_surface, validate_shrink_offset,
+        MyAzookeyService, MAX_RECONVERSION_SUGGESTIONS,
     };
     use shared::proto::Suggestion;
-    use std::{ffi::OsStr, path::Path};
+    use std::{ffi::OsStr, path::Path, sync::Arc};
 
     #[tokio::test]
     async fn mutation_lock_keeps_replace_transaction_exclusive() {
-        let service = MyAzookeyService::default();
+        let service = MyAzookeyService::new(Arc::new(FakeConverter::default()));
         let first = service.mutation_lock.lock().await;
 
         let second = tokio::time::timeout(
@@ -2890,6 +3230,24 @@ mod path_tests {
         assert!(decode_reconversion_readings("not-json").is_err());
     }
 
+    #[test]
+    fn learned_entry_transport_requires_every_field() {
+        let entries = decode_learned_entries(
+            r#"[{"reading":"かんじ","word":"幹事","count":3,"last_used_unix_ms":1700000000000}]"#,
+        )
+        .expect("valid learned entry JSON");
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].reading.as_str(), entries[0].word.as_str()),
+            ("かんじ", "幹事")
+        );
+        assert_eq!(entries[0].count, 3);
+        assert_eq!(entries[0].last_used_unix_ms, 1_700_000_000_000);
+        assert!(decode_learned_entries(r#"[{"reading":"かんじ","count":1}]"#).is_err());
+        assert!(decode_learned_entries("not-json").is_err());
+    }
+
     #[test]
     fn reconversion_merges_ambiguous_readings_while_preserving_the_surface() {
         let groups = vec![
@@ -0,0 +1,123 @@
+use shared::proto::ComposingText;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::{sync::Notify, time::Instant};
+
+/// Longest a `get_refined_candidates` call waits for a pending refinement.
+/// Zenzai finishes well within this on supported hardware; anything slower is
+/// reported as unavailable so a client never hangs on a wedged converter.
+pub(crate) const MAX_REFINEMENT_WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RefinementOutcome {
+    Pending,
+    Ready(ComposingText),
+    Stale,
+    Unavailable,
+}
+
+#[derive(Debug)]
+struct RefinementSlot {
+    request_id: u64,
+    outcome: RefinementOutcome,
+}
+
+/// Results of deferred Zenzai refinements, one slot per composition session.
+///
+/// A deferred `append_text` opens the slot for its request id and a
+/// background task fills it. Any later request on the same session marks the
+/// slot stale, since the refined candidates no longer describe the
+/// composition the client is showing.
+#[derive(Debug, Default)]
+pub(crate) struct Refinements {
+    slots: Mutex<HashMap<u64, RefinementSlot>>,
+    changed: Notify,
+}
+
+impl Refinements {
+    pub(crate) fn begin(&self, session_id: u64, request_id: u64) {
+        let previous = self.slots().insert(
+            session_id,
+            RefinementSlot {
+                request_id,
+                outcome: RefinementOutcome::Pending,
+            },
+        );
+        if previous.is_some() {
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Records the result of the refinement for `request_id`. Ignored when the
+    /// slot was superseded in the meantime.
+    pub(crate) fn finish(&self, session_id: u64, request_id: u64, outcome: RefinementOutcome) {
+        let mut slots = self.slots();
+        let Some(slot) = slots.get_mut(&session_id) else {
+            return;
+        };
+        if slot.request_id != request_id || slot.outcome != RefinementOutcome::Pending {
+            return;
+        }
+        slot.outcome = outcome;
+        drop(slots);
+        self.changed.notify_waiters();
+    }
+
+    /// Marks the session's refinement stale because its composition changed.
+    pub(crate) fn supersede(&self, session_id: u64) {
+        let mut slots = self.slots();
+        let Some(slot) = slots.get_mut(&session_id) else {
+            return;
+        };
+        if slot.outcome == RefinementOutcome::Stale {
+            return;
+        }
+        slot.outcome = RefinementOutcome::Stale;
+        drop(slots);
+        self.changed.notify_waiters();
+    }
+
+    pub(crate) fn remove(&self, session_id: u64) {
+        if self.slots().remove(&session_id).is_some() {
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Waits until the refinement for `request_id` settles or `timeout`
+    /// elapses. A slot owned by a different request reads as stale, and a
+    /// missing slot as unavailable.
+    pub(crate) async fn wait(
+        &self,
+        session_id: u64,
+        request_id: u64,
+        timeout: Duration,
+    ) -> RefinementOutcome {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let outcome = match self.slots().get(&session_id) {
+                None => RefinementOutcome::Unavailable,
+                Some(slot) if slot.request_id != request_id => RefinementOutcome::Stale,
+                Some(slot) => slot.outcome.clone(),
+            };
+            if outcome != RefinementOutcome::Pending {
+                return outcome;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return RefinementOutcome::Unavailable;
+            }
+        }
+    }
+
+    fn slots(&self) -> MutexGuard<'_, HashMap<u64, RefinementSlot>> {
+        self.slots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
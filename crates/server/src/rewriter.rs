@@ -0,0 +1,512 @@
+//! Candidates derived from the reading instead of looked up in the converter's
+//! dictionary: the current date and time, other forms of a number, calculator
+//! results, symbols and emoji from a bundled reading table, and characters
+//! entered by Unicode or JIS code.
+//!
+//! Rewrites are added after the first whole-composition candidate, so the live
+//! conversion the user is looking at does not change under them. They carry no
+//! candidate id, which keeps them out of learning like reconversion candidates.
+
+use crate::converter::ComposedText;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use shared::{proto::Suggestion, RewriterConfig};
+use std::{borrow::Cow, collections::HashSet};
+
+mod code_input;
+mod symbol;
+
+const DATE_ANNOTATION: &str = "日付";
+const TIME_ANNOTATION: &str = "時刻";
+const NUMBER_ANNOTATION: &str = "数字";
+const CALCULATOR_ANNOTATION: &str = "計算結果";
+
+/// Longest digit run offered in kanji, up to the 京 group.
+const MAX_KANJI_NUMBER_DIGITS: usize = 20;
+
+pub(crate) fn rewrite(composed: &mut ComposedText, config: &RewriterConfig, now: NaiveDateTime) {
+    let Some(reading) = composed.hiragana.as_deref() else {
+        return;
+    };
+    let Some(base_index) = composed
+        .suggestions
+        .iter()
+        .position(|suggestion| suggestion.subtext.is_empty())
+    else {
+        return;
+    };
+
+    let mut rewrites: Vec<(String, Cow<'static, str>)> = Vec::new();
+    let mut add = |candidates: Vec<(String, &'static str)>| {
+        rewrites.extend(
+            candidates
+                .into_iter()
+                .map(|(text, annotation)| (text, Cow::Borrowed(annotation))),
+        );
+    };
+    if config.date_time {
+        add(date_time_candidates(reading, now));
+    }
+    if config.number {
+        add(number_candidates(reading)
+            .into_iter()
+            .map(|text| (text, NUMBER_ANNOTATION))
+            .collect());
+    }
+    if config.calculator {
+        add(calculate(reading)
+            .map(|text| (text, CALCULATOR_ANNOTATION))
+            .into_iter()
+            .collect());
+    }
+    if config.symbol || config.emoji {
+        add(symbol::candidates(reading, config.symbol, config.emoji));
+    }
+    if config.code_input {
+        rewrites.extend(
+            code_input::candidate(reading).map(|(text, annotation)| (text, annotation.into())),
+        );
+    }
+    if rewrites.is_empty() {
+        return;
+    }
+
+    let corresponding_count = composed.suggestions[base_index].corresponding_count;
+    let mut seen = composed
+        .suggestions
+        .iter()
+        .map(|suggestion| suggestion.text.clone())
+        .collect::<HashSet<_>>();
+    let rewritten = rewrites
+        .into_iter()
+        .filter(|(text, _)| seen.insert(text.clone()))
+        .map(|(text, annotation)| Suggestion {
+            text,
+            corresponding_count,
+            annotation: Some(annotation.into_owned()),
+            ..Suggestion::default()
+        })
+        .collect::<Vec<_>>();
+    let insert_at = base_index + 1;
+    composed.suggestions.splice(insert_at..insert_at, rewritten);
+}
+
+fn date_time_candidates(reading: &str, now: NaiveDateTime) -> Vec<(String, &'static str)> {
+    let day_offset = match reading {
+        "おととい" | "おとつい" => Some(-2),
+        "きのう" | "さくじつ" => Some(-1),
+        "きょう" | "ほんじつ" => Some(0),
+        "あした" | "あす" | "みょうにち" => Some(1),
+        "あさって" | "みょうごにち" => Some(2),
+        _ => None,
+    };
+    if let Some(offset) = day_offset {
+        let date = now.date() + Duration::days(offset);
+        return date_forms(date)
+            .into_iter()
+            .map(|text| (text, DATE_ANNOTATION))
+            .collect();
+    }
+
+    let year_offset = match reading {
+        "きょねん" | "さくねん" => Some(-1),
+        "ことし" | "こんねん" => Some(0),
+        "らいねん" => Some(1),
+        _ => None,
+    };
+    if let Some(offset) = year_offset {
+        let year = now.year() + offset;
+        let mut forms = vec![format!("{year}年")];
+        forms.extend(era_year(year));
+        return forms
+            .into_iter()
+            .map(|text| (text, DATE_ANNOTATION))
+            .collect();
+    }
+
+    let month_offset = match reading {
+        "せんげつ" => Some(-1),
+        "こんげつ" => Some(0),
+        "らいげつ" => Some(1),
+        _ => None,
+    };
+    if let Some(offset) = month_offset {
+        let months = now.year() * 12 + now.month0() as i32 + offset;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+        return [format!("{month}月"), format!("{year}年{month}月")]
+            .into_iter()
+            .map(|text| (text, DATE_ANNOTATION))
+            .collect();
+    }
+
+    if matches!(reading, "いま" | "げんざい" | "じこく") {
+        let (hour, minute) = (now.hour(), now.minute());
+        let (meridiem, hour12) = if hour < 12 {
+            ("午前", hour)
+        } else {
+            ("午後", hour - 12)
+        };
+        return [
+            format!("{hour}:{minute:02}"),
+            format!("{hour}時{minute:02}分"),
+            format!("{meridiem}{hour12}時{minute}分"),
+        ]
+        .into_iter()
+        .map(|text| (text, TIME_ANNOTATION))
+        .collect();
+    }
+
+    Vec::new()
+}
+
+fn date_forms(date: NaiveDate) -> Vec<String> {
+    let (year, month, day) = (date.year(), date.month(), date.day());
+    let weekday =
+        ["月", "火", "水", "木", "金", "土", "日"][date.weekday().num_days_from_monday() as usize];
+    let mut forms = vec![
+        format!("{year}/{month:02}/{day:02}"),
+        format!("{year}年{month}月{day}日"),
+        format!("{year}-{month:02}-{day:02}"),
+    ];
+    if let Some(era) = era_date(date) {
+        forms.push(format!("{era}{month}月{day}日"));
+    }
+    forms.push(format!("{month}月{day}日({weekday})"));
+    forms
+}
+
+/// The era year of a date, from 平成 on.
+fn era_date(date: NaiveDate) -> Option<String> {
+    let (name, first_year) = if date >= NaiveDate::from_ymd_opt(2019, 5, 1)? {
+        ("令和", 2019)
+    } else if date >= NaiveDate::from_ymd_opt(1989, 1, 8)? {
+        ("平成", 1989)
+    } else {
+        return None;
+    };
+    Some(era_name(name, date.year() - first_year + 1))
+}
+
+/// The era a calendar year mostly falls in, from 平成 on.
+fn era_year(year: i32) -> Option<String> {
+    match year {
+        2019.. => Some(era_name("令和", year - 2018)),
+        1989..=2018 => Some(era_name("平成", year - 1988)),
+        _ => None,
+    }
+}
+
+fn era_name(name: &str, year: i32) -> String {
+    if year == 1 {
+        format!("{name}元年")
+    } else {
+        format!("{name}{year}年")
+    }
+}
+
+fn number_candidates(reading: &str) -> Vec<String> {
+    let digits = reading
+        .chars()
+        .map(ascii_digit)
+        .collect::<Option<String>>()
+        .unwrap_or_default();
+    if digits.is_empty() {
+        return Vec::new();
+    }
+
+    let mut forms = Vec::new();
+    let plain_number = digits.len() == 1 || !digits.starts_with('0');
+    if plain_number && digits.len() > 3 {
+        forms.push(grouped(&digits));
+    }
+    if plain_number && digits.len() <= MAX_KANJI_NUMBER_DIGITS {
+        forms.push(kanji_number(&digits));
+    }
+    forms.push(
+        digits
+            .chars()
+            .filter_map(|digit| char::from_u32(digit as u32 - '0' as u32 + '０' as u32))
+            .collect(),
+    );
+    forms.push(digits);
+    forms
+}
+
+fn ascii_digit(character: char) -> Option<char> {
+    match character {
+        '0'..='9' => Some(character),
+        '０'..='９' => char::from_u32(character as u32 - '０' as u32 + '0' as u32),
+        _ => None,
+    }
+}
+
+fn grouped(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn kanji_number(digits: &str) -> String {
+    const DIGITS: [&str; 10] = ["〇", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+    const PLACES: [&str; 4] = ["千", "百", "十", ""];
+    const GROUPS: [&str; 5] = ["", "万", "億", "兆", "京"];
+
+    let values = digits
+        .bytes()
+        .map(|digit| usize::from(digit - b'0'))
+        .collect::<Vec<_>>();
+    if values.iter().all(|value| *value == 0) {
+        return DIGITS[0].to_string();
+    }
+
+    let padding = (4 - values.len() % 4) % 4;
+    let padded = std::iter::repeat_n(0, padding)
+        .chain(values)
+        .collect::<Vec<_>>();
+    let group_count = padded.len() / 4;
+    let mut kanji = String::new();
+    for (group_index, group) in padded.chunks(4).enumerate() {
+        if group.iter().all(|value| *value == 0) {
+            continue;
+        }
+        for (place, value) in group.iter().enumerate() {
+            match (*value, PLACES[place]) {
+                (0, _) => {}
+                (1, unit) if !unit.is_empty() => kanji.push_str(unit),
+                (value, unit) => {
+                    kanji.push_str(DIGITS[value]);
+                    kanji.push_str(unit);
+                }
+            }
+        }
+        kanji.push_str(GROUPS[group_count - group_index - 1]);
+    }
+    kanji
+}
+
+/// Evaluates `1+2*3=` style input. The romaji table turns some operators into
+/// kana punctuation (`-` into ー, `/` into ・), so those are read back.
+fn calculate(reading: &str) -> Option<String> {
+    let expression = reading
+        .chars()
+        .map(|character| match character {
+            '０'..='９' => ascii_digit(character),
+            '＋' => Some('+'),
+            'ー' | '－' | '−' => Some('-'),
+            '＊' | '×' => Some('*'),
+            '／' | '÷' | '・' => Some('/'),
+            '（' => Some('('),
+            '）' => Some(')'),
+            '．' | '。' => Some('.'),
+            '＝' => Some('='),
+            _ => Some(character),
+        })
+        .collect::<Option<String>>()?;
+    let expression = expression.strip_suffix('=')?;
+    if !expression
+        .chars()
+        .skip(1)
+        .any(|character| matches!(character, '+' | '-' | '*' | '/'))
+    {
+        return None;
+    }
+
+    let mut parser = Calculator {
+        input: expression.as_bytes(),
+        position: 0,
+    };
+    let value = parser.expression()?;
+    if parser.position != parser.input.len() || !value.is_finite() {
+        return None;
+    }
+    Some(format_result(value))
+}
+
+fn format_result(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let formatted = format!("{value:.10}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Recursive-descent evaluation of `+ - * /`, parentheses and unary minus.
+struct Calculator<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Calculator<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(operator @ (b'+' | b'-')) = self.peek() {
+            self.position += 1;
+            let rhs = self.term()?;
+            value = if operator == b'+' {
+                value + rhs
+            } else {
+                value - rhs
+            };
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        while let Some(operator @ (b'*' | b'/')) = self.peek() {
+            self.position += 1;
+            let rhs = self.factor()?;
+            value = if operator == b'*' {
+                value * rhs
+            } else if rhs == 0.0 {
+                return None;
+            } else {
+                value / rhs
+            };
+        }
+        Some(value)
+    }
+
+    fn factor(&mut self) -> Option<f64> {
+        match self.peek()? {
+            b'-' => {
+                self.position += 1;
+                Some(-self.factor()?)
+            }
+            b'(' => {
+                self.position += 1;
+                let value = self.expression()?;
+                (self.peek()? == b')').then_some(())?;
+                self.position += 1;
+                Some(value)
+            }
+            _ => {
+                let start = self.position;
+                while matches!(self.peek(), Some(b'0'..=b'9' | b'.')) {
+                    self.position += 1;
+                }
+                std::str::from_utf8(&self.input[start..self.position])
+                    .ok()?
+                    .parse()
+                    .ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewritten(reading: &str, config: RewriterConfig) -> Vec<(String, Option<String>)> {
+        let mut composed = ComposedText {
+            hiragana: Some(reading.to_string()),
+            suggestions: vec![Suggestion {
+                text: reading.to_string(),
+                corresponding_count: 3,
+                candidate_id: 7,
+                ..Suggestion::default()
+            }],
+        };
+        let now = NaiveDate::from_ymd_opt(2026, 10, 17)
+            .and_then(|date| date.and_hms_opt(14, 5, 0))
+            .expect("valid time");
+        rewrite(&mut composed, &config, now);
+
+        assert_eq!(composed.suggestions[0].candidate_id, 7);
+        assert!(composed.suggestions[1..]
+            .iter()
+            .all(|suggestion| suggestion.candidate_id == 0 && suggestion.corresponding_count == 3));
+        composed
+            .suggestions
+            .into_iter()
+            .skip(1)
+            .map(|suggestion| (suggestion.text, suggestion.annotation))
+            .collect()
+    }
+
+    fn rewritten_texts(reading: &str) -> Vec<String> {
+        rewritten(reading, RewriterConfig::default())
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn date_readings_offer_the_current_date() {
+        assert_eq!(
+            rewritten_texts("きょう"),
+            [
+                "2026/10/17",
+                "2026年10月17日",
+                "2026-10-17",
+                "令和8年10月17日",
+                "10月17日(土)",
+            ]
+        );
+        assert_eq!(rewritten_texts("あした")[0], "2026/10/18");
+        assert_eq!(rewritten_texts("ことし"), ["2026年", "令和8年"]);
+        assert_eq!(rewritten_texts("せんげつ"), ["9月", "2026年9月"]);
+        assert_eq!(rewritten_texts("いま"), ["14:05", "14時05分", "午後2時5分"]);
+        assert_eq!(
+            rewritten("きょう", RewriterConfig::default())[0]
+                .1
+                .as_deref(),
+            Some("日付")
+        );
+    }
+
+    #[test]
+    fn digit_readings_offer_other_number_forms() {
+        assert_eq!(
+            rewritten_texts("12345"),
+            ["12,345", "一万二千三百四十五", "１２３４５"]
+        );
+        assert_eq!(
+            rewritten_texts("１００２０００３"),
+            ["10,020,003", "千二万三", "10020003"]
+        );
+        assert_eq!(rewritten_texts("0"), ["〇", "０"]);
+        assert_eq!(rewritten_texts("007"), ["００７"]);
+    }
+
+    #[test]
+    fn expressions_ending_in_equals_offer_the_result() {
+        assert_eq!(rewritten_texts("1+2*3="), ["7"]);
+        assert_eq!(rewritten_texts("（1+2）*3＝"), ["9"]);
+        assert_eq!(rewritten_texts("10ー4・8="), ["9.5"]);
+        assert_eq!(rewritten_texts("1/0="), Vec::<String>::new());
+        assert_eq!(rewritten_texts("1+2"), Vec::<String>::new());
+        assert_eq!(rewritten_texts("-5="), Vec::<String>::new());
+    }
+
+    #[test]
+    fn disabled_rewriters_add_nothing() {
+        let config = RewriterConfig {
+            date_time: false,
+            number: true,
+            calculator: false,
+            symbol: true,
+            emoji: false,
+            code_input: false,
+        };
+
+        assert!(rewritten("きょう", config).is_empty());
+        assert!(rewritten("1+2=", config).is_empty());
+        assert!(rewritten("わらい", config).is_empty());
+        assert_eq!(rewritten("42", config).len(), 2);
+        assert_eq!(rewritten("おんぷ", config).len(), 3);
+        assert!(rewritten("U+2603", config).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Characters entered by code: `U+2603` (or `u2603`) for a Unicode code point,
+//! `J+2422` for a JIS X 0208 code and `S+81A0` for a Shift_JIS code.
+//!
+//! Romaji turns a leading `u` into う and lowercase hex letters into kana, so
+//! う is read as `u` and letters are expected to be typed in uppercase.
+
+use encoding_rs::{EUC_JP, SHIFT_JIS};
+
+const MIN_UNICODE_DIGITS: usize = 4;
+const MAX_UNICODE_DIGITS: usize = 6;
+const JIS_DIGITS: usize = 4;
+
+/// `(text, annotation)` for a reading that spells a code, if it does.
+pub(super) fn candidate(reading: &str) -> Option<(String, String)> {
+    let reading = reading
+        .chars()
+        .map(|character| match character {
+            'う' => 'u',
+            '！'..='～' => char::from_u32(character as u32 - 0xFEE0).unwrap_or(character),
+            _ => character,
+        })
+        .collect::<String>();
+    let mut chars = reading.chars();
+    let prefix = chars.next()?.to_ascii_uppercase();
+    let digits = chars.as_str();
+    let digits = digits.strip_prefix('+').unwrap_or(digits);
+    if digits.is_empty() || !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code = u32::from_str_radix(digits, 16).ok()?;
+
+    let (character, code_label) = match prefix {
+        'U' if (MIN_UNICODE_DIGITS..=MAX_UNICODE_DIGITS).contains(&digits.len()) => {
+            (char::from_u32(code)?, None)
+        }
+        'J' if digits.len() == JIS_DIGITS => {
+            let [_, _, row, cell] = code.to_be_bytes();
+            if !(0x21..=0x7E).contains(&row) || !(0x21..=0x7E).contains(&cell) {
+                return None;
+            }
+            (
+                decode(EUC_JP, &[row | 0x80, cell | 0x80])?,
+                Some(format!("JIS {code:04X}")),
+            )
+        }
+        'S' if digits.len() == JIS_DIGITS => {
+            let [_, _, lead, trail] = code.to_be_bytes();
+            (
+                decode(SHIFT_JIS, &[lead, trail])?,
+                Some(format!("Shift_JIS {code:04X}")),
+            )
+        }
+        _ => return None,
+    };
+    if character.is_control() {
+        return None;
+    }
+
+    let unicode_label = format!("U+{:04X}", character as u32);
+    let code = match code_label {
+        Some(label) => format!("{label}, {unicode_label}"),
+        None => unicode_label,
+    };
+    let annotation = match unicode_names2::name(character) {
+        Some(name) => format!("{name} ({code})"),
+        None => code,
+    };
+    Some((character.to_string(), annotation))
+}
+
+/// The single character `bytes` encode, rejecting invalid or partial codes.
+fn decode(encoding: &'static encoding_rs::Encoding, bytes: &[u8]) -> Option<char> {
+    let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+    let mut chars = text.chars();
+    match (had_errors, chars.next(), chars.next()) {
+        (false, Some(character), None) => Some(character),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(reading: &str) -> Option<String> {
+        candidate(reading).map(|(text, _)| text)
+    }
+
+    #[test]
+    fn code_readings_offer_the_character_with_its_name() {
+        assert_eq!(
+            candidate("U+2603"),
+            Some(("☃".to_string(), "SNOWMAN (U+2603)".to_string()))
+        );
+        assert_eq!(text("う2603").as_deref(), Some("☃"));
+        assert_eq!(text("Ｕ＋１Ｆ６００").as_deref(), Some("😀"));
+        assert_eq!(
+            candidate("J+3441"),
+            Some((
+                "漢".to_string(),
+                "CJK UNIFIED IDEOGRAPH-6F22 (JIS 3441, U+6F22)".to_string()
+            ))
+        );
+        assert_eq!(text("S+8E9A").as_deref(), Some("字"));
+    }
+
+    #[test]
+    fn invalid_codes_offer_nothing() {
+        assert_eq!(candidate("U+D800"), None);
+        assert_eq!(candidate("U+0007"), None);
+        assert_eq!(candidate("J+7F7F"), None);
+        assert_eq!(candidate("S+4142"), None);
+        assert_eq!(candidate("う12"), None);
+    }
+}
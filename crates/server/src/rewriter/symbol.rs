@@ -0,0 +1,110 @@
+//! The bundled symbol and emoji reading table, `symbols.tsv`.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+/// The reading that lists every symbol in the table.
+const SYMBOL_LIST_READING: &str = "きごう";
+const EMOJI_KIND: &str = "絵文字";
+
+struct Entry {
+    reading: &'static str,
+    text: &'static str,
+    /// 記号, 環境依存 or 絵文字; shown as the candidate's annotation.
+    kind: &'static str,
+}
+
+fn entries() -> &'static [Entry] {
+    static ENTRIES: OnceLock<Vec<Entry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| {
+        include_str!("symbols.tsv")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                Some(Entry {
+                    reading: fields.next()?,
+                    text: fields.next()?,
+                    kind: fields.next()?,
+                })
+            })
+            .collect()
+    })
+}
+
+/// `(text, annotation)` for `reading`, in table order.
+pub(super) fn candidates(reading: &str, symbols: bool, emoji: bool) -> Vec<(String, &'static str)> {
+    let wanted = |entry: &Entry| {
+        if entry.kind == EMOJI_KIND {
+            emoji
+        } else {
+            symbols
+        }
+    };
+
+    if reading == SYMBOL_LIST_READING {
+        if !symbols {
+            return Vec::new();
+        }
+        let mut seen = HashSet::new();
+        return entries()
+            .iter()
+            .filter(|entry| entry.kind != EMOJI_KIND && seen.insert(entry.text))
+            .map(|entry| (entry.text.to_string(), entry.kind))
+            .collect();
+    }
+
+    entries()
+        .iter()
+        .filter(|entry| entry.reading == reading && wanted(entry))
+        .map(|entry| (entry.text.to_string(), entry.kind))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(reading: &str) -> Vec<String> {
+        candidates(reading, true, true)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn symbol_readings_offer_annotated_symbols_and_emoji() {
+        assert_eq!(
+            candidates("おんぷ", true, true),
+            [
+                ("♪".to_string(), "記号"),
+                ("♫".to_string(), "環境依存"),
+                ("♬".to_string(), "環境依存"),
+                ("🎵".to_string(), "絵文字"),
+                ("🎶".to_string(), "絵文字"),
+            ]
+        );
+        assert_eq!(texts("かぶしきがいしゃ"), ["㈱"]);
+        assert_eq!(texts("みぎ"), ["→", "⇒", "➡️"]);
+    }
+
+    #[test]
+    fn symbols_and_emoji_are_offered_separately() {
+        assert_eq!(
+            candidates("おんぷ", false, true),
+            [("🎵".to_string(), "絵文字"), ("🎶".to_string(), "絵文字")]
+        );
+        assert_eq!(candidates("おんぷ", true, false).len(), 3);
+        assert!(candidates("きごう", false, true).is_empty());
+    }
+
+    #[test]
+    fn symbol_list_reading_offers_every_symbol_once() {
+        let texts = texts("きごう");
+
+        assert!(texts.len() > 100, "{}", texts.len());
+        assert_eq!(&texts[..2], ["★", "☆"]);
+        assert!(texts.contains(&"㈱".to_string()));
+        assert!(!texts.contains(&"⭐".to_string()));
+        assert_eq!(texts.iter().filter(|text| *text == "×").count(), 1);
+    }
+}
@@ -0,0 +1,1004 @@
+use super::converter::FakeConverter;
+use super::session::MAX_COMPOSITION_SESSIONS;
+use super::MyAzookeyService;
+use hyper_util::rt::TokioIo;
+use shared::proto::azookey_service_client::AzookeyServiceClient;
+use shared::proto::azookey_service_server::{AzookeyService, AzookeyServiceServer};
+use shared::proto::{
+    AddUserWordRequest, AdvanceClauseRequest, AppendTextRequest, CandidateOrigin, ClearTextRequest,
+    CommitLearningCandidateRequest, CommitLearningCandidatesRequest, ComposingText,
+    CompositionOperation, CompositionOperationKind, CreateCompositionSessionRequest,
+    DestroyCompositionSessionRequest, ForgetLearnedEntryRequest, ForgetLearningCandidateRequest,
+    GetRefinedCandidatesRequest, GetRefinedCandidatesResponse, ImportUserWordsRequest, InputStyle,
+    LearningCandidateCommit, ListLearnedEntriesRequest, ListUserWordsRequest, MoveCursorRequest,
+    PartOfSpeech, PredictRequest, RefinementStatus, RemoveTextRequest, RemoveUserWordRequest,
+    ReplaceCompositionRequest, SearchUserWordsRequest, SetContextRequest, UserWord,
+};
+use shared::transport::{self, IpcEndpoint, IpcListener};
+use shared::{DictionarySettings, UserDictionaryConfig};
+use std::{fs, sync::Arc};
+use tonic::{
+    transport::{Endpoint, Server},
+    Code, Request,
+};
+use tower::service_fn;
+
+fn service() -> (MyAzookeyService, Arc<FakeConverter>) {
+    let converter = Arc::new(FakeConverter::default());
+    (MyAzookeyService::new(converter.clone()), converter)
+}
+
+async fn append(service: &MyAzookeyService, text: &str) -> ComposingText {
+    service
+        .append_text(Request::new(AppendTextRequest {
+            text_to_append: text.to_string(),
+            ..Default::default()
+        }))
+        .await
+        .expect("append_text")
+        .into_inner()
+        .composing_text
+        .expect("composing text")
+}
+
+async fn create_session(service: &MyAzookeyService) -> u64 {
+    service
+        .create_composition_session(Request::new(CreateCompositionSessionRequest::default()))
+        .await
+        .expect("create_composition_session")
+        .into_inner()
+        .composition_session_id
+}
+
+async fn append_in(
+    service: &MyAzookeyService,
+    composition_session_id: u64,
+    text: &str,
+) -> Result<ComposingText, tonic::Status> {
+    Ok(service
+        .append_text(Request::new(AppendTextRequest {
+            text_to_append: text.to_string(),
+            composition_session_id,
+            ..Default::default()
+        }))
+        .await?
+        .into_inner()
+        .composing_text
+        .expect("composing text"))
+}
+
+async fn append_deferred(
+    service: &MyAzookeyService,
+    request_id: u64,
+    text: &str,
+) -> (ComposingText, bool) {
+    let response = service
+        .append_text(Request::new(AppendTextRequest {
+            text_to_append: text.to_string(),
+            request_id,
+            defer_refinement: true,
+            ..Default::default()
+        }))
+        .await
+        .expect("append_text")
+        .into_inner();
+    (
+        response.composing_text.expect("composing text"),
+        response.refinement_pending,
+    )
+}
+
+async fn refined(service: &MyAzookeyService, request_id: u64) -> GetRefinedCandidatesResponse {
+    service
+        .get_refined_candidates(Request::new(GetRefinedCandidatesRequest {
+            request_id,
+            ..Default::default()
+        }))
+        .await
+        .expect("get_refined_candidates")
+        .into_inner()
+}
+
+fn texts(composing_text: &ComposingText) -> Vec<&str> {
+    composing_text
+        .suggestions
+        .iter()
+        .map(|suggestion| suggestion.text.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn append_text_converts_romaji_through_the_backend() {
+    let (service, _) = service();
+
+    let composing_text = append(&service, "kanji").await;
+
+    assert_eq!(composing_text.hiragana, "かんじ");
+    let texts = texts(&composing_text);
+    assert!(texts.contains(&"漢字"), "{texts:?}");
+    assert!(texts.contains(&"かんじ"), "{texts:?}");
+}
+
+#[tokio::test]
+async fn direct_input_style_bypasses_romaji_conversion() {
+    let (service, _) = service();
+
+    let composing_text = service
+        .append_text(Request::new(AppendTextRequest {
+            text_to_append: "ka".to_string(),
+            input_style: InputStyle::Direct as i32,
+            ..Default::default()
+        }))
+        .await
+        .expect("append_text")
+        .into_inner()
+        .composing_text
+        .expect("composing text");
+
+    assert_eq!(composing_text.hiragana, "ka");
+}
+
+#[tokio::test]
+async fn kana_input_style_folds_voicing_marks_into_the_previous_kana() {
+    let (service, _) = service();
+    let append_kana = |text: &str| {
+        service.append_text(Request::new(AppendTextRequest {
+            text_to_append: text.to_string(),
+            input_style: InputStyle::Kana as i32,
+            ..Default::default()
+        }))
+    };
+
+    append_kana("か").await.expect("append_text");
+    append_kana("゛").await.expect("append_text");
+    let composing_text = append_kana("は゜")
+        .await
+        .expect("append_text")
+        .into_inner()
+        .composing_text
+        .expect("composing text");
+    assert_eq!(composing_text.hiragana, "がぱ");
+
+    let response = service
+        .remove_text(Request::new(RemoveTextRequest::default()))
+        .await
+        .expect("remove_text")
+        .into_inner();
+    assert_eq!(
+        response.composing_text.expect("composing text").hiragana,
+        "が"
+    );
+}
+
+#[tokio::test]
+async fn calculator_results_follow_the_live_conversion() {
+    let (service, _) = service();
+
+    let composing_text = append(&service, "1+2*3=").await;
+
+    let texts = texts(&composing_text);
+    assert_eq!(texts.get(1), Some(&"7"), "{texts:?}");
+    assert_eq!(
+        composing_text.suggestions[1].annotation.as_deref(),
+        Some("計算結果")
+    );
+}
+
+#[tokio::test]
+async fn replace_composition_rebuilds_from_a_cleared_state() {
+    let (service, _) = service();
+    append(&service, "tenki").await;
+
+    let append_operation = |text: &str| CompositionOperation {
+        kind: CompositionOperationKind::Append as i32,
+        text: text.to_string(),
+        ..Default::default()
+    };
+    let composing_text = service
+        .replace_composition(Request::new(ReplaceCompositionRequest {
+            operations: vec![
+                append_operation("kanj"),
+                CompositionOperation {
+                    kind: CompositionOperationKind::Remove as i32,
+                    ..Default::default()
+                },
+                append_operation("ji"),
+            ],
+            ..Default::default()
+        }))
+        .await
+        .expect("replace_composition")
+        .into_inner()
+        .composing_text
+        .expect("composing text");
+
+    assert_eq!(composing_text.hiragana, "かんじ");
+}
+
+#[tokio::test]
+async fn remove_text_reports_remaining_raw_input() {
+    let (service, _) = service();
+    append(&service, "kanji").await;
+
+    let response = service
+        .remove_text(Request::new(RemoveTextRequest::default()))
+        .await
+        .expect("remove_text")
+        .into_inner();
+
+    assert_eq!(
+        response.composing_text.expect("composing text").hiragana,
+        "かん"
+    );
+    assert!(response.raw_input.is_some());
+}
+
+#[tokio::test]
+async fn move_cursor_refresh_converts_only_the_prefix() {
+    let (service, _) = service();
+    append(&service, "kyouhatenki").await;
+    service
+        .move_cursor(Request::new(MoveCursorRequest {
+            offset: -3,
+            ..Default::default()
+        }))
+        .await
+        .expect("move_cursor");
+
+    let response = service
+        .move_cursor(Request::new(MoveCursorRequest::default()))
+        .await
+        .expect("move_cursor")
+        .into_inner();
+
+    let composing_text = response.composing_text.expect("composing text");
+    assert_eq!(composing_text.hiragana, "きょうはてんき");
+    let texts = texts(&composing_text);
+    assert!(texts.iter().all(|text| !text.contains("天気")), "{texts:?}");
+}
+
+#[tokio::test]
+async fn advance_clause_pushes_a_snapshot_before_shrinking() {
+    let (service, converter) = service();
+    append(&service, "kyouhatenki").await;
+
+    let response = service
+        .advance_clause(Request::new(AdvanceClauseRequest {
+            offset: 4,
+            ..Default::default()
+        }))
+        .await
+        .expect("advance_clause")
+        .into_inner();
+
+    assert_eq!(converter.snapshot_count(), 1);
+    assert_eq!(response.raw_input, "hatenki");
+    assert_eq!(
+        response.shrunk_text.expect("shrunk text").hiragana,
+        "はてんき"
+    );
+}
+
+#[tokio::test]
+async fn clear_text_discards_the_composition() {
+    let (service, _) = service();
+    append(&service, "kanji").await;
+
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+
+    assert_eq!(append(&service, "ha").await.hiragana, "は");
+}
+
+#[tokio::test]
+async fn set_context_forwards_only_the_last_line() {
+    let (service, converter) = service();
+
+    service
+        .set_context(Request::new(SetContextRequest {
+            context: "first line\rsecond line\r".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .expect("set_context");
+
+    assert_eq!(converter.context(), "second line");
+}
+
+#[tokio::test]
+async fn set_context_forwards_the_rest_of_the_caret_line() {
+    let (service, converter) = service();
+
+    service
+        .set_context(Request::new(SetContextRequest {
+            context: "前の行\r今日は".to_string(),
+            right_context: "いい天気です。\r次の行".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .expect("set_context");
+
+    assert_eq!(converter.context(), "今日は");
+    assert_eq!(converter.right_context(), "いい天気です。");
+}
+
+#[tokio::test]
+async fn composition_sessions_keep_independent_state() {
+    let (service, converter) = service();
+    let first = create_session(&service).await;
+    let second = create_session(&service).await;
+    assert_ne!(first, second);
+
+    append_in(&service, first, "kan").await.unwrap();
+    append_in(&service, second, "tenki").await.unwrap();
+    service
+        .set_context(Request::new(SetContextRequest {
+            context: "second context".to_string(),
+            composition_session_id: second,
+            ..Default::default()
+        }))
+        .await
+        .expect("set_context");
+    let first_text = append_in(&service, first, "ji").await.unwrap();
+
+    assert_eq!(first_text.hiragana, "かんじ");
+    assert_eq!(converter.context(), "");
+    assert_eq!(append(&service, "ha").await.hiragana, "は");
+    assert_eq!(
+        append_in(&service, second, "ha").await.unwrap().hiragana,
+        "てんきは"
+    );
+    assert_eq!(converter.context(), "second context");
+}
+
+#[tokio::test]
+async fn unknown_and_destroyed_sessions_are_not_found() {
+    let (service, converter) = service();
+    let session = create_session(&service).await;
+    append_in(&service, session, "kanji").await.unwrap();
+
+    let destroyed = service
+        .destroy_composition_session(Request::new(DestroyCompositionSessionRequest {
+            composition_session_id: session,
+            ..Default::default()
+        }))
+        .await
+        .expect("destroy_composition_session")
+        .into_inner()
+        .destroyed;
+
+    assert!(destroyed);
+    assert_eq!(converter.session_count(), 1);
+    let error = append_in(&service, session, "a").await.unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+    let error = append_in(&service, session.wrapping_add(1000), "a")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn idle_sessions_are_evicted_least_recently_used_first() {
+    let (service, converter) = service();
+    let first = create_session(&service).await;
+    let oldest = create_session(&service).await;
+    append_in(&service, oldest, "a").await.unwrap();
+    for _ in 2..MAX_COMPOSITION_SESSIONS {
+        create_session(&service).await;
+    }
+    append_in(&service, first, "kan").await.unwrap();
+
+    create_session(&service).await;
+
+    let error = append_in(&service, oldest, "a").await.unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+    assert_eq!(
+        append_in(&service, first, "ji").await.unwrap().hiragana,
+        "かんじ"
+    );
+    // Only the default session and `first` still hold converter state.
+    assert_eq!(converter.session_count(), 2);
+}
+
+#[tokio::test]
+async fn committed_candidates_come_back_marked_as_learned() {
+    let (service, _) = service();
+
+    let composing_text = append(&service, "kanji").await;
+    let kanji = composing_text
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == "幹事")
+        .expect("幹事 candidate");
+    assert_eq!(kanji.origin(), CandidateOrigin::SystemDictionary);
+    service
+        .commit_learning_candidate(Request::new(CommitLearningCandidateRequest {
+            candidate_id: kanji.candidate_id,
+            ..Default::default()
+        }))
+        .await
+        .expect("commit_learning_candidate");
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+
+    let composing_text = append(&service, "kanji").await;
+    let origins = composing_text
+        .suggestions
+        .iter()
+        .map(|suggestion| (suggestion.text.as_str(), suggestion.origin()))
+        .collect::<Vec<_>>();
+    assert!(
+        origins.contains(&("幹事", CandidateOrigin::Learned)),
+        "{origins:?}"
+    );
+    assert!(
+        origins.contains(&("漢字", CandidateOrigin::SystemDictionary)),
+        "{origins:?}"
+    );
+}
+
+#[tokio::test]
+async fn incognito_commits_are_acknowledged_without_learning() {
+    let (service, _) = service();
+
+    let composing_text = append(&service, "kanji").await;
+    let kanji = composing_text
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == "幹事")
+        .expect("幹事 candidate");
+    service
+        .commit_learning_candidate(Request::new(CommitLearningCandidateRequest {
+            candidate_id: kanji.candidate_id,
+            do_not_learn: true,
+            ..Default::default()
+        }))
+        .await
+        .expect("commit_learning_candidate");
+    let committed_count = service
+        .commit_learning_candidates(Request::new(CommitLearningCandidatesRequest {
+            commits: vec![LearningCandidateCommit {
+                candidate_id: kanji.candidate_id,
+                ..Default::default()
+            }],
+            do_not_learn: true,
+            ..Default::default()
+        }))
+        .await
+        .expect("commit_learning_candidates")
+        .into_inner()
+        .committed_count;
+    assert_eq!(committed_count, 0);
+
+    let entries = service
+        .list_learned_entries(Request::new(ListLearnedEntriesRequest::default()))
+        .await
+        .expect("list_learned_entries")
+        .into_inner()
+        .entries;
+    assert!(entries.is_empty(), "{entries:?}");
+}
+
+async fn commit_candidate(service: &MyAzookeyService, input: &str, text: &str) {
+    let composing_text = append(service, input).await;
+    let candidate_id = composing_text
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == text)
+        .map(|suggestion| suggestion.candidate_id)
+        .expect("candidate to commit");
+    service
+        .commit_learning_candidate(Request::new(CommitLearningCandidateRequest {
+            candidate_id,
+            ..Default::default()
+        }))
+        .await
+        .expect("commit_learning_candidate");
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+}
+
+#[tokio::test]
+async fn learned_entries_are_listed_and_forgotten() {
+    let (service, _) = service();
+    commit_candidate(&service, "kanji", "幹事").await;
+    commit_candidate(&service, "kanji", "漢字").await;
+    commit_candidate(&service, "kanji", "幹事").await;
+
+    let entries = service
+        .list_learned_entries(Request::new(ListLearnedEntriesRequest::default()))
+        .await
+        .expect("list_learned_entries")
+        .into_inner()
+        .entries
+        .into_iter()
+        .map(|entry| (entry.reading, entry.word, entry.count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [
+            ("かんじ".to_string(), "幹事".to_string(), 2),
+            ("かんじ".to_string(), "漢字".to_string(), 1),
+        ]
+    );
+
+    let forget = |word: &str| ForgetLearnedEntryRequest {
+        reading: "かんじ".to_string(),
+        word: word.to_string(),
+        ..Default::default()
+    };
+    let response = service
+        .forget_learned_entry(Request::new(forget("幹事")))
+        .await
+        .expect("forget_learned_entry")
+        .into_inner();
+    assert!(response.forgotten);
+    let response = service
+        .forget_learned_entry(Request::new(forget("幹事")))
+        .await
+        .expect("forget_learned_entry")
+        .into_inner();
+    assert!(!response.forgotten);
+
+    assert_eq!(
+        origin_of(&service, "kanji", "幹事").await,
+        Some(CandidateOrigin::SystemDictionary)
+    );
+    assert_eq!(
+        origin_of(&service, "kanji", "漢字").await,
+        Some(CandidateOrigin::Learned)
+    );
+}
+
+#[tokio::test]
+async fn forgetting_a_shown_candidate_drops_its_learning() {
+    let (service, _) = service();
+    commit_candidate(&service, "kanji", "幹事").await;
+
+    let composing_text = append(&service, "kanji").await;
+    let kanji = composing_text
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == "幹事")
+        .expect("幹事 candidate");
+    assert_eq!(kanji.origin(), CandidateOrigin::Learned);
+    let forgotten = |candidate_id| ForgetLearningCandidateRequest {
+        candidate_id,
+        ..Default::default()
+    };
+    let response = service
+        .forget_learning_candidate(Request::new(forgotten(kanji.candidate_id)))
+        .await
+        .expect("forget_learning_candidate")
+        .into_inner();
+    assert!(response.forgotten);
+    let response = service
+        .forget_learning_candidate(Request::new(forgotten(u64::MAX)))
+        .await
+        .expect("forget_learning_candidate")
+        .into_inner();
+    assert!(!response.forgotten);
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+
+    assert_eq!(
+        origin_of(&service, "kanji", "幹事").await,
+        Some(CandidateOrigin::SystemDictionary)
+    );
+    let entries = service
+        .list_learned_entries(Request::new(ListLearnedEntriesRequest::default()))
+        .await
+        .expect("list_learned_entries")
+        .into_inner()
+        .entries;
+    assert!(entries.is_empty(), "{entries:?}");
+}
+
+async fn add_user_word(
+    service: &MyAzookeyService,
+    reading: &str,
+    word: &str,
+) -> Result<UserWord, tonic::Status> {
+    Ok(service
+        .add_user_word(Request::new(AddUserWordRequest {
+            reading: reading.to_string(),
+            word: word.to_string(),
+            part_of_speech: PartOfSpeech::PersonName.into(),
+            comment: "同僚".to_string(),
+            ..Default::default()
+        }))
+        .await?
+        .into_inner()
+        .word
+        .expect("added word"))
+}
+
+async fn origin_of(service: &MyAzookeyService, input: &str, text: &str) -> Option<CandidateOrigin> {
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+    append(service, input)
+        .await
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == text)
+        .map(|suggestion| suggestion.origin())
+}
+
+#[tokio::test]
+async fn user_words_apply_to_conversion_as_they_change() {
+    let (service, _) = service();
+    assert_eq!(origin_of(&service, "kanji", "寛治").await, None);
+
+    let added = add_user_word(&service, "かんじ", "寛治").await.unwrap();
+    assert_eq!(added.part_of_speech(), PartOfSpeech::PersonName);
+    assert_eq!(added.comment, "同僚");
+    assert_eq!(
+        origin_of(&service, "kanji", "寛治").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+
+    let removed = service
+        .remove_user_word(Request::new(RemoveUserWordRequest {
+            id: added.id,
+            ..Default::default()
+        }))
+        .await
+        .expect("remove_user_word")
+        .into_inner()
+        .removed;
+    assert!(removed);
+    assert_eq!(origin_of(&service, "kanji", "寛治").await, None);
+    assert!(
+        !service
+            .remove_user_word(Request::new(RemoveUserWordRequest {
+                id: added.id,
+                ..Default::default()
+            }))
+            .await
+            .expect("remove_user_word")
+            .into_inner()
+            .removed
+    );
+}
+
+#[tokio::test]
+async fn shared_dictionaries_merge_with_personal_words_and_reload() {
+    let directory = std::env::temp_dir().join(format!(
+        "azookey-shared-dictionaries-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&directory).unwrap();
+    let glossary = directory.join("team.txt");
+    fs::write(&glossary, "かんじ\t缶字\t名詞\n").unwrap();
+    let mut config = UserDictionaryConfig {
+        shared_directory: directory.to_string_lossy().into_owned(),
+        ..UserDictionaryConfig::default()
+    };
+    let (service, _) = service();
+    let service = service.with_user_dictionary_config(&config);
+    assert_eq!(
+        origin_of(&service, "kanji", "缶字").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+
+    let added = add_user_word(&service, "かんじ", "寛治").await.unwrap();
+    assert_eq!(
+        origin_of(&service, "kanji", "寛治").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+    service
+        .remove_user_word(Request::new(RemoveUserWordRequest {
+            id: added.id,
+            ..Default::default()
+        }))
+        .await
+        .expect("remove_user_word");
+    assert_eq!(origin_of(&service, "kanji", "寛治").await, None);
+    assert_eq!(
+        origin_of(&service, "kanji", "缶字").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+
+    fs::write(&glossary, "かんじ\t缶字\t名詞\nかんじ\t完侍\t名詞\n").unwrap();
+    service.reload_shared_dictionaries(None).await;
+    assert_eq!(
+        origin_of(&service, "kanji", "完侍").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+
+    config.shared.insert(
+        "team.txt".to_string(),
+        DictionarySettings {
+            enabled: false,
+            priority: 0,
+        },
+    );
+    service.reload_shared_dictionaries(Some(config)).await;
+    assert_eq!(origin_of(&service, "kanji", "缶字").await, None);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+async fn predictions(service: &MyAzookeyService) -> ComposingText {
+    service
+        .predict(Request::new(PredictRequest::default()))
+        .await
+        .expect("predict")
+        .into_inner()
+        .composing_text
+        .expect("composing text")
+}
+
+#[tokio::test]
+async fn predictions_complete_the_reading_and_put_learned_and_user_words_first() {
+    let (service, _) = service();
+    let composing_text = append(&service, "arigato").await;
+    assert_eq!(composing_text.hiragana, "ありがと");
+    let predicted = predictions(&service).await;
+    assert_eq!(texts(&predicted), ["ありがとう", "ありがとうございます"]);
+    assert!(predicted
+        .suggestions
+        .iter()
+        .all(|suggestion| suggestion.subtext.is_empty() && suggestion.corresponding_count == 7));
+
+    add_user_word(&service, "ありがとうね", "有難うね")
+        .await
+        .unwrap();
+    let candidate_id = predictions(&service)
+        .await
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.text == "ありがとうございます")
+        .map(|suggestion| suggestion.candidate_id)
+        .expect("prediction to commit");
+    service
+        .commit_learning_candidate(Request::new(CommitLearningCandidateRequest {
+            candidate_id,
+            ..Default::default()
+        }))
+        .await
+        .expect("commit_learning_candidate");
+
+    let predicted = predictions(&service).await;
+    assert_eq!(
+        texts(&predicted),
+        ["ありがとうございます", "有難うね", "ありがとう"]
+    );
+    assert_eq!(predicted.suggestions[0].origin(), CandidateOrigin::Learned);
+    assert_eq!(
+        predicted.suggestions[1].origin(),
+        CandidateOrigin::UserDictionary
+    );
+}
+
+#[tokio::test]
+async fn user_words_are_listed_searched_and_deduplicated() {
+    let (service, _) = service();
+    for index in 0..60 {
+        add_user_word(&service, &format!("たんご{index}"), &format!("単語{index}"))
+            .await
+            .unwrap();
+    }
+    add_user_word(&service, "あずき", "azooKey").await.unwrap();
+
+    let duplicate = add_user_word(&service, "アズキ", "azooKey")
+        .await
+        .expect_err("duplicate word");
+    assert_eq!(duplicate.code(), Code::AlreadyExists);
+    let blank = add_user_word(&service, " ", "空")
+        .await
+        .expect_err("blank reading");
+    assert_eq!(blank.code(), Code::InvalidArgument);
+
+    let listed = service
+        .list_user_words(Request::new(ListUserWordsRequest::default()))
+        .await
+        .expect("list_user_words")
+        .into_inner()
+        .words;
+    assert_eq!(listed.len(), 61);
+    assert_eq!(listed[0].word, "単語0");
+
+    let found = service
+        .search_user_words(Request::new(SearchUserWordsRequest {
+            query: "AZOO".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .expect("search_user_words")
+        .into_inner()
+        .words;
+    assert_eq!(
+        found
+            .iter()
+            .map(|word| word.word.as_str())
+            .collect::<Vec<_>>(),
+        ["azooKey"]
+    );
+}
+
+#[tokio::test]
+async fn imported_user_words_skip_registered_ones() {
+    let (service, _) = service();
+    add_user_word(&service, "あずき", "azooKey").await.unwrap();
+    let word = |reading: &str, word: &str| UserWord {
+        reading: reading.to_string(),
+        word: word.to_string(),
+        ..Default::default()
+    };
+
+    let response = service
+        .import_user_words(Request::new(ImportUserWordsRequest {
+            words: vec![
+                word("かんじ", "寛治"),
+                word("アズキ", "azooKey"),
+                word("", "空"),
+            ],
+            ..Default::default()
+        }))
+        .await
+        .expect("import_user_words")
+        .into_inner();
+
+    assert_eq!(response.added.len(), 1);
+    assert_eq!(response.added[0].part_of_speech(), PartOfSpeech::ProperNoun);
+    assert_eq!(response.skipped_indexes, [1, 2]);
+    assert_eq!(
+        origin_of(&service, "kanji", "寛治").await,
+        Some(CandidateOrigin::UserDictionary)
+    );
+}
+
+#[tokio::test]
+async fn deferred_append_delivers_zenzai_candidates_in_a_second_phase() {
+    let (service, converter) = service();
+    converter.set_zenzai_enabled(true);
+
+    let (first_phase, pending) = append_deferred(&service, 7, "kanji").await;
+    assert!(pending);
+    let response = refined(&service, 7).await;
+
+    assert_eq!(response.status(), RefinementStatus::Ready);
+    let refined = response.composing_text.expect("refined composing text");
+    assert_eq!(refined.hiragana, first_phase.hiragana);
+    let first_texts = texts(&first_phase);
+    let refined_texts = texts(&refined);
+    assert_eq!(refined_texts[0], first_texts[1], "{refined_texts:?}");
+    assert_eq!(refined_texts[1], first_texts[0], "{refined_texts:?}");
+}
+
+#[tokio::test]
+async fn newer_input_makes_a_pending_refinement_stale() {
+    let (service, converter) = service();
+    converter.set_zenzai_enabled(true);
+
+    let (_, pending) = append_deferred(&service, 7, "kanji").await;
+    assert!(pending);
+    append(&service, "a").await;
+
+    assert_eq!(refined(&service, 7).await.status(), RefinementStatus::Stale);
+}
+
+#[tokio::test]
+async fn predictions_keep_a_pending_refinement() {
+    let (service, converter) = service();
+    converter.set_zenzai_enabled(true);
+
+    let (_, pending) = append_deferred(&service, 7, "kanji").await;
+    assert!(pending);
+    predictions(&service).await;
+
+    assert_eq!(refined(&service, 7).await.status(), RefinementStatus::Ready);
+}
+
+#[tokio::test]
+async fn refinement_is_not_deferred_when_zenzai_would_not_run() {
+    let (service, converter) = service();
+
+    let (_, pending) = append_deferred(&service, 7, "kanji").await;
+    assert!(!pending);
+    assert_eq!(
+        refined(&service, 7).await.status(),
+        RefinementStatus::Unavailable
+    );
+
+    service
+        .clear_text(Request::new(ClearTextRequest::default()))
+        .await
+        .expect("clear_text");
+    converter.set_zenzai_enabled(true);
+    let (_, pending) = append_deferred(&service, 8, "ka").await;
+    assert!(!pending, "too short for Zenzai");
+}
+
+#[tokio::test]
+async fn a_session_can_turn_zenzai_off_for_its_application() {
+    let (service, converter) = service();
+    converter.set_zenzai_enabled(true);
+    let terminal = create_session(&service).await;
+    service
+        .set_context(Request::new(SetContextRequest {
+            composition_session_id: terminal,
+            zenzai_disabled: true,
+            ..Default::default()
+        }))
+        .await
+        .expect("set_context");
+
+    let terminal_append = service
+        .append_text(Request::new(AppendTextRequest {
+            text_to_append: "kanji".to_string(),
+            composition_session_id: terminal,
+            defer_refinement: true,
+            ..Default::default()
+        }))
+        .await
+        .expect("append_text")
+        .into_inner();
+    assert!(!terminal_append.refinement_pending);
+
+    let (_, pending) = append_deferred(&service, 7, "kanji").await;
+    assert!(pending, "other sessions keep Zenzai");
+}
+
+async fn assert_round_trip_over(endpoint: IpcEndpoint) {
+    let listener = IpcListener::bind(&endpoint).expect("bind");
+    let endpoint = listener.local_endpoint().expect("local endpoint");
+    let (service, _) = service();
+    let server = tokio::spawn(
+        Server::builder()
+            .add_service(AzookeyServiceServer::new(service))
+            .serve_with_incoming(listener.incoming()),
+    );
+
+    let channel = Endpoint::from_static("http://[::]:50051")
+        .connect_with_connector(service_fn(move |_| {
+            let endpoint = endpoint.clone();
+            async move { transport::connect(&endpoint).await.map(TokioIo::new) }
+        }))
+        .await
+        .expect("connect");
+    let response = AzookeyServiceClient::new(channel)
+        .append_text(AppendTextRequest {
+            text_to_append: "nihongo".to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("append_text over IPC")
+        .into_inner();
+
+    let composing_text = response.composing_text.expect("composing text");
+    assert_eq!(composing_text.hiragana, "にほんご");
+    assert!(texts(&composing_text).contains(&"日本語"));
+    server.abort();
+}
+
+#[tokio::test]
+async fn service_round_trips_over_loopback_tcp() {
+    assert_round_trip_over("tcp:127.0.0.1:0".parse().unwrap()).await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn service_round_trips_over_a_unix_socket() {
+    let nonce = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "azookey_service_test_{}_{nonce}.sock",
+        std::process::id()
+    ));
+
+    assert_round_trip_over(IpcEndpoint::UnixSocket(path)).await;
+}
@@ -0,0 +1,156 @@
+use crate::converter::ConverterBackend;
+use std::collections::HashMap;
+
+/// The shared session used by clients that never create one. It is never
+/// evicted and cannot be destroyed.
+pub(crate) const DEFAULT_COMPOSITION_SESSION_ID: u64 = 0;
+/// Number of created sessions kept before the least recently used one is
+/// evicted. Each TSF document context owns one session, so this bounds the
+/// memory held by windows that were closed without destroying theirs.
+pub(crate) const MAX_COMPOSITION_SESSIONS: usize = 32;
+
+/// Bookkeeping for per-document composition sessions.
+///
+/// The converter holds the composition state itself; this only tracks which
+/// ids exist, which one the converter currently has selected, and how
+/// recently each was used. It lives inside the service's mutation lock, so
+/// selecting a session and the operation that follows are one transaction.
+///
+/// The last-used tick doubles as a session revision: every request that
+/// selects a session advances it, so deferred work can tell whether the
+/// composition it was scheduled for has changed since.
+#[derive(Debug)]
+pub(crate) struct CompositionSessions {
+    next_id: u64,
+    clock: u64,
+    selected: u64,
+    default_last_used: u64,
+    last_used: HashMap<u64, u64>,
+}
+
+impl CompositionSessions {
+    /// `first_id` seeds session ids so that ids from a previous server
+    /// process are unlikely to name a live session after a restart.
+    pub(crate) fn new(first_id: u64) -> Self {
+        Self {
+            next_id: first_id,
+            clock: 0,
+            selected: DEFAULT_COMPOSITION_SESSION_ID,
+            default_last_used: 0,
+            last_used: HashMap::new(),
+        }
+    }
+
+    /// Registers a new session and returns its id together with the ids of
+    /// sessions evicted to stay within [`MAX_COMPOSITION_SESSIONS`].
+    pub(crate) fn create(&mut self, converter: &dyn ConverterBackend) -> (u64, Vec<u64>) {
+        let mut session_id = self.next_id;
+        while session_id == DEFAULT_COMPOSITION_SESSION_ID
+            || self.last_used.contains_key(&session_id)
+        {
+            session_id = session_id.wrapping_add(1);
+        }
+        self.next_id = session_id.wrapping_add(1);
+        let tick = self.tick();
+        self.last_used.insert(session_id, tick);
+
+        let mut evicted = Vec::new();
+        while self.last_used.len() > MAX_COMPOSITION_SESSIONS {
+            let Some(victim) = self
+                .last_used
+                .iter()
+                .filter(|(id, _)| **id != session_id && **id != self.selected)
+                .min_by_key(|(_, last_used)| **last_used)
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+            self.destroy(victim, converter);
+            evicted.push(victim);
+        }
+        (session_id, evicted)
+    }
+
+    /// Points the converter at `session_id`. Returns `None` for an id that
+    /// was never created or has been evicted, and `Some(true)` when the
+    /// converter switched away from another session.
+    pub(crate) fn select(
+        &mut self,
+        session_id: u64,
+        converter: &dyn ConverterBackend,
+    ) -> Option<bool> {
+        let tick = self.tick();
+        if session_id == DEFAULT_COMPOSITION_SESSION_ID {
+            self.default_last_used = tick;
+        } else {
+            *self.last_used.get_mut(&session_id)? = tick;
+        }
+        Some(self.switch_to(session_id, converter))
+    }
+
+    /// Current revision of `session_id`, or `None` for an unknown id.
+    pub(crate) fn revision(&self, session_id: u64) -> Option<u64> {
+        if session_id == DEFAULT_COMPOSITION_SESSION_ID {
+            Some(self.default_last_used)
+        } else {
+            self.last_used.get(&session_id).copied()
+        }
+    }
+
+    /// Like [`Self::select`], but without advancing the revision, for
+    /// requests that only read the composition.
+    pub(crate) fn select_for_read(
+        &mut self,
+        session_id: u64,
+        converter: &dyn ConverterBackend,
+    ) -> Option<bool> {
+        self.revision(session_id)?;
+        Some(self.switch_to(session_id, converter))
+    }
+
+    /// Like [`Self::select`], but only when no request has touched the
+    /// session since `revision`, and without advancing the revision.
+    pub(crate) fn select_unchanged(
+        &mut self,
+        session_id: u64,
+        revision: u64,
+        converter: &dyn ConverterBackend,
+    ) -> Option<bool> {
+        if self.revision(session_id)? != revision {
+            return None;
+        }
+        Some(self.switch_to(session_id, converter))
+    }
+
+    fn switch_to(&mut self, session_id: u64, converter: &dyn ConverterBackend) -> bool {
+        if self.selected == session_id {
+            return false;
+        }
+        converter.select_session(session_id);
+        self.selected = session_id;
+        true
+    }
+
+    /// Drops a created session. Returns `false` for the default session and
+    /// for unknown ids.
+    pub(crate) fn destroy(&mut self, session_id: u64, converter: &dyn ConverterBackend) -> bool {
+        if self.last_used.remove(&session_id).is_none() {
+            return false;
+        }
+        if self.selected == session_id {
+            converter.select_session(DEFAULT_COMPOSITION_SESSION_ID);
+            self.selected = DEFAULT_COMPOSITION_SESSION_ID;
+        }
+        converter.destroy_session(session_id);
+        true
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.last_used.len()
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
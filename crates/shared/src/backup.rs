@@ -0,0 +1,574 @@
+//! Backup bundles of everything a user accumulates: settings, the user
+//! dictionary and the learning memory.
+//!
+//! A bundle is one file so it can be carried to another machine. It starts
+//! with [`BUNDLE_MAGIC`], followed by the byte length of a JSON manifest (u32,
+//! little endian), the manifest itself and the contents of every file listed
+//! in it, back to back. Learning memory files are binary, which is why the
+//! bundle is not plain JSON.
+//!
+//! Restoring replaces the files under the same [`ConfigWriteGuard`] that
+//! serializes settings writes. The running server keeps its own copy of the
+//! dictionary and learning memory, so callers restart it afterwards.
+
+use crate::{
+    ensure_config_dir, get_config_root, parse_config, replace_config_file, temporary_config_path,
+    user_dictionary::{UserDictionaryStore, USER_DICTIONARY_FILENAME},
+    write_temp_config, ConfigError, ConfigWriteGuard, SETTINGS_FILENAME,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const BUNDLE_MAGIC: &[u8] = b"AZOOKEY-BACKUP\n";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const LEARNING_MEMORY_DIRNAME: &str = "LearningMemory";
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: String,
+    /// `version` of the bundled `settings.json`, if there was one.
+    #[serde(default)]
+    pub settings_version: Option<String>,
+    pub entries: Vec<BundleEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    /// Relative to the config root, with `/` separators.
+    pub path: String,
+    pub size: u64,
+}
+
+/// A file's bundle path and contents.
+pub type BundleFile<'a> = (String, &'a [u8]);
+
+/// What a restore put back, for the settings UI to report.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    pub settings: bool,
+    pub user_dictionary: bool,
+    pub learning_files: usize,
+}
+
+#[derive(Debug)]
+pub enum BackupError {
+    Config(ConfigError),
+    Io { path: PathBuf, source: io::Error },
+    InvalidBundle(String),
+    FutureFormat { stored: u32, current: u32 },
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Config(error) => write!(f, "{error}"),
+            BackupError::Io { path, source } => {
+                write!(f, "failed to access {}: {source}", path.display())
+            }
+            BackupError::InvalidBundle(message) => write!(f, "invalid backup bundle: {message}"),
+            BackupError::FutureFormat { stored, current } => write!(
+                f,
+                "backup format {stored} is newer than supported format {current}"
+            ),
+        }
+    }
+}
+
+impl error::Error for BackupError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BackupError::Config(error) => Some(error),
+            BackupError::Io { source, .. } => Some(source),
+            BackupError::InvalidBundle(_) | BackupError::FutureFormat { .. } => None,
+        }
+    }
+}
+
+impl From<ConfigError> for BackupError {
+    fn from(error: ConfigError) -> Self {
+        BackupError::Config(error)
+    }
+}
+
+impl BackupError {
+    /// Whether the bundle came from a newer azooKey than this one.
+    pub fn is_version_compatibility_error(&self) -> bool {
+        match self {
+            BackupError::Config(error) => error.is_version_compatibility_error(),
+            BackupError::FutureFormat { .. } => true,
+            BackupError::Io { .. } | BackupError::InvalidBundle(_) => false,
+        }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> BackupError + '_ {
+    move |source| BackupError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Bundles the current settings, user dictionary and learning memory.
+pub fn create() -> Result<Vec<u8>, BackupError> {
+    let _write_guard = ConfigWriteGuard::acquire()?;
+    create_from_root(&get_config_root()?)
+}
+
+/// Replaces the settings, user dictionary and learning memory with the
+/// contents of `bundle`. Files the bundle lacks are left alone, except that
+/// the learning memory is replaced as a whole when the bundle has any.
+pub fn restore(bundle: &[u8]) -> Result<RestoreSummary, BackupError> {
+    let _write_guard = ConfigWriteGuard::acquire()?;
+    restore_to_root(&get_config_root()?, bundle)
+}
+
+fn create_from_root(config_root: &Path) -> Result<Vec<u8>, BackupError> {
+    let mut files = Vec::new();
+    let mut settings_version = None;
+    for name in [SETTINGS_FILENAME, USER_DICTIONARY_FILENAME] {
+        let path = config_root.join(name);
+        match fs::read(&path) {
+            Ok(contents) => {
+                if name == SETTINGS_FILENAME {
+                    let config_str = String::from_utf8_lossy(&contents);
+                    settings_version = Some(parse_config(&path, &config_str)?.version);
+                }
+                files.push((name.to_string(), contents));
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(source) => return Err(BackupError::Io { path, source }),
+        }
+    }
+
+    let learning_dir = config_root.join(LEARNING_MEMORY_DIRNAME);
+    match fs::read_dir(&learning_dir) {
+        Ok(dir_entries) => {
+            let mut learning_files = Vec::new();
+            for dir_entry in dir_entries {
+                let dir_entry = dir_entry.map_err(io_error(&learning_dir))?;
+                let path = dir_entry.path();
+                let is_file = dir_entry.file_type().map_err(io_error(&path))?.is_file();
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                if !is_file || !is_bundled_file_name(name) {
+                    continue;
+                }
+                let contents = fs::read(&path).map_err(io_error(&path))?;
+                learning_files.push((format!("{LEARNING_MEMORY_DIRNAME}/{name}"), contents));
+            }
+            learning_files.sort_by(|left, right| left.0.cmp(&right.0));
+            files.extend(learning_files);
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(source) => {
+            return Err(BackupError::Io {
+                path: learning_dir,
+                source,
+            })
+        }
+    }
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: chrono::Local::now().to_rfc3339(),
+        settings_version,
+        entries: files
+            .iter()
+            .map(|(path, contents)| BundleEntry {
+                path: path.clone(),
+                size: contents.len() as u64,
+            })
+            .collect(),
+    };
+    let manifest =
+        serde_json::to_vec(&manifest).map_err(|source| ConfigError::Serialize { source })?;
+    let manifest_len = u32::try_from(manifest.len())
+        .map_err(|_| BackupError::InvalidBundle("manifest is too large".to_string()))?;
+
+    let mut bundle = Vec::with_capacity(
+        BUNDLE_MAGIC.len()
+            + 4
+            + manifest.len()
+            + files
+                .iter()
+                .map(|(_, contents)| contents.len())
+                .sum::<usize>(),
+    );
+    bundle.extend_from_slice(BUNDLE_MAGIC);
+    bundle.extend_from_slice(&manifest_len.to_le_bytes());
+    bundle.extend_from_slice(&manifest);
+    for (_, contents) in files {
+        bundle.extend_from_slice(&contents);
+    }
+    Ok(bundle)
+}
+
+/// Splits a bundle into its manifest and `(path, contents)` pairs, checking
+/// the format version and that every path stays inside the config root.
+pub fn parse(bundle: &[u8]) -> Result<(BundleManifest, Vec<BundleFile<'_>>), BackupError> {
+    let invalid = |message: &str| BackupError::InvalidBundle(message.to_string());
+    let rest = bundle
+        .strip_prefix(BUNDLE_MAGIC)
+        .ok_or_else(|| invalid("not an azooKey backup"))?;
+    let (length, rest) = rest
+        .split_first_chunk::<4>()
+        .ok_or_else(|| invalid("truncated manifest"))?;
+    let length = u32::from_le_bytes(*length) as usize;
+    if rest.len() < length {
+        return Err(invalid("truncated manifest"));
+    }
+    let (manifest, mut contents) = rest.split_at(length);
+    let manifest: BundleManifest = serde_json::from_slice(manifest)
+        .map_err(|error| BackupError::InvalidBundle(format!("unreadable manifest: {error}")))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(BackupError::FutureFormat {
+            stored: manifest.format_version,
+            current: BUNDLE_FORMAT_VERSION,
+        });
+    }
+
+    let mut files = Vec::with_capacity(manifest.entries.len());
+    for entry in &manifest.entries {
+        if !is_bundled_path(&entry.path) {
+            return Err(BackupError::InvalidBundle(format!(
+                "unexpected file {:?}",
+                entry.path
+            )));
+        }
+        if files.iter().any(|(path, _)| *path == entry.path) {
+            return Err(BackupError::InvalidBundle(format!(
+                "duplicate file {:?}",
+                entry.path
+            )));
+        }
+        let size = usize::try_from(entry.size)
+            .ok()
+            .filter(|size| *size <= contents.len())
+            .ok_or_else(|| invalid("truncated file contents"))?;
+        let (file, rest) = contents.split_at(size);
+        files.push((entry.path.clone(), file));
+        contents = rest;
+    }
+    if !contents.is_empty() {
+        return Err(invalid("trailing data after the last file"));
+    }
+    Ok((manifest, files))
+}
+
+fn is_bundled_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':'])
+        // Leftovers of interrupted atomic writes.
+        && !name.contains(".tmp-")
+}
+
+fn is_bundled_path(path: &str) -> bool {
+    match path.split_once('/') {
+        None => path == SETTINGS_FILENAME || path == USER_DICTIONARY_FILENAME,
+        Some((dir, name)) => dir == LEARNING_MEMORY_DIRNAME && is_bundled_file_name(name),
+    }
+}
+
+fn restore_to_root(config_root: &Path, bundle: &[u8]) -> Result<RestoreSummary, BackupError> {
+    let (_, files) = parse(bundle)?;
+
+    // Validate and migrate everything before touching the config root.
+    let mut settings = None;
+    let mut user_dictionary = None;
+    let mut learning_files = Vec::new();
+    for (path, contents) in files {
+        match path.split_once('/') {
+            Some((_, name)) => learning_files.push((name.to_string(), contents)),
+            None if path == SETTINGS_FILENAME => {
+                let settings_path = config_root.join(SETTINGS_FILENAME);
+                let config_str = std::str::from_utf8(contents)
+                    .map_err(|_| BackupError::InvalidBundle("settings are not UTF-8".into()))?;
+                // Refuses settings from a newer azooKey and migrates older ones.
+                let config = parse_config(&settings_path, config_str)?;
+                settings = Some(
+                    serde_json::to_string_pretty(&config)
+                        .map_err(|source| ConfigError::Serialize { source })?,
+                );
+            }
+            None => {
+                let store: UserDictionaryStore =
+                    serde_json::from_slice(contents).map_err(|error| {
+                        BackupError::InvalidBundle(format!("unreadable user dictionary: {error}"))
+                    })?;
+                user_dictionary = Some(
+                    serde_json::to_string_pretty(&store)
+                        .map_err(|source| ConfigError::Serialize { source })?,
+                );
+            }
+        }
+    }
+
+    ensure_config_dir(config_root)?;
+    let staged_learning_dir = if learning_files.is_empty() {
+        None
+    } else {
+        Some(stage_learning_memory(config_root, &learning_files)?)
+    };
+    let mut staged_files = Vec::new();
+    for (name, contents) in [
+        (SETTINGS_FILENAME, &settings),
+        (USER_DICTIONARY_FILENAME, &user_dictionary),
+    ] {
+        let Some(contents) = contents else {
+            continue;
+        };
+        let temp_path = temporary_config_path(config_root, name);
+        if let Err(error) = write_temp_config(&temp_path, contents.as_bytes()) {
+            discard_staged(&staged_files, staged_learning_dir.as_deref());
+            return Err(error.into());
+        }
+        staged_files.push((temp_path, config_root.join(name)));
+    }
+
+    // Everything is staged; swap the learning memory first because it is the
+    // only step that can be rolled back.
+    if let Some(staged_learning_dir) = &staged_learning_dir {
+        if let Err(error) = swap_learning_memory(config_root, staged_learning_dir) {
+            discard_staged(&staged_files, Some(staged_learning_dir));
+            return Err(error);
+        }
+    }
+    for (index, (temp_path, path)) in staged_files.iter().enumerate() {
+        replace_config_file(temp_path, path).map_err(|source| {
+            discard_staged(&staged_files[index..], None);
+            ConfigError::Persist {
+                from: temp_path.clone(),
+                to: path.clone(),
+                source,
+            }
+        })?;
+    }
+
+    Ok(RestoreSummary {
+        settings: settings.is_some(),
+        user_dictionary: user_dictionary.is_some(),
+        learning_files: learning_files.len(),
+    })
+}
+
+fn stage_learning_memory(
+    config_root: &Path,
+    learning_files: &[(String, &[u8])],
+) -> Result<PathBuf, BackupError> {
+    let staged_dir = temporary_config_path(config_root, LEARNING_MEMORY_DIRNAME);
+    fs::create_dir(&staged_dir).map_err(io_error(&staged_dir))?;
+    for (name, contents) in learning_files {
+        let path = staged_dir.join(name);
+        if let Err(source) = fs::write(&path, contents) {
+            let _ = fs::remove_dir_all(&staged_dir);
+            return Err(BackupError::Io { path, source });
+        }
+    }
+    Ok(staged_dir)
+}
+
+fn swap_learning_memory(config_root: &Path, staged_dir: &Path) -> Result<(), BackupError> {
+    let learning_dir = config_root.join(LEARNING_MEMORY_DIRNAME);
+    let previous_dir = temporary_config_path(config_root, "LearningMemory.previous");
+    let had_previous = match fs::rename(&learning_dir, &previous_dir) {
+        Ok(()) => true,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+        Err(source) => {
+            return Err(BackupError::Io {
+                path: learning_dir,
+                source,
+            })
+        }
+    };
+    if let Err(source) = fs::rename(staged_dir, &learning_dir) {
+        if had_previous {
+            let _ = fs::rename(&previous_dir, &learning_dir);
+        }
+        return Err(BackupError::Io {
+            path: learning_dir,
+            source,
+        });
+    }
+    if had_previous {
+        let _ = fs::remove_dir_all(&previous_dir);
+    }
+    Ok(())
+}
+
+fn discard_staged(staged_files: &[(PathBuf, PathBuf)], staged_learning_dir: Option<&Path>) {
+    for (temp_path, _) in staged_files {
+        let _ = fs::remove_file(temp_path);
+    }
+    if let Some(staged_learning_dir) = staged_learning_dir {
+        let _ = fs::remove_dir_all(staged_learning_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppConfig, CONFIG_VERSION};
+
+    fn write_sample_root(config_root: &Path) {
+        let mut config = AppConfig::default();
+        config.general.punctuation_commit = !config.general.punctuation_commit;
+        fs::write(
+            config_root.join(SETTINGS_FILENAME),
+            serde_json::to_string_pretty(&config).unwrap(),
+        )
+        .unwrap();
+        let mut store = UserDictionaryStore::default();
+        store
+            .add("あずき", "azooKey", Default::default(), String::new())
+            .unwrap();
+        fs::write(
+            config_root.join(USER_DICTIONARY_FILENAME),
+            serde_json::to_string(&store).unwrap(),
+        )
+        .unwrap();
+        let learning_dir = config_root.join(LEARNING_MEMORY_DIRNAME);
+        fs::create_dir_all(&learning_dir).unwrap();
+        fs::write(learning_dir.join("memory.louds"), [0u8, 1, 2, 255]).unwrap();
+        fs::write(learning_dir.join("selection-overrides.json"), b"{}").unwrap();
+    }
+
+    #[test]
+    fn bundles_round_trip_into_an_empty_root() {
+        let source = tempfile::tempdir().unwrap();
+        write_sample_root(source.path());
+        let bundle = create_from_root(source.path()).unwrap();
+
+        let (manifest, files) = parse(&bundle).unwrap();
+        assert_eq!(manifest.format_version, BUNDLE_FORMAT_VERSION);
+        assert_eq!(manifest.settings_version.as_deref(), Some(CONFIG_VERSION));
+        assert_eq!(
+            files
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            [
+                SETTINGS_FILENAME,
+                USER_DICTIONARY_FILENAME,
+                "LearningMemory/memory.louds",
+                "LearningMemory/selection-overrides.json",
+            ]
+        );
+
+        let target = tempfile::tempdir().unwrap();
+        let summary = restore_to_root(target.path(), &bundle).unwrap();
+        assert_eq!(
+            summary,
+            RestoreSummary {
+                settings: true,
+                user_dictionary: true,
+                learning_files: 2,
+            }
+        );
+        for path in [
+            "LearningMemory/memory.louds",
+            "LearningMemory/selection-overrides.json",
+        ] {
+            assert_eq!(
+                fs::read(target.path().join(path)).unwrap(),
+                fs::read(source.path().join(path)).unwrap(),
+                "{path}"
+            );
+        }
+        let read_config = |root: &Path| {
+            let path = root.join(SETTINGS_FILENAME);
+            parse_config(&path, &fs::read_to_string(&path).unwrap()).unwrap()
+        };
+        assert_eq!(read_config(target.path()), read_config(source.path()));
+        let restored: UserDictionaryStore = serde_json::from_slice(
+            &fs::read(target.path().join(USER_DICTIONARY_FILENAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(restored.words()[0].word, "azooKey");
+    }
+
+    #[test]
+    fn restore_replaces_the_whole_learning_memory() {
+        let source = tempfile::tempdir().unwrap();
+        write_sample_root(source.path());
+        let bundle = create_from_root(source.path()).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let stale = target
+            .path()
+            .join(LEARNING_MEMORY_DIRNAME)
+            .join("stale.bin");
+        fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        fs::write(&stale, b"stale").unwrap();
+        restore_to_root(target.path(), &bundle).unwrap();
+
+        assert!(!stale.exists());
+        let mut leftovers = fs::read_dir(target.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        leftovers.sort();
+        assert_eq!(
+            leftovers,
+            [
+                LEARNING_MEMORY_DIRNAME,
+                SETTINGS_FILENAME,
+                USER_DICTIONARY_FILENAME
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_or_malformed_bundles_leave_the_root_untouched() {
+        let source = tempfile::tempdir().unwrap();
+        write_sample_root(source.path());
+        let future_settings = fs::read_to_string(source.path().join(SETTINGS_FILENAME))
+            .unwrap()
+            .replace(CONFIG_VERSION, "99.0.0");
+        fs::write(source.path().join(SETTINGS_FILENAME), future_settings).unwrap();
+        let bundle = create_from_root(source.path());
+        // Creating a bundle already refuses settings this build cannot read.
+        assert!(bundle.unwrap_err().is_version_compatibility_error());
+
+        write_sample_root(source.path());
+        let mut bundle = create_from_root(source.path()).unwrap();
+        let target = tempfile::tempdir().unwrap();
+
+        let (mut manifest, _) = parse(&bundle).unwrap();
+        manifest.format_version = BUNDLE_FORMAT_VERSION + 1;
+        let mut future = BUNDLE_MAGIC.to_vec();
+        let manifest = serde_json::to_vec(&manifest).unwrap();
+        future.extend_from_slice(&(manifest.len() as u32).to_le_bytes());
+        future.extend_from_slice(&manifest);
+        let error = restore_to_root(target.path(), &future).unwrap_err();
+        assert!(error.is_version_compatibility_error(), "{error}");
+
+        bundle.pop();
+        assert!(matches!(
+            restore_to_root(target.path(), &bundle),
+            Err(BackupError::InvalidBundle(_))
+        ));
+        assert!(matches!(
+            restore_to_root(target.path(), b"settings"),
+            Err(BackupError::InvalidBundle(_))
+        ));
+        assert_eq!(fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn paths_outside_the_config_root_are_rejected() {
+        assert!(is_bundled_path(SETTINGS_FILENAME));
+        assert!(is_bundled_path("LearningMemory/memory.louds"));
+        assert!(!is_bundled_path("LearningMemory/../settings.json"));
+        assert!(!is_bundled_path("LearningMemory/a\\b"));
+        assert!(!is_bundled_path("../settings.json"));
+        assert!(!is_bundled_path("Other/file"));
+        assert!(!is_bundled_file_name("settings.json.tmp-1-2"));
+    }
+}
@@ -0,0 +1,138 @@
+//! The JIS kana keyboard layout used by かな入力.
+//!
+//! Keys are Windows virtual-key codes as reported for a JIS keyboard. The
+//! dakuten (゛, on `@`) and handakuten (゜, on `[`) keys are sent as their own
+//! characters; [`push_kana`] folds them into the kana before them, which is
+//! what both the client's raw input mirror and the server's converter input do.
+
+pub const DAKUTEN: char = '゛';
+pub const HANDAKUTEN: char = '゜';
+
+/// `(virtual key, unshifted, shifted)` for every key that carries kana.
+const LAYOUT: &[(u16, char, Option<char>)] = &[
+    (0x31, 'ぬ', None),
+    (0x32, 'ふ', None),
+    (0x33, 'あ', Some('ぁ')),
+    (0x34, 'う', Some('ぅ')),
+    (0x35, 'え', Some('ぇ')),
+    (0x36, 'お', Some('ぉ')),
+    (0x37, 'や', Some('ゃ')),
+    (0x38, 'ゆ', Some('ゅ')),
+    (0x39, 'よ', Some('ょ')),
+    (0x30, 'わ', Some('を')),
+    (0xBD, 'ほ', None),             // -
+    (0xDE, 'へ', None),             // ^
+    (0xDC, 'ー', None),             // ¥
+    (0x51, 'た', None),             // Q
+    (0x57, 'て', None),             // W
+    (0x45, 'い', Some('ぃ')),       // E
+    (0x52, 'す', None),             // R
+    (0x54, 'か', None),             // T
+    (0x59, 'ん', None),             // Y
+    (0x55, 'な', None),             // U
+    (0x49, 'に', None),             // I
+    (0x4F, 'ら', None),             // O
+    (0x50, 'せ', None),             // P
+    (0xC0, DAKUTEN, None),          // @
+    (0xDB, HANDAKUTEN, Some('「')), // [
+    (0x41, 'ち', None),             // A
+    (0x53, 'と', None),             // S
+    (0x44, 'し', None),             // D
+    (0x46, 'は', None),             // F
+    (0x47, 'き', None),             // G
+    (0x48, 'く', None),             // H
+    (0x4A, 'ま', None),             // J
+    (0x4B, 'の', None),             // K
+    (0x4C, 'り', None),             // L
+    (0xBB, 'れ', None),             // ;
+    (0xBA, 'け', None),             // :
+    (0xDD, 'む', Some('」')),       // ]
+    (0x5A, 'つ', Some('っ')),       // Z
+    (0x58, 'さ', None),             // X
+    (0x43, 'そ', None),             // C
+    (0x56, 'ひ', None),             // V
+    (0x42, 'こ', None),             // B
+    (0x4E, 'み', None),             // N
+    (0x4D, 'も', None),             // M
+    (0xBC, 'ね', Some('、')),       // ,
+    (0xBE, 'る', Some('。')),       // .
+    (0xBF, 'め', Some('・')),       // /
+    (0xE2, 'ろ', None),             // \
+];
+
+/// The kana a key types on the JIS kana layout, if it types one.
+pub fn kana_for_key(vkey: u16, shift: bool) -> Option<char> {
+    let &(_, plain, shifted) = LAYOUT.iter().find(|(key, _, _)| *key == vkey)?;
+    if shift {
+        shifted
+    } else {
+        Some(plain)
+    }
+}
+
+pub fn is_voicing_mark(character: char) -> bool {
+    matches!(character, DAKUTEN | HANDAKUTEN)
+}
+
+/// `base` with `mark` applied, such as か + ゛ = が or は + ゜ = ぱ.
+pub fn voiced_kana(base: char, mark: char) -> Option<char> {
+    match mark {
+        DAKUTEN => match base {
+            'う' => Some('ゔ'),
+            'か' | 'き' | 'く' | 'け' | 'こ' | 'さ' | 'し' | 'す' | 'せ' | 'そ' | 'た' | 'ち'
+            | 'つ' | 'て' | 'と' | 'は' | 'ひ' | 'ふ' | 'へ' | 'ほ' => {
+                char::from_u32(base as u32 + 1)
+            }
+            _ => None,
+        },
+        HANDAKUTEN => match base {
+            'は' | 'ひ' | 'ふ' | 'へ' | 'ほ' => char::from_u32(base as u32 + 2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Appends typed kana to `text`, folding a voicing mark into the kana before it.
+/// A mark that has nothing to combine with stays as typed.
+pub fn push_kana(text: &mut String, kana: char) {
+    if is_voicing_mark(kana) {
+        if let Some(voiced) = text.chars().last().and_then(|base| voiced_kana(base, kana)) {
+            text.pop();
+            text.push(voiced);
+            return;
+        }
+    }
+    text.push(kana);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_jis_kana_keys() {
+        assert_eq!(kana_for_key(0x31, false), Some('ぬ'));
+        assert_eq!(kana_for_key(0x32, false), Some('ふ'));
+        assert_eq!(kana_for_key(0x30, true), Some('を'));
+        assert_eq!(kana_for_key(0x5A, true), Some('っ'));
+        assert_eq!(kana_for_key(0xC0, false), Some(DAKUTEN));
+        assert_eq!(kana_for_key(0xDB, false), Some(HANDAKUTEN));
+        assert_eq!(kana_for_key(0xE2, false), Some('ろ'));
+        assert_eq!(kana_for_key(0x31, true), None);
+        assert_eq!(kana_for_key(0x70, false), None);
+    }
+
+    #[test]
+    fn folds_voicing_marks_into_the_previous_kana() {
+        let mut text = String::new();
+        for kana in ['か', DAKUTEN, 'は', HANDAKUTEN, 'う', DAKUTEN] {
+            push_kana(&mut text, kana);
+        }
+        assert_eq!(text, "がぱゔ");
+
+        push_kana(&mut text, DAKUTEN);
+        assert_eq!(text, "がぱゔ゛");
+        assert_eq!(voiced_kana('あ', HANDAKUTEN), None);
+    }
+}
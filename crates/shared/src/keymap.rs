@@ -0,0 +1,554 @@
+//! The key map: which command a key runs in each composition state.
+//!
+//! A preset supplies the base table and `overrides` replace or add single
+//! rows, so `settings.json` only records what the user changed. Keys are kept
+//! as strings such as `"Ctrl+Shift+F7"`; a row whose key does not parse is
+//! ignored when the table is resolved instead of failing the whole config.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error, fmt, str::FromStr};
+
+/// Composition state a binding applies to, in Mozc's terms.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapState {
+    /// Nothing is being composed.
+    Precomposition,
+    /// Kana is being typed and has not been converted yet.
+    Composition,
+    /// A conversion is shown, live or with the candidate window open.
+    Conversion,
+}
+
+impl KeymapState {
+    pub const ALL: [Self; 3] = [Self::Precomposition, Self::Composition, Self::Conversion];
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapPreset {
+    #[default]
+    MsIme,
+    Atok,
+    Kotoeri,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCommand {
+    /// Hand the key to the application untouched.
+    PassThrough,
+    Commit,
+    CommitFirstClause,
+    CommitAndNextClause,
+    Convert,
+    Cancel,
+    Backspace,
+    Delete,
+    ForgetCandidate,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    ShrinkClause,
+    ExpandClause,
+    ToHiragana,
+    ToKatakana,
+    ToHalfKatakana,
+    ToFullLatin,
+    ToHalfLatin,
+    ToggleInputMode,
+    InputModeOn,
+    InputModeOff,
+    InputModeKatakana,
+    InputModeHalfKatakana,
+    InputModeFullLatin,
+    Reconvert,
+    /// Reopens the text committed last as the composition it came from.
+    UndoCommit,
+    /// Picks the next shown prediction, or acts as a plain Tab when there is
+    /// none.
+    NextPrediction,
+    PreviousPrediction,
+    /// Suspends learning and context upload until pressed again.
+    ToggleIncognito,
+}
+
+/// Virtual-key code standing for the 英数 key. Windows reports it as CapsLock
+/// and the client decides, per keyboard layout, whether a press means 英数.
+pub const VK_EISU: u16 = 0x14;
+const VK_HANKAKU: u16 = 0xF3;
+const VK_ZENKAKU: u16 = 0xF4;
+
+const KEY_NAMES: &[(&str, u16)] = &[
+    ("Backspace", 0x08),
+    ("Tab", 0x09),
+    ("Enter", 0x0D),
+    ("Eisu", VK_EISU),
+    ("Kana", 0x15),
+    ("ImeOn", 0x16),
+    ("ImeOff", 0x1A),
+    ("Escape", 0x1B),
+    ("Henkan", 0x1C),
+    ("Muhenkan", 0x1D),
+    ("Space", 0x20),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("End", 0x23),
+    ("Home", 0x24),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Semicolon", 0xBA),
+    ("Equal", 0xBB),
+    ("Comma", 0xBC),
+    ("Minus", 0xBD),
+    ("Period", 0xBE),
+    ("Slash", 0xBF),
+    ("Backquote", 0xC0),
+    ("BracketLeft", 0xDB),
+    ("Backslash", 0xDC),
+    ("BracketRight", 0xDD),
+    ("Quote", 0xDE),
+    ("Hankaku", VK_HANKAKU),
+];
+
+/// A key and the modifiers held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: u16,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: u16, ctrl: bool, shift: bool, alt: bool) -> Self {
+        // 半角/全角 reports a different code depending on the mode it leaves.
+        let key = if key == VK_ZENKAKU { VK_HANKAKU } else { key };
+        Self {
+            key,
+            ctrl,
+            shift,
+            alt,
+        }
+    }
+
+    fn key_from_name(name: &str) -> Option<u16> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            let c = c.to_ascii_uppercase();
+            if c.is_ascii_uppercase() || c.is_ascii_digit() {
+                return Some(c as u16);
+            }
+        }
+        if let Some(number) = name
+            .strip_prefix(['F', 'f'])
+            .and_then(|number| number.parse::<u16>().ok())
+        {
+            return (1..=24).contains(&number).then_some(0x70 + number - 1);
+        }
+        KEY_NAMES
+            .iter()
+            .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+            .map(|(_, key)| *key)
+    }
+
+    fn key_name(key: u16) -> Option<String> {
+        match key {
+            0x30..=0x39 | 0x41..=0x5A => Some(char::from(key as u8).to_string()),
+            0x70..=0x87 => Some(format!("F{}", key - 0x70 + 1)),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(_, code)| *code == key)
+                .map(|(name, _)| name.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChordParseError {
+    pub key: String,
+}
+
+impl fmt::Display for KeyChordParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key: {:?}", self.key)
+    }
+}
+
+impl error::Error for KeyChordParseError {}
+
+impl FromStr for KeyChord {
+    type Err = KeyChordParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || KeyChordParseError {
+            key: value.to_string(),
+        };
+        let mut parts: Vec<&str> = value.split('+').map(str::trim).collect();
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(error)?;
+        let (mut ctrl, mut shift, mut alt) = (false, false, false);
+        for modifier in parts {
+            let flag = if modifier.eq_ignore_ascii_case("ctrl") {
+                &mut ctrl
+            } else if modifier.eq_ignore_ascii_case("shift") {
+                &mut shift
+            } else if modifier.eq_ignore_ascii_case("alt") {
+                &mut alt
+            } else {
+                return Err(error());
+            };
+            if std::mem::replace(flag, true) {
+                return Err(error());
+            }
+        }
+        let key = Self::key_from_name(key).ok_or_else(error)?;
+        Ok(Self::new(key, ctrl, shift, alt))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        match Self::key_name(self.key) {
+            Some(name) => f.write_str(&name),
+            None => write!(f, "0x{:02X}", self.key),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct KeymapEntry {
+    pub state: KeymapState,
+    pub key: String,
+    pub command: KeyCommand,
+}
+
+impl KeymapEntry {
+    fn new(state: KeymapState, key: &str, command: KeyCommand) -> Self {
+        Self {
+            state,
+            key: key.to_string(),
+            command,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct KeymapConfig {
+    #[serde(default)]
+    pub preset: KeymapPreset,
+    /// Rows that replace the preset's binding for the same state and key, or
+    /// add one where the preset has none.
+    #[serde(default)]
+    pub overrides: Vec<KeymapEntry>,
+}
+
+impl KeymapConfig {
+    /// The effective table: the preset with the overrides applied, in order.
+    pub fn entries(&self) -> Vec<KeymapEntry> {
+        let mut entries = preset_entries(self.preset);
+        for entry in &self.overrides {
+            let Ok(chord) = entry.key.parse::<KeyChord>() else {
+                continue;
+            };
+            let existing = entries.iter_mut().find(|existing| {
+                existing.state == entry.state && existing.key.parse::<KeyChord>() == Ok(chord)
+            });
+            match existing {
+                Some(existing) => existing.command = entry.command,
+                None => entries.push(KeymapEntry {
+                    state: entry.state,
+                    key: chord.to_string(),
+                    command: entry.command,
+                }),
+            }
+        }
+        entries
+    }
+
+    pub fn resolve(&self) -> Keymap {
+        let bindings = self
+            .entries()
+            .into_iter()
+            .filter_map(|entry| {
+                let chord = entry.key.parse::<KeyChord>().ok()?;
+                Some(((entry.state, chord), entry.command))
+            })
+            .collect();
+        Keymap { bindings }
+    }
+
+    /// Adds an override unless the user already bound the same key.
+    pub(crate) fn override_if_unset(&mut self, state: KeymapState, key: &str, command: KeyCommand) {
+        let chord = key.parse::<KeyChord>().ok();
+        let already_set = self
+            .overrides
+            .iter()
+            .any(|entry| entry.state == state && entry.key.parse::<KeyChord>().ok() == chord);
+        if !already_set {
+            self.overrides.push(KeymapEntry::new(state, key, command));
+        }
+    }
+}
+
+/// A resolved key map, ready for per-keystroke lookups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<(KeymapState, KeyChord), KeyCommand>,
+}
+
+impl Keymap {
+    pub fn lookup(&self, state: KeymapState, chord: &KeyChord) -> Option<KeyCommand> {
+        self.bindings.get(&(state, *chord)).copied()
+    }
+}
+
+pub fn preset_entries(preset: KeymapPreset) -> Vec<KeymapEntry> {
+    use KeyCommand::*;
+    use KeymapState::*;
+
+    let mut entries = Vec::new();
+    let mut bind = |states: &[KeymapState], key: &str, command: KeyCommand| {
+        for state in states {
+            entries.push(KeymapEntry::new(*state, key, command));
+        }
+    };
+    let all = &KeymapState::ALL[..];
+    let composing = &[Composition, Conversion][..];
+
+    bind(all, "Ctrl+Space", ToggleInputMode);
+    bind(all, "Alt+Backquote", ToggleInputMode);
+    bind(all, "Hankaku", ToggleInputMode);
+    bind(all, "ImeOn", InputModeOn);
+    bind(all, "ImeOff", InputModeOff);
+    bind(all, "Ctrl+Shift+F10", ToggleIncognito);
+    if preset == KeymapPreset::Kotoeri {
+        // Mac keyboards put 英数 and かな beside the space bar; on Windows they
+        // arrive as 無変換 and 変換.
+        bind(all, "Muhenkan", InputModeOff);
+        bind(all, "Henkan", InputModeOn);
+    } else {
+        bind(all, "Henkan", Reconvert);
+    }
+
+    bind(composing, "Enter", Commit);
+    bind(composing, "Ctrl+Enter", CommitFirstClause);
+    bind(composing, "Ctrl+Down", CommitAndNextClause);
+    bind(composing, "Space", Convert);
+    bind(composing, "Escape", Cancel);
+    bind(composing, "Backspace", Backspace);
+    bind(composing, "Delete", Delete);
+    bind(composing, "Left", MoveLeft);
+    bind(composing, "Right", MoveRight);
+    bind(composing, "Up", MoveUp);
+    bind(composing, "Down", MoveDown);
+    bind(composing, "Shift+Left", ShrinkClause);
+    bind(composing, "Shift+Right", ExpandClause);
+    bind(composing, "F6", ToHiragana);
+    bind(composing, "F7", ToKatakana);
+    bind(composing, "F8", ToHalfKatakana);
+    bind(composing, "F9", ToFullLatin);
+    bind(composing, "F10", ToHalfLatin);
+    bind(&[Conversion], "Ctrl+Delete", ForgetCandidate);
+    bind(&[Precomposition], "Ctrl+Backspace", UndoCommit);
+    bind(composing, "Tab", NextPrediction);
+    bind(composing, "Shift+Tab", PreviousPrediction);
+
+    match preset {
+        KeymapPreset::MsIme => {
+            bind(composing, "Ctrl+U", ToHiragana);
+            bind(composing, "Ctrl+I", ToKatakana);
+            bind(composing, "Ctrl+O", ToHalfKatakana);
+            bind(composing, "Ctrl+P", ToFullLatin);
+            bind(composing, "Ctrl+T", ToHalfLatin);
+        }
+        KeymapPreset::Atok => {
+            bind(composing, "Ctrl+U", ToHiragana);
+            bind(composing, "Ctrl+I", ToKatakana);
+            bind(composing, "Ctrl+O", ToHalfKatakana);
+            bind(composing, "Ctrl+P", ToFullLatin);
+            bind(composing, "Ctrl+K", ShrinkClause);
+            bind(composing, "Ctrl+L", ExpandClause);
+            bind(composing, "Muhenkan", ToKatakana);
+        }
+        KeymapPreset::Kotoeri => {
+            bind(composing, "Ctrl+J", ToHiragana);
+            bind(composing, "Ctrl+K", ToKatakana);
+            bind(composing, "Ctrl+Semicolon", ToHalfKatakana);
+            bind(composing, "Ctrl+L", ToFullLatin);
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(key: &str) -> KeyChord {
+        key.parse().unwrap()
+    }
+
+    #[test]
+    fn chords_round_trip_through_their_names() {
+        for key in [
+            "Ctrl+Shift+F7",
+            "Alt+Backquote",
+            "Muhenkan",
+            "Ctrl+K",
+            "5",
+            "Ctrl+Enter",
+        ] {
+            assert_eq!(chord(key).to_string(), key);
+        }
+        assert_eq!(chord("shift + ctrl + f7"), chord("Ctrl+Shift+F7"));
+        assert_eq!(KeyChord::new(0xF4, false, false, false), chord("Hankaku"));
+        for key in ["", "Ctrl+", "Ctrl+Ctrl+K", "Super+K", "F25", "Nope"] {
+            assert!(key.parse::<KeyChord>().is_err(), "{key:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn ms_ime_preset_keeps_the_built_in_shortcuts() {
+        let keymap = KeymapConfig::default().resolve();
+
+        for (key, command) in [
+            ("Ctrl+U", KeyCommand::ToHiragana),
+            ("Ctrl+I", KeyCommand::ToKatakana),
+            ("Ctrl+O", KeyCommand::ToHalfKatakana),
+            ("Ctrl+P", KeyCommand::ToFullLatin),
+            ("Ctrl+T", KeyCommand::ToHalfLatin),
+            ("Ctrl+Enter", KeyCommand::CommitFirstClause),
+            ("Ctrl+Down", KeyCommand::CommitAndNextClause),
+            ("Shift+Left", KeyCommand::ShrinkClause),
+        ] {
+            assert_eq!(
+                keymap.lookup(KeymapState::Conversion, &chord(key)),
+                Some(command),
+                "{key}"
+            );
+        }
+        for state in KeymapState::ALL {
+            assert_eq!(
+                keymap.lookup(state, &chord("Ctrl+Space")),
+                Some(KeyCommand::ToggleInputMode)
+            );
+            assert_eq!(keymap.lookup(state, &chord("Eisu")), None);
+        }
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &chord("Ctrl+Delete")),
+            Some(KeyCommand::ForgetCandidate)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &chord("Ctrl+Delete")),
+            None
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Precomposition, &chord("Ctrl+Backspace")),
+            Some(KeyCommand::UndoCommit)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &chord("Ctrl+Backspace")),
+            None
+        );
+        for state in KeymapState::ALL {
+            assert_eq!(
+                keymap.lookup(state, &chord("Ctrl+Shift+F10")),
+                Some(KeyCommand::ToggleIncognito)
+            );
+        }
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &chord("Tab")),
+            Some(KeyCommand::NextPrediction)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &chord("Shift+Tab")),
+            Some(KeyCommand::PreviousPrediction)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Precomposition, &chord("Tab")),
+            None
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &chord("Ctrl+K")),
+            None
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &chord("Ctrl+Alt+U")),
+            None
+        );
+    }
+
+    #[test]
+    fn presets_differ_where_the_original_imes_do() {
+        let atok = KeymapConfig {
+            preset: KeymapPreset::Atok,
+            ..KeymapConfig::default()
+        }
+        .resolve();
+        assert_eq!(
+            atok.lookup(KeymapState::Conversion, &chord("Ctrl+L")),
+            Some(KeyCommand::ExpandClause)
+        );
+        assert_eq!(atok.lookup(KeymapState::Conversion, &chord("Ctrl+T")), None);
+
+        let kotoeri = KeymapConfig {
+            preset: KeymapPreset::Kotoeri,
+            ..KeymapConfig::default()
+        }
+        .resolve();
+        assert_eq!(
+            kotoeri.lookup(KeymapState::Composition, &chord("Ctrl+J")),
+            Some(KeyCommand::ToHiragana)
+        );
+        assert_eq!(
+            kotoeri.lookup(KeymapState::Precomposition, &chord("Muhenkan")),
+            Some(KeyCommand::InputModeOff)
+        );
+    }
+
+    #[test]
+    fn overrides_replace_or_extend_the_preset() {
+        let config = KeymapConfig {
+            preset: KeymapPreset::MsIme,
+            overrides: vec![
+                KeymapEntry::new(KeymapState::Conversion, "ctrl+u", KeyCommand::PassThrough),
+                KeymapEntry::new(KeymapState::Composition, "Ctrl+K", KeyCommand::ShrinkClause),
+                KeymapEntry::new(KeymapState::Composition, "Bogus", KeyCommand::Commit),
+            ],
+        };
+
+        let entries = config.entries();
+        assert_eq!(entries.len(), preset_entries(KeymapPreset::MsIme).len() + 1);
+        let keymap = config.resolve();
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &chord("Ctrl+U")),
+            Some(KeyCommand::PassThrough)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &chord("Ctrl+U")),
+            Some(KeyCommand::ToHiragana)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &chord("Ctrl+K")),
+            Some(KeyCommand::ShrinkClause)
+        );
+    }
+}
@@ -1,10 +1,18 @@
+use keymap::{KeyCommand, KeymapConfig, KeymapState};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env, error, fmt, fs, io,
     path::{Path, PathBuf},
 };
 
+pub mod backup;
+pub mod kana_layout;
+pub mod keymap;
+pub mod romaji_table;
+pub mod transport;
+pub mod user_dictionary;
+
 pub mod proto {
     include!(concat!(env!("OUT_DIR"), "/azookey.rs"));
     include!(concat!(env!("OUT_DIR"), "/window.rs"));
@@ -412,6 +420,26 @@ pub enum SymbolStyle {
     SquareBracketMiddleDot,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionMode {
+    /// Show the top conversion while typing.
+    #[default]
+    Live,
+    /// Show the reading while typing and convert on Space/変換, like MS-IME.
+    Classic,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KanaInputMethod {
+    /// Type readings with the romaji table.
+    #[default]
+    Romaji,
+    /// Type kana directly on the JIS kana layout (かな入力).
+    Kana,
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum SpaceInputMode {
@@ -546,6 +574,47 @@ fn legacy_groups_from_symbol_fullwidth(
     }
 }
 
+/// Extra candidates the server derives from the reading, one switch each.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct RewriterConfig {
+    /// きょう, いま, ことし and similar offer the current date or time.
+    #[serde(default = "default_rewriter_enabled")]
+    pub date_time: bool,
+    /// Digits offer grouped, kanji and full-width forms.
+    #[serde(default = "default_rewriter_enabled")]
+    pub number: bool,
+    /// An expression ending in `=` offers its result.
+    #[serde(default = "default_rewriter_enabled")]
+    pub calculator: bool,
+    /// Readings such as ほし or やじるし offer symbols, and きごう offers the
+    /// whole symbol list.
+    #[serde(default = "default_rewriter_enabled")]
+    pub symbol: bool,
+    /// Readings such as わらい or ねこ offer emoji.
+    #[serde(default = "default_rewriter_enabled")]
+    pub emoji: bool,
+    /// `U+2603`, `J+2422` and `S+81A0` offer the character with that code.
+    #[serde(default = "default_rewriter_enabled")]
+    pub code_input: bool,
+}
+
+impl Default for RewriterConfig {
+    fn default() -> Self {
+        Self {
+            date_time: true,
+            number: true,
+            calculator: true,
+            symbol: true,
+            emoji: true,
+            code_input: true,
+        }
+    }
+}
+
+fn default_rewriter_enabled() -> bool {
+    true
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct GeneralConfig {
     #[serde(default)]
@@ -557,6 +626,10 @@ pub struct GeneralConfig {
     #[serde(default)]
     pub numpad_input: NumpadInputMode,
     #[serde(default)]
+    pub conversion_mode: ConversionMode,
+    #[serde(default)]
+    pub input_method: KanaInputMethod,
+    #[serde(default)]
     pub punctuation_commit: bool,
     #[serde(default = "default_punctuation_commit_target_enabled")]
     pub punctuation_commit_punctuation: bool,
@@ -572,6 +645,10 @@ pub struct GeneralConfig {
     pub live_conversion_reading_vertical_adjustment: i32,
     #[serde(default)]
     pub experimental_typo_correction: bool,
+    #[serde(default)]
+    pub predictive_suggestions: bool,
+    #[serde(default)]
+    pub rewriters: RewriterConfig,
 }
 
 impl Default for GeneralConfig {
@@ -581,6 +658,8 @@ impl Default for GeneralConfig {
             symbol_style: SymbolStyle::CornerBracketMiddleDot,
             space_input: SpaceInputMode::AlwaysHalf,
             numpad_input: NumpadInputMode::DirectInput,
+            conversion_mode: ConversionMode::Live,
+            input_method: KanaInputMethod::Romaji,
             punctuation_commit: false,
             punctuation_commit_punctuation: true,
             punctuation_commit_exclamation: true,
@@ -590,6 +669,8 @@ impl Default for GeneralConfig {
             live_conversion_reading_vertical_adjustment:
                 LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_DEFAULT,
             experimental_typo_correction: false,
+            predictive_suggestions: false,
+            rewriters: RewriterConfig::default(),
         }
     }
 }
@@ -620,29 +701,7 @@ fn is_legacy_removed_default_row(row: &RomajiRule) -> bool {
 }
 
 fn default_romaji_rows() -> Vec<RomajiRule> {
-    include_str!("default_romaji_table.txt")
-        .lines()
-        .filter_map(|line| {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                return None;
-            }
-
-            let mut parts = trimmed.split('\t');
-            let input = parts.next()?.trim();
-            let output = parts.next()?.trim();
-            if input.is_empty() || output.is_empty() {
-                return None;
-            }
-            let next_input = parts.next().unwrap_or_default().trim();
-
-            Some(RomajiRule {
-                input: input.to_string(),
-                output: output.to_string(),
-                next_input: next_input.to_string(),
-            })
-        })
-        .collect()
+    romaji_table::parse_text(include_str!("default_romaji_table.txt")).rows
 }
 
 pub fn get_default_romaji_rows() -> Vec<RomajiRule> {
@@ -666,8 +725,10 @@ mod tests {
     #[cfg(windows)]
     use super::ConfigWriteGuard;
     use super::{
-        AppConfig, ConfigError, DebugConfig, GeneralConfig, LearningConfig, LearningMode,
-        NumpadInputMode, ReconversionKey, ShortcutConfig, WidthMode, CONFIG_VERSION,
+        parse_config, AppConfig, AppInputMode, AppProfile, ConfigError, ConversionMode,
+        DebugConfig, GeneralConfig, KanaInputMethod, KeyCommand, KeymapState, LearningConfig,
+        LearningMode, NumpadInputMode, ReconversionKey, RewriterConfig, ShortcutConfig,
+        SpaceInputMode, SymbolStyle, WidthMode, CONFIG_VERSION,
         LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_DEFAULT, SETTINGS_FILENAME,
     };
     use std::{
@@ -749,6 +810,51 @@ mod tests {
         assert!(!deserialized.show_candidate_window_after_space);
     }
 
+    #[test]
+    fn conversion_mode_defaults_to_live() {
+        assert_eq!(
+            GeneralConfig::default().conversion_mode,
+            ConversionMode::Live
+        );
+
+        let deserialized: GeneralConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(deserialized.conversion_mode, ConversionMode::Live);
+
+        let classic: GeneralConfig =
+            serde_json::from_str(r#"{"conversion_mode":"classic"}"#).unwrap();
+        assert_eq!(classic.conversion_mode, ConversionMode::Classic);
+    }
+
+    #[test]
+    fn rewriters_default_to_on() {
+        assert_eq!(
+            GeneralConfig::default().rewriters,
+            RewriterConfig::default()
+        );
+
+        let deserialized: GeneralConfig =
+            serde_json::from_str(r#"{"rewriters":{"calculator":false,"emoji":false}}"#).unwrap();
+        assert!(deserialized.rewriters.date_time);
+        assert!(deserialized.rewriters.number);
+        assert!(!deserialized.rewriters.calculator);
+        assert!(deserialized.rewriters.symbol);
+        assert!(!deserialized.rewriters.emoji);
+    }
+
+    #[test]
+    fn input_method_defaults_to_romaji() {
+        assert_eq!(
+            GeneralConfig::default().input_method,
+            KanaInputMethod::Romaji
+        );
+
+        let deserialized: GeneralConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(deserialized.input_method, KanaInputMethod::Romaji);
+
+        let kana: GeneralConfig = serde_json::from_str(r#"{"input_method":"kana"}"#).unwrap();
+        assert_eq!(kana.input_method, KanaInputMethod::Kana);
+    }
+
     #[test]
     fn live_conversion_reading_defaults_to_on() {
         let default_config = GeneralConfig::default();
@@ -786,6 +892,58 @@ mod tests {
         assert!(enabled.experimental_typo_correction);
     }
 
+    #[test]
+    fn predictive_suggestions_default_to_off() {
+        assert!(!GeneralConfig::default().predictive_suggestions);
+        let deserialized: GeneralConfig = serde_json::from_str("{}").unwrap();
+        assert!(!deserialized.predictive_suggestions);
+        let enabled: GeneralConfig =
+            serde_json::from_str(r#"{"predictive_suggestions":true}"#).unwrap();
+        assert!(enabled.predictive_suggestions);
+    }
+
+    #[test]
+    fn app_profile_overrides_only_the_fields_it_sets() {
+        let mut config = AppConfig::default();
+        config.general.symbol_style = SymbolStyle::SquareBracketBackslash;
+        config.app_profiles = serde_json::from_str(
+            r#"{"WindowsTerminal.exe":{"default_input_mode":"latin","space_input":"follow_input_mode","zenzai":false}}"#,
+        )
+        .unwrap();
+        config.zenzai.enable = true;
+
+        let profile = config.app_profile("windowsterminal.exe").unwrap();
+        assert_eq!(profile.default_input_mode, Some(AppInputMode::Latin));
+        assert_eq!(profile.learning_mode, None);
+
+        let terminal = config.clone().for_app("WINDOWSTERMINAL.EXE");
+        assert_eq!(
+            terminal.general.space_input,
+            SpaceInputMode::FollowInputMode
+        );
+        assert!(!terminal.zenzai.enable);
+        assert_eq!(
+            terminal.general.symbol_style,
+            SymbolStyle::SquareBracketBackslash
+        );
+        assert_eq!(terminal.learning.mode, LearningMode::Enabled);
+
+        assert_eq!(config.clone().for_app("notepad.exe"), config);
+    }
+
+    #[test]
+    fn app_profiles_default_to_empty_and_omit_unset_overrides() {
+        assert!(AppConfig::default().app_profiles.is_empty());
+        let profile = AppProfile {
+            learning_mode: Some(LearningMode::Disabled),
+            ..AppProfile::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&profile).unwrap(),
+            r#"{"learning_mode":"disabled"}"#
+        );
+    }
+
     #[test]
     fn punctuation_commit_defaults_to_off() {
         let default_config = GeneralConfig::default();
@@ -815,20 +973,53 @@ mod tests {
     }
 
     #[test]
-    fn shortcut_toggles_default_to_expected_values() {
+    fn shortcut_config_defaults_to_win_slash_reconversion() {
         let default_config = ShortcutConfig::default();
-        assert!(default_config.ctrl_space_toggle);
-        assert!(default_config.alt_backquote_toggle);
-        assert!(!default_config.eisu_toggle);
         assert_eq!(default_config.reconversion_key, ReconversionKey::WinSlash);
 
         let deserialized: ShortcutConfig = serde_json::from_str("{}").unwrap();
-        assert!(deserialized.ctrl_space_toggle);
-        assert!(deserialized.alt_backquote_toggle);
-        assert!(!deserialized.eisu_toggle);
         assert_eq!(deserialized.reconversion_key, ReconversionKey::WinSlash);
     }
 
+    #[test]
+    fn legacy_shortcut_toggles_become_keymap_overrides() {
+        let config_str = format!(
+            r#"{{"version":"{CONFIG_VERSION}","zenzai":{{"enable":false,"profile":"","backend":"cpu"}},"shortcuts":{{"ctrl_space_toggle":false,"alt_backquote_toggle":true,"eisu_toggle":true}},"keymap":{{"overrides":[{{"state":"conversion","key":"Ctrl+Space","command":"toggle_input_mode"}}]}}}}"#
+        );
+
+        let config = parse_config(Path::new("settings.json"), &config_str).unwrap();
+        let keymap = config.keymap.resolve();
+        let ctrl_space = "Ctrl+Space".parse().unwrap();
+        let alt_backquote = "Alt+Backquote".parse().unwrap();
+        let eisu = "Eisu".parse().unwrap();
+
+        assert_eq!(
+            keymap.lookup(KeymapState::Composition, &ctrl_space),
+            Some(KeyCommand::PassThrough)
+        );
+        // An override the user already made wins over the migrated toggle.
+        assert_eq!(
+            keymap.lookup(KeymapState::Conversion, &ctrl_space),
+            Some(KeyCommand::ToggleInputMode)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Precomposition, &alt_backquote),
+            Some(KeyCommand::ToggleInputMode)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapState::Precomposition, &eisu),
+            Some(KeyCommand::ToggleInputMode)
+        );
+
+        let saved = serde_json::to_value(&config).unwrap();
+        assert!(saved["shortcuts"].get("ctrl_space_toggle").is_none());
+        assert!(saved["shortcuts"].get("eisu_toggle").is_none());
+        assert_eq!(
+            parse_config(Path::new("settings.json"), &saved.to_string()).unwrap(),
+            config
+        );
+    }
+
     #[test]
     fn reconversion_key_serializes_as_a_preset() {
         let config = ShortcutConfig {
@@ -1213,16 +1404,17 @@ pub enum ReconversionKey {
     Disabled,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ShortcutConfig {
-    #[serde(default = "default_shortcut_enabled")]
-    pub ctrl_space_toggle: bool,
-    #[serde(default = "default_shortcut_enabled")]
-    pub alt_backquote_toggle: bool,
-    #[serde(default)]
-    pub eisu_toggle: bool,
     #[serde(default)]
     pub reconversion_key: ReconversionKey,
+    // 旧仕様との互換: 入力モード切替の on/off は keymap の上書きへ移行する
+    #[serde(rename = "ctrl_space_toggle", default, skip_serializing)]
+    legacy_ctrl_space_toggle: Option<bool>,
+    #[serde(rename = "alt_backquote_toggle", default, skip_serializing)]
+    legacy_alt_backquote_toggle: Option<bool>,
+    #[serde(rename = "eisu_toggle", default, skip_serializing)]
+    legacy_eisu_toggle: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
@@ -1259,10 +1451,105 @@ pub struct UserDictionaryEntry {
     pub word: String,
 }
 
+/// Which user dictionaries the converter uses and in what order.
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
 pub struct UserDictionaryConfig {
+    /// Words registered before the dictionary moved to its own store. Only
+    /// read to seed [`user_dictionary::UserDictionaryStore`] on first start.
     #[serde(default)]
     pub entries: Vec<UserDictionaryEntry>,
+    /// The dictionary edited in the settings app.
+    #[serde(default)]
+    pub personal: DictionarySettings,
+    /// Folder of read-only dictionaries, such as a team glossary on a synced
+    /// share. Empty when there is none.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub shared_directory: String,
+    /// Settings of the shared dictionaries keyed by file name. Files without
+    /// an entry use the defaults.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shared: BTreeMap<String, DictionarySettings>,
+}
+
+impl UserDictionaryConfig {
+    /// The settings of the shared dictionary in `file_name`, matched
+    /// case-insensitively as Windows file names are.
+    pub fn shared_settings(&self, file_name: &str) -> DictionarySettings {
+        self.shared
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(file_name))
+            .map(|(_, settings)| *settings)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct DictionarySettings {
+    #[serde(default = "default_dictionary_enabled")]
+    pub enabled: bool,
+    /// Dictionaries with a higher priority come first; the personal
+    /// dictionary wins ties.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl Default for DictionarySettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_dictionary_enabled(),
+            priority: 0,
+        }
+    }
+}
+
+fn default_dictionary_enabled() -> bool {
+    true
+}
+
+/// Input mode an application starts in when its profile sets one.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppInputMode {
+    Latin,
+    Kana,
+    FullLatin,
+    Katakana,
+    HalfKatakana,
+}
+
+/// Overrides applied over the base configuration while one application has
+/// focus. Unset fields keep the base value.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct AppProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_input_mode: Option<AppInputMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub punctuation_style: Option<PunctuationStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub space_input: Option<SpaceInputMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learning_mode: Option<LearningMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zenzai: Option<bool>,
+}
+
+impl AppProfile {
+    fn apply_to(&self, config: &mut AppConfig) {
+        if let Some(punctuation_style) = self.punctuation_style {
+            config.general.punctuation_style = punctuation_style;
+        }
+        if let Some(space_input) = self.space_input {
+            config.general.space_input = space_input;
+        }
+        if let Some(learning_mode) = self.learning_mode {
+            config.learning.mode = learning_mode;
+        }
+        // The model and backend are set globally, so a profile can only
+        // turn Zenzai off.
+        if let Some(zenzai) = self.zenzai {
+            config.zenzai.enable &= zenzai;
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
@@ -1288,10 +1575,6 @@ impl Default for CharacterWidthConfig {
     }
 }
 
-fn default_shortcut_enabled() -> bool {
-    true
-}
-
 fn default_server_log_level() -> String {
     "warn".to_string()
 }
@@ -1312,17 +1595,6 @@ fn default_live_conversion_reading_vertical_adjustment() -> i32 {
     LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_DEFAULT
 }
 
-impl Default for ShortcutConfig {
-    fn default() -> Self {
-        Self {
-            ctrl_space_toggle: true,
-            alt_backquote_toggle: true,
-            eisu_toggle: false,
-            reconversion_key: ReconversionKey::default(),
-        }
-    }
-}
-
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct AppConfig {
     pub version: String,
@@ -1332,6 +1604,8 @@ pub struct AppConfig {
     #[serde(default)]
     pub shortcuts: ShortcutConfig,
     #[serde(default)]
+    pub keymap: KeymapConfig,
+    #[serde(default)]
     pub general: GeneralConfig,
     #[serde(default)]
     pub romaji_table: RomajiTableConfig,
@@ -1341,6 +1615,10 @@ pub struct AppConfig {
     pub user_dictionary: UserDictionaryConfig,
     #[serde(default)]
     pub learning: LearningConfig,
+    /// Per-application overrides keyed by executable file name, e.g.
+    /// `WindowsTerminal.exe`.
+    #[serde(default)]
+    pub app_profiles: BTreeMap<String, AppProfile>,
 }
 
 impl Default for AppConfig {
@@ -1354,16 +1632,34 @@ impl Default for AppConfig {
                 backend: "cpu".to_string(),
             },
             shortcuts: ShortcutConfig::default(),
+            keymap: KeymapConfig::default(),
             general: GeneralConfig::default(),
             romaji_table: RomajiTableConfig::default(),
             character_width: CharacterWidthConfig::default(),
             user_dictionary: UserDictionaryConfig::default(),
             learning: LearningConfig::default(),
+            app_profiles: BTreeMap::new(),
         }
     }
 }
 
 impl AppConfig {
+    /// The profile for `executable`, matched case-insensitively.
+    pub fn app_profile(&self, executable: &str) -> Option<&AppProfile> {
+        self.app_profiles
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(executable))
+            .map(|(_, profile)| profile)
+    }
+
+    /// This configuration as seen by `executable`, with its profile applied.
+    pub fn for_app(mut self, executable: &str) -> Self {
+        if let Some(profile) = self.app_profile(executable).cloned() {
+            profile.apply_to(&mut self);
+        }
+        self
+    }
+
     pub fn settings_path() -> Result<PathBuf, ConfigError> {
         Ok(get_config_root()?.join(SETTINGS_FILENAME))
     }
@@ -1374,7 +1670,7 @@ impl AppConfig {
         ensure_config_dir(&config_root)?;
         let config_path = config_root.join(SETTINGS_FILENAME);
         ensure_existing_config_is_writable(&config_path)?;
-        let temp_path = temporary_config_path(&config_root);
+        let temp_path = temporary_config_path(&config_root, SETTINGS_FILENAME);
         let config_str = serde_json::to_string_pretty(self)
             .map_err(|source| ConfigError::Serialize { source })?;
         compare_config_version(&config_path, &config_str)?;
@@ -1557,11 +1853,41 @@ fn parse_config(config_path: &Path, config_str: &str) -> Result<AppConfig, Confi
         config.version = CONFIG_VERSION.to_string();
     }
 
+    migrate_legacy_shortcut_toggles(&mut config);
     repair_mojibake_default_romaji_table(&mut config);
 
     Ok(config)
 }
 
+fn migrate_legacy_shortcut_toggles(config: &mut AppConfig) {
+    let shortcuts = &mut config.shortcuts;
+    let disabled_keys = [
+        (shortcuts.legacy_ctrl_space_toggle.take(), "Ctrl+Space"),
+        (
+            shortcuts.legacy_alt_backquote_toggle.take(),
+            "Alt+Backquote",
+        ),
+    ];
+    let eisu_toggle = shortcuts.legacy_eisu_toggle.take();
+
+    for (enabled, key) in disabled_keys {
+        if enabled == Some(false) {
+            for state in KeymapState::ALL {
+                config
+                    .keymap
+                    .override_if_unset(state, key, KeyCommand::PassThrough);
+            }
+        }
+    }
+    if eisu_toggle == Some(true) {
+        for state in KeymapState::ALL {
+            config
+                .keymap
+                .override_if_unset(state, "Eisu", KeyCommand::ToggleInputMode);
+        }
+    }
+}
+
 fn repair_mojibake_default_romaji_table(config: &mut AppConfig) {
     if romaji_table_looks_like_mojibake_default(&config.romaji_table.rows) {
         config.romaji_table.rows = default_romaji_rows();
@@ -1626,10 +1952,10 @@ fn write_temp_config(temp_path: &Path, bytes: &[u8]) -> Result<(), ConfigError>
         })
 }
 
-fn temporary_config_path(config_root: &Path) -> PathBuf {
+fn temporary_config_path(config_root: &Path, file_name: &str) -> PathBuf {
     let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S%f");
     config_root.join(format!(
-        "{SETTINGS_FILENAME}.tmp-{}-{timestamp}",
+        "{file_name}.tmp-{}-{timestamp}",
         std::process::id()
     ))
 }
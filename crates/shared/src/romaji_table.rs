@@ -0,0 +1,508 @@
+//! Romaji tables: the shipped presets, Google IME `.txt` import and export, and
+//! diagnostics for tables edited by hand.
+//!
+//! Diagnostics refer to 1-based row numbers, which are also the line numbers of
+//! the table once written out by [`serialize`].
+
+use crate::RomajiRule;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error, fmt};
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RomajiPreset {
+    /// The table shipped as the default, following Google Japanese Input.
+    #[default]
+    GoogleIme,
+    /// Google's table without the `z` symbol shortcuts MS-IME does not have.
+    MsIme,
+    /// AZIK: `ん` and double-vowel endings after a consonant (`kz` かん,
+    /// `kp` こう), `q` for ん and `;` for っ.
+    Azik,
+    /// ACT, AZIK for Dvorak: `c` types か行 and the endings sit on the keys
+    /// that are in AZIK's places on a Dvorak keyboard.
+    Act,
+}
+
+impl RomajiPreset {
+    pub const ALL: [Self; 4] = [Self::GoogleIme, Self::MsIme, Self::Azik, Self::Act];
+
+    pub fn rows(self) -> Vec<RomajiRule> {
+        let base = crate::get_default_romaji_rows();
+        match self {
+            Self::GoogleIme => base,
+            Self::MsIme => base
+                .into_iter()
+                .filter(|row| !is_z_symbol_shortcut(row))
+                .collect(),
+            Self::Azik => {
+                let extensions = ending_rows(&base, AZIK_CONSONANTS, AZIK_ENDINGS)
+                    .chain([rule("q", "ん"), rule(";", "っ")])
+                    .collect::<Vec<_>>();
+                with_overrides(base, extensions)
+            }
+            Self::Act => {
+                let c_row = ["a", "i", "u", "e", "o"]
+                    .into_iter()
+                    .zip(["か", "き", "く", "け", "こ"])
+                    .map(|(vowel, kana)| rule(&format!("c{vowel}"), kana))
+                    .chain(
+                        [("cya", "きゃ"), ("cyu", "きゅ"), ("cyo", "きょ")]
+                            .map(|(input, output)| rule(input, output)),
+                    )
+                    .collect::<Vec<_>>();
+                let base = with_overrides(base, c_row);
+                let extensions = ending_rows(&base, ACT_CONSONANTS, ACT_ENDINGS).collect();
+                with_overrides(base, extensions)
+            }
+        }
+    }
+}
+
+/// `(key, vowel, appended kana)`: the key typed after a consonant stands for the
+/// vowel followed by the kana, so `k` + `z` is か + ん.
+type Ending = (char, &'static str, &'static str);
+
+const AZIK_ENDINGS: &[Ending] = &[
+    ('z', "a", "ん"),
+    ('k', "i", "ん"),
+    ('j', "u", "ん"),
+    ('d', "e", "ん"),
+    ('l', "o", "ん"),
+    ('q', "a", "い"),
+    ('h', "u", "う"),
+    ('w', "e", "い"),
+    ('p', "o", "う"),
+];
+
+const ACT_ENDINGS: &[Ending] = &[
+    (';', "a", "ん"),
+    ('t', "i", "ん"),
+    ('h', "u", "ん"),
+    ('d', "e", "ん"),
+    ('r', "o", "ん"),
+    ('\'', "a", "い"),
+    ('g', "u", "う"),
+    (',', "e", "い"),
+    ('l', "o", "う"),
+];
+
+const AZIK_CONSONANTS: &[&str] = &[
+    "k", "s", "t", "n", "h", "m", "r", "g", "z", "d", "b", "p", "ky", "sy", "ty", "ny", "hy", "my",
+    "ry", "gy", "zy", "by", "py",
+];
+
+const ACT_CONSONANTS: &[&str] = &[
+    "c", "s", "t", "n", "h", "m", "r", "g", "z", "d", "b", "p", "cy", "sy", "ty", "ny", "hy", "my",
+    "ry", "gy", "zy", "by", "py",
+];
+
+fn rule(input: &str, output: &str) -> RomajiRule {
+    RomajiRule {
+        input: input.to_string(),
+        output: output.to_string(),
+        next_input: String::new(),
+    }
+}
+
+fn is_z_symbol_shortcut(row: &RomajiRule) -> bool {
+    row.input.len() == 2
+        && row.input.starts_with('z')
+        && !row
+            .output
+            .chars()
+            .any(|character| matches!(character, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}'))
+}
+
+fn ending_rows<'a>(
+    base: &'a [RomajiRule],
+    consonants: &'a [&'a str],
+    endings: &'a [Ending],
+) -> impl Iterator<Item = RomajiRule> + 'a {
+    let kana = base
+        .iter()
+        .filter(|row| row.next_input.is_empty())
+        .map(|row| (row.input.as_str(), row.output.as_str()))
+        .collect::<HashMap<_, _>>();
+    consonants.iter().flat_map(move |consonant| {
+        let kana = kana.clone();
+        endings.iter().filter_map(move |(key, vowel, appended)| {
+            let syllable = kana.get(format!("{consonant}{vowel}").as_str())?;
+            Some(rule(
+                &format!("{consonant}{key}"),
+                &format!("{syllable}{appended}"),
+            ))
+        })
+    })
+}
+
+/// Replaces the rows whose input an extension redefines and appends the rest.
+fn with_overrides(mut rows: Vec<RomajiRule>, extensions: Vec<RomajiRule>) -> Vec<RomajiRule> {
+    let mut positions = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| (row.input.clone(), index))
+        .collect::<HashMap<_, _>>();
+    for extension in extensions {
+        match positions.get(&extension.input) {
+            Some(&index) => rows[index] = extension,
+            None => {
+                positions.insert(extension.input.clone(), rows.len());
+                rows.push(extension);
+            }
+        }
+    }
+    rows
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The row does not do what it says, or breaks typing.
+    Error,
+    /// The row works, but perhaps not as the author expects.
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// An imported line has an input but no output.
+    MissingOutput,
+    /// The input is empty, so no typing reaches the row.
+    Unreachable,
+    /// Same input as `line` with a different result; only `line` is used.
+    DuplicateInput { line: usize },
+    /// Same row as `line`.
+    RepeatedRow { line: usize },
+    /// The input starts the longer input on `line`, so the row only applies
+    /// when the next key does not continue it.
+    ShadowedByLongerInput { line: usize },
+    /// `next_input` feeds back into these rows without another key, forever.
+    NextInputLoop { lines: Vec<usize> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::RepeatedRow { .. } | DiagnosticKind::ShadowedByLongerInput { .. } => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DiagnosticKind::MissingOutput => write!(f, "expected an input and an output"),
+            DiagnosticKind::Unreachable => write!(f, "input is empty"),
+            DiagnosticKind::DuplicateInput { line } => {
+                write!(f, "input is already defined on line {line}")
+            }
+            DiagnosticKind::RepeatedRow { line } => write!(f, "repeats line {line}"),
+            DiagnosticKind::ShadowedByLongerInput { line } => {
+                write!(f, "applies only when the input on line {line} is not typed")
+            }
+            DiagnosticKind::NextInputLoop { lines } => {
+                let lines = lines
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "next input loops through lines {lines}")
+            }
+        }
+    }
+}
+
+/// Checks a table the way the romaji converter reads it: the longest input
+/// wins, and a row fires once no longer input can still match.
+pub fn validate(rows: &[RomajiRule]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut first_lines = HashMap::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        let line = index + 1;
+        if row.input.is_empty() {
+            diagnostics.push(Diagnostic {
+                line,
+                kind: DiagnosticKind::Unreachable,
+            });
+            continue;
+        }
+        if let Some(&first_line) = first_lines.get(row.input.as_str()) {
+            let first: &RomajiRule = &rows[first_line - 1];
+            let kind = if first == row {
+                DiagnosticKind::RepeatedRow { line: first_line }
+            } else {
+                DiagnosticKind::DuplicateInput { line: first_line }
+            };
+            diagnostics.push(Diagnostic { line, kind });
+            continue;
+        }
+        first_lines.insert(row.input.as_str(), line);
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        if row.input.is_empty() || first_lines.get(row.input.as_str()) != Some(&(index + 1)) {
+            continue;
+        }
+        if let Some(longer) = longer_input_line(rows, &row.input) {
+            diagnostics.push(Diagnostic {
+                line: index + 1,
+                kind: DiagnosticKind::ShadowedByLongerInput { line: longer },
+            });
+        }
+    }
+
+    diagnostics.extend(next_input_loops(rows, &first_lines));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+fn longer_input_line(rows: &[RomajiRule], input: &str) -> Option<usize> {
+    rows.iter()
+        .position(|row| row.input.len() > input.len() && row.input.starts_with(input))
+        .map(|index| index + 1)
+}
+
+/// A row whose `next_input` is exactly another row's input, with nothing longer
+/// to wait for, hands over to that row at once. A cycle of such hand-overs never
+/// ends.
+fn next_input_loops(rows: &[RomajiRule], first_lines: &HashMap<&str, usize>) -> Vec<Diagnostic> {
+    let next_line = |line: usize| {
+        let next_input = rows[line - 1].next_input.as_str();
+        if next_input.is_empty() || longer_input_line(rows, next_input).is_some() {
+            return None;
+        }
+        first_lines.get(next_input).copied()
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut reported = Vec::new();
+    let mut starts = first_lines.values().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    for start in starts {
+        let mut path = vec![start];
+        let mut current = start;
+        while let Some(next) = next_line(current) {
+            if let Some(position) = path.iter().position(|&line| line == next) {
+                let mut cycle = path.split_off(position);
+                cycle.sort_unstable();
+                if !reported.contains(&cycle) {
+                    diagnostics.push(Diagnostic {
+                        line: cycle[0],
+                        kind: DiagnosticKind::NextInputLoop {
+                            lines: cycle.clone(),
+                        },
+                    });
+                    reported.push(cycle);
+                }
+                break;
+            }
+            path.push(next);
+            current = next;
+        }
+    }
+    diagnostics
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedTable {
+    pub rows: Vec<RomajiRule>,
+    /// Lines that could not become a row, by line in the imported file.
+    pub errors: Vec<Diagnostic>,
+}
+
+/// The file is not UTF-8 text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError;
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected UTF-8 text")
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Reads a Google IME romaji table: `input\toutput[\tnext input]` per line,
+/// with blank lines and `#` comments skipped.
+pub fn parse(contents: &[u8]) -> Result<ParsedTable, DecodeError> {
+    let contents = contents.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(contents);
+    let text = std::str::from_utf8(contents).map_err(|_| DecodeError)?;
+    Ok(parse_text(text))
+}
+
+pub(crate) fn parse_text(text: &str) -> ParsedTable {
+    let mut parsed = ParsedTable::default();
+    for (index, raw_line) in text.lines().enumerate() {
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = trimmed.split('\t');
+        let input = parts.next().unwrap_or_default().trim();
+        let output = parts.next().unwrap_or_default().trim();
+        if input.is_empty() || output.is_empty() {
+            parsed.errors.push(Diagnostic {
+                line: index + 1,
+                kind: DiagnosticKind::MissingOutput,
+            });
+            continue;
+        }
+        let next_input = parts.next().unwrap_or_default().trim();
+        parsed.rows.push(RomajiRule {
+            input: input.to_string(),
+            output: output.to_string(),
+            next_input: next_input.to_string(),
+        });
+    }
+    parsed
+}
+
+/// Writes rows in the Google IME format [`parse`] reads.
+pub fn serialize(rows: &[RomajiRule]) -> String {
+    rows.iter()
+        .map(|row| {
+            if row.next_input.is_empty() {
+                format!("{}\t{}\n", row.input, row.output)
+            } else {
+                format!("{}\t{}\t{}\n", row.input, row.output, row.next_input)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_with_next(input: &str, output: &str, next_input: &str) -> RomajiRule {
+        RomajiRule {
+            next_input: next_input.to_string(),
+            ..rule(input, output)
+        }
+    }
+
+    fn output_of<'a>(rows: &'a [RomajiRule], input: &str) -> Option<&'a str> {
+        rows.iter()
+            .find(|row| row.input == input)
+            .map(|row| row.output.as_str())
+    }
+
+    #[test]
+    fn presets_have_no_errors() {
+        for preset in RomajiPreset::ALL {
+            let errors = validate(&preset.rows())
+                .into_iter()
+                .filter(|diagnostic| diagnostic.severity() == Severity::Error)
+                .collect::<Vec<_>>();
+            assert!(errors.is_empty(), "{preset:?}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn presets_extend_the_default_table() {
+        let ms_ime = RomajiPreset::MsIme.rows();
+        assert_eq!(output_of(&ms_ime, "zh"), None);
+        assert_eq!(output_of(&ms_ime, "za"), Some("ざ"));
+
+        let azik = RomajiPreset::Azik.rows();
+        assert_eq!(output_of(&azik, "kz"), Some("かん"));
+        assert_eq!(output_of(&azik, "kk"), Some("きん"));
+        assert_eq!(output_of(&azik, "kyp"), Some("きょう"));
+        assert_eq!(output_of(&azik, "q"), Some("ん"));
+        assert_eq!(output_of(&azik, "ka"), Some("か"));
+
+        let act = RomajiPreset::Act.rows();
+        assert_eq!(output_of(&act, "ci"), Some("き"));
+        assert_eq!(output_of(&act, "c;"), Some("かん"));
+        assert_eq!(output_of(&act, "sl"), Some("そう"));
+    }
+
+    #[test]
+    fn validate_reports_each_problem_on_its_line() {
+        let rows = vec![
+            rule("ka", "か"),
+            rule("ka", "カ"),
+            rule("n", "ん"),
+            rule("na", "な"),
+            rule("", "x"),
+            rule("n", "ん"),
+            rule_with_next("xa", "", "xb"),
+            rule_with_next("xb", "", "xa"),
+        ];
+
+        assert_eq!(
+            validate(&rows),
+            vec![
+                Diagnostic {
+                    line: 2,
+                    kind: DiagnosticKind::DuplicateInput { line: 1 },
+                },
+                Diagnostic {
+                    line: 3,
+                    kind: DiagnosticKind::ShadowedByLongerInput { line: 4 },
+                },
+                Diagnostic {
+                    line: 5,
+                    kind: DiagnosticKind::Unreachable,
+                },
+                Diagnostic {
+                    line: 6,
+                    kind: DiagnosticKind::RepeatedRow { line: 3 },
+                },
+                Diagnostic {
+                    line: 7,
+                    kind: DiagnosticKind::NextInputLoop { lines: vec![7, 8] },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn next_input_waiting_for_a_longer_input_is_not_a_loop() {
+        let rows = vec![
+            rule_with_next("ww", "っ", "w"),
+            rule_with_next("www", "w", "ww"),
+        ];
+
+        assert_eq!(
+            validate(&rows),
+            vec![Diagnostic {
+                line: 1,
+                kind: DiagnosticKind::ShadowedByLongerInput { line: 2 },
+            }]
+        );
+    }
+
+    #[test]
+    fn google_ime_tables_round_trip() {
+        let contents = "\u{FEFF}# exported\nka\tか\ntt\tっ\tt\n\nbroken\n";
+        let parsed = parse(contents.as_bytes()).unwrap();
+
+        assert_eq!(
+            parsed.rows,
+            vec![rule("ka", "か"), rule_with_next("tt", "っ", "t")]
+        );
+        assert_eq!(
+            parsed.errors,
+            vec![Diagnostic {
+                line: 5,
+                kind: DiagnosticKind::MissingOutput,
+            }]
+        );
+        assert_eq!(serialize(&parsed.rows), "ka\tか\ntt\tっ\tt\n");
+        assert_eq!(parse(&[0xFF, 0xFE]), Err(DecodeError));
+    }
+}
@@ -0,0 +1,537 @@
+//! IPC endpoints for `AzookeyService` and `WindowService`.
+//!
+//! Production uses session-local named pipes on Windows. Unix domain sockets
+//! and loopback TCP exist so the server, candidate window and clients can run
+//! against each other on development machines and in CI.
+
+use async_stream::stream;
+use futures_core::stream::Stream;
+use std::{
+    env, fmt, io,
+    net::SocketAddr,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tonic::transport::server::Connected;
+
+#[cfg(windows)]
+mod named_pipe;
+
+/// Overrides the `AzookeyService` endpoint, e.g. `tcp:127.0.0.1:50051`.
+pub const SERVER_ENDPOINT_ENV: &str = "AZOOKEY_SERVER_ENDPOINT";
+/// Overrides the `WindowService` endpoint, e.g. `unix:/tmp/azookey/ui.sock`.
+pub const UI_ENDPOINT_ENV: &str = "AZOOKEY_UI_ENDPOINT";
+
+const NAMED_PIPE_SCHEME: &str = "pipe:";
+const UNIX_SOCKET_SCHEME: &str = "unix:";
+const TCP_SCHEME: &str = "tcp:";
+const SERVER_SOCKET_FILE_NAME: &str = "server.sock";
+const UI_SOCKET_FILE_NAME: &str = "ui.sock";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcEndpoint {
+    NamedPipe(String),
+    UnixSocket(PathBuf),
+    /// Always a loopback address; other addresses are rejected on parse.
+    Tcp(SocketAddr),
+}
+
+impl IpcEndpoint {
+    /// The `AzookeyService` endpoint, honoring [`SERVER_ENDPOINT_ENV`].
+    pub fn server() -> io::Result<Self> {
+        Self::from_env_or_default(
+            SERVER_ENDPOINT_ENV,
+            crate::SERVER_PIPE_PATH,
+            SERVER_SOCKET_FILE_NAME,
+        )
+    }
+
+    /// The `WindowService` endpoint, honoring [`UI_ENDPOINT_ENV`].
+    pub fn ui() -> io::Result<Self> {
+        Self::from_env_or_default(UI_ENDPOINT_ENV, crate::UI_PIPE_PATH, UI_SOCKET_FILE_NAME)
+    }
+
+    fn from_env_or_default(
+        variable: &str,
+        pipe_path: &str,
+        socket_file_name: &str,
+    ) -> io::Result<Self> {
+        match env::var(variable) {
+            Ok(value) if !value.trim().is_empty() => value.trim().parse().map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("{variable}: {error}"))
+            }),
+            _ => Ok(Self::platform_default(pipe_path, socket_file_name)),
+        }
+    }
+
+    #[cfg(windows)]
+    fn platform_default(pipe_path: &str, _socket_file_name: &str) -> Self {
+        Self::NamedPipe(pipe_path.to_string())
+    }
+
+    #[cfg(not(windows))]
+    fn platform_default(_pipe_path: &str, socket_file_name: &str) -> Self {
+        Self::UnixSocket(default_socket_dir().join(socket_file_name))
+    }
+}
+
+#[cfg(not(windows))]
+fn default_socket_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("azookey")
+}
+
+impl fmt::Display for IpcEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NamedPipe(path) => write!(f, "{NAMED_PIPE_SCHEME}{path}"),
+            Self::UnixSocket(path) => write!(f, "{UNIX_SOCKET_SCHEME}{}", path.display()),
+            Self::Tcp(address) => write!(f, "{TCP_SCHEME}{address}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointParseError(String);
+
+impl fmt::Display for EndpointParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EndpointParseError {}
+
+impl FromStr for IpcEndpoint {
+    type Err = EndpointParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = value.strip_prefix(NAMED_PIPE_SCHEME) {
+            if !path.starts_with(r"\\.\pipe\") {
+                return Err(EndpointParseError(format!(
+                    "named pipe endpoint must start with \\\\.\\pipe\\: {value}"
+                )));
+            }
+            return Ok(Self::NamedPipe(path.to_string()));
+        }
+        if let Some(path) = value.strip_prefix(UNIX_SOCKET_SCHEME) {
+            if path.is_empty() {
+                return Err(EndpointParseError(
+                    "unix socket endpoint requires a path".to_string(),
+                ));
+            }
+            return Ok(Self::UnixSocket(PathBuf::from(path)));
+        }
+        if let Some(address) = value.strip_prefix(TCP_SCHEME) {
+            let address: SocketAddr = address
+                .parse()
+                .map_err(|error| EndpointParseError(format!("invalid TCP endpoint: {error}")))?;
+            // The services carry keystrokes and have no authentication of
+            // their own, so they must never be reachable from another host.
+            if !address.ip().is_loopback() {
+                return Err(EndpointParseError(format!(
+                    "TCP endpoint must use a loopback address: {address}"
+                )));
+            }
+            return Ok(Self::Tcp(address));
+        }
+
+        Err(EndpointParseError(format!(
+            "unknown IPC endpoint `{value}`; expected pipe:, unix: or tcp:"
+        )))
+    }
+}
+
+/// How a failed connect attempt should be treated by a retrying caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectFailure {
+    /// The endpoint exists but every server instance is currently busy.
+    Busy,
+    /// Nothing is listening yet, typically while the server is starting.
+    NotListening,
+    /// Retrying will not help.
+    Fatal,
+}
+
+pub fn classify_connect_error(error: &io::Error) -> ConnectFailure {
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::{
+            ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, ERROR_PIPE_BUSY,
+        };
+        match error.raw_os_error() {
+            Some(code) if code == ERROR_PIPE_BUSY.0 as i32 => return ConnectFailure::Busy,
+            Some(code)
+                if code == ERROR_FILE_NOT_FOUND.0 as i32
+                    || code == ERROR_PATH_NOT_FOUND.0 as i32 =>
+            {
+                return ConnectFailure::NotListening
+            }
+            _ => {}
+        }
+    }
+
+    match error.kind() {
+        io::ErrorKind::WouldBlock => ConnectFailure::Busy,
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => ConnectFailure::NotListening,
+        _ => ConnectFailure::Fatal,
+    }
+}
+
+/// Opens one client connection. Callers own retry and deadline policy.
+pub async fn connect(endpoint: &IpcEndpoint) -> io::Result<IpcConnection> {
+    match endpoint {
+        #[cfg(windows)]
+        IpcEndpoint::NamedPipe(path) => {
+            named_pipe::connect(path).map(IpcConnection::NamedPipeClient)
+        }
+        #[cfg(unix)]
+        IpcEndpoint::UnixSocket(path) => tokio::net::UnixStream::connect(path)
+            .await
+            .map(IpcConnection::Unix),
+        IpcEndpoint::Tcp(address) => {
+            let stream = tokio::net::TcpStream::connect(address).await?;
+            stream.set_nodelay(true)?;
+            Ok(IpcConnection::Tcp(stream))
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(unsupported(endpoint)),
+    }
+}
+
+fn unsupported(endpoint: &IpcEndpoint) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{endpoint} is not supported on this platform"),
+    )
+}
+
+pub struct IpcListener {
+    inner: ListenerInner,
+}
+
+enum ListenerInner {
+    #[cfg(windows)]
+    NamedPipe(named_pipe::NamedPipeListener),
+    #[cfg(unix)]
+    Unix {
+        listener: tokio::net::UnixListener,
+        socket_file: UnixSocketFile,
+    },
+    Tcp(tokio::net::TcpListener),
+}
+
+impl IpcListener {
+    /// Starts listening on `endpoint`. Like the first named-pipe instance,
+    /// binding fails if another server is already serving the endpoint.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn bind(endpoint: &IpcEndpoint) -> io::Result<Self> {
+        let inner = match endpoint {
+            #[cfg(windows)]
+            IpcEndpoint::NamedPipe(path) => {
+                ListenerInner::NamedPipe(named_pipe::NamedPipeListener::bind(path)?)
+            }
+            #[cfg(unix)]
+            IpcEndpoint::UnixSocket(path) => {
+                let socket_file = UnixSocketFile::prepare(path)?;
+                let listener = tokio::net::UnixListener::bind(path)?;
+                socket_file.restrict_permissions()?;
+                ListenerInner::Unix {
+                    listener,
+                    socket_file,
+                }
+            }
+            IpcEndpoint::Tcp(address) => {
+                let listener = std::net::TcpListener::bind(address)?;
+                listener.set_nonblocking(true)?;
+                ListenerInner::Tcp(tokio::net::TcpListener::from_std(listener)?)
+            }
+            #[allow(unreachable_patterns)]
+            _ => return Err(unsupported(endpoint)),
+        };
+        Ok(Self { inner })
+    }
+
+    /// The bound endpoint. For TCP this reports the actual port when binding
+    /// to port 0.
+    pub fn local_endpoint(&self) -> io::Result<IpcEndpoint> {
+        match &self.inner {
+            #[cfg(windows)]
+            ListenerInner::NamedPipe(listener) => {
+                Ok(IpcEndpoint::NamedPipe(listener.path().to_string()))
+            }
+            #[cfg(unix)]
+            ListenerInner::Unix { socket_file, .. } => {
+                Ok(IpcEndpoint::UnixSocket(socket_file.path.clone()))
+            }
+            ListenerInner::Tcp(listener) => listener.local_addr().map(IpcEndpoint::Tcp),
+        }
+    }
+
+    /// Accepted connections, suitable for `Server::serve_with_incoming`.
+    pub fn incoming(self) -> impl Stream<Item = io::Result<IpcConnection>> + Send + 'static {
+        stream! {
+            match self.inner {
+                #[cfg(windows)]
+                ListenerInner::NamedPipe(listener) => {
+                    for await connection in listener.incoming() {
+                        yield connection.map(IpcConnection::NamedPipeServer);
+                    }
+                }
+                #[cfg(unix)]
+                ListenerInner::Unix { listener, socket_file } => {
+                    // Removes the socket file once the server stops accepting.
+                    let _socket_file = socket_file;
+                    loop {
+                        yield listener.accept().await.map(|(stream, _)| IpcConnection::Unix(stream));
+                    }
+                }
+                ListenerInner::Tcp(listener) => {
+                    loop {
+                        yield listener.accept().await.and_then(|(stream, _)| {
+                            stream.set_nodelay(true)?;
+                            Ok(IpcConnection::Tcp(stream))
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+struct UnixSocketFile {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocketFile {
+    fn prepare(path: &std::path::Path) -> io::Result<Self> {
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(parent)?;
+        }
+
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} is already being served", path.display()),
+                    ));
+                }
+                // A socket nobody accepts on is left over from a crashed server.
+                std::fs::remove_file(path)?;
+            }
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn restrict_permissions(&self) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// One accepted or opened IPC byte stream, independent of the transport.
+#[derive(Debug)]
+pub enum IpcConnection {
+    #[cfg(windows)]
+    NamedPipeServer(tokio::net::windows::named_pipe::NamedPipeServer),
+    #[cfg(windows)]
+    NamedPipeClient(tokio::net::windows::named_pipe::NamedPipeClient),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+    Tcp(tokio::net::TcpStream),
+}
+
+macro_rules! with_connection {
+    ($connection:expr, $inner:ident => $body:expr) => {
+        match $connection {
+            #[cfg(windows)]
+            IpcConnection::NamedPipeServer($inner) => $body,
+            #[cfg(windows)]
+            IpcConnection::NamedPipeClient($inner) => $body,
+            #[cfg(unix)]
+            IpcConnection::Unix($inner) => $body,
+            IpcConnection::Tcp($inner) => $body,
+        }
+    };
+}
+
+impl Connected for IpcConnection {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) -> Self::ConnectInfo {}
+}
+
+impl AsyncRead for IpcConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        with_connection!(self.get_mut(), inner => Pin::new(inner).poll_read(cx, buf))
+    }
+}
+
+impl AsyncWrite for IpcConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        with_connection!(self.get_mut(), inner => Pin::new(inner).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        with_connection!(self.get_mut(), inner => Pin::new(inner).poll_flush(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        with_connection!(self.get_mut(), inner => Pin::new(inner).poll_shutdown(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_connect_error, connect, ConnectFailure, IpcEndpoint, IpcListener};
+    use futures_core::Stream;
+    use std::{io, pin::Pin};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn next<S: Stream>(mut stream: Pin<&mut S>) -> Option<S::Item> {
+        std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await
+    }
+
+    #[test]
+    fn endpoints_round_trip_through_their_display_form() {
+        for value in [
+            r"pipe:\\.\pipe\LOCAL\azookey_server",
+            "unix:/run/user/1000/azookey/server.sock",
+            "tcp:127.0.0.1:50051",
+            "tcp:[::1]:50052",
+        ] {
+            let endpoint: IpcEndpoint = value.parse().expect(value);
+            assert_eq!(endpoint.to_string(), value);
+        }
+    }
+
+    #[test]
+    fn tcp_endpoints_are_limited_to_loopback() {
+        assert!("tcp:0.0.0.0:50051".parse::<IpcEndpoint>().is_err());
+        assert!("tcp:192.168.1.2:50051".parse::<IpcEndpoint>().is_err());
+        assert!("tcp:localhost:50051".parse::<IpcEndpoint>().is_err());
+    }
+
+    #[test]
+    fn malformed_endpoints_are_rejected() {
+        assert!("".parse::<IpcEndpoint>().is_err());
+        assert!("unix:".parse::<IpcEndpoint>().is_err());
+        assert!(r"pipe:C:\temp\pipe".parse::<IpcEndpoint>().is_err());
+        assert!("http://127.0.0.1:50051".parse::<IpcEndpoint>().is_err());
+    }
+
+    #[test]
+    fn connect_errors_distinguish_startup_from_fatal_failures() {
+        assert_eq!(
+            classify_connect_error(&io::Error::from(io::ErrorKind::ConnectionRefused)),
+            ConnectFailure::NotListening
+        );
+        assert_eq!(
+            classify_connect_error(&io::Error::from(io::ErrorKind::NotFound)),
+            ConnectFailure::NotListening
+        );
+        assert_eq!(
+            classify_connect_error(&io::Error::from(io::ErrorKind::WouldBlock)),
+            ConnectFailure::Busy
+        );
+        assert_eq!(
+            classify_connect_error(&io::Error::from(io::ErrorKind::PermissionDenied)),
+            ConnectFailure::Fatal
+        );
+    }
+
+    async fn assert_echo(endpoint: &IpcEndpoint) {
+        let listener = IpcListener::bind(endpoint).unwrap();
+        let endpoint = listener.local_endpoint().unwrap();
+        let server = tokio::spawn(async move {
+            let incoming = listener.incoming();
+            tokio::pin!(incoming);
+            let mut connection = next(incoming.as_mut()).await.unwrap().unwrap();
+            let mut buffer = [0; 4];
+            connection.read_exact(&mut buffer).await.unwrap();
+            connection.write_all(&buffer).await.unwrap();
+        });
+
+        let mut client = connect(&endpoint).await.unwrap();
+        client.write_all(b"ping").await.unwrap();
+        let mut buffer = [0; 4];
+        client.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"ping");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn loopback_tcp_listener_accepts_clients() {
+        assert_echo(&"tcp:127.0.0.1:0".parse().unwrap()).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_listener_accepts_clients_and_cleans_up() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("nested").join("server.sock");
+
+        assert_echo(&IpcEndpoint::UnixSocket(path.clone())).await;
+
+        assert!(!path.exists(), "socket file should be removed on shutdown");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_listener_refuses_a_live_endpoint() {
+        let directory = tempfile::tempdir().unwrap();
+        let endpoint = IpcEndpoint::UnixSocket(directory.path().join("server.sock"));
+        let _listener = IpcListener::bind(&endpoint).unwrap();
+
+        let error = match IpcListener::bind(&endpoint) {
+            Ok(_) => panic!("second listener must not replace the live socket"),
+            Err(error) => error,
+        };
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+    }
+}
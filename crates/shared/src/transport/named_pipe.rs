@@ -1,11 +1,7 @@
 use async_stream::stream;
 use futures_core::stream::Stream;
-use std::{ffi::c_void, pin::Pin, ptr::addr_of_mut};
-use tokio::{
-    io::{self, AsyncRead, AsyncWrite},
-    net::windows::named_pipe::{NamedPipeServer, ServerOptions},
-};
-use tonic::transport::server::Connected;
+use std::{ffi::c_void, io, os::windows::io::IntoRawHandle, ptr::addr_of_mut};
+use tokio::net::windows::named_pipe::{NamedPipeClient, NamedPipeServer, ServerOptions};
 use windows::{
     core::{PCWSTR, PWSTR},
     Win32::{
@@ -29,6 +25,14 @@ unsafe impl Send for UnsafeSecurityAttributes {}
 unsafe impl Sync for UnsafeSecurityAttributes {}
 
 impl UnsafeSecurityAttributes {
+    fn new(security_descriptor: &OwnedSecurityDescriptor) -> Self {
+        Self(SECURITY_ATTRIBUTES {
+            nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: security_descriptor.as_ptr(),
+            bInheritHandle: false.into(),
+        })
+    }
+
     fn as_mut_ptr(&mut self) -> *mut c_void {
         addr_of_mut!(self.0).cast()
     }
@@ -53,102 +57,60 @@ impl Drop for OwnedSecurityDescriptor {
     }
 }
 
-pub struct TonicNamedPipeServer {
-    inner: NamedPipeServer,
+pub(super) fn connect(path: &str) -> io::Result<NamedPipeClient> {
+    let handle = crate::open_named_pipe_client_handle(path)?;
+    unsafe { NamedPipeClient::from_raw_handle(handle.into_raw_handle()) }
 }
 
-impl Connected for TonicNamedPipeServer {
-    type ConnectInfo = ();
-
-    fn connect_info(&self) -> Self::ConnectInfo {}
+pub(super) struct NamedPipeListener {
+    path: String,
+    // Keep the LocalAlloc-owned descriptor alive for every pipe instance.
+    security_descriptor: OwnedSecurityDescriptor,
+    first_instance: NamedPipeServer,
 }
 
-impl AsyncRead for TonicNamedPipeServer {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-        buf: &mut tokio::io::ReadBuf<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        Pin::new(&mut self.inner).poll_read(cx, buf)
+impl NamedPipeListener {
+    pub(super) fn bind(path: &str) -> io::Result<Self> {
+        let security_descriptor = create_pipe_security_descriptor()?;
+        let first_instance = create_pipe_instance(path, &security_descriptor, true)?;
+        Ok(Self {
+            path: path.to_string(),
+            security_descriptor,
+            first_instance,
+        })
     }
-}
 
-impl AsyncWrite for TonicNamedPipeServer {
-    fn poll_write(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-        buf: &[u8],
-    ) -> std::task::Poll<Result<usize, std::io::Error>> {
-        Pin::new(&mut self.inner).poll_write(cx, buf)
+    pub(super) fn path(&self) -> &str {
+        &self.path
     }
 
-    fn poll_flush(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut self.inner).poll_flush(cx)
-    }
+    pub(super) fn incoming(self) -> impl Stream<Item = io::Result<NamedPipeServer>> {
+        stream! {
+            let Self { path, security_descriptor, first_instance } = self;
+            let mut server = first_instance;
 
-    fn poll_shutdown(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut self.inner).poll_shutdown(cx)
-    }
-}
+            loop {
+                server.connect().await?;
+                let client = server;
 
-impl TonicNamedPipeServer {
-    pub fn new(path: &str) -> io::Result<impl Stream<Item = io::Result<TonicNamedPipeServer>>> {
-        Self::new_with_first_pipe_callback(path, || {})
-    }
+                yield Ok(client);
 
-    pub fn new_with_first_pipe_callback<F>(
-        path: &str,
-        on_first_pipe_created: F,
-    ) -> io::Result<impl Stream<Item = io::Result<TonicNamedPipeServer>>>
-    where
-        F: FnOnce() + Send + 'static,
-    {
-        let name = path.to_string();
-        let security_descriptor = create_pipe_security_descriptor()?;
-        let mut security_attributes = UnsafeSecurityAttributes(SECURITY_ATTRIBUTES {
-            nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
-            lpSecurityDescriptor: security_descriptor.as_ptr(),
-            bInheritHandle: false.into(),
-        });
-
-        Ok(stream! {
-            // Keep the LocalAlloc-owned descriptor alive for every pipe instance.
-            let _security_descriptor = security_descriptor;
-            unsafe {
-                let mut on_first_pipe_created = Some(on_first_pipe_created);
-                let mut server = ServerOptions::new()
-                    .first_pipe_instance(true)
-                    .create_with_security_attributes_raw(
-                        &name,
-                        security_attributes.as_mut_ptr()
-                    )?;
-                if let Some(on_first_pipe_created) = on_first_pipe_created.take() {
-                    on_first_pipe_created();
-                }
-
-                loop {
-                    server.connect().await?;
-
-                    let client = TonicNamedPipeServer {
-                        inner: server,
-                    };
-
-                    yield Ok(client);
-
-                    server = ServerOptions::new()
-                        .create_with_security_attributes_raw(
-                            &name,
-                            security_attributes.as_mut_ptr()
-                        )?;
-                }
+                server = create_pipe_instance(&path, &security_descriptor, false)?;
             }
-        })
+        }
+    }
+}
+
+fn create_pipe_instance(
+    path: &str,
+    security_descriptor: &OwnedSecurityDescriptor,
+    first_pipe_instance: bool,
+) -> io::Result<NamedPipeServer> {
+    let mut security_attributes = UnsafeSecurityAttributes::new(security_descriptor);
+    unsafe {
+        ServerOptions::new()
+            .first_pipe_instance(first_pipe_instance)
+            .create_with_security_attributes_raw(path, security_attributes.as_mut_ptr())
     }
 }
 
@@ -253,10 +215,7 @@ mod tests {
         core::w,
         Win32::{
             Foundation::{LocalFree, BOOL, ERROR_ACCESS_DENIED, HANDLE, HLOCAL},
-            Security::{
-                Authorization::ConvertStringSidToSidW, CheckTokenMembership, PSID,
-                SECURITY_ATTRIBUTES,
-            },
+            Security::{Authorization::ConvertStringSidToSidW, CheckTokenMembership, PSID},
         },
     };
 
@@ -293,11 +252,7 @@ mod tests {
     #[tokio::test]
     async fn secured_session_local_pipe_enforces_network_and_logon_access() {
         let security_descriptor = create_pipe_security_descriptor().unwrap();
-        let mut security_attributes = UnsafeSecurityAttributes(SECURITY_ATTRIBUTES {
-            nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
-            lpSecurityDescriptor: security_descriptor.as_ptr(),
-            bInheritHandle: false.into(),
-        });
+        let mut security_attributes = UnsafeSecurityAttributes::new(&security_descriptor);
         let nonce = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -314,7 +269,7 @@ mod tests {
                 .create_with_security_attributes_raw(&pipe_path, security_attributes.as_mut_ptr())
                 .unwrap()
         };
-        let client_handle = shared::open_named_pipe_client_handle(&pipe_path);
+        let client_handle = crate::open_named_pipe_client_handle(&pipe_path);
         // The VM test runner connects through OpenSSH and therefore carries the
         // NETWORK SID. Production explicitly denies that token; interactive
         // and service runners exercise the same-logon success path below.
@@ -0,0 +1,396 @@
+//! The user dictionary store.
+//!
+//! Words live in `user_dictionary.json` beside `settings.json` rather than in
+//! `AppConfig`, so editing the dictionary neither rewrites the settings nor
+//! reloads the converter. The server owns the store and applies each change
+//! to the converter as it happens; settings clients go through its RPCs.
+
+use crate::{
+    ensure_config_dir, get_config_root, parse_config, proto, replace_config_file,
+    temporary_config_path, write_temp_config, ConfigError, ConfigWriteGuard, UserDictionaryEntry,
+    SETTINGS_FILENAME,
+};
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, fs, io, path::Path};
+
+pub mod shared_dictionaries;
+pub mod transfer;
+
+pub(crate) const USER_DICTIONARY_FILENAME: &str = "user_dictionary.json";
+const USER_DICTIONARY_VERSION: u32 = 1;
+
+/// Part of speech of a user word. The converter maps each onto a context id,
+/// which decides how the word connects to its neighbours.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PartOfSpeech {
+    Noun,
+    /// What every word registered before parts of speech existed became.
+    #[default]
+    ProperNoun,
+    PersonName,
+    PlaceName,
+    Organization,
+}
+
+impl From<proto::PartOfSpeech> for PartOfSpeech {
+    fn from(value: proto::PartOfSpeech) -> Self {
+        match value {
+            proto::PartOfSpeech::Unspecified | proto::PartOfSpeech::ProperNoun => Self::ProperNoun,
+            proto::PartOfSpeech::Noun => Self::Noun,
+            proto::PartOfSpeech::PersonName => Self::PersonName,
+            proto::PartOfSpeech::PlaceName => Self::PlaceName,
+            proto::PartOfSpeech::Organization => Self::Organization,
+        }
+    }
+}
+
+impl From<PartOfSpeech> for proto::PartOfSpeech {
+    fn from(value: PartOfSpeech) -> Self {
+        match value {
+            PartOfSpeech::Noun => Self::Noun,
+            PartOfSpeech::ProperNoun => Self::ProperNoun,
+            PartOfSpeech::PersonName => Self::PersonName,
+            PartOfSpeech::PlaceName => Self::PlaceName,
+            PartOfSpeech::Organization => Self::Organization,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct UserWord {
+    pub id: u64,
+    pub reading: String,
+    pub word: String,
+    #[serde(default)]
+    pub part_of_speech: PartOfSpeech,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub comment: String,
+}
+
+impl From<&UserWord> for proto::UserWord {
+    fn from(word: &UserWord) -> Self {
+        Self {
+            id: word.id,
+            reading: word.reading.clone(),
+            word: word.word.clone(),
+            part_of_speech: proto::PartOfSpeech::from(word.part_of_speech).into(),
+            comment: word.comment.clone(),
+        }
+    }
+}
+
+impl From<proto::UserWord> for UserWord {
+    fn from(word: proto::UserWord) -> Self {
+        Self {
+            id: word.id,
+            part_of_speech: word.part_of_speech().into(),
+            reading: word.reading,
+            word: word.word,
+            comment: word.comment,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserWordError {
+    EmptyReading,
+    EmptyWord,
+    Duplicate { id: u64 },
+}
+
+impl fmt::Display for UserWordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserWordError::EmptyReading => write!(f, "reading must not be empty"),
+            UserWordError::EmptyWord => write!(f, "word must not be empty"),
+            UserWordError::Duplicate { id } => {
+                write!(
+                    f,
+                    "the same reading and word are already registered as {id}"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for UserWordError {}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct UserDictionaryStore {
+    version: u32,
+    next_id: u64,
+    #[serde(default)]
+    entries: Vec<UserWord>,
+}
+
+impl Default for UserDictionaryStore {
+    fn default() -> Self {
+        Self {
+            version: USER_DICTIONARY_VERSION,
+            next_id: 1,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl UserDictionaryStore {
+    /// Reads the store. Before the first write there is no store file, and the
+    /// words are taken from the `user_dictionary` section of `settings.json`.
+    pub fn read() -> Result<Self, ConfigError> {
+        Self::read_from_root(&get_config_root()?)
+    }
+
+    pub fn write(&self) -> Result<(), ConfigError> {
+        let _write_guard = ConfigWriteGuard::acquire()?;
+        self.write_to_root(&get_config_root()?)
+    }
+
+    fn read_from_root(config_root: &Path) -> Result<Self, ConfigError> {
+        let path = config_root.join(USER_DICTIONARY_FILENAME);
+        match fs::read_to_string(&path) {
+            Ok(store_str) => serde_json::from_str(&store_str)
+                .map_err(|source| ConfigError::Parse { path, source }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::from_legacy_settings(config_root))
+            }
+            Err(source) => Err(ConfigError::Read { path, source }),
+        }
+    }
+
+    fn from_legacy_settings(config_root: &Path) -> Self {
+        let settings_path = config_root.join(SETTINGS_FILENAME);
+        let entries = fs::read_to_string(&settings_path)
+            .ok()
+            .and_then(|config_str| parse_config(&settings_path, &config_str).ok())
+            .map(|config| config.user_dictionary.entries)
+            .unwrap_or_default();
+        Self::from_legacy_entries(&entries)
+    }
+
+    /// Builds a store from `settings.json` entries, dropping the blank and
+    /// duplicate ones the converter used to skip.
+    pub fn from_legacy_entries(entries: &[UserDictionaryEntry]) -> Self {
+        let mut store = Self::default();
+        for entry in entries {
+            let _ = store.add(
+                &entry.reading,
+                &entry.word,
+                PartOfSpeech::default(),
+                String::new(),
+            );
+        }
+        store
+    }
+
+    fn write_to_root(&self, config_root: &Path) -> Result<(), ConfigError> {
+        ensure_config_dir(config_root)?;
+        let path = config_root.join(USER_DICTIONARY_FILENAME);
+        let temp_path = temporary_config_path(config_root, USER_DICTIONARY_FILENAME);
+        let store_str = serde_json::to_string_pretty(self)
+            .map_err(|source| ConfigError::Serialize { source })?;
+
+        write_temp_config(&temp_path, store_str.as_bytes())?;
+        replace_config_file(&temp_path, &path).map_err(|source| {
+            let _ = fs::remove_file(&temp_path);
+            ConfigError::Persist {
+                from: temp_path.clone(),
+                to: path,
+                source,
+            }
+        })
+    }
+
+    /// Words in registration order, which is also their priority order.
+    pub fn words(&self) -> &[UserWord] {
+        &self.entries
+    }
+
+    pub fn get(&self, id: u64) -> Option<&UserWord> {
+        self.entries.iter().find(|word| word.id == id)
+    }
+
+    pub fn add(
+        &mut self,
+        reading: &str,
+        word: &str,
+        part_of_speech: PartOfSpeech,
+        comment: String,
+    ) -> Result<&UserWord, UserWordError> {
+        let reading = reading.trim();
+        let word = word.trim();
+        if reading.is_empty() {
+            return Err(UserWordError::EmptyReading);
+        }
+        if word.is_empty() {
+            return Err(UserWordError::EmptyWord);
+        }
+        let normalized_reading = normalize_kana(reading);
+        if let Some(existing) = self.entries.iter().find(|existing| {
+            existing.word == word && normalize_kana(&existing.reading) == normalized_reading
+        }) {
+            return Err(UserWordError::Duplicate { id: existing.id });
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(UserWord {
+            id,
+            reading: reading.to_string(),
+            word: word.to_string(),
+            part_of_speech,
+            comment: comment.trim().to_string(),
+        });
+        Ok(self.entries.last().expect("entry was just pushed"))
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<UserWord> {
+        let index = self.entries.iter().position(|word| word.id == id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Words whose reading, word or comment contains `query`, ignoring case
+    /// and the hiragana/katakana distinction. An empty query matches all.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&UserWord> {
+        let query = normalize_for_search(query.trim());
+        self.entries
+            .iter()
+            .filter(|word| {
+                query.is_empty()
+                    || [&word.reading, &word.word, &word.comment]
+                        .into_iter()
+                        .any(|field| normalize_for_search(field).contains(&query))
+            })
+            .take(if limit == 0 { usize::MAX } else { limit })
+            .collect()
+    }
+}
+
+/// Folds hiragana onto katakana, as the converter does with readings.
+fn normalize_kana(value: &str) -> String {
+    value
+        .chars()
+        .map(|character| match character {
+            'ぁ'..='ゖ' => char::from_u32(character as u32 + 0x60).unwrap_or(character),
+            _ => character,
+        })
+        .collect()
+}
+
+fn normalize_for_search(value: &str) -> String {
+    normalize_kana(&value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(words: &[(&str, &str)]) -> UserDictionaryStore {
+        let mut store = UserDictionaryStore::default();
+        for (reading, word) in words {
+            store
+                .add(reading, word, PartOfSpeech::default(), String::new())
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn add_trims_and_rejects_blank_and_duplicate_words() {
+        let mut store = store_with(&[("あずき", "azooKey")]);
+
+        let added = store
+            .add(
+                " さとう ",
+                "佐藤",
+                PartOfSpeech::PersonName,
+                " 友人 ".into(),
+            )
+            .unwrap()
+            .clone();
+
+        assert_eq!(added.id, 2);
+        assert_eq!(
+            (added.reading.as_str(), added.word.as_str()),
+            ("さとう", "佐藤")
+        );
+        assert_eq!(added.comment, "友人");
+        assert_eq!(
+            store.add("  ", "x", PartOfSpeech::Noun, String::new()),
+            Err(UserWordError::EmptyReading)
+        );
+        assert_eq!(
+            store.add("アズキ", "azooKey", PartOfSpeech::Noun, String::new()),
+            Err(UserWordError::Duplicate { id: 1 })
+        );
+    }
+
+    #[test]
+    fn removed_ids_are_never_reused() {
+        let mut store = store_with(&[("いち", "一"), ("に", "二")]);
+
+        assert_eq!(
+            store.remove(2).map(|word| word.word),
+            Some("二".to_string())
+        );
+        assert_eq!(store.remove(2), None);
+        let added = store
+            .add("さん", "三", PartOfSpeech::Noun, String::new())
+            .unwrap();
+        assert_eq!(added.id, 3);
+    }
+
+    #[test]
+    fn search_ignores_case_and_kana_type_across_fields() {
+        let mut store = store_with(&[("あずき", "azooKey"), ("へんかん", "変換")]);
+        store
+            .add("てすと", "テスト", PartOfSpeech::Noun, "Memo".into())
+            .unwrap();
+
+        let words = |query: &str| {
+            store
+                .search(query, 0)
+                .into_iter()
+                .map(|word| word.word.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("AZOO"), ["azooKey"]);
+        assert_eq!(words("ヘン"), ["変換"]);
+        assert_eq!(words("memo"), ["テスト"]);
+        assert_eq!(words("").len(), 3);
+        assert_eq!(store.search("", 2).len(), 2);
+    }
+
+    #[test]
+    fn store_round_trips_and_falls_back_to_settings_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(
+            temp.path().join(SETTINGS_FILENAME),
+            r#"{
+                "version": "0.1.3",
+                "zenzai": { "enable": false, "profile": "", "backend": "cpu" },
+                "user_dictionary": { "entries": [
+                    { "reading": "あずき", "word": "azooKey" },
+                    { "reading": "", "word": "blank" },
+                    { "reading": "あずき", "word": "azooKey" }
+                ] }
+            }"#,
+        )
+        .unwrap();
+
+        let mut store = UserDictionaryStore::read_from_root(temp.path()).unwrap();
+        assert_eq!(store.words().len(), 1);
+        assert_eq!(store.words()[0].part_of_speech, PartOfSpeech::ProperNoun);
+
+        store
+            .add("ちめい", "地名", PartOfSpeech::PlaceName, String::new())
+            .unwrap();
+        store.write_to_root(temp.path()).unwrap();
+
+        assert_eq!(
+            UserDictionaryStore::read_from_root(temp.path()).unwrap(),
+            store
+        );
+    }
+}
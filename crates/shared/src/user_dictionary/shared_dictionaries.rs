@@ -0,0 +1,367 @@
+//! Read-only dictionaries loaded from the files in a configured folder, such
+//! as a team glossary on a synced share.
+//!
+//! Each file is one dictionary in one of the import formats. The folder is
+//! polled rather than watched: sync clients often replace a file instead of
+//! writing to it, and a changed size or modification time catches both.
+
+use super::{
+    normalize_kana,
+    transfer::{self, DictionaryFormat},
+    UserWord,
+};
+use crate::UserDictionaryConfig;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Larger files are not read; a glossary stays far below this.
+const MAX_DICTIONARY_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The id of every shared word. The personal store never hands it out, so
+/// removing a personal word from the converter leaves the shared ones alone.
+pub const SHARED_WORD_ID: u64 = 0;
+
+/// The format of a dictionary file, from its extension and byte order mark.
+/// `None` for files that are not dictionaries.
+pub fn format_for_file(file_name: &str, contents: &[u8]) -> Option<DictionaryFormat> {
+    let extension = Path::new(file_name)
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "csv" => Some(DictionaryFormat::Csv),
+        "txt" | "tsv" if contents.starts_with(&[0xFF, 0xFE]) => Some(DictionaryFormat::MsIme),
+        "txt" | "tsv" => Some(DictionaryFormat::Mozc),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedDictionary {
+    pub file_name: String,
+    pub words: Vec<UserWord>,
+    /// Lines that could not become a word.
+    pub skipped_lines: usize,
+    /// Why the file could not be read. It then contributes no words.
+    pub error: Option<String>,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl SharedDictionary {
+    fn load(file: DictionaryFile) -> Self {
+        let mut dictionary = Self {
+            file_name: file.file_name,
+            words: Vec::new(),
+            skipped_lines: 0,
+            error: None,
+            modified: file.modified,
+            len: file.len,
+        };
+        if file.len > MAX_DICTIONARY_FILE_BYTES {
+            dictionary.error = Some(format!(
+                "the file is larger than {} MB",
+                MAX_DICTIONARY_FILE_BYTES / 1024 / 1024
+            ));
+            return dictionary;
+        }
+        let contents = match fs::read(&file.path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                dictionary.error = Some(error.to_string());
+                return dictionary;
+            }
+        };
+        let Some(format) = format_for_file(&dictionary.file_name, &contents) else {
+            return dictionary;
+        };
+        match transfer::parse(format, &contents) {
+            Ok(parsed) => {
+                dictionary.skipped_lines = parsed.errors.len();
+                dictionary.words = parsed
+                    .words
+                    .into_iter()
+                    .map(|word| UserWord {
+                        id: SHARED_WORD_ID,
+                        reading: word.reading,
+                        word: word.word,
+                        part_of_speech: word.part_of_speech,
+                        comment: word.comment,
+                    })
+                    .collect();
+            }
+            Err(error) => dictionary.error = Some(error.to_string()),
+        }
+        dictionary
+    }
+}
+
+/// A dictionary file as listed, before it is read.
+struct DictionaryFile {
+    file_name: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// The shared dictionaries of the configured folder, kept in file name order.
+#[derive(Debug, Clone, Default)]
+pub struct SharedDictionaries {
+    config: UserDictionaryConfig,
+    dictionaries: Vec<SharedDictionary>,
+    directory_error: Option<String>,
+}
+
+impl SharedDictionaries {
+    /// Applies `config` and rereads the folder. Returns whether the merged
+    /// words may have changed.
+    pub fn configure(&mut self, config: &UserDictionaryConfig) -> bool {
+        let changed = self.config != *config;
+        if self.config.shared_directory != config.shared_directory {
+            self.dictionaries.clear();
+        }
+        self.config = config.clone();
+        self.refresh() || changed
+    }
+
+    /// Rereads the files added, changed or removed since the last call.
+    /// Returns whether any dictionary changed.
+    pub fn refresh(&mut self) -> bool {
+        let files = match self.list_files() {
+            Ok(files) => {
+                self.directory_error = None;
+                files
+            }
+            Err(error) => {
+                self.directory_error = Some(error);
+                Vec::new()
+            }
+        };
+
+        let mut previous = std::mem::take(&mut self.dictionaries)
+            .into_iter()
+            .map(|dictionary| (dictionary.file_name.clone(), dictionary))
+            .collect::<HashMap<_, _>>();
+        let mut changed = files.len() != previous.len();
+        for file in files {
+            let dictionary = match previous.remove(&file.file_name) {
+                Some(dictionary)
+                    if dictionary.modified == file.modified && dictionary.len == file.len =>
+                {
+                    dictionary
+                }
+                _ => {
+                    changed = true;
+                    SharedDictionary::load(file)
+                }
+            };
+            self.dictionaries.push(dictionary);
+        }
+        changed
+    }
+
+    /// Dictionary files in the folder, sorted by name.
+    fn list_files(&self) -> Result<Vec<DictionaryFile>, String> {
+        let directory = self.config.shared_directory.trim();
+        if directory.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut files = fs::read_dir(directory)
+            .map_err(|error| error.to_string())?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok().filter(fs::Metadata::is_file)?;
+                let file_name = entry.file_name().into_string().ok()?;
+                format_for_file(&file_name, &[])?;
+                Some(DictionaryFile {
+                    file_name,
+                    path: entry.path(),
+                    modified: metadata.modified().ok(),
+                    len: metadata.len(),
+                })
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        Ok(files)
+    }
+
+    pub fn dictionaries(&self) -> &[SharedDictionary] {
+        &self.dictionaries
+    }
+
+    /// Why the folder could not be listed, if it could not.
+    pub fn directory_error(&self) -> Option<&str> {
+        self.directory_error.as_deref()
+    }
+
+    /// The words of every enabled dictionary, `personal` included, in
+    /// priority order. A reading and word registered in more than one
+    /// dictionary is kept only where it comes first.
+    pub fn merge(&self, personal: &[UserWord]) -> Vec<UserWord> {
+        let mut sources = self
+            .dictionaries
+            .iter()
+            .map(|dictionary| {
+                (
+                    self.config.shared_settings(&dictionary.file_name),
+                    dictionary.words.as_slice(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // The personal dictionary goes first so that it wins priority ties.
+        sources.insert(0, (self.config.personal, personal));
+        sources.retain(|(settings, _)| settings.enabled);
+        sources.sort_by_key(|(settings, _)| std::cmp::Reverse(settings.priority));
+
+        let mut seen = HashSet::new();
+        sources
+            .into_iter()
+            .flat_map(|(_, words)| words)
+            .filter(|word| seen.insert((normalize_kana(&word.reading), word.word.clone())))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the merged words are exactly the personal ones, so a change to
+    /// the personal dictionary can be applied to the converter by itself.
+    pub fn serves_personal_only(&self) -> bool {
+        self.config.personal.enabled
+            && self.dictionaries.iter().all(|dictionary| {
+                dictionary.words.is_empty()
+                    || !self.config.shared_settings(&dictionary.file_name).enabled
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{user_dictionary::PartOfSpeech, DictionarySettings};
+
+    fn personal_word(id: u64, reading: &str, word: &str) -> UserWord {
+        UserWord {
+            id,
+            reading: reading.to_string(),
+            word: word.to_string(),
+            part_of_speech: PartOfSpeech::ProperNoun,
+            comment: String::new(),
+        }
+    }
+
+    fn surfaces(words: &[UserWord]) -> Vec<&str> {
+        words.iter().map(|word| word.word.as_str()).collect()
+    }
+
+    #[test]
+    fn dictionary_files_are_read_by_extension() {
+        assert_eq!(
+            format_for_file("glossary.TXT", b"a\tb"),
+            Some(DictionaryFormat::Mozc)
+        );
+        assert_eq!(
+            format_for_file("glossary.txt", &[0xFF, 0xFE]),
+            Some(DictionaryFormat::MsIme)
+        );
+        assert_eq!(
+            format_for_file("glossary.csv", b""),
+            Some(DictionaryFormat::Csv)
+        );
+        assert_eq!(format_for_file("notes.md", b""), None);
+        assert_eq!(format_for_file("glossary", b""), None);
+    }
+
+    #[test]
+    fn merge_orders_dictionaries_by_priority_and_drops_repeats() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(
+            temp.path().join("product.txt"),
+            "あずき\tazooKey\t固有名詞\nぜんざい\tZenzai\t固有名詞\n",
+        )
+        .unwrap();
+        fs::write(temp.path().join("jargon.csv"), "しゃない,社内\n").unwrap();
+        fs::write(temp.path().join("readme.md"), "not a dictionary").unwrap();
+
+        let mut config = UserDictionaryConfig {
+            shared_directory: temp.path().to_string_lossy().into_owned(),
+            ..UserDictionaryConfig::default()
+        };
+        config.shared.insert(
+            "PRODUCT.txt".to_string(),
+            DictionarySettings {
+                enabled: true,
+                priority: 10,
+            },
+        );
+        let mut shared = SharedDictionaries::default();
+        assert!(shared.configure(&config));
+        assert_eq!(shared.dictionaries().len(), 2);
+
+        let personal = [
+            personal_word(1, "しゃない", "社内"),
+            personal_word(2, "あずき", "小豆"),
+        ];
+        let merged = shared.merge(&personal);
+        assert_eq!(surfaces(&merged), ["azooKey", "Zenzai", "社内", "小豆"]);
+        assert_eq!(merged[2].id, 1);
+        assert_eq!(merged[0].id, SHARED_WORD_ID);
+        assert!(!shared.serves_personal_only());
+
+        config.shared.insert(
+            "product.txt".to_string(),
+            DictionarySettings {
+                enabled: false,
+                priority: 10,
+            },
+        );
+        config.shared.remove("PRODUCT.txt");
+        config.personal.enabled = false;
+        assert!(shared.configure(&config));
+        assert_eq!(surfaces(&shared.merge(&personal)), ["社内"]);
+        assert_eq!(shared.merge(&personal)[0].id, SHARED_WORD_ID);
+    }
+
+    #[test]
+    fn refresh_rereads_only_changed_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let glossary = temp.path().join("glossary.txt");
+        fs::write(&glossary, "あずき\tazooKey\n").unwrap();
+        let config = UserDictionaryConfig {
+            shared_directory: temp.path().to_string_lossy().into_owned(),
+            ..UserDictionaryConfig::default()
+        };
+        let mut shared = SharedDictionaries::default();
+        shared.configure(&config);
+        assert!(!shared.refresh());
+
+        fs::write(&glossary, "あずき\tazooKey\nぜんざい\tZenzai\n").unwrap();
+        assert!(shared.refresh());
+        assert_eq!(shared.dictionaries()[0].words.len(), 2);
+
+        fs::write(temp.path().join("a.csv"), "いち,一\n").unwrap();
+        assert!(shared.refresh());
+        assert_eq!(shared.dictionaries()[0].file_name, "a.csv");
+        assert_eq!(shared.dictionaries()[1].words.len(), 2);
+
+        fs::remove_file(&glossary).unwrap();
+        assert!(shared.refresh());
+        assert_eq!(shared.dictionaries().len(), 1);
+        assert!(!shared.serves_personal_only());
+    }
+
+    #[test]
+    fn missing_folder_serves_the_personal_dictionary_alone() {
+        let mut shared = SharedDictionaries::default();
+        assert!(shared.configure(&UserDictionaryConfig {
+            shared_directory: "/nonexistent/azookey-shared".to_string(),
+            ..UserDictionaryConfig::default()
+        }));
+        assert!(shared.directory_error().is_some());
+        assert!(shared.serves_personal_only());
+        let personal = [personal_word(1, "あずき", "azooKey")];
+        assert_eq!(shared.merge(&personal), personal);
+    }
+}
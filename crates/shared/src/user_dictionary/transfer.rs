@@ -0,0 +1,491 @@
+//! Import and export of user dictionaries in other IMEs' formats.
+//!
+//! Parsing never stops at a bad line: every line that cannot become a word is
+//! reported with its line number, so a settings client can show the user what
+//! was left out of an otherwise successful import.
+
+use super::{normalize_kana, PartOfSpeech, UserWord};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error, fmt};
+
+const MS_IME_HEADER: &str = "!Microsoft IME Dictionary Tool\r\n!Format:WORDLIST\r\n\r\n";
+const CSV_HEADER: &str = "reading,word,part_of_speech,comment";
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DictionaryFormat {
+    /// Tab-separated export of Google Japanese Input and Mozc:
+    /// `reading\tword\tpart of speech\tcomment`, `#` starting a comment line.
+    Mozc,
+    /// MS-IME's text export: UTF-16 with a byte order mark, `!` header lines
+    /// and `reading\tword\tpart of speech` rows.
+    MsIme,
+    /// `reading,word,part_of_speech,comment` with an optional header row.
+    Csv,
+}
+
+/// A word read from an imported file, not yet registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedWord {
+    /// 1-based line in the imported file.
+    pub line: usize,
+    pub reading: String,
+    pub word: String,
+    pub part_of_speech: PartOfSpeech,
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineErrorKind {
+    MissingWord,
+    EmptyReading,
+    EmptyWord,
+    UnsupportedPartOfSpeech(String),
+    UnterminatedQuote,
+    /// Same reading and word as the word on `line` earlier in the file.
+    Duplicate {
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+    pub line: usize,
+    pub kind: LineErrorKind,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            LineErrorKind::MissingWord => write!(f, "expected a reading and a word"),
+            LineErrorKind::EmptyReading => write!(f, "reading is empty"),
+            LineErrorKind::EmptyWord => write!(f, "word is empty"),
+            LineErrorKind::UnsupportedPartOfSpeech(name) => {
+                write!(f, "unsupported part of speech {name:?}")
+            }
+            LineErrorKind::UnterminatedQuote => write!(f, "unterminated quoted field"),
+            LineErrorKind::Duplicate { line } => write!(f, "duplicates line {line}"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedDictionary {
+    pub words: Vec<ImportedWord>,
+    pub errors: Vec<LineError>,
+}
+
+/// The file is not text in an encoding the format allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub format: DictionaryFormat,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            DictionaryFormat::MsIme => write!(f, "expected UTF-16 or UTF-8 text"),
+            DictionaryFormat::Mozc | DictionaryFormat::Csv => write!(f, "expected UTF-8 text"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+pub fn parse(format: DictionaryFormat, contents: &[u8]) -> Result<ParsedDictionary, DecodeError> {
+    let text = decode(format, contents).ok_or(DecodeError { format })?;
+    let mut parsed = ParsedDictionary::default();
+    let mut seen = HashMap::new();
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = index + 1;
+        let fields = match split_line(format, raw_line, parsed.words.is_empty()) {
+            Ok(Some(fields)) => fields,
+            Ok(None) => continue,
+            Err(kind) => {
+                parsed.errors.push(LineError { line, kind });
+                continue;
+            }
+        };
+        match imported_word(line, &fields) {
+            Ok(word) => {
+                let key = (normalize_kana(&word.reading), word.word.clone());
+                if let Some(first_line) = seen.get(&key) {
+                    parsed.errors.push(LineError {
+                        line,
+                        kind: LineErrorKind::Duplicate { line: *first_line },
+                    });
+                } else {
+                    seen.insert(key, line);
+                    parsed.words.push(word);
+                }
+            }
+            Err(kind) => parsed.errors.push(LineError { line, kind }),
+        }
+    }
+    Ok(parsed)
+}
+
+pub fn serialize(format: DictionaryFormat, words: &[UserWord]) -> Vec<u8> {
+    match format {
+        DictionaryFormat::Mozc => words
+            .iter()
+            .map(|word| {
+                format!(
+                    "{}\t{}\t{}\t{}\n",
+                    tsv_field(&word.reading),
+                    tsv_field(&word.word),
+                    part_of_speech_name(word.part_of_speech),
+                    tsv_field(&word.comment)
+                )
+            })
+            .collect::<String>()
+            .into_bytes(),
+        DictionaryFormat::MsIme => {
+            let mut text = MS_IME_HEADER.to_string();
+            for word in words {
+                text.push_str(&format!(
+                    "{}\t{}\t{}",
+                    tsv_field(&word.reading),
+                    tsv_field(&word.word),
+                    part_of_speech_name(word.part_of_speech)
+                ));
+                if !word.comment.is_empty() {
+                    text.push('\t');
+                    text.push_str(&tsv_field(&word.comment));
+                }
+                text.push_str("\r\n");
+            }
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        }
+        DictionaryFormat::Csv => {
+            let mut text = format!("{CSV_HEADER}\r\n");
+            for word in words {
+                text.push_str(&format!(
+                    "{},{},{},{}\r\n",
+                    csv_field(&word.reading),
+                    csv_field(&word.word),
+                    part_of_speech_name(word.part_of_speech),
+                    csv_field(&word.comment)
+                ));
+            }
+            text.into_bytes()
+        }
+    }
+}
+
+/// Maps a part of speech as other IMEs name it, or as this IME serializes it,
+/// onto the ones the converter knows. Parts of speech the converter cannot
+/// place, such as verbs, have no mapping.
+pub fn part_of_speech_from_name(name: &str) -> Option<PartOfSpeech> {
+    match name.trim() {
+        "" | "固有名詞" | "proper_noun" => Some(PartOfSpeech::ProperNoun),
+        "名詞" | "一般名詞" | "名詞サ変" | "名詞形動" | "サ変名詞" | "形動名詞" | "短縮よみ"
+        | "顔文字" | "記号" | "noun" => Some(PartOfSpeech::Noun),
+        "人名" | "姓" | "名" | "person_name" => Some(PartOfSpeech::PersonName),
+        "地名" | "地名その他" | "place_name" => Some(PartOfSpeech::PlaceName),
+        "組織" | "組織名" | "organization" => Some(PartOfSpeech::Organization),
+        _ => None,
+    }
+}
+
+fn part_of_speech_name(part_of_speech: PartOfSpeech) -> &'static str {
+    match part_of_speech {
+        PartOfSpeech::Noun => "名詞",
+        PartOfSpeech::ProperNoun => "固有名詞",
+        PartOfSpeech::PersonName => "人名",
+        PartOfSpeech::PlaceName => "地名",
+        PartOfSpeech::Organization => "組織",
+    }
+}
+
+fn decode(format: DictionaryFormat, contents: &[u8]) -> Option<String> {
+    if let Some(rest) = contents.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8(rest.to_vec()).ok();
+    }
+    if format == DictionaryFormat::MsIme {
+        if let Some(rest) = contents.strip_prefix(&[0xFF, 0xFE]) {
+            return decode_utf16(rest, u16::from_le_bytes);
+        }
+        if let Some(rest) = contents.strip_prefix(&[0xFE, 0xFF]) {
+            return decode_utf16(rest, u16::from_be_bytes);
+        }
+    }
+    String::from_utf8(contents.to_vec()).ok()
+}
+
+fn decode_utf16(contents: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if !contents.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = contents
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16(&units).ok()
+}
+
+/// Splits one line into fields. `Ok(None)` is a line that holds no word:
+/// blank, a comment or header, or the CSV header row when `first` is set.
+fn split_line(
+    format: DictionaryFormat,
+    line: &str,
+    first: bool,
+) -> Result<Option<Vec<String>>, LineErrorKind> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    match format {
+        DictionaryFormat::Mozc if line.starts_with('#') => Ok(None),
+        DictionaryFormat::MsIme if line.starts_with('!') => Ok(None),
+        DictionaryFormat::Mozc | DictionaryFormat::MsIme => {
+            Ok(Some(line.split('\t').map(str::to_string).collect()))
+        }
+        DictionaryFormat::Csv => {
+            let fields = split_csv_line(line)?;
+            let is_header = first
+                && fields
+                    .first()
+                    .is_some_and(|field| matches!(field.trim(), "reading" | "読み" | "よみ"));
+            Ok((!is_header).then_some(fields))
+        }
+    }
+}
+
+fn split_csv_line(line: &str) -> Result<Vec<String>, LineErrorKind> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut characters = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(character) = characters.next() {
+        match (quoted, character) {
+            (true, '"') if characters.peek() == Some(&'"') => {
+                characters.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, character) => field.push(character),
+        }
+    }
+    if quoted {
+        return Err(LineErrorKind::UnterminatedQuote);
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn imported_word(line: usize, fields: &[String]) -> Result<ImportedWord, LineErrorKind> {
+    let [reading, word, rest @ ..] = fields else {
+        return Err(LineErrorKind::MissingWord);
+    };
+    let reading = reading.trim();
+    let word = word.trim();
+    if reading.is_empty() {
+        return Err(LineErrorKind::EmptyReading);
+    }
+    if word.is_empty() {
+        return Err(LineErrorKind::EmptyWord);
+    }
+    let part_of_speech_name = rest.first().map(String::as_str).unwrap_or_default();
+    let part_of_speech = part_of_speech_from_name(part_of_speech_name).ok_or_else(|| {
+        LineErrorKind::UnsupportedPartOfSpeech(part_of_speech_name.trim().to_string())
+    })?;
+    Ok(ImportedWord {
+        line,
+        reading: reading.to_string(),
+        word: word.to_string(),
+        part_of_speech,
+        comment: rest
+            .get(1)
+            .map(|comment| comment.trim())
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// Tabs and line breaks would split the row, so they become spaces.
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\r', '\n'], " ")
+}
+
+fn csv_field(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.contains([',', '"']) || value.trim() != value {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(parsed: &ParsedDictionary) -> Vec<(&str, &str, PartOfSpeech, &str)> {
+        parsed
+            .words
+            .iter()
+            .map(|word| {
+                (
+                    word.reading.as_str(),
+                    word.word.as_str(),
+                    word.part_of_speech,
+                    word.comment.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    fn user_word(id: u64, reading: &str, word: &str, part_of_speech: PartOfSpeech) -> UserWord {
+        UserWord {
+            id,
+            reading: reading.to_string(),
+            word: word.to_string(),
+            part_of_speech,
+            comment: String::new(),
+        }
+    }
+
+    #[test]
+    fn mozc_export_is_parsed_with_a_report_of_skipped_lines() {
+        let contents = "# Google 日本語入力 export\n\
+            さとう\t佐藤\t姓\t同僚\n\
+            はしる\t走る\t動詞ラ行五段\t\n\
+            \n\
+            とうきょう\t東京\t地名\n\
+            サトウ\t佐藤\t人名\t\n\
+            ひとつだけ\n";
+
+        let parsed = parse(DictionaryFormat::Mozc, contents.as_bytes()).unwrap();
+
+        assert_eq!(
+            words(&parsed),
+            [
+                ("さとう", "佐藤", PartOfSpeech::PersonName, "同僚"),
+                ("とうきょう", "東京", PartOfSpeech::PlaceName, ""),
+            ]
+        );
+        assert_eq!(
+            parsed.errors,
+            [
+                LineError {
+                    line: 3,
+                    kind: LineErrorKind::UnsupportedPartOfSpeech("動詞ラ行五段".into()),
+                },
+                LineError {
+                    line: 6,
+                    kind: LineErrorKind::Duplicate { line: 2 },
+                },
+                LineError {
+                    line: 7,
+                    kind: LineErrorKind::MissingWord,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ms_ime_export_is_read_as_utf16() {
+        let text = "!Microsoft IME Dictionary Tool\r\n!Version:\r\n!Format:WORDLIST\r\n\r\nあずき\tazooKey\t固有名詞\r\n";
+        let mut contents = vec![0xFF, 0xFE];
+        contents.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+        let parsed = parse(DictionaryFormat::MsIme, &contents).unwrap();
+
+        assert_eq!(
+            words(&parsed),
+            [("あずき", "azooKey", PartOfSpeech::ProperNoun, "")]
+        );
+        assert!(parsed.errors.is_empty());
+        assert_eq!(
+            parse(DictionaryFormat::MsIme, &contents[..contents.len() - 1]),
+            Err(DecodeError {
+                format: DictionaryFormat::MsIme
+            })
+        );
+    }
+
+    #[test]
+    fn csv_honours_quotes_and_skips_the_header_row() {
+        let contents = "読み,単語,品詞,コメント\n\
+            かぶしきがいしゃ,\"株式会社, 例\",組織,\"\"\"引用\"\" つき\"\n\
+            めいし,名詞,noun\n\
+            \"とじない,壊れ\n";
+
+        let parsed = parse(DictionaryFormat::Csv, contents.as_bytes()).unwrap();
+
+        assert_eq!(
+            words(&parsed),
+            [
+                (
+                    "かぶしきがいしゃ",
+                    "株式会社, 例",
+                    PartOfSpeech::Organization,
+                    "\"引用\" つき"
+                ),
+                ("めいし", "名詞", PartOfSpeech::Noun, ""),
+            ]
+        );
+        assert_eq!(
+            parsed.errors,
+            [LineError {
+                line: 4,
+                kind: LineErrorKind::UnterminatedQuote,
+            }]
+        );
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let mut commented = user_word(2, "かいしゃ", "会社, \"A\"", PartOfSpeech::Organization);
+        commented.comment = "line\nbreak".to_string();
+        let words = [
+            user_word(1, "あずき", "azooKey", PartOfSpeech::ProperNoun),
+            commented,
+            user_word(3, "とうきょう", "東京", PartOfSpeech::PlaceName),
+        ];
+
+        for format in [
+            DictionaryFormat::Mozc,
+            DictionaryFormat::MsIme,
+            DictionaryFormat::Csv,
+        ] {
+            let parsed = parse(format, &serialize(format, &words)).unwrap();
+            assert!(parsed.errors.is_empty(), "{format:?}: {:?}", parsed.errors);
+            let round_tripped = parsed
+                .words
+                .iter()
+                .map(|word| {
+                    (
+                        word.reading.as_str(),
+                        word.word.as_str(),
+                        word.part_of_speech,
+                        word.comment.as_str(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                round_tripped,
+                [
+                    ("あずき", "azooKey", PartOfSpeech::ProperNoun, ""),
+                    (
+                        "かいしゃ",
+                        "会社, \"A\"",
+                        PartOfSpeech::Organization,
+                        "line break"
+                    ),
+                    ("とうきょう", "東京", PartOfSpeech::PlaceName, ""),
+                ],
+                "{format:?}"
+            );
+        }
+    }
+}
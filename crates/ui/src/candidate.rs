@@ -140,6 +140,27 @@ fn candidate_html() -> String {
                         font-size: 0.8rem;
                         user-select: none;
                     }
+                    main[data-suggest] li::before {
+                        display: none;
+                    }
+                    main[data-suggest] #candidate-annotation,
+                    #candidate-hint {
+                        display: none;
+                    }
+                    main[data-suggest] #candidate-hint {
+                        display: inline;
+                        line-height: 14px;
+                        color: #757575;
+                    }
+                    #candidate-annotation {
+                        min-width: 0;
+                        overflow: hidden;
+                        text-overflow: ellipsis;
+                        white-space: nowrap;
+                        /* Keep the footer height the same with or without an annotation. */
+                        line-height: 14px;
+                        color: #757575;
+                    }
 
                     @media (prefers-color-scheme: dark) {
                         body {
@@ -168,6 +189,10 @@ fn candidate_html() -> String {
                         footer {
                             border-top: 1px solid #424242;
                         }
+                        #candidate-annotation,
+                        main[data-suggest] #candidate-hint {
+                            color: #BDBDBD;
+                        }
                     }
                 </style>
                 <script>
@@ -182,6 +207,7 @@ fn candidate_html() -> String {
                         calculateRenderRange,
                     } = CandidateScroller;
                     let currentCandidates = [];
+                    let currentAnnotations = [];
                     let currentSelectionIndex = 0;
                     let currentItemHeight = 0;
                     let renderedRangeStart = -1;
@@ -318,21 +344,33 @@ fn candidate_html() -> String {
                         renderedRangeEnd = range.end;
                     }
 
-                    function updateCandidates(candidates, selectedIndex = null) {
+                    function renderAnnotation() {
+                        const annotation = document.getElementById('candidate-annotation');
+                        if (annotation) {
+                            annotation.textContent = currentAnnotations[currentSelectionIndex] || '';
+                        }
+                    }
+
+                    function updateCandidates(candidates, selectedIndex = null, annotations = []) {
                         if (!Array.isArray(candidates)) {
                             return;
                         }
 
                         currentCandidates = candidates;
-                        currentSelectionIndex = clampSelectionIndex(
-                            selectedIndex === null ? currentSelectionIndex : selectedIndex
-                        );
+                        currentAnnotations = Array.isArray(annotations) ? annotations : [];
+                        // -1 shows predictions before one is picked, with nothing highlighted.
+                        currentSelectionIndex = selectedIndex === -1
+                            ? -1
+                            : clampSelectionIndex(
+                                selectedIndex === null ? currentSelectionIndex : selectedIndex
+                            );
+                        renderAnnotation();
 
                         const candidateList = document.getElementById('candidate-list');
                         if (candidateList) {
                             const itemHeight = currentItemHeight || measureListItemHeight(candidateList);
                             const desiredScrollTop = selectionPageScrollTop(
-                                currentSelectionIndex,
+                                Math.max(currentSelectionIndex, 0),
                                 currentCandidates.length,
                                 itemHeight
                             );
@@ -358,6 +396,19 @@ fn candidate_html() -> String {
                         scheduleAdjustWindowSize();
                     }
 
+                    function setSuggestMode(suggest) {
+                        const main = document.querySelector('main');
+                        if (!main) {
+                            return;
+                        }
+
+                        if (suggest) {
+                            main.setAttribute('data-suggest', '');
+                        } else {
+                            main.removeAttribute('data-suggest');
+                        }
+                    }
+
                     function updateSelection(index) {
                         const candidateList = document.getElementById('candidate-list');
                         if (!candidateList || currentCandidates.length === 0) {
@@ -367,6 +418,7 @@ fn candidate_html() -> String {
                         const itemHeight = currentItemHeight || measureListItemHeight(candidateList);
                         const safeIndex = clampSelectionIndex(index);
                         currentSelectionIndex = safeIndex;
+                        renderAnnotation();
                         if (!isSelectionFullyVisible(
                             safeIndex,
                             candidateList.scrollTop,
@@ -427,6 +479,8 @@ fn candidate_html() -> String {
                         <svg width="20" height="14" viewBox="0 0 22 16" fill="none" xmlns="http://www.w3.org/2000/svg">
                             <path d="M3.5 8C4.59202 9.04403 7.54398 10.3978 13.5068 9.93754M1.25349 5.39919C2.77722 0.413397 8.08911 0.79692 10.9673 1.24436C14.2687 1.71311 20.8969 3.82675 20.9985 8.53129C21.1255 14.412 13.1894 15.3069 10.0784 14.9233C6.96748 14.5398 -0.46071 13.0696 1.25349 5.39919Z" stroke="#838384" stroke-width="1.5" stroke-linecap="round"/>
                         </svg>
+                        <span id="candidate-annotation"></span>
+                        <span id="candidate-hint">Tab で選択</span>
                     </footer>
                 </main>
             </body>
@@ -38,11 +38,15 @@ pub enum WindowAction {
         visible: Option<bool>,
         position: Option<WindowPositionAction>,
         candidates: Option<Vec<String>>,
+        // candidates と同じ並びの補足表示 (候補の出典や環境依存文字など)
+        annotations: Option<Vec<String>>,
         selected_index: Option<i32>,
         input_mode: Option<String>,
         reading: Option<String>,
         candidate_list_visible: Option<bool>,
         reading_vertical_adjustment: Option<i32>,
+        // 候補一覧を予測候補として表示するか (候補一覧の更新時のみ意味を持つ)
+        suggest: Option<bool>,
     },
 }
 
@@ -146,19 +150,22 @@ impl WindowServiceProto for WindowService {
             bottom: position.bottom,
             right: position.right,
         });
-        let candidates = request
+        let (candidates, annotations) = request
             .candidates
-            .map(|candidate_list| candidate_list.candidates);
+            .map(|candidate_list| (candidate_list.candidates, candidate_list.annotations))
+            .unzip();
 
         self.send_action(WindowAction::UpdateCandidateWindow {
             visible: request.visible,
             position,
             candidates,
+            annotations,
             selected_index: request.selected_index,
             input_mode: request.input_mode,
             reading: request.reading,
             candidate_list_visible: request.candidate_list_visible,
             reading_vertical_adjustment: request.reading_vertical_adjustment,
+            suggest: request.suggest,
         })
         .await
     }
@@ -236,12 +243,14 @@ mod tests {
                 }),
                 candidates: Some(shared::proto::CandidateList {
                     candidates: vec!["候補".to_string()],
+                    annotations: vec!["環境依存".to_string()],
                 }),
                 selected_index: Some(0),
                 input_mode: Some("あ".to_string()),
                 reading: Some("こうほ".to_string()),
                 candidate_list_visible: Some(true),
                 reading_vertical_adjustment: Some(4),
+                suggest: Some(true),
             }))
             .await
             .expect("batched update should be sent");
@@ -251,11 +260,13 @@ mod tests {
                 visible,
                 position,
                 candidates,
+                annotations,
                 selected_index,
                 input_mode,
                 reading,
                 candidate_list_visible,
                 reading_vertical_adjustment,
+                suggest,
             } => {
                 assert_eq!(visible, Some(true));
                 let position = position.expect("position should be included");
@@ -264,11 +275,13 @@ mod tests {
                     (1, 2, 3, 4)
                 );
                 assert_eq!(candidates, Some(vec!["候補".to_string()]));
+                assert_eq!(annotations, Some(vec!["環境依存".to_string()]));
                 assert_eq!(selected_index, Some(0));
                 assert_eq!(input_mode, Some("あ".to_string()));
                 assert_eq!(reading, Some("こうほ".to_string()));
                 assert_eq!(candidate_list_visible, Some(true));
                 assert_eq!(reading_vertical_adjustment, Some(4));
+                assert_eq!(suggest, Some(true));
             }
             action => panic!("unexpected action: {action:?}"),
         }
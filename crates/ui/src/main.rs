@@ -2,11 +2,11 @@
 
 use std::{fs, sync::Arc};
 
-use azookey_server::TonicNamedPipeServer;
 use ipc::{WindowAction, WindowController, WindowService};
 use shared::{
     proto::window_service_server::WindowServiceServer,
-    LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_DEFAULT, UI_PIPE_PATH,
+    transport::{IpcEndpoint, IpcListener},
+    LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_DEFAULT,
 };
 use tao::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
 use tao::platform::windows::{EventLoopBuilderExtWindows, WindowExtWindows};
@@ -196,16 +196,22 @@ fn set_candidate_window_width(candidate_window: &tao::window::Window, candidates
 fn update_candidate_list(
     candidate_webview: &wry::WebView,
     candidates: &[String],
+    annotations: &[String],
     selected_index: Option<i32>,
 ) {
-    match serde_json::to_string(candidates) {
-        Ok(candidates) => {
+    match serde_json::to_string(candidates).and_then(|candidates| {
+        serde_json::to_string(annotations).map(|annotations| (candidates, annotations))
+    }) {
+        Ok((candidates, annotations)) => {
             let selected_index = selected_index
                 .map(|index| index.to_string())
                 .unwrap_or_else(|| "null".to_string());
             evaluate_script(
                 candidate_webview,
-                &format!("updateCandidates({}, {})", candidates, selected_index),
+                &format!(
+                    "updateCandidates({}, {}, {})",
+                    candidates, selected_index, annotations
+                ),
             );
         }
         Err(error) => {
@@ -256,6 +262,10 @@ fn set_candidate_list_visible(candidate_webview: &wry::WebView, visible: bool) {
     );
 }
 
+fn set_suggest_mode(candidate_webview: &wry::WebView, suggest: bool) {
+    evaluate_script(candidate_webview, &format!("setSuggestMode({})", suggest));
+}
+
 fn update_indicator(indicator_webview: &wry::WebView, input_method: &str) {
     match serde_json::to_string(input_method) {
         Ok(input_method) => evaluate_script(
@@ -355,12 +365,13 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // start grpc server
-    let incoming = TonicNamedPipeServer::new(UI_PIPE_PATH)?;
+    let endpoint = IpcEndpoint::ui()?;
+    let listener = IpcListener::bind(&endpoint)?;
     tokio::spawn(async move {
-        println!("WindowServer listening");
+        println!("WindowServer listening on {endpoint}");
         Server::builder()
             .add_service(WindowServiceServer::new(grpc_service))
-            .serve_with_incoming(incoming)
+            .serve_with_incoming(listener.incoming())
             .await
             .expect("gRPC server failed");
     });
@@ -630,8 +641,9 @@ async fn main() -> anyhow::Result<()> {
                         WindowAction::SetCandidate { candidates } => {
                             current_candidate_list_visible = true;
                             set_candidate_list_visible(&candidate_webview, true);
+                            set_suggest_mode(&candidate_webview, false);
                             set_candidate_window_width(&candidate_window, &candidates);
-                            update_candidate_list(&candidate_webview, &candidates, None);
+                            update_candidate_list(&candidate_webview, &candidates, &[], None);
                             if let Some(rect) = last_candidate_rect {
                                 place_candidate_windows(
                                     &candidate_window,
@@ -688,11 +700,13 @@ async fn main() -> anyhow::Result<()> {
                             visible,
                             position,
                             candidates,
+                            annotations,
                             selected_index,
                             input_mode,
                             reading,
                             candidate_list_visible,
                             reading_vertical_adjustment,
+                            suggest,
                         } => {
                             if let Some(reading_vertical_adjustment) = reading_vertical_adjustment {
                                 current_reading_vertical_adjustment = reading_vertical_adjustment;
@@ -724,10 +738,12 @@ async fn main() -> anyhow::Result<()> {
                             }
 
                             if let Some(ref candidates) = candidates {
+                                set_suggest_mode(&candidate_webview, suggest.unwrap_or(false));
                                 set_candidate_window_width(&candidate_window, candidates);
                                 update_candidate_list(
                                     &candidate_webview,
                                     candidates,
+                                    annotations.as_deref().unwrap_or_default(),
                                     selected_index,
                                 );
                             }
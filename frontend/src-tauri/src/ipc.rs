@@ -1,25 +1,21 @@
 use anyhow::Result;
 use hyper_util::rt::TokioIo;
 use shared::proto::azookey_service_client::AzookeyServiceClient;
+use shared::transport::{self, ConnectFailure, IpcEndpoint};
+use shared::user_dictionary::{transfer::ImportedWord, PartOfSpeech, UserWord};
 use std::{
-    os::windows::io::IntoRawHandle,
+    future::Future,
     sync::Arc,
     time::{Duration, Instant},
 };
-use tokio::{net::windows::named_pipe::NamedPipeClient, time};
+use tokio::time;
 use tonic::transport::Endpoint;
 use tower::service_fn;
-use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, ERROR_PIPE_BUSY};
 
 const IPC_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 const IPC_CONNECT_DEADLINE: Duration = Duration::from_secs(2);
 const SETTINGS_RPC_DEADLINE: Duration = Duration::from_secs(5);
 
-fn open_named_pipe_client(pipe_name: &str) -> std::io::Result<NamedPipeClient> {
-    let handle = shared::open_named_pipe_client_handle(pipe_name)?;
-    unsafe { NamedPipeClient::from_raw_handle(handle.into_raw_handle()) }
-}
-
 // connect to kkc server
 #[derive(Debug, Clone)]
 pub struct IPCService {
@@ -56,25 +52,24 @@ impl IPCService {
         let runtime = tokio::runtime::Runtime::new()?;
         let connect_deadline = connect_deadline(timeout);
 
+        let ipc_endpoint = IpcEndpoint::server()?;
         let endpoint = Endpoint::try_from("http://[::]:50051")?;
-        let connect = endpoint.connect_with_connector(service_fn(move |_| async move {
-            let started_at = Instant::now();
-            let client = loop {
-                match open_named_pipe_client(shared::SERVER_PIPE_PATH) {
-                    Ok(client) => break client,
-                    Err(e)
-                        if should_retry_pipe_connect_error(
-                            e.raw_os_error(),
-                            started_at,
-                            timeout,
-                        ) => {}
-                    Err(e) => return Err(e),
-                }
-
-                time::sleep(IPC_RETRY_INTERVAL).await;
-            };
-
-            Ok::<_, std::io::Error>(TokioIo::new(client))
+        let connect = endpoint.connect_with_connector(service_fn(move |_| {
+            let ipc_endpoint = ipc_endpoint.clone();
+            async move {
+                let started_at = Instant::now();
+                let client = loop {
+                    match transport::connect(&ipc_endpoint).await {
+                        Ok(client) => break client,
+                        Err(e) if should_retry_connect_error(&e, started_at, timeout) => {}
+                        Err(e) => return Err(e),
+                    }
+
+                    time::sleep(IPC_RETRY_INTERVAL).await;
+                };
+
+                Ok::<_, std::io::Error>(TokioIo::new(client))
+            }
         }));
         let server_channel: tonic::transport::Channel = runtime.block_on(async {
             match time::timeout(connect_deadline, connect).await {
@@ -96,20 +91,19 @@ fn connect_deadline(timeout: Option<Duration>) -> Duration {
     timeout.unwrap_or(IPC_CONNECT_DEADLINE)
 }
 
-fn should_retry_pipe_connect_error(
-    raw_os_error: Option<i32>,
+fn should_retry_connect_error(
+    error: &std::io::Error,
     started_at: Instant,
     timeout: Option<Duration>,
 ) -> bool {
+    let failure = transport::classify_connect_error(error);
     match timeout {
         Some(timeout) if started_at.elapsed() >= timeout => return false,
         Some(_) => {}
-        None => return raw_os_error == Some(ERROR_PIPE_BUSY.0 as i32),
+        None => return failure == ConnectFailure::Busy,
     }
 
-    raw_os_error == Some(ERROR_FILE_NOT_FOUND.0 as i32)
-        || raw_os_error == Some(ERROR_PATH_NOT_FOUND.0 as i32)
-        || raw_os_error == Some(ERROR_PIPE_BUSY.0 as i32)
+    failure != ConnectFailure::Fatal
 }
 
 // implement methods to interact with kkc server
@@ -154,15 +148,162 @@ impl IPCService {
 
         Ok(response.reset)
     }
+
+    pub fn list_learned_entries(&mut self) -> anyhow::Result<Vec<shared::proto::LearnedEntry>> {
+        let request = shared::proto::ListLearnedEntriesRequest { request_id: 0 };
+        let response = self.settings_rpc(
+            "list_learned_entries",
+            request,
+            |mut client, request| async move { client.list_learned_entries(request).await },
+        )?;
+        Ok(response.entries)
+    }
+
+    pub fn forget_learned_entry(&mut self, reading: String, word: String) -> anyhow::Result<bool> {
+        let request = shared::proto::ForgetLearnedEntryRequest {
+            request_id: 0,
+            reading,
+            word,
+        };
+        let response = self.settings_rpc(
+            "forget_learned_entry",
+            request,
+            |mut client, request| async move { client.forget_learned_entry(request).await },
+        )?;
+        Ok(response.forgotten)
+    }
+
+    pub fn list_user_words(&mut self) -> anyhow::Result<Vec<UserWord>> {
+        let request = shared::proto::ListUserWordsRequest { request_id: 0 };
+        let response = self.settings_rpc(
+            "list_user_words",
+            request,
+            |mut client, request| async move { client.list_user_words(request).await },
+        )?;
+        Ok(response.words.into_iter().map(UserWord::from).collect())
+    }
+
+    pub fn search_user_words(&mut self, query: String) -> anyhow::Result<Vec<UserWord>> {
+        let request = shared::proto::SearchUserWordsRequest {
+            request_id: 0,
+            query,
+            limit: 0,
+        };
+        let response = self.settings_rpc(
+            "search_user_words",
+            request,
+            |mut client, request| async move { client.search_user_words(request).await },
+        )?;
+        Ok(response.words.into_iter().map(UserWord::from).collect())
+    }
+
+    pub fn add_user_word(
+        &mut self,
+        reading: String,
+        word: String,
+        part_of_speech: PartOfSpeech,
+        comment: String,
+    ) -> anyhow::Result<UserWord> {
+        let request = shared::proto::AddUserWordRequest {
+            request_id: 0,
+            reading,
+            word,
+            part_of_speech: shared::proto::PartOfSpeech::from(part_of_speech).into(),
+            comment,
+        };
+        let response =
+            self.settings_rpc("add_user_word", request, |mut client, request| async move {
+                client.add_user_word(request).await
+            })?;
+        response
+            .word
+            .map(UserWord::from)
+            .ok_or_else(|| anyhow::anyhow!("add_user_word returned no word"))
+    }
+
+    /// Registers `words` in one request. Returns the registered words and the
+    /// indexes of the ones skipped as already registered.
+    pub fn import_user_words(
+        &mut self,
+        words: &[ImportedWord],
+    ) -> anyhow::Result<(Vec<UserWord>, Vec<usize>)> {
+        let request = shared::proto::ImportUserWordsRequest {
+            request_id: 0,
+            words: words
+                .iter()
+                .map(|word| shared::proto::UserWord {
+                    id: 0,
+                    reading: word.reading.clone(),
+                    word: word.word.clone(),
+                    part_of_speech: shared::proto::PartOfSpeech::from(word.part_of_speech).into(),
+                    comment: word.comment.clone(),
+                })
+                .collect(),
+        };
+        let response = self.settings_rpc(
+            "import_user_words",
+            request,
+            |mut client, request| async move { client.import_user_words(request).await },
+        )?;
+        Ok((
+            response.added.into_iter().map(UserWord::from).collect(),
+            response
+                .skipped_indexes
+                .into_iter()
+                .map(|index| index as usize)
+                .collect(),
+        ))
+    }
+
+    pub fn remove_user_word(&mut self, id: u64) -> anyhow::Result<bool> {
+        let request = shared::proto::RemoveUserWordRequest { request_id: 0, id };
+        let response = self.settings_rpc(
+            "remove_user_word",
+            request,
+            |mut client, request| async move { client.remove_user_word(request).await },
+        )?;
+        Ok(response.removed)
+    }
+
+    /// Sends one settings RPC under [`SETTINGS_RPC_DEADLINE`].
+    fn settings_rpc<Req, Res, F, Fut>(
+        &mut self,
+        operation: &str,
+        message: Req,
+        call: F,
+    ) -> anyhow::Result<Res>
+    where
+        F: FnOnce(
+            AzookeyServiceClient<tonic::transport::channel::Channel>,
+            tonic::Request<Req>,
+        ) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<Res>, tonic::Status>>,
+    {
+        let mut request = tonic::Request::new(message);
+        request.set_timeout(SETTINGS_RPC_DEADLINE);
+        let client = self.azookey_client.clone();
+        let response = self.runtime.clone().block_on(async {
+            match time::timeout(SETTINGS_RPC_DEADLINE, call(client, request)).await {
+                Ok(result) => result.map_err(anyhow::Error::from),
+                Err(_) => Err(anyhow::anyhow!("{operation} IPC deadline exceeded")),
+            }
+        })?;
+        Ok(response.into_inner())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{
-        connect_deadline, should_retry_pipe_connect_error, ERROR_FILE_NOT_FOUND,
-        ERROR_PATH_NOT_FOUND, ERROR_PIPE_BUSY, IPC_CONNECT_DEADLINE,
+    use super::{connect_deadline, should_retry_connect_error, IPC_CONNECT_DEADLINE};
+    use std::{
+        io,
+        time::{Duration, Instant},
     };
-    use std::time::{Duration, Instant};
+    use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, ERROR_PIPE_BUSY};
+
+    fn os_error(code: u32) -> io::Error {
+        io::Error::from_raw_os_error(code as i32)
+    }
 
     #[test]
     fn explicit_reconnect_timeout_controls_outer_deadline() {
@@ -177,18 +318,18 @@ mod tests {
         let started_at = Instant::now();
         let timeout = Some(Duration::from_secs(10));
 
-        assert!(should_retry_pipe_connect_error(
-            Some(ERROR_FILE_NOT_FOUND.0 as i32),
+        assert!(should_retry_connect_error(
+            &os_error(ERROR_FILE_NOT_FOUND.0),
             started_at,
             timeout
         ));
-        assert!(should_retry_pipe_connect_error(
-            Some(ERROR_PATH_NOT_FOUND.0 as i32),
+        assert!(should_retry_connect_error(
+            &os_error(ERROR_PATH_NOT_FOUND.0),
             started_at,
             timeout
         ));
-        assert!(should_retry_pipe_connect_error(
-            Some(ERROR_PIPE_BUSY.0 as i32),
+        assert!(should_retry_connect_error(
+            &os_error(ERROR_PIPE_BUSY.0),
             started_at,
             timeout
         ));
@@ -199,8 +340,8 @@ mod tests {
         let timeout = Duration::from_secs(10);
         let started_at = Instant::now() - timeout;
 
-        assert!(!should_retry_pipe_connect_error(
-            Some(ERROR_PIPE_BUSY.0 as i32),
+        assert!(!should_retry_connect_error(
+            &os_error(ERROR_PIPE_BUSY.0),
             started_at,
             Some(timeout)
         ));
@@ -210,8 +351,8 @@ mod tests {
     fn retries_busy_pipe_without_timeout() {
         let started_at = Instant::now();
 
-        assert!(should_retry_pipe_connect_error(
-            Some(ERROR_PIPE_BUSY.0 as i32),
+        assert!(should_retry_connect_error(
+            &os_error(ERROR_PIPE_BUSY.0),
             started_at,
             None
         ));
@@ -221,13 +362,13 @@ mod tests {
     fn does_not_retry_missing_pipe_without_timeout() {
         let started_at = Instant::now();
 
-        assert!(!should_retry_pipe_connect_error(
-            Some(ERROR_FILE_NOT_FOUND.0 as i32),
+        assert!(!should_retry_connect_error(
+            &os_error(ERROR_FILE_NOT_FOUND.0),
             started_at,
             None
         ));
-        assert!(!should_retry_pipe_connect_error(
-            Some(ERROR_PATH_NOT_FOUND.0 as i32),
+        assert!(!should_retry_connect_error(
+            &os_error(ERROR_PATH_NOT_FOUND.0),
             started_at,
             None
         ));
@@ -238,11 +379,15 @@ mod tests {
         let started_at = Instant::now();
         let timeout = Some(Duration::from_secs(10));
 
-        assert!(!should_retry_pipe_connect_error(
-            Some(5),
+        assert!(!should_retry_connect_error(
+            &os_error(5),
+            started_at,
+            timeout
+        ));
+        assert!(!should_retry_connect_error(
+            &io::Error::other("unexpected"),
             started_at,
             timeout
         ));
-        assert!(!should_retry_pipe_connect_error(None, started_at, timeout));
     }
 }
@@ -5,7 +5,15 @@ mod updater;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use shared::{
+    backup::{self, BackupError, RestoreSummary},
+    romaji_table::{self, Diagnostic, DiagnosticKind, RomajiPreset, Severity},
+    user_dictionary::{
+        shared_dictionaries::SharedDictionaries,
+        transfer::{self, DictionaryFormat, LineError, LineErrorKind},
+        PartOfSpeech, UserWord,
+    },
     AppConfig, AppConfigLoadResult, ConfigError, ConfigRecovery, ConfigWriteGuard, RomajiRule,
+    UserDictionaryConfig,
 };
 use std::{path::PathBuf, sync::Mutex, time::Duration};
 
@@ -70,6 +78,81 @@ struct UpdateConfigResponse {
     message: Option<String>,
 }
 
+#[derive(Debug, Serialize, Clone)]
+struct ImportUserDictionaryResponse {
+    added: usize,
+    issues: Vec<ImportIssue>,
+}
+
+/// A line of an imported dictionary that was not registered.
+#[derive(Debug, Serialize, Clone)]
+struct ImportIssue {
+    line: usize,
+    message: String,
+}
+
+impl From<&LineError> for ImportIssue {
+    fn from(error: &LineError) -> Self {
+        let message = match &error.kind {
+            LineErrorKind::MissingWord => "読みと単語の列がありません".to_string(),
+            LineErrorKind::EmptyReading => "読みが空です".to_string(),
+            LineErrorKind::EmptyWord => "単語が空です".to_string(),
+            LineErrorKind::UnsupportedPartOfSpeech(name) => {
+                format!("品詞「{name}」には対応していません")
+            }
+            LineErrorKind::UnterminatedQuote => "引用符が閉じていません".to_string(),
+            LineErrorKind::Duplicate { line } => format!("{line} 行目と重複しています"),
+        };
+        Self {
+            line: error.line,
+            message,
+        }
+    }
+}
+
+/// A row of the romaji table the editor should point out.
+#[derive(Debug, Serialize, Clone)]
+struct RomajiTableIssue {
+    line: usize,
+    severity: Severity,
+    message: String,
+}
+
+impl From<&Diagnostic> for RomajiTableIssue {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let message = match &diagnostic.kind {
+            DiagnosticKind::MissingOutput => "入力と出力の列がありません".to_string(),
+            DiagnosticKind::Unreachable => "入力が空のため使われません".to_string(),
+            DiagnosticKind::DuplicateInput { line } => {
+                format!("{line} 行目と入力が重複しています（{line} 行目が使われます）")
+            }
+            DiagnosticKind::RepeatedRow { line } => format!("{line} 行目と同じ行です"),
+            DiagnosticKind::ShadowedByLongerInput { line } => {
+                format!("{line} 行目の入力が続かないときだけ使われます")
+            }
+            DiagnosticKind::NextInputLoop { lines } => {
+                let lines = lines
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("・");
+                format!("次の入力が {lines} 行目の間で無限に繰り返されます")
+            }
+        };
+        Self {
+            line: diagnostic.line,
+            severity: diagnostic.severity(),
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ImportRomajiTableResponse {
+    rows: Vec<RomajiRule>,
+    issues: Vec<RomajiTableIssue>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 struct ResetLearningHistoryResponse {
     reset: bool,
@@ -77,6 +160,25 @@ struct ResetLearningHistoryResponse {
     message: Option<String>,
 }
 
+#[derive(Debug, Serialize, Clone, PartialEq)]
+struct LearnedEntry {
+    reading: String,
+    word: String,
+    count: u32,
+    last_used_unix_ms: i64,
+}
+
+impl From<shared::proto::LearnedEntry> for LearnedEntry {
+    fn from(entry: shared::proto::LearnedEntry) -> Self {
+        Self {
+            reading: entry.reading,
+            word: entry.word,
+            count: entry.count,
+            last_used_unix_ms: entry.last_used_unix_ms,
+        }
+    }
+}
+
 fn notice_from_recovery(recovery: &ConfigRecovery) -> ConfigStartupNotice {
     ConfigStartupNotice {
         kind: "recovered".to_string(),
@@ -172,6 +274,138 @@ fn update_config(
     update_config_impl(&state, base_config, new_config)
 }
 
+#[tauri::command]
+fn import_user_dictionary(
+    state: tauri::State<AppState>,
+    format: DictionaryFormat,
+    contents: Vec<u8>,
+) -> Result<ImportUserDictionaryResponse, String> {
+    let parsed = transfer::parse(format, &contents)
+        .map_err(|_| "ファイルの文字コードを読み取れませんでした。".to_string())?;
+    let mut issues = parsed
+        .errors
+        .iter()
+        .map(ImportIssue::from)
+        .collect::<Vec<_>>();
+    let added = if parsed.words.is_empty() {
+        0
+    } else {
+        let (added, skipped_indexes) =
+            with_user_dictionary(&state, |ipc| ipc.import_user_words(&parsed.words))?;
+        issues.extend(skipped_indexes.into_iter().filter_map(|index| {
+            parsed.words.get(index).map(|word| ImportIssue {
+                line: word.line,
+                message: "すでに登録されています".to_string(),
+            })
+        }));
+        added.len()
+    };
+    issues.sort_by_key(|issue| issue.line);
+
+    Ok(ImportUserDictionaryResponse { added, issues })
+}
+
+#[tauri::command]
+fn export_user_dictionary(
+    state: tauri::State<AppState>,
+    format: DictionaryFormat,
+) -> Result<Vec<u8>, String> {
+    let words = with_user_dictionary(&state, |ipc| ipc.list_user_words())?;
+    Ok(transfer::serialize(format, &words))
+}
+
+/// A dictionary file in the shared dictionary folder.
+#[derive(Debug, Serialize, Clone)]
+struct SharedDictionaryInfo {
+    file_name: String,
+    word_count: usize,
+    skipped_lines: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ListSharedDictionariesResponse {
+    dictionaries: Vec<SharedDictionaryInfo>,
+    /// Why the folder could not be listed.
+    error: Option<String>,
+}
+
+/// Reads the dictionaries in `directory` the way the server will, so the
+/// settings app can show them before the folder is saved.
+#[tauri::command]
+fn list_shared_dictionaries(directory: String) -> ListSharedDictionariesResponse {
+    let mut shared = SharedDictionaries::default();
+    shared.configure(&UserDictionaryConfig {
+        shared_directory: directory,
+        ..UserDictionaryConfig::default()
+    });
+    ListSharedDictionariesResponse {
+        dictionaries: shared
+            .dictionaries()
+            .iter()
+            .map(|dictionary| SharedDictionaryInfo {
+                file_name: dictionary.file_name.clone(),
+                word_count: dictionary.words.len(),
+                skipped_lines: dictionary.skipped_lines,
+                error: dictionary.error.clone(),
+            })
+            .collect(),
+        error: shared.directory_error().map(str::to_string),
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct RestoreBackupResponse {
+    summary: RestoreSummary,
+    server_restarted: bool,
+    message: Option<String>,
+}
+
+fn backup_error_message(error: &BackupError) -> String {
+    eprintln!("Backup request failed: {error}");
+    if error.is_version_compatibility_error() {
+        "このアプリより新しいバージョンで作成されたバックアップのため、復元できません。".to_string()
+    } else if matches!(error, BackupError::InvalidBundle(_)) {
+        "azooKey のバックアップファイルとして読み取れませんでした。".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+#[tauri::command]
+fn create_backup(state: tauri::State<AppState>) -> Result<Vec<u8>, String> {
+    let _update_guard = state.config_update_lock.lock().unwrap();
+    backup::create().map_err(|error| backup_error_message(&error))
+}
+
+#[tauri::command]
+fn restore_backup(
+    state: tauri::State<AppState>,
+    contents: Vec<u8>,
+) -> Result<RestoreBackupResponse, String> {
+    let summary = {
+        let _update_guard = state.config_update_lock.lock().unwrap();
+        backup::restore(&contents).map_err(|error| backup_error_message(&error))?
+    };
+
+    // The server still holds the previous dictionary and learning memory.
+    match restart_server_impl(&state) {
+        Ok(()) => Ok(RestoreBackupResponse {
+            summary,
+            server_restarted: true,
+            message: None,
+        }),
+        Err(error) => {
+            eprintln!("Failed to restart server after restoring a backup: {error}");
+            Ok(RestoreBackupResponse {
+                summary,
+                server_restarted: false,
+                message: Some(error.to_string()),
+            })
+        }
+    }
+}
+
 fn apply_config_delta(current: &mut Value, base: &Value, updated: &Value) {
     if base == updated {
         return;
@@ -331,6 +565,93 @@ fn reset_learning_history_impl(state: &AppState) -> ResetLearningHistoryResponse
     }
 }
 
+#[tauri::command]
+fn list_learned_entries(state: tauri::State<AppState>) -> Result<Vec<LearnedEntry>, String> {
+    let mut ipc = state.ipc.lock().unwrap();
+    let Some(ipc) = ipc.as_mut() else {
+        return Err("IPC service is not initialized".to_string());
+    };
+    let entries = ipc.list_learned_entries().map_err(|error| {
+        eprintln!("Failed to list learned entries: {}", error);
+        error.to_string()
+    })?;
+    Ok(entries.into_iter().map(LearnedEntry::from).collect())
+}
+
+#[tauri::command]
+fn forget_learned_entry(
+    state: tauri::State<AppState>,
+    reading: String,
+    word: String,
+) -> Result<bool, String> {
+    let mut ipc = state.ipc.lock().unwrap();
+    let Some(ipc) = ipc.as_mut() else {
+        return Err("IPC service is not initialized".to_string());
+    };
+    ipc.forget_learned_entry(reading, word).map_err(|error| {
+        eprintln!("Failed to forget learned entry: {}", error);
+        error.to_string()
+    })
+}
+
+/// Runs a user dictionary RPC. The server owns the dictionary, so every
+/// operation fails while it is unreachable.
+fn with_user_dictionary<T>(
+    state: &AppState,
+    operation: impl FnOnce(&mut ipc::IPCService) -> anyhow::Result<T>,
+) -> Result<T, String> {
+    let mut ipc = state.ipc.lock().unwrap();
+    let Some(ipc) = ipc.as_mut() else {
+        return Err("IPC service is not initialized".to_string());
+    };
+    operation(ipc).map_err(|error| {
+        eprintln!("User dictionary request failed: {}", error);
+        match error
+            .downcast_ref::<tonic::Status>()
+            .map(tonic::Status::code)
+        {
+            Some(tonic::Code::AlreadyExists) => {
+                "同じ読みと単語の組み合わせがすでに登録されています。".to_string()
+            }
+            Some(tonic::Code::InvalidArgument) => {
+                "読みと単語の両方を入力してください。".to_string()
+            }
+            _ => error.to_string(),
+        }
+    })
+}
+
+#[tauri::command]
+fn list_user_words(state: tauri::State<AppState>) -> Result<Vec<UserWord>, String> {
+    with_user_dictionary(&state, |ipc| ipc.list_user_words())
+}
+
+#[tauri::command]
+fn search_user_words(
+    state: tauri::State<AppState>,
+    query: String,
+) -> Result<Vec<UserWord>, String> {
+    with_user_dictionary(&state, |ipc| ipc.search_user_words(query))
+}
+
+#[tauri::command]
+fn add_user_word(
+    state: tauri::State<AppState>,
+    reading: String,
+    word: String,
+    part_of_speech: PartOfSpeech,
+    comment: String,
+) -> Result<UserWord, String> {
+    with_user_dictionary(&state, |ipc| {
+        ipc.add_user_word(reading, word, part_of_speech, comment)
+    })
+}
+
+#[tauri::command]
+fn remove_user_word(state: tauri::State<AppState>, id: u64) -> Result<bool, String> {
+    with_user_dictionary(&state, |ipc| ipc.remove_user_word(id))
+}
+
 #[tauri::command]
 fn restart_server(state: tauri::State<AppState>) -> Result<(), String> {
     restart_server_impl(&state).map_err(|error| error.to_string())
@@ -418,6 +739,34 @@ fn get_default_romaji_rows() -> Vec<RomajiRule> {
     shared::get_default_romaji_rows()
 }
 
+#[tauri::command]
+fn get_romaji_preset_rows(preset: RomajiPreset) -> Vec<RomajiRule> {
+    preset.rows()
+}
+
+#[tauri::command]
+fn validate_romaji_table(rows: Vec<RomajiRule>) -> Vec<RomajiTableIssue> {
+    romaji_table::validate(&rows)
+        .iter()
+        .map(RomajiTableIssue::from)
+        .collect()
+}
+
+#[tauri::command]
+fn import_romaji_table(contents: Vec<u8>) -> Result<ImportRomajiTableResponse, String> {
+    let parsed = romaji_table::parse(&contents)
+        .map_err(|_| "ファイルの文字コードを読み取れませんでした。".to_string())?;
+    Ok(ImportRomajiTableResponse {
+        issues: parsed.errors.iter().map(RomajiTableIssue::from).collect(),
+        rows: parsed.rows,
+    })
+}
+
+#[tauri::command]
+fn export_romaji_table(rows: Vec<RomajiRule>) -> Vec<u8> {
+    romaji_table::serialize(&rows).into_bytes()
+}
+
 #[tauri::command]
 async fn check_for_updates() -> Result<updater::UpdateCheckResponse, String> {
     updater::check_for_updates()
@@ -480,13 +829,28 @@ pub fn run() {
             get_config,
             take_config_startup_notice,
             update_config,
+            import_user_dictionary,
+            export_user_dictionary,
+            list_shared_dictionaries,
+            create_backup,
+            restore_backup,
             check_capability,
             get_default_romaji_rows,
+            get_romaji_preset_rows,
+            validate_romaji_table,
+            import_romaji_table,
+            export_romaji_table,
             check_for_updates,
             start_update,
             take_update_install_result,
             restart_server,
-            reset_learning_history
+            reset_learning_history,
+            list_learned_entries,
+            forget_learned_entry,
+            list_user_words,
+            search_user_words,
+            add_user_word,
+            remove_user_word
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -503,6 +867,7 @@ pub(crate) fn test_env_lock() -> std::sync::MutexGuard<'static, ()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared::keymap::KeymapPreset;
     use std::{env, ffi::OsString, fs, io, path::Path, sync::MutexGuard};
 
     fn env_lock() -> MutexGuard<'static, ()> {
@@ -635,16 +1000,16 @@ mod tests {
         current.zenzai.profile = "newer profile".to_string();
         current.write().unwrap();
         let mut stale_update = base.clone();
-        stale_update.shortcuts.eisu_toggle = true;
+        stale_update.keymap.preset = KeymapPreset::Atok;
 
         let result = update_config_impl(&state, base, stale_update).unwrap();
 
         assert!(result.changed);
         assert_eq!(result.config.zenzai.profile, "newer profile");
-        assert!(result.config.shortcuts.eisu_toggle);
+        assert_eq!(result.config.keymap.preset, KeymapPreset::Atok);
         let persisted = AppConfig::read().unwrap();
         assert_eq!(persisted.zenzai.profile, "newer profile");
-        assert!(persisted.shortcuts.eisu_toggle);
+        assert_eq!(persisted.keymap.preset, KeymapPreset::Atok);
     }
 
     #[test]
@@ -1,155 +1,358 @@
-import { useEffect, useRef, useState } from "react";
+import { ChangeEvent, FormEvent, useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
-import { Plus, Save, Trash2 } from "lucide-react";
+import { Download, FolderSync, Plus, Search, Trash2, Upload } from "lucide-react";
 
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
 import { saveConfigWithToast } from "@/lib/config";
+import {
+    Select,
+    SelectContent,
+    SelectItem,
+    SelectTrigger,
+    SelectValue,
+} from "@/components/ui/select";
 
-type DictionaryEntry = {
+type PartOfSpeech =
+    | "noun"
+    | "proper_noun"
+    | "person_name"
+    | "place_name"
+    | "organization";
+
+type UserWord = {
+    id: number;
     reading: string;
     word: string;
+    part_of_speech: PartOfSpeech;
+    comment?: string;
 };
 
-const MAX_ENTRIES = 50;
+const PART_OF_SPEECH_OPTIONS: Array<{ value: PartOfSpeech; label: string }> = [
+    { value: "proper_noun", label: "固有名詞" },
+    { value: "noun", label: "名詞" },
+    { value: "person_name", label: "人名" },
+    { value: "place_name", label: "地名" },
+    { value: "organization", label: "組織名" },
+];
 
-const normalizeDictionaryEntries = (value?: unknown): DictionaryEntry[] => {
-    if (!Array.isArray(value)) {
-        return [];
-    }
+type DictionaryFormat = "mozc" | "ms_ime" | "csv";
 
-    return value
-        .map((entry) => {
-            if (!entry || typeof entry !== "object") {
-                return null;
-            }
-            const record = entry as Record<string, unknown>;
-            if (typeof record.reading !== "string" || typeof record.word !== "string") {
-                return null;
-            }
-            return {
-                reading: record.reading,
-                word: record.word,
-            };
-        })
-        .filter((entry): entry is DictionaryEntry => entry !== null);
+type ImportIssue = {
+    line: number;
+    message: string;
+};
+
+type ImportUserDictionaryResponse = {
+    added: number;
+    issues: ImportIssue[];
+};
+
+const FORMAT_OPTIONS: Array<{ value: DictionaryFormat; label: string; fileName: string }> = [
+    { value: "mozc", label: "Google 日本語入力 / Mozc (TSV)", fileName: "user_dictionary.txt" },
+    { value: "ms_ime", label: "Microsoft IME (テキスト)", fileName: "user_dictionary_msime.txt" },
+    { value: "csv", label: "CSV", fileName: "user_dictionary.csv" },
+];
+
+type LearnedEntry = {
+    reading: string;
+    word: string;
+    count: number;
+    last_used_unix_ms: number;
 };
 
-const trimDictionaryEntries = (entries: DictionaryEntry[]): DictionaryEntry[] =>
-    entries.map((entry) => ({
-        reading: entry.reading.trim(),
-        word: entry.word.trim(),
-    }));
+type DictionarySettings = {
+    enabled: boolean;
+    priority: number;
+};
+
+type SharedDictionaryInfo = {
+    file_name: string;
+    word_count: number;
+    skipped_lines: number;
+    error?: string | null;
+};
+
+type ListSharedDictionariesResponse = {
+    dictionaries: SharedDictionaryInfo[];
+    error?: string | null;
+};
+
+const DEFAULT_DICTIONARY_SETTINGS: DictionarySettings = { enabled: true, priority: 0 };
+
+const normalizeDictionarySettings = (value: any): DictionarySettings => ({
+    enabled: typeof value?.enabled === "boolean" ? value.enabled : true,
+    priority: Number.isInteger(value?.priority) ? value.priority : 0,
+});
+
+const sharedSettingsFor = (
+    shared: Record<string, DictionarySettings>,
+    fileName: string,
+): DictionarySettings => {
+    const key = Object.keys(shared).find(
+        (name) => name.toLowerCase() === fileName.toLowerCase(),
+    );
+    return key ? shared[key] : DEFAULT_DICTIONARY_SETTINGS;
+};
+
+const partOfSpeechLabel = (value: PartOfSpeech) =>
+    PART_OF_SPEECH_OPTIONS.find((option) => option.value === value)?.label ?? value;
+
+const errorMessage = (error: unknown, fallback: string) =>
+    typeof error === "string" && error ? error : fallback;
 
 export const Dictionary = () => {
-    const [entries, setEntries] = useState<DictionaryEntry[]>([]);
+    const [words, setWords] = useState<UserWord[]>([]);
+    const [totalCount, setTotalCount] = useState(0);
+    const [query, setQuery] = useState("");
+    const [reading, setReading] = useState("");
+    const [word, setWord] = useState("");
+    const [partOfSpeech, setPartOfSpeech] = useState<PartOfSpeech>("proper_noun");
+    const [comment, setComment] = useState("");
     const [isLoading, setIsLoading] = useState(true);
-    const [isSaving, setIsSaving] = useState(false);
-    const [pendingFocusIndex, setPendingFocusIndex] = useState<number | null>(null);
-    const readingInputRefs = useRef<Array<HTMLInputElement | null>>([]);
+    const [isAdding, setIsAdding] = useState(false);
+    const readingInputRef = useRef<HTMLInputElement | null>(null);
+    const searchSequence = useRef(0);
+    const [format, setFormat] = useState<DictionaryFormat>("mozc");
+    const [isTransferring, setIsTransferring] = useState(false);
+    const [importIssues, setImportIssues] = useState<ImportIssue[]>([]);
+    const importInputRef = useRef<HTMLInputElement | null>(null);
+    const [learnedEntries, setLearnedEntries] = useState<LearnedEntry[]>([]);
+    const [personalSettings, setPersonalSettings] =
+        useState<DictionarySettings>(DEFAULT_DICTIONARY_SETTINGS);
+    const [sharedDirectory, setSharedDirectory] = useState("");
+    const [sharedDirectoryDraft, setSharedDirectoryDraft] = useState("");
+    const [sharedSettings, setSharedSettings] = useState<Record<string, DictionarySettings>>({});
+    const [sharedDictionaries, setSharedDictionaries] = useState<SharedDictionaryInfo[]>([]);
+    const [sharedDirectoryError, setSharedDirectoryError] = useState<string | null>(null);
+
+    const refresh = async (nextQuery: string) => {
+        const sequence = ++searchSequence.current;
+        try {
+            const [matches, all] = await Promise.all([
+                invoke<UserWord[]>("search_user_words", { query: nextQuery }),
+                invoke<UserWord[]>("list_user_words"),
+            ]);
+            if (sequence !== searchSequence.current) {
+                return;
+            }
+            setWords(matches);
+            setTotalCount(all.length);
+        } catch (error) {
+            toast(errorMessage(error, "ユーザ辞書の読み込みに失敗しました"));
+        } finally {
+            if (sequence === searchSequence.current) {
+                setIsLoading(false);
+            }
+        }
+    };
+
+    useEffect(() => {
+        void refresh(query);
+    }, [query]);
+
+    const refreshLearnedEntries = async () => {
+        try {
+            setLearnedEntries(await invoke<LearnedEntry[]>("list_learned_entries"));
+        } catch (error) {
+            toast(errorMessage(error, "学習履歴の読み込みに失敗しました"));
+        }
+    };
+
+    useEffect(() => {
+        void refreshLearnedEntries();
+    }, []);
+
+    const applyDictionaryConfig = (config: any) => {
+        const userDictionary = config?.user_dictionary ?? {};
+        const shared: Record<string, DictionarySettings> = {};
+        for (const [name, value] of Object.entries(userDictionary.shared ?? {})) {
+            shared[name] = normalizeDictionarySettings(value);
+        }
+        setPersonalSettings(normalizeDictionarySettings(userDictionary.personal));
+        setSharedSettings(shared);
+        setSharedDirectory(userDictionary.shared_directory ?? "");
+    };
 
     useEffect(() => {
         invoke<any>("get_config")
-            .then((data) => {
-                setEntries(
-                    normalizeDictionaryEntries(data.user_dictionary?.entries),
-                );
+            .then((config) => {
+                applyDictionaryConfig(config);
+                setSharedDirectoryDraft(config?.user_dictionary?.shared_directory ?? "");
             })
             .catch(() => {
-                toast("辞書設定の読み込みに失敗しました");
-            })
-            .finally(() => {
-                setIsLoading(false);
+                // Keep defaults if config fetch fails
             });
     }, []);
 
-    useEffect(() => {
-        if (pendingFocusIndex === null || pendingFocusIndex >= entries.length) {
+    const refreshSharedDictionaries = async (directory: string) => {
+        if (!directory.trim()) {
+            setSharedDictionaries([]);
+            setSharedDirectoryError(null);
             return;
         }
+        try {
+            const result = await invoke<ListSharedDictionariesResponse>(
+                "list_shared_dictionaries",
+                { directory },
+            );
+            setSharedDictionaries(result.dictionaries);
+            setSharedDirectoryError(result.error ?? null);
+        } catch (error) {
+            toast(errorMessage(error, "共有辞書の読み込みに失敗しました"));
+        }
+    };
 
-        const rafId = requestAnimationFrame(() => {
-            const input = readingInputRefs.current[pendingFocusIndex];
-            if (input) {
-                input.focus();
-                input.scrollIntoView({
-                    behavior: "auto",
-                    block: "nearest",
-                    inline: "nearest",
-                });
-            }
-            setPendingFocusIndex(null);
-        });
+    useEffect(() => {
+        void refreshSharedDictionaries(sharedDirectory);
+    }, [sharedDirectory]);
 
-        return () => cancelAnimationFrame(rafId);
-    }, [entries.length, pendingFocusIndex]);
-
-    const setEntryValue = (
-        index: number,
-        key: keyof DictionaryEntry,
-        value: string,
-    ) => {
-        setEntries((prev) => {
-            const next = [...prev];
-            next[index] = { ...next[index], [key]: value };
-            return next;
+    const updateDictionaryConfig = async (updater: (userDictionary: any) => void) => {
+        const data = await saveConfigWithToast((config) => {
+            config.user_dictionary = config.user_dictionary ?? {};
+            updater(config.user_dictionary);
         });
+        if (data) {
+            applyDictionaryConfig(data);
+        }
     };
 
-    const addEntry = () => {
-        if (entries.length >= MAX_ENTRIES) {
-            toast(`ユーザ辞書は最大 ${MAX_ENTRIES} 件までです`);
+    const updatePersonalSettings = (next: DictionarySettings) =>
+        updateDictionaryConfig((userDictionary) => {
+            userDictionary.personal = next;
+        });
+
+    const updateSharedSettings = (fileName: string, next: DictionarySettings) =>
+        updateDictionaryConfig((userDictionary) => {
+            const shared = userDictionary.shared ?? {};
+            for (const name of Object.keys(shared)) {
+                if (name.toLowerCase() === fileName.toLowerCase()) {
+                    delete shared[name];
+                }
+            }
+            shared[fileName] = next;
+            userDictionary.shared = shared;
+        });
+
+    const saveSharedDirectory = (event: FormEvent) => {
+        event.preventDefault();
+        const directory = sharedDirectoryDraft.trim();
+        if (directory === sharedDirectory) {
+            void refreshSharedDictionaries(directory);
             return;
         }
-        const nextFocusIndex = entries.length;
-        setEntries((prev) => [...prev, { reading: "", word: "" }]);
-        setPendingFocusIndex(nextFocusIndex);
+        void updateDictionaryConfig((userDictionary) => {
+            userDictionary.shared_directory = directory;
+        });
     };
 
-    const removeEntry = (index: number) => {
-        setEntries((prev) => prev.filter((_, rowIndex) => rowIndex !== index));
+    const parsePriority = (value: string) => {
+        const priority = Number.parseInt(value, 10);
+        return Number.isNaN(priority) ? 0 : priority;
     };
 
-    const saveEntries = async () => {
-        if (isSaving) {
-            return;
+    const forgetLearnedEntry = async (target: LearnedEntry) => {
+        try {
+            await invoke<boolean>("forget_learned_entry", {
+                reading: target.reading,
+                word: target.word,
+            });
+            await refreshLearnedEntries();
+        } catch (error) {
+            toast(errorMessage(error, "学習履歴の削除に失敗しました"));
         }
+    };
 
-        const normalized = trimDictionaryEntries(entries);
-        if (normalized.some((entry) => !entry.reading || !entry.word)) {
+    const addWord = async (event: FormEvent) => {
+        event.preventDefault();
+        if (isAdding) {
+            return;
+        }
+        if (!reading.trim() || !word.trim()) {
             toast("読みと単語の両方を入力してください");
             return;
         }
 
-        if (normalized.length > MAX_ENTRIES) {
-            toast(`ユーザ辞書は最大 ${MAX_ENTRIES} 件までです`);
+        setIsAdding(true);
+        try {
+            const added = await invoke<UserWord>("add_user_word", {
+                reading,
+                word,
+                partOfSpeech,
+                comment,
+            });
+            setReading("");
+            setWord("");
+            setComment("");
+            readingInputRef.current?.focus();
+            toast(`「${added.word}」を登録しました`);
+            await refresh(query);
+        } catch (error) {
+            toast(errorMessage(error, "単語の登録に失敗しました"));
+        } finally {
+            setIsAdding(false);
+        }
+    };
+
+    const removeWord = async (target: UserWord) => {
+        try {
+            await invoke<boolean>("remove_user_word", { id: target.id });
+            await refresh(query);
+        } catch (error) {
+            toast(errorMessage(error, "単語の削除に失敗しました"));
+        }
+    };
+
+    const importDictionary = async (event: ChangeEvent<HTMLInputElement>) => {
+        const file = event.target.files?.[0];
+        event.target.value = "";
+        if (!file || isTransferring) {
             return;
         }
 
-        const unique = new Set(
-            normalized.map((entry) => `${entry.reading}\u0000${entry.word}`),
-        );
-        if (unique.size !== normalized.length) {
-            toast("同じ読み・単語の組み合わせが重複しています");
+        setIsTransferring(true);
+        try {
+            const contents = Array.from(new Uint8Array(await file.arrayBuffer()));
+            const result = await invoke<ImportUserDictionaryResponse>("import_user_dictionary", {
+                format,
+                contents,
+            });
+            setImportIssues(result.issues);
+            toast(
+                result.issues.length > 0
+                    ? `${result.added} 件を取り込みました（${result.issues.length} 行は取り込めませんでした）`
+                    : `${result.added} 件を取り込みました`,
+            );
+            await refresh(query);
+        } catch (error) {
+            toast(errorMessage(error, "辞書の取り込みに失敗しました"));
+        } finally {
+            setIsTransferring(false);
+        }
+    };
+
+    const exportDictionary = async () => {
+        if (isTransferring) {
             return;
         }
 
-        setIsSaving(true);
+        setIsTransferring(true);
         try {
-            const config = await saveConfigWithToast((config) => {
-                config.user_dictionary = config.user_dictionary ?? {};
-                config.user_dictionary.entries = normalized;
-            }, "ユーザ辞書の保存に失敗しました");
-            if (!config) {
-                return;
-            }
-            setEntries(normalized);
-            toast("ユーザ辞書を保存しました");
-        } catch (_error) {
-            toast("ユーザ辞書の保存に失敗しました");
+            const contents = await invoke<number[]>("export_user_dictionary", { format });
+            const url = URL.createObjectURL(new Blob([new Uint8Array(contents)]));
+            const link = document.createElement("a");
+            link.href = url;
+            link.download =
+                FORMAT_OPTIONS.find((option) => option.value === format)?.fileName ??
+                "user_dictionary.txt";
+            link.click();
+            URL.revokeObjectURL(url);
+        } catch (error) {
+            toast(errorMessage(error, "辞書の書き出しに失敗しました"));
         } finally {
-            setIsSaving(false);
+            setIsTransferring(false);
         }
     };
 
@@ -158,28 +361,130 @@ export const Dictionary = () => {
             <section className="space-y-2">
                 <h1 className="text-sm font-bold text-foreground">ユーザ辞書</h1>
                 <p className="text-sm text-muted-foreground">
-                    読みと単語を登録できます（最大 {MAX_ENTRIES} 件）。
+                    読みと単語を登録できます。登録・削除はすぐに変換へ反映されます。
                 </p>
             </section>
 
+            <form className="space-y-3 rounded-md border p-4" onSubmit={addWord}>
+                <div className="grid gap-2 sm:grid-cols-[1fr_1fr_160px]">
+                    <Input
+                        ref={readingInputRef}
+                        value={reading}
+                        placeholder="よみ"
+                        onChange={(event) => setReading(event.target.value)}
+                    />
+                    <Input
+                        value={word}
+                        placeholder="単語"
+                        onChange={(event) => setWord(event.target.value)}
+                    />
+                    <Select
+                        value={partOfSpeech}
+                        onValueChange={(value) => setPartOfSpeech(value as PartOfSpeech)}
+                    >
+                        <SelectTrigger>
+                            <SelectValue placeholder="品詞を選択" />
+                        </SelectTrigger>
+                        <SelectContent>
+                            {PART_OF_SPEECH_OPTIONS.map((option) => (
+                                <SelectItem key={option.value} value={option.value}>
+                                    {option.label}
+                                </SelectItem>
+                            ))}
+                        </SelectContent>
+                    </Select>
+                </div>
+                <div className="flex gap-2">
+                    <Input
+                        value={comment}
+                        placeholder="コメント（任意）"
+                        onChange={(event) => setComment(event.target.value)}
+                    />
+                    <Button type="submit" disabled={isLoading || isAdding}>
+                        <Plus className="h-4 w-4" />
+                        登録
+                    </Button>
+                </div>
+            </form>
+
             <section className="space-y-3 rounded-md border p-4">
                 <div className="flex flex-wrap items-center gap-2">
-                    <p className="text-sm font-medium">登録件数: {entries.length} 件</p>
-                    <div className="ml-auto flex w-full justify-end gap-2 sm:w-auto">
-                        <Button variant="secondary" onClick={addEntry} disabled={entries.length >= MAX_ENTRIES || isLoading}>
-                            <Plus className="h-4 w-4" />
-                            追加
+                    <p className="text-sm font-medium">インポート / エクスポート</p>
+                    <div className="ml-auto flex w-full flex-wrap justify-end gap-2 sm:w-auto">
+                        <Select
+                            value={format}
+                            onValueChange={(value) => setFormat(value as DictionaryFormat)}
+                        >
+                            <SelectTrigger className="w-64">
+                                <SelectValue placeholder="形式を選択" />
+                            </SelectTrigger>
+                            <SelectContent>
+                                {FORMAT_OPTIONS.map((option) => (
+                                    <SelectItem key={option.value} value={option.value}>
+                                        {option.label}
+                                    </SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                        <input
+                            ref={importInputRef}
+                            type="file"
+                            accept=".txt,.tsv,.csv"
+                            className="hidden"
+                            onChange={(event) => void importDictionary(event)}
+                        />
+                        <Button
+                            variant="secondary"
+                            onClick={() => importInputRef.current?.click()}
+                            disabled={isLoading || isTransferring}
+                        >
+                            <Upload className="h-4 w-4" />
+                            インポート
                         </Button>
-                        <Button onClick={saveEntries} disabled={isLoading || isSaving}>
-                            <Save className="h-4 w-4" />
-                            保存
+                        <Button
+                            variant="secondary"
+                            onClick={() => void exportDictionary()}
+                            disabled={isLoading || isTransferring}
+                        >
+                            <Download className="h-4 w-4" />
+                            エクスポート
                         </Button>
                     </div>
                 </div>
 
-                {entries.length === 0 ? (
+                {importIssues.length > 0 && (
+                    <div className="max-h-40 overflow-y-auto rounded-md border bg-muted/30 p-3 text-xs">
+                        <p className="mb-1 font-medium">取り込めなかった行</p>
+                        <ul className="space-y-0.5 text-muted-foreground">
+                            {importIssues.map((issue) => (
+                                <li key={`${issue.line}-${issue.message}`}>
+                                    {issue.line} 行目: {issue.message}
+                                </li>
+                            ))}
+                        </ul>
+                    </div>
+                )}
+            </section>
+
+            <section className="space-y-3 rounded-md border p-4">
+                <div className="flex flex-wrap items-center gap-2">
+                    <p className="text-sm font-medium">登録件数: {totalCount} 件</p>
+                    <div className="relative ml-auto w-full sm:w-64">
+                        <Search className="absolute left-2 top-1/2 h-4 w-4 -translate-y-1/2 text-muted-foreground" />
+                        <Input
+                            className="pl-8"
+                            value={query}
+                            placeholder="読み・単語・コメントで検索"
+                            onChange={(event) => setQuery(event.target.value)}
+                        />
+                    </div>
+                </div>
+
+                {words.length === 0 ? (
                     <p className="text-sm text-muted-foreground">
-                        まだ登録がありません。右上の「追加」から作成してください。
+                        {query
+                            ? "一致する単語はありません。"
+                            : "まだ登録がありません。上のフォームから登録してください。"}
                     </p>
                 ) : (
                     <div className="overflow-x-auto rounded-md border">
@@ -187,45 +492,211 @@ export const Dictionary = () => {
                             <colgroup>
                                 <col />
                                 <col />
+                                <col className="w-24" />
+                                <col />
                                 <col className="w-14" />
                             </colgroup>
                             <thead className="bg-muted/30 text-left text-xs text-muted-foreground">
                                 <tr>
                                     <th className="px-3 py-2 font-medium">読み</th>
                                     <th className="px-3 py-2 font-medium">単語</th>
+                                    <th className="px-3 py-2 font-medium">品詞</th>
+                                    <th className="px-3 py-2 font-medium">コメント</th>
                                     <th className="px-2 py-2 text-center font-medium">操作</th>
                                 </tr>
                             </thead>
                             <tbody>
-                                {entries.map((entry, index) => (
-                                    <tr key={`row-${index}`} className="border-t">
+                                {words.map((entry) => (
+                                    <tr key={entry.id} className="border-t">
+                                        <td className="truncate px-3 py-2">{entry.reading}</td>
+                                        <td className="truncate px-3 py-2">{entry.word}</td>
+                                        <td className="px-3 py-2">
+                                            {partOfSpeechLabel(entry.part_of_speech)}
+                                        </td>
+                                        <td className="truncate px-3 py-2 text-muted-foreground">
+                                            {entry.comment}
+                                        </td>
+                                        <td className="px-2 py-2 text-center">
+                                            <Button
+                                                variant="ghost"
+                                                size="icon"
+                                                onClick={() => void removeWord(entry)}
+                                                aria-label="単語を削除"
+                                            >
+                                                <Trash2 className="h-4 w-4" />
+                                            </Button>
+                                        </td>
+                                    </tr>
+                                ))}
+                            </tbody>
+                        </table>
+                    </div>
+                )}
+            </section>
+
+            <section className="space-y-3 rounded-md border p-4">
+                <div className="space-y-1">
+                    <p className="text-sm font-medium">辞書の優先順位</p>
+                    <p className="text-xs text-muted-foreground">
+                        共有フォルダ内の辞書ファイル（.txt / .tsv / .csv）を読み取り専用の辞書として使います。
+                        ファイルが更新されると自動で読み込み直します。優先度の大きい辞書が先に使われ、同じ優先度ではユーザ辞書が優先されます。
+                    </p>
+                </div>
+
+                <form className="flex gap-2" onSubmit={saveSharedDirectory}>
+                    <Input
+                        value={sharedDirectoryDraft}
+                        placeholder="共有辞書フォルダ（例: \\server\share\azooKey）"
+                        onChange={(event) => setSharedDirectoryDraft(event.target.value)}
+                    />
+                    <Button type="submit" variant="secondary">
+                        <FolderSync className="h-4 w-4" />
+                        読み込み
+                    </Button>
+                </form>
+                {sharedDirectoryError && (
+                    <p className="text-xs text-destructive">
+                        フォルダを読み込めません: {sharedDirectoryError}
+                    </p>
+                )}
+
+                <div className="overflow-x-auto rounded-md border">
+                    <table className="w-full table-fixed text-sm">
+                        <colgroup>
+                            <col />
+                            <col className="w-40" />
+                            <col className="w-24" />
+                            <col className="w-16" />
+                        </colgroup>
+                        <thead className="bg-muted/30 text-left text-xs text-muted-foreground">
+                            <tr>
+                                <th className="px-3 py-2 font-medium">辞書</th>
+                                <th className="px-3 py-2 font-medium">単語数</th>
+                                <th className="px-3 py-2 font-medium">優先度</th>
+                                <th className="px-2 py-2 text-center font-medium">有効</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            <tr className="border-t">
+                                <td className="truncate px-3 py-2">ユーザ辞書</td>
+                                <td className="px-3 py-2">{totalCount}</td>
+                                <td className="px-3 py-2">
+                                    <Input
+                                        type="number"
+                                        value={personalSettings.priority}
+                                        onChange={(event) =>
+                                            void updatePersonalSettings({
+                                                ...personalSettings,
+                                                priority: parsePriority(event.target.value),
+                                            })
+                                        }
+                                    />
+                                </td>
+                                <td className="px-2 py-2 text-center">
+                                    <Switch
+                                        checked={personalSettings.enabled}
+                                        onCheckedChange={(enabled) =>
+                                            void updatePersonalSettings({ ...personalSettings, enabled })
+                                        }
+                                    />
+                                </td>
+                            </tr>
+                            {sharedDictionaries.map((dictionary) => {
+                                const settings = sharedSettingsFor(
+                                    sharedSettings,
+                                    dictionary.file_name,
+                                );
+                                return (
+                                    <tr key={dictionary.file_name} className="border-t">
+                                        <td className="truncate px-3 py-2">
+                                            {dictionary.file_name}
+                                            <span className="ml-1 text-xs text-muted-foreground">
+                                                （読み取り専用）
+                                            </span>
+                                        </td>
+                                        <td className="truncate px-3 py-2 text-muted-foreground">
+                                            {dictionary.error
+                                                ? `読み込めません: ${dictionary.error}`
+                                                : dictionary.skipped_lines > 0
+                                                  ? `${dictionary.word_count}（${dictionary.skipped_lines} 行スキップ）`
+                                                  : dictionary.word_count}
+                                        </td>
                                         <td className="px-3 py-2">
                                             <Input
-                                                ref={(element) => {
-                                                    readingInputRefs.current[index] = element;
-                                                }}
-                                                value={entry.reading}
-                                                placeholder="よみ"
+                                                type="number"
+                                                value={settings.priority}
                                                 onChange={(event) =>
-                                                    setEntryValue(index, "reading", event.target.value)
+                                                    void updateSharedSettings(dictionary.file_name, {
+                                                        ...settings,
+                                                        priority: parsePriority(event.target.value),
+                                                    })
                                                 }
                                             />
                                         </td>
-                                        <td className="px-3 py-2">
-                                            <Input
-                                                value={entry.word}
-                                                placeholder="単語"
-                                                onChange={(event) =>
-                                                    setEntryValue(index, "word", event.target.value)
+                                        <td className="px-2 py-2 text-center">
+                                            <Switch
+                                                checked={settings.enabled}
+                                                onCheckedChange={(enabled) =>
+                                                    void updateSharedSettings(dictionary.file_name, {
+                                                        ...settings,
+                                                        enabled,
+                                                    })
                                                 }
                                             />
                                         </td>
+                                    </tr>
+                                );
+                            })}
+                        </tbody>
+                    </table>
+                </div>
+            </section>
+
+            <section className="space-y-3 rounded-md border p-4">
+                <div className="space-y-1">
+                    <p className="text-sm font-medium">学習した変換: {learnedEntries.length} 件</p>
+                    <p className="text-xs text-muted-foreground">
+                        不要な学習は個別に削除できます。変換中に候補を選んで Ctrl+Delete
+                        を押しても削除できます。
+                    </p>
+                </div>
+
+                {learnedEntries.length === 0 ? (
+                    <p className="text-sm text-muted-foreground">学習した変換はありません。</p>
+                ) : (
+                    <div className="max-h-80 overflow-y-auto rounded-md border">
+                        <table className="w-full table-fixed text-sm">
+                            <colgroup>
+                                <col />
+                                <col />
+                                <col className="w-16" />
+                                <col className="w-40" />
+                                <col className="w-14" />
+                            </colgroup>
+                            <thead className="bg-muted/30 text-left text-xs text-muted-foreground">
+                                <tr>
+                                    <th className="px-3 py-2 font-medium">読み</th>
+                                    <th className="px-3 py-2 font-medium">変換</th>
+                                    <th className="px-3 py-2 font-medium">回数</th>
+                                    <th className="px-3 py-2 font-medium">最終使用</th>
+                                    <th className="px-2 py-2 text-center font-medium">操作</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {learnedEntries.map((entry) => (
+                                    <tr key={`${entry.reading}\u0000${entry.word}`} className="border-t">
+                                        <td className="truncate px-3 py-2">{entry.reading}</td>
+                                        <td className="truncate px-3 py-2">{entry.word}</td>
+                                        <td className="px-3 py-2">{entry.count}</td>
+                                        <td className="truncate px-3 py-2 text-muted-foreground">
+                                            {new Date(entry.last_used_unix_ms).toLocaleString()}
+                                        </td>
                                         <td className="px-2 py-2 text-center">
                                             <Button
                                                 variant="ghost"
                                                 size="icon"
-                                                onClick={() => removeEntry(index)}
-                                                aria-label="行を削除"
+                                                onClick={() => void forgetLearnedEntry(entry)}
+                                                aria-label="学習を削除"
                                             >
                                                 <Trash2 className="h-4 w-4" />
                                             </Button>
@@ -237,7 +708,6 @@ export const Dictionary = () => {
                     </div>
                 )}
             </section>
-
         </div>
     );
 };
@@ -1,8 +1,24 @@
-import { useEffect, useMemo, useRef, useState } from "react";
+import {
+    ChangeEvent,
+    KeyboardEvent,
+    useEffect,
+    useMemo,
+    useRef,
+    useState,
+} from "react";
 import { getVersion } from "@tauri-apps/api/app";
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
-import { Download, FlaskConical, Keyboard, RefreshCcw, Table2, Trash2 } from "lucide-react";
+import {
+    Download,
+    FlaskConical,
+    Keyboard,
+    Plus,
+    RefreshCcw,
+    Table2,
+    Trash2,
+    Upload,
+} from "lucide-react";
 
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
@@ -30,8 +46,70 @@ import { saveConfigWithToast } from "@/lib/config";
 type WidthMode = "half" | "full";
 type LearningMode = "enabled" | "read_only" | "disabled";
 type ReconversionKey = "convert" | "shift_convert" | "space" | "win_slash" | "disabled";
+type KeymapPreset = "ms_ime" | "atok" | "kotoeri";
+type KeymapState = "precomposition" | "composition" | "conversion";
+type KeyCommand =
+    | "pass_through"
+    | "commit"
+    | "commit_first_clause"
+    | "commit_and_next_clause"
+    | "convert"
+    | "cancel"
+    | "backspace"
+    | "delete"
+    | "forget_candidate"
+    | "move_left"
+    | "move_right"
+    | "move_up"
+    | "move_down"
+    | "shrink_clause"
+    | "expand_clause"
+    | "to_hiragana"
+    | "to_katakana"
+    | "to_half_katakana"
+    | "to_full_latin"
+    | "to_half_latin"
+    | "toggle_input_mode"
+    | "input_mode_on"
+    | "input_mode_off"
+    | "input_mode_katakana"
+    | "input_mode_half_katakana"
+    | "input_mode_full_latin"
+    | "reconvert"
+    | "undo_commit"
+    | "next_prediction"
+    | "previous_prediction"
+    | "toggle_incognito";
+
+type KeymapEntry = {
+    state: KeymapState;
+    key: string;
+    command: KeyCommand;
+};
+
+type KeymapValue = {
+    preset: KeymapPreset;
+    overrides: KeymapEntry[];
+};
+
+type AppInputMode = "latin" | "kana" | "full_latin" | "katakana" | "half_katakana";
+
+type AppProfile = {
+    default_input_mode?: AppInputMode;
+    punctuation_style?: string;
+    space_input?: string;
+    learning_mode?: LearningMode;
+    zenzai?: boolean;
+};
+
+type AppProfileRow = {
+    executable: string;
+    profile: AppProfile;
+};
 
 type GeneralConfigState = {
+    conversion_mode: string;
+    input_method: string;
     punctuation_style: string;
     symbol_style: string;
     space_input: string;
@@ -44,6 +122,17 @@ type GeneralConfigState = {
     show_live_conversion_reading: boolean;
     live_conversion_reading_vertical_adjustment: number;
     experimental_typo_correction: boolean;
+    predictive_suggestions: boolean;
+    rewriters: RewritersState;
+};
+
+type RewritersState = {
+    date_time: boolean;
+    number: boolean;
+    calculator: boolean;
+    symbol: boolean;
+    emoji: boolean;
+    code_input: boolean;
 };
 
 type CharacterWidthGroupsState = {
@@ -66,6 +155,26 @@ type RomajiRow = {
     next_input: string;
 };
 
+type RomajiPreset = "google_ime" | "ms_ime" | "azik" | "act";
+
+type RomajiTableIssue = {
+    line: number;
+    severity: "error" | "warning";
+    message: string;
+};
+
+type ImportRomajiTableResponse = {
+    rows: RomajiRow[];
+    issues: RomajiTableIssue[];
+};
+
+const ROMAJI_PRESET_OPTIONS: Array<{ value: RomajiPreset; label: string }> = [
+    { value: "google_ime", label: "Google 日本語入力" },
+    { value: "ms_ime", label: "Microsoft IME" },
+    { value: "azik", label: "AZIK" },
+    { value: "act", label: "ACT (Dvorak)" },
+];
+
 type UpdateCheckResponse = {
     current_version: string;
     latest_version: string;
@@ -81,6 +190,16 @@ type ResetLearningHistoryResponse = {
     message?: string | null;
 };
 
+type RestoreBackupResponse = {
+    summary: {
+        settings: boolean;
+        user_dictionary: boolean;
+        learning_files: number;
+    };
+    server_restarted: boolean;
+    message: string | null;
+};
+
 type UpdateStatus =
     | "idle"
     | "checking"
@@ -90,6 +209,8 @@ type UpdateStatus =
     | "error";
 
 const DEFAULT_GENERAL_CONFIG: GeneralConfigState = {
+    conversion_mode: "live",
+    input_method: "romaji",
     punctuation_style: "touten_kuten",
     symbol_style: "corner_bracket_middle_dot",
     space_input: "always_half",
@@ -102,8 +223,30 @@ const DEFAULT_GENERAL_CONFIG: GeneralConfigState = {
     show_live_conversion_reading: true,
     live_conversion_reading_vertical_adjustment: 4,
     experimental_typo_correction: false,
+    predictive_suggestions: false,
+    rewriters: {
+        date_time: true,
+        number: true,
+        calculator: true,
+        symbol: true,
+        emoji: true,
+        code_input: true,
+    },
 };
 
+const REWRITER_OPTIONS: { key: keyof RewritersState; label: string; description: string }[] = [
+    { key: "date_time", label: "日付・時刻", description: "きょう・いま などから日付や時刻を候補に出します" },
+    { key: "number", label: "数字", description: "12345 から 12,345・一万二千三百四十五 などを候補に出します" },
+    { key: "calculator", label: "計算", description: "1+2*3= のような式の計算結果を候補に出します" },
+    { key: "symbol", label: "記号", description: "ほし・やじるし などから記号を、きごう から記号の一覧を候補に出します" },
+    { key: "emoji", label: "絵文字", description: "わらい・ねこ などから絵文字を候補に出します" },
+    {
+        key: "code_input",
+        label: "文字コード入力",
+        description: "U+2603 (Unicode)、J+3441 (JIS)、S+8E9A (Shift_JIS) から文字を候補に出します",
+    },
+];
+
 const LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_MIN = -12;
 const LIVE_CONVERSION_READING_VERTICAL_ADJUSTMENT_MAX = 12;
 
@@ -157,6 +300,127 @@ const normalizeReconversionKey = (value: unknown): ReconversionKey =>
         ? (value as ReconversionKey)
         : "win_slash";
 
+const KEYMAP_PRESET_OPTIONS: Array<{ value: KeymapPreset; label: string }> = [
+    { value: "ms_ime", label: "MS-IME" },
+    { value: "atok", label: "ATOK" },
+    { value: "kotoeri", label: "ことえり" },
+];
+
+const KEYMAP_STATE_OPTIONS: Array<{ value: KeymapState; label: string }> = [
+    { value: "precomposition", label: "入力前" },
+    { value: "composition", label: "入力中" },
+    { value: "conversion", label: "変換中" },
+];
+
+const KEY_COMMAND_OPTIONS: Array<{ value: KeyCommand; label: string }> = [
+    { value: "pass_through", label: "アプリに渡す" },
+    { value: "commit", label: "確定" },
+    { value: "commit_first_clause", label: "先頭文節を確定" },
+    { value: "commit_and_next_clause", label: "文節を確定して次へ" },
+    { value: "convert", label: "変換" },
+    { value: "cancel", label: "キャンセル" },
+    { value: "backspace", label: "後退" },
+    { value: "delete", label: "削除" },
+    { value: "forget_candidate", label: "候補の学習を削除" },
+    { value: "move_left", label: "左へ移動" },
+    { value: "move_right", label: "右へ移動" },
+    { value: "move_up", label: "前の候補" },
+    { value: "move_down", label: "次の候補" },
+    { value: "shrink_clause", label: "文節を縮める" },
+    { value: "expand_clause", label: "文節を伸ばす" },
+    { value: "to_hiragana", label: "ひらがな" },
+    { value: "to_katakana", label: "全角カタカナ" },
+    { value: "to_half_katakana", label: "半角カタカナ" },
+    { value: "to_full_latin", label: "全角英数" },
+    { value: "to_half_latin", label: "半角英数" },
+    { value: "toggle_input_mode", label: "入力モード切替" },
+    { value: "input_mode_on", label: "ひらがな入力" },
+    { value: "input_mode_off", label: "英数入力" },
+    { value: "input_mode_katakana", label: "全角カタカナ入力" },
+    { value: "input_mode_half_katakana", label: "半角カタカナ入力" },
+    { value: "input_mode_full_latin", label: "全角英数入力" },
+    { value: "reconvert", label: "再変換" },
+    { value: "undo_commit", label: "確定の取り消し" },
+    { value: "next_prediction", label: "次の予測候補" },
+    { value: "previous_prediction", label: "前の予測候補" },
+    { value: "toggle_incognito", label: "シークレットモード切替" },
+];
+
+// KeyboardEvent.code -> key name used by the keymap in settings.json.
+const KEY_CODE_NAMES: Record<string, string> = {
+    Backspace: "Backspace",
+    Tab: "Tab",
+    Enter: "Enter",
+    CapsLock: "Eisu",
+    KanaMode: "Kana",
+    Escape: "Escape",
+    Convert: "Henkan",
+    NonConvert: "Muhenkan",
+    Space: "Space",
+    PageUp: "PageUp",
+    PageDown: "PageDown",
+    End: "End",
+    Home: "Home",
+    ArrowLeft: "Left",
+    ArrowUp: "Up",
+    ArrowRight: "Right",
+    ArrowDown: "Down",
+    Insert: "Insert",
+    Delete: "Delete",
+    Semicolon: "Semicolon",
+    Equal: "Equal",
+    Comma: "Comma",
+    Minus: "Minus",
+    Period: "Period",
+    Slash: "Slash",
+    Backquote: "Backquote",
+    BracketLeft: "BracketLeft",
+    Backslash: "Backslash",
+    BracketRight: "BracketRight",
+    Quote: "Quote",
+};
+
+const keyChordFromEvent = (event: KeyboardEvent<HTMLInputElement>): string | null => {
+    const { code } = event;
+    let key: string | undefined;
+    if (/^Key[A-Z]$/.test(code)) {
+        key = code.slice(3);
+    } else if (/^Digit[0-9]$/.test(code)) {
+        key = code.slice(5);
+    } else if (/^F([1-9]|1[0-9]|2[0-4])$/.test(code)) {
+        key = code;
+    } else {
+        key = KEY_CODE_NAMES[code];
+    }
+    if (!key) {
+        return null;
+    }
+    const modifiers = [
+        event.ctrlKey ? "Ctrl" : null,
+        event.shiftKey ? "Shift" : null,
+        event.altKey ? "Alt" : null,
+    ].filter((modifier): modifier is string => modifier !== null);
+    return [...modifiers, key].join("+");
+};
+
+const normalizeKeymap = (value?: Record<string, unknown>): KeymapValue => {
+    const preset = KEYMAP_PRESET_OPTIONS.some((option) => option.value === value?.preset)
+        ? (value?.preset as KeymapPreset)
+        : "ms_ime";
+    const rawOverrides = value?.overrides;
+    const overrides = Array.isArray(rawOverrides)
+        ? rawOverrides.filter(
+              (entry): entry is KeymapEntry =>
+                  !!entry &&
+                  typeof entry === "object" &&
+                  KEYMAP_STATE_OPTIONS.some((option) => option.value === entry.state) &&
+                  typeof entry.key === "string" &&
+                  KEY_COMMAND_OPTIONS.some((option) => option.value === entry.command),
+          )
+        : [];
+    return { preset, overrides };
+};
+
 const SYMBOL_OPTIONS = [
     { value: "corner_bracket_middle_dot", label: "「」・" },
     { value: "square_bracket_backslash", label: "［］／" },
@@ -169,6 +433,16 @@ const SPACE_OPTIONS = [
     { value: "follow_input_mode", label: "入力モードに従う" },
 ];
 
+const CONVERSION_MODE_OPTIONS = [
+    { value: "live", label: "ライブ変換" },
+    { value: "classic", label: "通常変換" },
+];
+
+const INPUT_METHOD_OPTIONS = [
+    { value: "romaji", label: "ローマ字入力" },
+    { value: "kana", label: "かな入力 (JIS配列)" },
+];
+
 const NUMPAD_OPTIONS = [
     { value: "direct_input", label: "直接入力" },
     { value: "always_half", label: "常に半角" },
@@ -181,6 +455,48 @@ const LEARNING_MODE_OPTIONS: Array<{ value: LearningMode; label: string }> = [
     { value: "disabled", label: "無効" },
 ];
 
+// Profile selects use this value for "keep the base setting".
+const INHERIT = "inherit";
+
+const APP_INPUT_MODE_OPTIONS: Array<{ value: AppInputMode; label: string }> = [
+    { value: "kana", label: "ひらがな" },
+    { value: "katakana", label: "全角カタカナ" },
+    { value: "full_latin", label: "全角英数" },
+    { value: "half_katakana", label: "半角カタカナ" },
+    { value: "latin", label: "半角英数" },
+];
+
+const APP_ZENZAI_OPTIONS = [{ value: "off", label: "無効" }];
+
+const APP_PROFILE_FIELDS: Array<{
+    key: Exclude<keyof AppProfile, "zenzai">;
+    label: string;
+    options: Array<{ value: string; label: string }>;
+}> = [
+    { key: "default_input_mode", label: "入力モード", options: APP_INPUT_MODE_OPTIONS },
+    { key: "punctuation_style", label: "句読点", options: PUNCTUATION_OPTIONS },
+    { key: "space_input", label: "スペース", options: SPACE_OPTIONS },
+    { key: "learning_mode", label: "学習", options: LEARNING_MODE_OPTIONS },
+];
+
+const normalizeAppProfiles = (value: unknown): AppProfileRow[] => {
+    if (!value || typeof value !== "object" || Array.isArray(value)) {
+        return [];
+    }
+    return Object.entries(value as Record<string, any>).map(([executable, raw]) => {
+        const profile: AppProfile = {};
+        for (const field of APP_PROFILE_FIELDS) {
+            if (field.options.some((option) => option.value === raw?.[field.key])) {
+                (profile as Record<string, unknown>)[field.key] = raw[field.key];
+            }
+        }
+        if (typeof raw?.zenzai === "boolean") {
+            profile.zenzai = raw.zenzai;
+        }
+        return { executable, profile };
+    });
+};
+
 const WIDTH_OPTIONS = [
     { value: "half", label: "半角" },
     { value: "full", label: "全角" },
@@ -203,6 +519,9 @@ const WIDTH_ROWS: Array<{
 ];
 
 const normalizeGeneralConfig = (value?: Record<string, unknown>): GeneralConfigState => ({
+    conversion_mode:
+        value?.conversion_mode === "classic" ? "classic" : DEFAULT_GENERAL_CONFIG.conversion_mode,
+    input_method: value?.input_method === "kana" ? "kana" : DEFAULT_GENERAL_CONFIG.input_method,
     punctuation_style:
         typeof value?.punctuation_style === "string"
             ? value.punctuation_style
@@ -257,8 +576,29 @@ const normalizeGeneralConfig = (value?: Record<string, unknown>): GeneralConfigS
         typeof value?.experimental_typo_correction === "boolean"
             ? value.experimental_typo_correction
             : DEFAULT_GENERAL_CONFIG.experimental_typo_correction,
+    predictive_suggestions:
+        typeof value?.predictive_suggestions === "boolean"
+            ? value.predictive_suggestions
+            : DEFAULT_GENERAL_CONFIG.predictive_suggestions,
+    rewriters: normalizeRewriters(value?.rewriters),
 });
 
+const normalizeRewriters = (value?: unknown): RewritersState => {
+    const rewriters = (value ?? {}) as Record<string, unknown>;
+    const flag = (key: keyof RewritersState) =>
+        typeof rewriters[key] === "boolean"
+            ? (rewriters[key] as boolean)
+            : DEFAULT_GENERAL_CONFIG.rewriters[key];
+    return {
+        date_time: flag("date_time"),
+        number: flag("number"),
+        calculator: flag("calculator"),
+        symbol: flag("symbol"),
+        emoji: flag("emoji"),
+        code_input: flag("code_input"),
+    };
+};
+
 const normalizeLearningMode = (value?: unknown): LearningMode => {
     if (value === "enabled" || value === "read_only" || value === "disabled") {
         return value;
@@ -321,21 +661,25 @@ const normalizeRomajiRowsForSave = (rows: RomajiRow[]): RomajiRow[] =>
 
 export const General = () => {
     const [shortcutValue, setShortcutValue] = useState({
-        ctrlSpaceToggle: true,
-        altBackquoteToggle: true,
-        eisuToggle: false,
         reconversionKey: "win_slash" as ReconversionKey,
     });
+    const [keymapValue, setKeymapValue] = useState<KeymapValue>({
+        preset: "ms_ime",
+        overrides: [],
+    });
     const [generalValue, setGeneralValue] = useState<GeneralConfigState>(
         DEFAULT_GENERAL_CONFIG,
     );
     const [learningMode, setLearningMode] = useState<LearningMode>("enabled");
+    const [appProfiles, setAppProfiles] = useState<AppProfileRow[]>([]);
     const [widthGroups, setWidthGroups] =
         useState<CharacterWidthGroupsState>(DEFAULT_WIDTH_GROUPS);
     const [romajiRows, setRomajiRows] = useState<RomajiRow[]>([]);
     const [isRomajiEditorOpen, setIsRomajiEditorOpen] = useState(false);
     const [romajiDraftRows, setRomajiDraftRows] = useState<RomajiRow[]>([]);
     const [defaultRomajiRows, setDefaultRomajiRows] = useState<RomajiRow[]>([]);
+    const [romajiPreset, setRomajiPreset] = useState<RomajiPreset>("google_ime");
+    const [romajiIssues, setRomajiIssues] = useState<RomajiTableIssue[]>([]);
     const [appVersion, setAppVersion] = useState<string | null>(null);
     const [updateStatus, setUpdateStatus] = useState<UpdateStatus>("idle");
     const [updateCheck, setUpdateCheck] = useState<UpdateCheckResponse | null>(null);
@@ -343,7 +687,9 @@ export const General = () => {
     const [pendingFocusNewRow, setPendingFocusNewRow] = useState(false);
     const didCheckUpdatesOnStartup = useRef(false);
     const romajiEditorScrollRef = useRef<HTMLDivElement | null>(null);
+    const backupInputRef = useRef<HTMLInputElement | null>(null);
     const romajiInputRefs = useRef<Array<HTMLInputElement | null>>([]);
+    const romajiImportInputRef = useRef<HTMLInputElement | null>(null);
     const liveConversionReadingAdjustmentSaveRef = useRef<{
         saving: boolean;
         pendingValue: number | null;
@@ -354,14 +700,13 @@ export const General = () => {
             .then((data) => {
                 const shortcuts = data.shortcuts ?? {};
                 setShortcutValue({
-                    ctrlSpaceToggle: shortcuts.ctrl_space_toggle ?? true,
-                    altBackquoteToggle: shortcuts.alt_backquote_toggle ?? true,
-                    eisuToggle: shortcuts.eisu_toggle ?? false,
                     reconversionKey: normalizeReconversionKey(shortcuts.reconversion_key),
                 });
+                setKeymapValue(normalizeKeymap(data.keymap));
 
                 setGeneralValue(normalizeGeneralConfig(data.general));
                 setLearningMode(normalizeLearningMode(data.learning?.mode));
+                setAppProfiles(normalizeAppProfiles(data.app_profiles));
                 setWidthGroups(normalizeWidthGroups(data.character_width?.groups));
                 setRomajiRows(normalizeRomajiRows(data.romaji_table?.rows));
             })
@@ -389,6 +734,42 @@ export const General = () => {
         }
     }, []);
 
+    useEffect(() => {
+        if (!isRomajiEditorOpen) {
+            setRomajiIssues([]);
+            return;
+        }
+
+        let cancelled = false;
+        const rows = romajiDraftRows.map((row) => ({
+            input: row.input.trim(),
+            output: row.output.trim(),
+            next_input: row.next_input.trim(),
+        }));
+        invoke<RomajiTableIssue[]>("validate_romaji_table", { rows })
+            .then((issues) => {
+                if (!cancelled) {
+                    setRomajiIssues(issues);
+                }
+            })
+            .catch(() => {
+                if (!cancelled) {
+                    setRomajiIssues([]);
+                }
+            });
+        return () => {
+            cancelled = true;
+        };
+    }, [isRomajiEditorOpen, romajiDraftRows]);
+
+    const romajiIssuesByLine = useMemo(() => {
+        const issues = new Map<number, RomajiTableIssue[]>();
+        for (const issue of romajiIssues) {
+            issues.set(issue.line, [...(issues.get(issue.line) ?? []), issue]);
+        }
+        return issues;
+    }, [romajiIssues]);
+
     useEffect(() => {
         if (!isRomajiEditorOpen || !pendingFocusNewRow || romajiDraftRows.length === 0) {
             return;
@@ -493,42 +874,85 @@ export const General = () => {
         return null;
     })();
 
-    const handleCtrlSpaceToggle = async () => {
-        const nextValue = !shortcutValue.ctrlSpaceToggle;
+    const saveKeymap = async (nextValue: KeymapValue) => {
         const data = await updateConfig((config) => {
-            config.shortcuts = config.shortcuts ?? {};
-            config.shortcuts.ctrl_space_toggle = nextValue;
+            config.keymap = nextValue;
         });
 
         if (data) {
-            setShortcutValue((prev) => ({ ...prev, ctrlSpaceToggle: nextValue }));
+            setKeymapValue(nextValue);
         }
     };
 
-    const handleAltBackquoteToggle = async () => {
-        const nextValue = !shortcutValue.altBackquoteToggle;
-        const data = await updateConfig((config) => {
-            config.shortcuts = config.shortcuts ?? {};
-            config.shortcuts.alt_backquote_toggle = nextValue;
+    const handleKeymapPreset = (preset: KeymapPreset) =>
+        void saveKeymap({ ...keymapValue, preset });
+
+    const updateKeymapOverride = (index: number, patch: Partial<KeymapEntry>) =>
+        void saveKeymap({
+            ...keymapValue,
+            overrides: keymapValue.overrides.map((entry, i) =>
+                i === index ? { ...entry, ...patch } : entry,
+            ),
         });
 
-        if (data) {
-            setShortcutValue((prev) => ({ ...prev, altBackquoteToggle: nextValue }));
+    const addKeymapOverride = () =>
+        void saveKeymap({
+            ...keymapValue,
+            overrides: [
+                ...keymapValue.overrides,
+                { state: "composition", key: "Ctrl+K", command: "pass_through" },
+            ],
+        });
+
+    const removeKeymapOverride = (index: number) =>
+        void saveKeymap({
+            ...keymapValue,
+            overrides: keymapValue.overrides.filter((_, i) => i !== index),
+        });
+
+    const handleKeymapKeyDown = (index: number, event: KeyboardEvent<HTMLInputElement>) => {
+        if (event.key === "Tab" && !event.ctrlKey && !event.altKey) {
+            return;
+        }
+        event.preventDefault();
+        const key = keyChordFromEvent(event);
+        if (key) {
+            updateKeymapOverride(index, { key });
         }
     };
 
-    const handleEisuToggle = async () => {
-        const nextValue = !shortcutValue.eisuToggle;
+    const saveAppProfiles = async (nextRows: AppProfileRow[]) => {
         const data = await updateConfig((config) => {
-            config.shortcuts = config.shortcuts ?? {};
-            config.shortcuts.eisu_toggle = nextValue;
+            config.app_profiles = Object.fromEntries(
+                nextRows
+                    .filter((row) => row.executable.trim().length > 0)
+                    .map((row) => [row.executable.trim(), row.profile]),
+            );
         });
 
         if (data) {
-            setShortcutValue((prev) => ({ ...prev, eisuToggle: nextValue }));
+            setAppProfiles(nextRows);
         }
     };
 
+    const updateAppProfile = (index: number, patch: Partial<AppProfile>) =>
+        void saveAppProfiles(
+            appProfiles.map((row, i) =>
+                i === index ? { ...row, profile: { ...row.profile, ...patch } } : row,
+            ),
+        );
+
+    const renameAppProfile = (index: number, executable: string) =>
+        setAppProfiles((prev) =>
+            prev.map((row, i) => (i === index ? { ...row, executable } : row)),
+        );
+
+    const addAppProfile = () =>
+        setAppProfiles((prev) => [...prev, { executable: "", profile: {} }]);
+
+    const removeAppProfile = (index: number) =>
+        void saveAppProfiles(appProfiles.filter((_, i) => i !== index));
+
     const handleReconversionKey = async (nextValue: ReconversionKey) => {
         const data = await updateConfig((config) => {
             config.shortcuts = config.shortcuts ?? {};
@@ -551,6 +975,8 @@ export const General = () => {
             | "show_live_conversion_reading"
             | "live_conversion_reading_vertical_adjustment"
             | "experimental_typo_correction"
+            | "predictive_suggestions"
+            | "rewriters"
         >,
         nextValue: string,
     ) => {
@@ -572,7 +998,8 @@ export const General = () => {
             | "punctuation_commit_question"
             | "show_candidate_window_after_space"
             | "show_live_conversion_reading"
-            | "experimental_typo_correction",
+            | "experimental_typo_correction"
+            | "predictive_suggestions",
         nextValue: boolean,
     ) => {
         const data = await updateConfig((config) => {
@@ -585,6 +1012,18 @@ export const General = () => {
         }
     };
 
+    const updateRewriter = async (key: keyof RewritersState, nextValue: boolean) => {
+        const data = await updateConfig((config) => {
+            config.general = config.general ?? {};
+            config.general.rewriters = config.general.rewriters ?? {};
+            config.general.rewriters[key] = nextValue;
+        });
+
+        if (data) {
+            setGeneralValue(normalizeGeneralConfig(data.general));
+        }
+    };
+
     const updateCandidateWindowDelay = async (nextValue: boolean) => {
         await updateGeneralBooleanConfig("show_candidate_window_after_space", nextValue);
     };
@@ -660,6 +1099,52 @@ export const General = () => {
         }
     };
 
+    const createBackup = async () => {
+        try {
+            const contents = await invoke<number[]>("create_backup");
+            const url = URL.createObjectURL(new Blob([new Uint8Array(contents)]));
+            const link = document.createElement("a");
+            const date = new Date();
+            const stamp = [date.getFullYear(), date.getMonth() + 1, date.getDate()]
+                .map((part) => String(part).padStart(2, "0"))
+                .join("");
+            link.href = url;
+            link.download = `azookey-backup-${stamp}.azkbackup`;
+            link.click();
+            URL.revokeObjectURL(url);
+        } catch (error) {
+            toast("バックアップを作成できませんでした", {
+                description: String(error),
+            });
+        }
+    };
+
+    const restoreBackup = async (event: ChangeEvent<HTMLInputElement>) => {
+        const file = event.target.files?.[0];
+        event.target.value = "";
+        if (!file) {
+            return;
+        }
+
+        try {
+            const contents = Array.from(new Uint8Array(await file.arrayBuffer()));
+            const result = await invoke<RestoreBackupResponse>("restore_backup", { contents });
+            if (result.server_restarted) {
+                toast("バックアップから復元しました");
+            } else {
+                toast("復元しましたが、サーバーを再起動できませんでした", {
+                    description: result.message ?? undefined,
+                });
+            }
+            // Every page holds settings read before the restore.
+            window.setTimeout(() => window.location.reload(), 1000);
+        } catch (error) {
+            toast("バックアップから復元できませんでした", {
+                description: String(error),
+            });
+        }
+    };
+
     const updateWidthGroup = async (
         key: keyof CharacterWidthGroupsState,
         nextValue: WidthMode,
@@ -734,6 +1219,70 @@ export const General = () => {
         toast("初期テーブルに戻しました（保存で反映されます）");
     };
 
+    const loadRomajiPreset = async () => {
+        try {
+            const rows = normalizeRomajiRows(
+                await invoke<any>("get_romaji_preset_rows", { preset: romajiPreset }),
+            );
+            setRomajiDraftRows(rows);
+            const label =
+                ROMAJI_PRESET_OPTIONS.find((option) => option.value === romajiPreset)?.label ??
+                romajiPreset;
+            toast(`${label} のテーブルを読み込みました（保存で反映されます）`);
+        } catch (_error) {
+            toast("プリセットの読み込みに失敗しました");
+        }
+    };
+
+    const importRomajiTable = async (event: ChangeEvent<HTMLInputElement>) => {
+        const file = event.target.files?.[0];
+        event.target.value = "";
+        if (!file) {
+            return;
+        }
+
+        try {
+            const contents = Array.from(new Uint8Array(await file.arrayBuffer()));
+            const result = await invoke<ImportRomajiTableResponse>("import_romaji_table", {
+                contents,
+            });
+            setRomajiDraftRows(normalizeRomajiRows(result.rows));
+            toast(
+                result.issues.length > 0
+                    ? `${result.rows.length} 行を読み込みました（${result.issues.length} 行は読み込めませんでした）`
+                    : `${result.rows.length} 行を読み込みました（保存で反映されます）`,
+                {
+                    description: result.issues
+                        .slice(0, 3)
+                        .map((issue) => `${issue.line} 行目: ${issue.message}`)
+                        .join("\n") || undefined,
+                },
+            );
+        } catch (error) {
+            toast("ローマ字テーブルを読み込めませんでした", {
+                description: String(error),
+            });
+        }
+    };
+
+    const exportRomajiTable = async () => {
+        try {
+            const contents = await invoke<number[]>("export_romaji_table", {
+                rows: normalizeRomajiRowsForSave(romajiDraftRows),
+            });
+            const url = URL.createObjectURL(new Blob([new Uint8Array(contents)]));
+            const link = document.createElement("a");
+            link.href = url;
+            link.download = "romaji_table.txt";
+            link.click();
+            URL.revokeObjectURL(url);
+        } catch (error) {
+            toast("ローマ字テーブルを書き出せませんでした", {
+                description: String(error),
+            });
+        }
+    };
+
     const saveRomajiTable = async () => {
         const normalizedRows = normalizeRomajiRowsForSave(romajiDraftRows);
 
@@ -798,6 +1347,48 @@ export const General = () => {
                 <section className="space-y-3">
                     <h1 className="text-sm font-bold text-foreground">基本設定</h1>
                     <div className="space-y-3 rounded-md border p-4">
+                        <div className="grid grid-cols-[1fr_220px] items-center gap-4">
+                            <p className="text-sm font-medium">変換方式</p>
+                            <div className="flex justify-end">
+                                <Select
+                                    value={generalValue.conversion_mode}
+                                    onValueChange={(value) => void updateGeneralConfig("conversion_mode", value)}
+                                >
+                                    <SelectTrigger>
+                                        <SelectValue placeholder="変換方式を選択" />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        {CONVERSION_MODE_OPTIONS.map((option) => (
+                                            <SelectItem key={option.value} value={option.value}>
+                                                {option.label}
+                                            </SelectItem>
+                                        ))}
+                                    </SelectContent>
+                                </Select>
+                            </div>
+                        </div>
+
+                        <div className="grid grid-cols-[1fr_220px] items-center gap-4">
+                            <p className="text-sm font-medium">入力方式</p>
+                            <div className="flex justify-end">
+                                <Select
+                                    value={generalValue.input_method}
+                                    onValueChange={(value) => void updateGeneralConfig("input_method", value)}
+                                >
+                                    <SelectTrigger>
+                                        <SelectValue placeholder="入力方式を選択" />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        {INPUT_METHOD_OPTIONS.map((option) => (
+                                            <SelectItem key={option.value} value={option.value}>
+                                                {option.label}
+                                            </SelectItem>
+                                        ))}
+                                    </SelectContent>
+                                </Select>
+                            </div>
+                        </div>
+
                         <div className="grid grid-cols-[1fr_220px] items-center gap-4">
                             <p className="text-sm font-medium">句読点</p>
                             <div className="flex justify-end">
@@ -916,6 +1507,21 @@ export const General = () => {
                             />
                         </div>
 
+                        <div className="flex items-center gap-4">
+                            <div className="flex-1 space-y-1">
+                                <p className="text-sm font-medium leading-none">予測変換を表示</p>
+                                <p className="text-xs text-muted-foreground">
+                                    入力中の読みに続く候補を表示し、Tab / Shift+Tab で選択します
+                                </p>
+                            </div>
+                            <Switch
+                                checked={generalValue.predictive_suggestions}
+                                onCheckedChange={(value) =>
+                                    void updateGeneralBooleanConfig("predictive_suggestions", value)
+                                }
+                            />
+                        </div>
+
                         <div className="flex items-center gap-4">
                             <div className="flex-1 space-y-1">
                                 <p className="text-sm font-medium leading-none">ライブ変換中の読みを表示</p>
@@ -1061,6 +1667,68 @@ export const General = () => {
                     </div>
                 </section>
 
+                <section className="space-y-3">
+                    <h1 className="text-sm font-bold text-foreground">バックアップ</h1>
+                    <div className="space-y-3 rounded-md border p-4">
+                        <div className="flex items-center gap-4">
+                            <div className="flex-1 space-y-1">
+                                <p className="text-sm font-medium leading-none">
+                                    バックアップを作成
+                                </p>
+                                <p className="text-xs text-muted-foreground">
+                                    設定・ユーザ辞書・変換学習を1つのファイルに保存します
+                                </p>
+                            </div>
+                            <Button variant="outline" onClick={() => void createBackup()}>
+                                <Download />
+                                保存
+                            </Button>
+                        </div>
+
+                        <div className="flex items-center gap-4 border-t pt-3">
+                            <div className="flex-1 space-y-1">
+                                <p className="text-sm font-medium leading-none">
+                                    バックアップから復元
+                                </p>
+                                <p className="text-xs text-muted-foreground">
+                                    現在の設定・ユーザ辞書・変換学習を置き換え、サーバーを再起動します
+                                </p>
+                            </div>
+                            <input
+                                ref={backupInputRef}
+                                type="file"
+                                accept=".azkbackup"
+                                className="hidden"
+                                onChange={(event) => void restoreBackup(event)}
+                            />
+                            <AlertDialog>
+                                <AlertDialogTrigger asChild>
+                                    <Button variant="outline">
+                                        <Upload />
+                                        復元
+                                    </Button>
+                                </AlertDialogTrigger>
+                                <AlertDialogContent>
+                                    <AlertDialogHeader>
+                                        <AlertDialogTitle>バックアップから復元</AlertDialogTitle>
+                                        <AlertDialogDescription>
+                                            現在の設定・ユーザ辞書・変換学習はバックアップの内容で置き換えられます。
+                                        </AlertDialogDescription>
+                                    </AlertDialogHeader>
+                                    <AlertDialogFooter>
+                                        <AlertDialogCancel>キャンセル</AlertDialogCancel>
+                                        <AlertDialogAction
+                                            onClick={() => backupInputRef.current?.click()}
+                                        >
+                                            ファイルを選択
+                                        </AlertDialogAction>
+                                    </AlertDialogFooter>
+                                </AlertDialogContent>
+                            </AlertDialog>
+                        </div>
+                    </div>
+                </section>
+
                 <section className="space-y-3">
                     <h1 className="text-sm font-bold text-foreground">句読点確定</h1>
                     <div className="space-y-3 rounded-md border p-4">
@@ -1122,6 +1790,24 @@ export const General = () => {
                     </div>
                 </section>
 
+                <section className="space-y-3">
+                    <h1 className="text-sm font-bold text-foreground">追加の変換候補</h1>
+                    <div className="space-y-3 rounded-md border p-4">
+                        {REWRITER_OPTIONS.map((option) => (
+                            <div key={option.key} className="flex items-center gap-4">
+                                <div className="flex-1 space-y-1">
+                                    <p className="text-sm font-medium leading-none">{option.label}</p>
+                                    <p className="text-xs text-muted-foreground">{option.description}</p>
+                                </div>
+                                <Switch
+                                    checked={generalValue.rewriters[option.key]}
+                                    onCheckedChange={(value) => void updateRewriter(option.key, value)}
+                                />
+                            </div>
+                        ))}
+                    </div>
+                </section>
+
                 <section className="space-y-3">
                     <h1 className="text-sm font-bold text-foreground">キー設定</h1>
                     <div className="space-y-3 rounded-md border p-4">
@@ -1188,37 +1874,230 @@ export const General = () => {
                 </section>
 
                 <section className="space-y-3">
-                    <h1 className="text-sm font-bold text-foreground">入力モード切替ショートカット</h1>
+                    <h1 className="text-sm font-bold text-foreground">キー設定</h1>
                     <div className="divide-y rounded-md border">
                         <div className="flex items-center gap-4 p-4">
                             <Keyboard className="h-4 w-4 shrink-0" />
                             <div className="flex-1 space-y-1">
-                                <p className="text-sm font-medium leading-none">Ctrl + Space を有効化</p>
+                                <p className="text-sm font-medium leading-none">キー配列</p>
                                 <p className="text-xs text-muted-foreground">
-                                    英数/かな切り替えのショートカットとして Ctrl + Space を使用します
+                                    各キーの動作をほかの IME に合わせます
                                 </p>
                             </div>
-                            <Switch checked={shortcutValue.ctrlSpaceToggle} onCheckedChange={handleCtrlSpaceToggle} />
+                            <div className="w-[220px]">
+                                <Select
+                                    value={keymapValue.preset}
+                                    onValueChange={(value: KeymapPreset) => handleKeymapPreset(value)}
+                                >
+                                    <SelectTrigger>
+                                        <SelectValue placeholder="キー配列を選択" />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        {KEYMAP_PRESET_OPTIONS.map((option) => (
+                                            <SelectItem key={option.value} value={option.value}>
+                                                {option.label}
+                                            </SelectItem>
+                                        ))}
+                                    </SelectContent>
+                                </Select>
+                            </div>
                         </div>
-                        <div className="flex items-center gap-4 p-4">
-                            <Keyboard className="h-4 w-4 shrink-0" />
-                            <div className="flex-1 space-y-1">
-                                <p className="text-sm font-medium leading-none">Alt + ` を有効化</p>
-                                <p className="text-xs text-muted-foreground">
-                                    英数/かな切り替えのショートカットとして Alt + ` を使用します
-                                </p>
+                        <div className="space-y-3 p-4">
+                            <div className="flex items-center gap-4">
+                                <div className="flex-1 space-y-1">
+                                    <p className="text-sm font-medium leading-none">個別の割り当て</p>
+                                    <p className="text-xs text-muted-foreground">
+                                        キー欄を選んで割り当てたいキーを押してください。キー配列の設定より優先されます
+                                    </p>
+                                </div>
+                                <Button variant="outline" size="sm" onClick={addKeymapOverride}>
+                                    <Plus className="h-4 w-4" />
+                                    追加
+                                </Button>
                             </div>
-                            <Switch checked={shortcutValue.altBackquoteToggle} onCheckedChange={handleAltBackquoteToggle} />
+                            {keymapValue.overrides.length > 0 && (
+                                <table className="w-full text-sm">
+                                    <thead>
+                                        <tr className="text-left text-xs text-muted-foreground">
+                                            <th className="pb-2 font-medium">状態</th>
+                                            <th className="pb-2 font-medium">キー</th>
+                                            <th className="pb-2 font-medium">動作</th>
+                                            <th className="pb-2" />
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {keymapValue.overrides.map((entry, index) => (
+                                            <tr key={index}>
+                                                <td className="py-1 pr-2">
+                                                    <Select
+                                                        value={entry.state}
+                                                        onValueChange={(value: KeymapState) =>
+                                                            updateKeymapOverride(index, { state: value })
+                                                        }
+                                                    >
+                                                        <SelectTrigger className="w-28">
+                                                            <SelectValue />
+                                                        </SelectTrigger>
+                                                        <SelectContent>
+                                                            {KEYMAP_STATE_OPTIONS.map((option) => (
+                                                                <SelectItem key={option.value} value={option.value}>
+                                                                    {option.label}
+                                                                </SelectItem>
+                                                            ))}
+                                                        </SelectContent>
+                                                    </Select>
+                                                </td>
+                                                <td className="py-1 pr-2">
+                                                    <Input
+                                                        aria-label="キー"
+                                                        readOnly
+                                                        value={entry.key}
+                                                        onKeyDown={(event) => handleKeymapKeyDown(index, event)}
+                                                    />
+                                                </td>
+                                                <td className="py-1 pr-2">
+                                                    <Select
+                                                        value={entry.command}
+                                                        onValueChange={(value: KeyCommand) =>
+                                                            updateKeymapOverride(index, { command: value })
+                                                        }
+                                                    >
+                                                        <SelectTrigger className="w-40">
+                                                            <SelectValue />
+                                                        </SelectTrigger>
+                                                        <SelectContent>
+                                                            {KEY_COMMAND_OPTIONS.map((option) => (
+                                                                <SelectItem key={option.value} value={option.value}>
+                                                                    {option.label}
+                                                                </SelectItem>
+                                                            ))}
+                                                        </SelectContent>
+                                                    </Select>
+                                                </td>
+                                                <td className="py-1 text-right">
+                                                    <Button
+                                                        variant="ghost"
+                                                        size="icon"
+                                                        aria-label="割り当てを削除"
+                                                        onClick={() => removeKeymapOverride(index)}
+                                                    >
+                                                        <Trash2 className="h-4 w-4" />
+                                                    </Button>
+                                                </td>
+                                            </tr>
+                                        ))}
+                                    </tbody>
+                                </table>
+                            )}
                         </div>
-                        <div className="flex items-center gap-4 p-4">
-                            <Keyboard className="h-4 w-4 shrink-0" />
-                            <div className="flex-1 space-y-1">
-                                <p className="text-sm font-medium leading-none">英数 (CapsLock) を有効化</p>
-                                <p className="text-xs text-muted-foreground">
-                                    英数/かな切り替えのショートカットとして英数 (CapsLock) を使用します
-                                </p>
+                    </div>
+                </section>
+
+                <section className="space-y-3">
+                    <h1 className="text-sm font-bold text-foreground">アプリごとの設定</h1>
+                    <div className="rounded-md border">
+                        <div className="space-y-3 p-4">
+                            <div className="flex items-center gap-4">
+                                <div className="flex-1 space-y-1">
+                                    <p className="text-sm font-medium leading-none">アプリの上書き設定</p>
+                                    <p className="text-xs text-muted-foreground">
+                                        実行ファイル名 (例: WindowsTerminal.exe) ごとに設定を上書きします。入力モードはウィンドウごとに記憶され、初めて開いたウィンドウでここでの設定が使われます
+                                    </p>
+                                </div>
+                                <Button variant="outline" size="sm" onClick={addAppProfile}>
+                                    <Plus className="h-4 w-4" />
+                                    追加
+                                </Button>
                             </div>
-                            <Switch checked={shortcutValue.eisuToggle} onCheckedChange={handleEisuToggle} />
+                            {appProfiles.length > 0 && (
+                                <table className="w-full text-sm">
+                                    <thead>
+                                        <tr className="text-left text-xs text-muted-foreground">
+                                            <th className="pb-2 font-medium">実行ファイル</th>
+                                            {APP_PROFILE_FIELDS.map((field) => (
+                                                <th key={field.key} className="pb-2 font-medium">
+                                                    {field.label}
+                                                </th>
+                                            ))}
+                                            <th className="pb-2 font-medium">Zenzai</th>
+                                            <th className="pb-2" />
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {appProfiles.map((row, index) => (
+                                            <tr key={index}>
+                                                <td className="py-1 pr-2">
+                                                    <Input
+                                                        aria-label="実行ファイル名"
+                                                        placeholder="app.exe"
+                                                        value={row.executable}
+                                                        onChange={(event) =>
+                                                            renameAppProfile(index, event.target.value)
+                                                        }
+                                                        onBlur={() => void saveAppProfiles(appProfiles)}
+                                                    />
+                                                </td>
+                                                {APP_PROFILE_FIELDS.map((field) => (
+                                                    <td key={field.key} className="py-1 pr-2">
+                                                        <Select
+                                                            value={row.profile[field.key] ?? INHERIT}
+                                                            onValueChange={(value) =>
+                                                                updateAppProfile(index, {
+                                                                    [field.key]: value === INHERIT ? undefined : value,
+                                                                } as Partial<AppProfile>)
+                                                            }
+                                                        >
+                                                            <SelectTrigger className="w-32">
+                                                                <SelectValue />
+                                                            </SelectTrigger>
+                                                            <SelectContent>
+                                                                <SelectItem value={INHERIT}>変更しない</SelectItem>
+                                                                {field.options.map((option) => (
+                                                                    <SelectItem key={option.value} value={option.value}>
+                                                                        {option.label}
+                                                                    </SelectItem>
+                                                                ))}
+                                                            </SelectContent>
+                                                        </Select>
+                                                    </td>
+                                                ))}
+                                                <td className="py-1 pr-2">
+                                                    <Select
+                                                        value={row.profile.zenzai === false ? "off" : INHERIT}
+                                                        onValueChange={(value) =>
+                                                            updateAppProfile(index, {
+                                                                zenzai: value === INHERIT ? undefined : false,
+                                                            })
+                                                        }
+                                                    >
+                                                        <SelectTrigger className="w-28">
+                                                            <SelectValue />
+                                                        </SelectTrigger>
+                                                        <SelectContent>
+                                                            <SelectItem value={INHERIT}>変更しない</SelectItem>
+                                                            {APP_ZENZAI_OPTIONS.map((option) => (
+                                                                <SelectItem key={option.value} value={option.value}>
+                                                                    {option.label}
+                                                                </SelectItem>
+                                                            ))}
+                                                        </SelectContent>
+                                                    </Select>
+                                                </td>
+                                                <td className="py-1 text-right">
+                                                    <Button
+                                                        variant="ghost"
+                                                        size="icon"
+                                                        aria-label="アプリの設定を削除"
+                                                        onClick={() => removeAppProfile(index)}
+                                                    >
+                                                        <Trash2 className="h-4 w-4" />
+                                                    </Button>
+                                                </td>
+                                            </tr>
+                                        ))}
+                                    </tbody>
+                                </table>
+                            )}
                         </div>
                     </div>
                 </section>
@@ -1282,7 +2161,23 @@ export const General = () => {
                                 <tbody>
                                     {romajiDraftRows.map((row, index) => (
                                         <tr key={`row-${index}`} className="border-t">
-                                            <td className="px-2 py-2 text-xs text-muted-foreground">{index + 1}</td>
+                                            <td
+                                                className={`px-2 py-2 text-xs ${
+                                                    romajiIssuesByLine
+                                                        .get(index + 1)
+                                                        ?.some((issue) => issue.severity === "error")
+                                                        ? "font-bold text-destructive"
+                                                        : romajiIssuesByLine.has(index + 1)
+                                                          ? "font-bold text-amber-600"
+                                                          : "text-muted-foreground"
+                                                }`}
+                                                title={romajiIssuesByLine
+                                                    .get(index + 1)
+                                                    ?.map((issue) => issue.message)
+                                                    .join("\n")}
+                                            >
+                                                {index + 1}
+                                            </td>
                                             <td className="px-2 py-2">
                                                 <Input
                                                     ref={(element) => {
@@ -1329,6 +2224,23 @@ export const General = () => {
                             </table>
                         </div>
 
+                        {romajiIssues.length > 0 && (
+                            <div className="mt-3 max-h-24 overflow-auto rounded-md border p-2 text-xs">
+                                {romajiIssues.map((issue, index) => (
+                                    <p
+                                        key={`${issue.line}-${index}`}
+                                        className={
+                                            issue.severity === "error"
+                                                ? "text-destructive"
+                                                : "text-muted-foreground"
+                                        }
+                                    >
+                                        {issue.line} 行目: {issue.message}
+                                    </p>
+                                ))}
+                            </div>
+                        )}
+
                         <div className="mt-3 flex items-center justify-between gap-2">
                             <div className="flex gap-2">
                                 <Button variant="secondary" onClick={addRomajiRow}>
@@ -1337,6 +2249,40 @@ export const General = () => {
                                 <Button variant="outline" onClick={() => void resetRomajiTableDraft()}>
                                     テーブルを初期化
                                 </Button>
+                                <Select
+                                    value={romajiPreset}
+                                    onValueChange={(value) => setRomajiPreset(value as RomajiPreset)}
+                                >
+                                    <SelectTrigger className="w-44">
+                                        <SelectValue placeholder="プリセットを選択" />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        {ROMAJI_PRESET_OPTIONS.map((option) => (
+                                            <SelectItem key={option.value} value={option.value}>
+                                                {option.label}
+                                            </SelectItem>
+                                        ))}
+                                    </SelectContent>
+                                </Select>
+                                <Button variant="outline" onClick={() => void loadRomajiPreset()}>
+                                    プリセットを読み込む
+                                </Button>
+                                <input
+                                    ref={romajiImportInputRef}
+                                    type="file"
+                                    accept=".txt,.tsv"
+                                    className="hidden"
+                                    onChange={(event) => void importRomajiTable(event)}
+                                />
+                                <Button
+                                    variant="outline"
+                                    onClick={() => romajiImportInputRef.current?.click()}
+                                >
+                                    インポート
+                                </Button>
+                                <Button variant="outline" onClick={() => void exportRomajiTable()}>
+                                    エクスポート
+                                </Button>
                             </div>
                             <div className="flex gap-2">
                                 <Button variant="outline" onClick={closeRomajiEditor}>
@@ -31,6 +31,7 @@ private let fallbackDictionaryURL =
 @MainActor var currentLearningMemoryDirectoryURL: URL?
 @MainActor var learningCandidateCache = LearningCandidateCache()
 @MainActor var learningSelectionOverrides: [String: String] = [:]
+@MainActor var learningHistory: [String: LearningHistoryEntry] = [:]
 @MainActor var reconversionDictionary = ReconversionDictionary()
 
 @MainActor var execURL = URL(filePath: "")
@@ -40,7 +41,6 @@ private let fallbackDictionaryURL =
     "backend": "cpu",
     "experimentalTypoCorrection": false,
 ]
-let maxUserDictionaryEntryCount = 50
 let minInputCountForZenzaiCandidates = 4
 let minHiraganaCountForZenzaiCandidates = 2
 let zenzaiWarmupRomanInput = "nihongo"
@@ -56,6 +56,8 @@ let maxLearningCandidateCacheBatchCount = 64
 let maxLearningCandidateCacheSlotCount = 8_192
 let maxLearningSelectionOverrideCount = 4_096
 let learningSelectionOverridesFilename = "selection-overrides.json"
+let maxLearningHistoryCount = 4_096
+let learningHistoryFilename = "learning-history.json"
 let backgroundWarmupPreloadsDictionary = false
 let maxReconversionSurfaceCount = 128
 let maxReconversionDictionarySpan = 40
@@ -859,6 +861,13 @@ struct LearningCandidateCache {
         return true
     }
 
+    func candidate(_ candidateId: UInt64) -> Candidate? {
+        guard candidateId > 0, let location = candidateLocation(for: candidateId) else {
+            return nil
+        }
+        return batches[location.batchIndex]!.candidates[location.candidateIndex]
+    }
+
     mutating func consume(_ candidateId: UInt64) -> Candidate? {
         guard candidateId > 0 else {
             return nil
@@ -964,6 +973,82 @@ struct LearningCandidateCache {
     learningCandidateCache.removeAll()
 }
 
+/// Composition state of a document that is not currently being edited. The
+/// active session lives in the `composingText`, `composingTextSnapshots`,
+/// `learningCandidateCache`, `config["context"]`, `config["right_context"]`
+/// and `sessionZenzaiAllowed` globals.
+struct CompositionSession {
+    var composingText = ComposingText()
+    var composingTextSnapshots: [ComposingText] = []
+    var learningCandidateCache = LearningCandidateCache()
+    var context = ""
+    var rightContext = ""
+    var zenzaiAllowed = true
+}
+
+@MainActor var activeCompositionSessionId: UInt64 = 0
+/// False when the application owning the active session turned Zenzai off.
+@MainActor var sessionZenzaiAllowed = true
+@MainActor var inactiveCompositionSessions: [UInt64: CompositionSession] = [:]
+
+@MainActor private func resetInactiveCompositionSessions(keepingComposition: Bool) {
+    for sessionId in inactiveCompositionSessions.keys {
+        if !keepingComposition {
+            inactiveCompositionSessions[sessionId]!.composingText = ComposingText()
+            inactiveCompositionSessions[sessionId]!.composingTextSnapshots.removeAll()
+        }
+        inactiveCompositionSessions[sessionId]!.learningCandidateCache.removeAll()
+    }
+}
+
+@_silgen_name("SelectCompositionSession")
+@MainActor public func select_composition_session(sessionId: UInt64) {
+    guard sessionId != activeCompositionSessionId else {
+        return
+    }
+    // The converter keeps incremental state for the previous composition.
+    converter.stopComposition()
+    normalNBestSupplementConverter.stopComposition()
+    inactiveCompositionSessions[activeCompositionSessionId] = CompositionSession(
+        composingText: composingText,
+        composingTextSnapshots: composingTextSnapshots,
+        learningCandidateCache: learningCandidateCache,
+        context: (config["context"] as? String) ?? "",
+        rightContext: (config["right_context"] as? String) ?? "",
+        zenzaiAllowed: sessionZenzaiAllowed
+    )
+    let session = inactiveCompositionSessions.removeValue(forKey: sessionId) ?? CompositionSession()
+    composingText = session.composingText
+    composingTextSnapshots = session.composingTextSnapshots
+    learningCandidateCache = session.learningCandidateCache
+    config["context"] = session.context
+    config["right_context"] = session.rightContext
+    sessionZenzaiAllowed = session.zenzaiAllowed
+    activeCompositionSessionId = sessionId
+    serverLog(
+        "DEBUG",
+        "SelectCompositionSession: completed sessionId=\(sessionId) inactiveCount=\(inactiveCompositionSessions.count)"
+    )
+}
+
+@_silgen_name("SetSessionZenzaiAllowed")
+@MainActor public func set_session_zenzai_allowed(allowed: Bool) {
+    sessionZenzaiAllowed = allowed
+}
+
+@_silgen_name("DestroyCompositionSession")
+@MainActor public func destroy_composition_session(sessionId: UInt64) {
+    guard sessionId != activeCompositionSessionId else {
+        serverLog("ERROR", "DestroyCompositionSession: refused active sessionId=\(sessionId)")
+        return
+    }
+    inactiveCompositionSessions.removeValue(forKey: sessionId)
+    serverLog(
+        "DEBUG",
+        "DestroyCompositionSession: completed sessionId=\(sessionId) inactiveCount=\(inactiveCompositionSessions.count)"
+    )
+}
+
 @MainActor func cacheLearningCandidates(_ candidates: [Candidate]) -> UInt64? {
     guard currentLearningType == .inputAndOutput else {
         return nil
@@ -986,6 +1071,36 @@ struct LearningCandidateCache {
     return learningCandidateCache.candidateId(at: index, batchFirstId: batchFirstId)
 }
 
+// Values of the CandidateOrigin enum in service.proto.
+private let candidateOriginSystemDictionary: Int32 = 1
+private let candidateOriginUserDictionary: Int32 = 2
+private let candidateOriginLearned: Int32 = 3
+private let candidateOriginTypoCorrection: Int32 = 4
+
+func candidateOrigin(_ candidate: Candidate) -> Int32 {
+    let metadata = candidate.data.map(\.metadata)
+    if metadata.contains(where: { $0.contains(.isKeyboardTypoCorrection) }) {
+        return candidateOriginTypoCorrection
+    }
+    if metadata.contains(where: { $0.contains(.isFromUserDictionary) }) {
+        return candidateOriginUserDictionary
+    }
+    if metadata.contains(where: { $0.contains(.isLearned) }) {
+        return candidateOriginLearned
+    }
+    return candidateOriginSystemDictionary
+}
+
+/// Short note for the candidate window, or nil when there is nothing to say.
+/// Characters outside Shift_JIS are flagged because they may not render in
+/// older applications.
+func candidateAnnotation(_ text: String) -> UnsafeMutablePointer<CChar>? {
+    guard !text.isEmpty, text.data(using: .shiftJIS) == nil else {
+        return nil
+    }
+    return _strdup("環境依存")
+}
+
 @MainActor func consumeLearningCandidate(_ candidateId: UInt64) -> Candidate? {
     learningCandidateCache.consume(candidateId)
 }
@@ -1109,6 +1224,147 @@ private func learningCandidateOutput(_ candidate: Candidate) -> String {
     saveLearningSelectionOverrides()
 }
 
+/// A learned conversion as the settings UI lists it. The converter's learning
+/// memory cannot be enumerated, so every learned commit is also journaled
+/// here, together with the dictionary elements needed to forget it again.
+struct LearningHistoryEntry: Codable {
+    struct Element: Codable {
+        let ruby: String
+        let word: String
+        let lcid: Int
+        let rcid: Int
+        let mid: Int
+    }
+
+    let ruby: String
+    let word: String
+    var count: Int
+    var lastUsed: Double
+    var elements: [Element]
+}
+
+private func learningHistoryKey(ruby: String, word: String) -> String {
+    ruby + "\u{0}" + word
+}
+
+@MainActor private func learningHistoryURL() -> URL? {
+    currentLearningMemoryDirectoryURL?.appendingPathComponent(
+        learningHistoryFilename,
+        isDirectory: false
+    )
+}
+
+@MainActor func loadLearningHistory() {
+    guard currentLearningType != .nothing,
+          let historyURL = learningHistoryURL(),
+          FileManager.default.fileExists(atPath: historyURL.path) else {
+        learningHistory.removeAll(keepingCapacity: false)
+        return
+    }
+
+    do {
+        let data = try Data(contentsOf: historyURL)
+        let entries = try JSONDecoder().decode([LearningHistoryEntry].self, from: data)
+        learningHistory = Dictionary(
+            entries.suffix(maxLearningHistoryCount).map {
+                (learningHistoryKey(ruby: $0.ruby, word: $0.word), $0)
+            },
+            uniquingKeysWith: { _, last in last }
+        )
+    } catch {
+        learningHistory.removeAll(keepingCapacity: false)
+        serverLog("WARN", "Failed to load learning history at \(historyURL.path): \(error)")
+    }
+}
+
+@MainActor private func saveLearningHistory() {
+    guard let historyURL = learningHistoryURL() else {
+        return
+    }
+
+    do {
+        ensureLearningMemoryDirectoryIfNeeded()
+        let entries = learningHistory.values.sorted { $0.lastUsed < $1.lastUsed }
+        let data = try JSONEncoder().encode(entries)
+        try data.write(to: historyURL, options: .atomic)
+    } catch {
+        serverLog("WARN", "Failed to save learning history at \(historyURL.path): \(error)")
+    }
+}
+
+@MainActor private func updateLearningHistory(_ candidate: Candidate) -> Bool {
+    guard candidate.isLearningTarget else {
+        return false
+    }
+
+    let ruby = learningCandidateRuby(candidate)
+    let word = learningCandidateOutput(candidate)
+    guard !ruby.isEmpty, !word.isEmpty else {
+        return false
+    }
+
+    let key = learningHistoryKey(ruby: ruby, word: word)
+    let now = Date().timeIntervalSince1970
+    let elements = candidate.data.map {
+        LearningHistoryEntry.Element(
+            ruby: $0.ruby,
+            word: $0.word,
+            lcid: $0.lcid,
+            rcid: $0.rcid,
+            mid: $0.mid
+        )
+    }
+    if var entry = learningHistory[key] {
+        entry.count += 1
+        entry.lastUsed = now
+        entry.elements = elements
+        learningHistory[key] = entry
+        return true
+    }
+
+    if learningHistory.count >= maxLearningHistoryCount,
+       let evictedKey = learningHistory.min(by: { $0.value.lastUsed < $1.value.lastUsed })?.key {
+        learningHistory.removeValue(forKey: evictedKey)
+    }
+    learningHistory[key] = LearningHistoryEntry(
+        ruby: ruby,
+        word: word,
+        count: 1,
+        lastUsed: now,
+        elements: elements
+    )
+    return true
+}
+
+/// Removes a learned conversion from the converters' learning memory, the
+/// selection overrides and the history. Returns false when it was never
+/// journaled and there is nothing to forget.
+@MainActor private func forgetLearnedConversion(
+    ruby: String,
+    word: String,
+    elements: [DicdataElement]
+) -> Bool {
+    let key = learningHistoryKey(ruby: ruby, word: word)
+    let journaled = learningHistory.removeValue(forKey: key) != nil
+    let overridden = learningSelectionOverrides[ruby] == word
+    guard journaled || overridden else {
+        return false
+    }
+
+    for element in elements {
+        converter.forgetMemory(element)
+        normalNBestSupplementConverter.forgetMemory(element)
+    }
+    if overridden {
+        learningSelectionOverrides.removeValue(forKey: ruby)
+        saveLearningSelectionOverrides()
+    }
+    if journaled {
+        saveLearningHistory()
+    }
+    return true
+}
+
 @MainActor func prioritizeLearningSelectionOverrides<Element>(
     _ elements: [Element],
     ruby: String,
@@ -1147,6 +1403,7 @@ private func learningCandidateOutput(_ candidate: Candidate) -> String {
 
 private func makeConvertRequestOptions(
     context: String,
+    rightContext: String = "",
     zenzaiEnabled: Bool,
     runtimeDirectoryURL: URL,
     emojiDictionaryURL: URL,
@@ -1154,10 +1411,11 @@ private func makeConvertRequestOptions(
     profile: String,
     learningType: LearningType = .nothing,
     learningMemoryDirectoryURL: URL? = nil,
-    experimentalKeyboardTypoCorrection: Bool = false
+    experimentalKeyboardTypoCorrection: Bool = false,
+    japanesePrediction: ConvertRequestOptions.PredictionMode = .disabled
 ) -> ConvertRequestOptions {
     return ConvertRequestOptions(
-        requireJapanesePrediction: .disabled,
+        requireJapanesePrediction: japanesePrediction,
         requireEnglishPrediction: .disabled,
         keyboardLanguage: .ja_JP,
         learningType: learningType,
@@ -1175,7 +1433,8 @@ private func makeConvertRequestOptions(
             versionDependentMode: .v3(
                 .init(
                     profile: profile,
-                    leftSideContext: context
+                    leftSideContext: context,
+                    rightSideContext: rightContext.isEmpty ? nil : rightContext
                 )
             )
         ) : .off,
@@ -1187,7 +1446,6 @@ private func makeConvertRequestOptions(
 private struct AppSettings: Decodable {
     let zenzai: ZenzaiSettings?
     let learning: LearningSettings?
-    let user_dictionary: UserDictionarySettings?
     let romaji_table: RomajiTableSettings?
     let general: GeneralSettings?
 }
@@ -1206,15 +1464,6 @@ private struct ZenzaiSettings: Decodable {
     let backend: String?
 }
 
-private struct UserDictionarySettings: Decodable {
-    let entries: [UserDictionaryEntry]?
-}
-
-private struct UserDictionaryEntry: Decodable {
-    let reading: String
-    let word: String
-}
-
 private struct RomajiTableSettings: Decodable {
     let rows: [RomajiTableRow]?
 }
@@ -1228,6 +1477,68 @@ private func normalizeReading(_ reading: String) -> String {
     reading.applyingTransform(.hiraganaToKatakana, reverse: false) ?? reading
 }
 
+/// A word from the server's user dictionary store. The server sends the whole
+/// list once after startup and then each change as it happens.
+private struct UserDictionaryWord: Decodable {
+    let id: UInt64
+    let reading: String
+    let word: String
+    let part_of_speech: String?
+}
+
+/// User words in priority order; earlier words rank higher.
+@MainActor private var userDictionaryWords: [UserDictionaryWord] = []
+
+private func userDictionaryCID(partOfSpeech: String?) -> Int {
+    switch partOfSpeech {
+    case "noun":
+        return CIDData.一般名詞.cid
+    case "person_name":
+        return CIDData.人名一般.cid
+    case "place_name":
+        return CIDData.地名一般.cid
+    case "organization":
+        return CIDData.固有名詞組織.cid
+    default:
+        return CIDData.固有名詞.cid
+    }
+}
+
+@MainActor private func applyUserDictionary() {
+    let userEntries = userDictionaryWords.enumerated().map { priorityRank, entry in
+        DicdataElement(
+            word: entry.word,
+            ruby: normalizeReading(entry.reading),
+            cid: userDictionaryCID(partOfSpeech: entry.part_of_speech),
+            mid: MIDData.一般.mid,
+            value: PValue(-5 - Float(priorityRank) * 0.01)
+        )
+    }
+    let conversionDictionary = makeConversionDictionaryEntries(
+        userEntries: userEntries,
+        experimentalTypoCorrectionEnabled:
+            (config["experimentalTypoCorrection"] as? Bool) ?? false
+    )
+    converter.importDynamicUserDictionary(conversionDictionary)
+    normalNBestSupplementConverter.importDynamicUserDictionary(conversionDictionary)
+    reconversionDictionary.replaceUserEntries(userEntries)
+}
+
+private func decodeUserDictionaryWords(
+    _ wordsJson: UnsafePointer<CChar>,
+    scope: String
+) -> [UserDictionaryWord]? {
+    do {
+        return try JSONDecoder().decode(
+            [UserDictionaryWord].self,
+            from: Data(String(cString: wordsJson).utf8)
+        )
+    } catch {
+        serverLog("ERROR", "\(scope): failed to decode user dictionary words: \(error)")
+        return nil
+    }
+}
+
 func resolveRomajiInputStyleSelection(
     rows: [RomajiTableRow]?
 ) -> RomajiInputStyleSelection {
@@ -1875,11 +2186,14 @@ private func preferCursorPrefixBoundary(
 
 @MainActor func getOptions(
     context: String = "",
-    zenzaiEnabled: Bool
+    rightContext: String = "",
+    zenzaiEnabled: Bool,
+    japanesePrediction: ConvertRequestOptions.PredictionMode = .disabled
 ) -> ConvertRequestOptions {
     configureEngineRuntime(zenzaiEnabled: zenzaiEnabled)
     return makeConvertRequestOptions(
         context: context,
+        rightContext: rightContext,
         zenzaiEnabled: zenzaiEnabled,
         runtimeDirectoryURL: converterRuntimeDirectoryURL(),
         emojiDictionaryURL: execURL
@@ -1890,7 +2204,8 @@ private func preferCursorPrefixBoundary(
         learningType: currentLearningType,
         learningMemoryDirectoryURL: currentLearningMemoryDirectoryURL,
         experimentalKeyboardTypoCorrection:
-            (config["experimentalTypoCorrection"] as? Bool) ?? false
+            (config["experimentalTypoCorrection"] as? Bool) ?? false,
+        japanesePrediction: japanesePrediction
     )
 }
 
@@ -2485,16 +2800,8 @@ func cursorPrefixBoundaryFirstClauseResults(
     let previousUsedCustomRomajiTable = customRomajiTableEnabled
     let previousLearningType = currentLearningType
     let previousLearningMemoryDirectoryURL = currentLearningMemoryDirectoryURL
-    var dynamicUserDictionary: [DicdataElement] = []
     defer {
-        let conversionDictionary = makeConversionDictionaryEntries(
-            userEntries: dynamicUserDictionary,
-            experimentalTypoCorrectionEnabled:
-                (config["experimentalTypoCorrection"] as? Bool) ?? false
-        )
-        converter.importDynamicUserDictionary(conversionDictionary)
-        normalNBestSupplementConverter.importDynamicUserDictionary(conversionDictionary)
-        reconversionDictionary.replaceUserEntries(dynamicUserDictionary)
+        applyUserDictionary()
     }
 
     config["enable"] = false
@@ -2526,44 +2833,6 @@ func cursorPrefixBoundaryFirstClauseResults(
         currentLearningType = learningType(for: settings.learning?.mode)
         config["experimentalTypoCorrection"] =
             settings.general?.experimental_typo_correction ?? false
-
-        let sourceEntries = settings.user_dictionary?.entries ?? []
-        var seen: Set<String> = []
-        var priorityRank = 0
-        for entry in sourceEntries {
-            if dynamicUserDictionary.count >= maxUserDictionaryEntryCount {
-                break
-            }
-
-            let reading = entry.reading.trimmingCharacters(in: .whitespacesAndNewlines)
-            let word = entry.word.trimmingCharacters(in: .whitespacesAndNewlines)
-            if reading.isEmpty || word.isEmpty {
-                continue
-            }
-
-            let normalizedReading = normalizeReading(reading)
-            let key = normalizedReading + "\u{0}" + word
-            if seen.contains(key) {
-                continue
-            }
-            seen.insert(key)
-
-            let priorityAdjustedValue = PValue(-5 - Float(priorityRank) * 0.01)
-            dynamicUserDictionary.append(
-                DicdataElement(
-                    word: word,
-                    ruby: normalizedReading,
-                    cid: CIDData.固有名詞.cid,
-                    mid: MIDData.一般.mid,
-                    value: priorityAdjustedValue
-                )
-            )
-            priorityRank += 1
-        }
-
-        if sourceEntries.count > maxUserDictionaryEntryCount {
-            serverLog("WARN", "User dictionary entries are truncated to \(maxUserDictionaryEntryCount).")
-        }
     } else if let settingsLoadError {
         serverLog("ERROR", "Failed to read settings: \(settingsLoadError)")
     } else {
@@ -2597,14 +2866,17 @@ func cursorPrefixBoundaryFirstClauseResults(
         composingText = ComposingText()
         composingTextSnapshots.removeAll()
         clearLearningCandidateCache()
+        resetInactiveCompositionSessions(keepingComposition: false)
     }
     if previousLearningType != currentLearningType
         || previousLearningMemoryDirectoryURL != currentLearningMemoryDirectoryURL
     {
         clearLearningCandidateCache()
+        resetInactiveCompositionSessions(keepingComposition: true)
     }
     ensureLearningMemoryDirectoryIfNeeded()
     loadLearningSelectionOverrides()
+    loadLearningHistory()
 
     serverLog(
         "INFO",
@@ -2636,6 +2908,9 @@ func cursorPrefixBoundaryFirstClauseResults(
     }
     rebuildConverter()
     clearLearningCandidateCache()
+    inactiveCompositionSessions.removeAll()
+    activeCompositionSessionId = 0
+    sessionZenzaiAllowed = true
 
     load_config()
 
@@ -3028,6 +3303,9 @@ func cursorPrefixBoundaryFirstClauseResults(
     converter.updateLearningData(candidate)
     converter.commitUpdateLearningData()
     recordLearningSelectionOverride(candidate)
+    if updateLearningHistory(candidate) {
+        saveLearningHistory()
+    }
     serverLog(
         "DEBUG",
         "CommitLearningCandidate: completed candidateId=\(candidateId) commitKind=\(commitKind)"
@@ -3076,16 +3354,21 @@ func cursorPrefixBoundaryFirstClauseResults(
 
     ensureLearningMemoryDirectoryIfNeeded()
     var selectionOverrideChanged = false
+    var historyChanged = false
     for entry in candidates {
         converter.setCompletedData(entry.candidate)
         converter.updateLearningData(entry.candidate)
         selectionOverrideChanged =
             updateLearningSelectionOverride(entry.candidate) || selectionOverrideChanged
+        historyChanged = updateLearningHistory(entry.candidate) || historyChanged
     }
     converter.commitUpdateLearningData()
     if selectionOverrideChanged {
         saveLearningSelectionOverrides()
     }
+    if historyChanged {
+        saveLearningHistory()
+    }
     serverLog(
         "DEBUG",
         "CommitLearningCandidates: completed requestedCount=\(count) committedCount=\(candidates.count)"
@@ -3099,11 +3382,107 @@ func cursorPrefixBoundaryFirstClauseResults(
     converter.resetMemory()
     normalNBestSupplementConverter.resetMemory()
     clearLearningCandidateCache()
+    resetInactiveCompositionSessions(keepingComposition: true)
     learningSelectionOverrides.removeAll(keepingCapacity: false)
+    learningHistory.removeAll(keepingCapacity: false)
     serverLog("INFO", "ResetLearningMemory: completed resetDirectory=\(resetDirectory)")
     return resetDirectory
 }
 
+@_silgen_name("ListLearnedEntries")
+@MainActor public func list_learned_entries() -> UnsafeMutablePointer<CChar>? {
+    struct LearnedEntry: Encodable {
+        let reading: String
+        let word: String
+        let count: Int
+        let last_used_unix_ms: Int64
+    }
+
+    let entries = learningHistory.values
+        .sorted { $0.lastUsed > $1.lastUsed }
+        .map {
+            LearnedEntry(
+                reading: $0.ruby.applyingTransform(.hiraganaToKatakana, reverse: true) ?? $0.ruby,
+                word: $0.word,
+                count: $0.count,
+                last_used_unix_ms: Int64($0.lastUsed * 1000)
+            )
+        }
+    guard let data = try? JSONEncoder().encode(entries),
+          let encodedEntries = String(data: data, encoding: .utf8) else {
+        serverLog("ERROR", "ListLearnedEntries: failed to encode entries")
+        return nil
+    }
+    return _strdup(encodedEntries)
+}
+
+@_silgen_name("ForgetLearnedEntry")
+@MainActor public func forget_learned_entry(
+    reading: UnsafePointer<CChar>,
+    word: UnsafePointer<CChar>
+) -> Bool {
+    let ruby = normalizedLearningRuby(String(cString: reading))
+    let word = String(cString: word)
+    let elements = learningHistory[learningHistoryKey(ruby: ruby, word: word)]?.elements.map {
+        DicdataElement(word: $0.word, ruby: $0.ruby, lcid: $0.lcid, rcid: $0.rcid, mid: $0.mid, value: 0)
+    } ?? []
+    let forgotten = forgetLearnedConversion(ruby: ruby, word: word, elements: elements)
+    serverLog("INFO", "ForgetLearnedEntry: completed forgotten=\(forgotten)")
+    return forgotten
+}
+
+@_silgen_name("ForgetLearningCandidate")
+@MainActor public func forget_learning_candidate(candidateId: UInt64) -> Bool {
+    guard let candidate = learningCandidateCache.candidate(candidateId) else {
+        serverLog("WARN", "ForgetLearningCandidate: candidate not found candidateId=\(candidateId)")
+        return false
+    }
+    let forgotten = forgetLearnedConversion(
+        ruby: learningCandidateRuby(candidate),
+        word: learningCandidateOutput(candidate),
+        elements: candidate.data
+    )
+    serverLog(
+        "INFO",
+        "ForgetLearningCandidate: completed candidateId=\(candidateId) forgotten=\(forgotten)"
+    )
+    return forgotten
+}
+
+@_silgen_name("SetUserDictionary")
+@MainActor public func set_user_dictionary(_ wordsJson: UnsafePointer<CChar>) -> Bool {
+    guard let words = decodeUserDictionaryWords(wordsJson, scope: "SetUserDictionary") else {
+        return false
+    }
+    userDictionaryWords = words
+    applyUserDictionary()
+    serverLog("INFO", "SetUserDictionary: completed entries=\(userDictionaryWords.count)")
+    return true
+}
+
+@_silgen_name("AddUserDictionaryWord")
+@MainActor public func add_user_dictionary_word(_ wordsJson: UnsafePointer<CChar>) -> Bool {
+    guard let words = decodeUserDictionaryWords(wordsJson, scope: "AddUserDictionaryWord") else {
+        return false
+    }
+    userDictionaryWords.append(contentsOf: words)
+    applyUserDictionary()
+    serverLog("INFO", "AddUserDictionaryWord: completed entries=\(userDictionaryWords.count)")
+    return true
+}
+
+@_silgen_name("RemoveUserDictionaryWord")
+@MainActor public func remove_user_dictionary_word(_ wordsJson: UnsafePointer<CChar>) -> Bool {
+    guard let words = decodeUserDictionaryWords(wordsJson, scope: "RemoveUserDictionaryWord") else {
+        return false
+    }
+    let removedIds = Set(words.map(\.id))
+    userDictionaryWords.removeAll { removedIds.contains($0.id) }
+    applyUserDictionary()
+    serverLog("INFO", "RemoveUserDictionaryWord: completed entries=\(userDictionaryWords.count)")
+    return true
+}
+
 func to_list_pointer(_ list: [FFICandidate]) -> UnsafeMutablePointer<UnsafeMutablePointer<FFICandidate>?> {
     let pointer = UnsafeMutablePointer<UnsafeMutablePointer<FFICandidate>?>.allocate(capacity: list.count)
     guard !list.isEmpty else {
@@ -3162,6 +3541,7 @@ public func free_candidate_list(
         free(candidate.text)
         free(candidate.subtext)
         free(candidate.hiragana)
+        free(candidate.annotation)
     }
 
     if isContiguousCandidateStorage, let candidateStorage {
@@ -3186,11 +3566,114 @@ public func free_candidate_list(
     get_composed_text_impl(lengthPtr: lengthPtr, allowZenzai: true)
 }
 
+/// Dictionary-only candidates for the first phase of a deferred Zenzai
+/// conversion. The neural result is requested afterwards with GetComposedText.
+@_silgen_name("GetComposedTextWithoutZenzai")
+@MainActor public func get_composed_text_without_zenzai(lengthPtr: UnsafeMutablePointer<CInt>) -> UnsafeMutablePointer<UnsafeMutablePointer<FFICandidate>?> {
+    get_composed_text_impl(lengthPtr: lengthPtr, allowZenzai: false)
+}
+
+@_silgen_name("ComposedTextWouldUseZenzai")
+@MainActor public func composed_text_would_use_zenzai() -> Bool {
+    effectiveZenzaiEnabledForCandidates(
+        isConfigured: zenzaiDiagnosticSnapshot().runtimeEnabled && sessionZenzaiAllowed,
+        inputCount: composingText.input.count,
+        hiraganaCount: composingText.convertTarget.count
+    )
+}
+
 @_silgen_name("GetComposedTextForReconversion")
 @MainActor public func get_composed_text_for_reconversion(lengthPtr: UnsafeMutablePointer<CInt>) -> UnsafeMutablePointer<UnsafeMutablePointer<FFICandidate>?> {
     get_composed_text_impl(lengthPtr: lengthPtr, allowZenzai: false)
 }
 
+/// Completions of the whole reading: learned conversions first, newest use
+/// first, then user dictionary words, then the converter's predictions.
+@_silgen_name("GetComposedTextForPrediction")
+@MainActor public func get_composed_text_for_prediction(lengthPtr: UnsafeMutablePointer<CInt>) -> UnsafeMutablePointer<UnsafeMutablePointer<FFICandidate>?> {
+    let hiragana = composingText.convertTarget
+    let reading = normalizedLearningRuby(hiragana)
+    let composingCount = ComposingCount.inputCount(composingText.input.count)
+    guard !reading.isEmpty else {
+        lengthPtr.pointee = 0
+        return to_list_pointer([])
+    }
+
+    let learned = learningHistory.values
+        .filter { $0.ruby.count > reading.count && $0.ruby.hasPrefix(reading) }
+        .sorted { $0.lastUsed > $1.lastUsed }
+        .map { entry in
+            Candidate(
+                text: entry.word,
+                value: 0,
+                composingCount: composingCount,
+                lastMid: entry.elements.last?.mid ?? MIDData.一般.mid,
+                data: entry.elements.map {
+                    var element = DicdataElement(
+                        word: $0.word,
+                        ruby: $0.ruby,
+                        lcid: $0.lcid,
+                        rcid: $0.rcid,
+                        mid: $0.mid,
+                        value: 0
+                    )
+                    element.metadata = .isLearned
+                    return element
+                }
+            )
+        }
+    let userWords = userDictionaryWords.compactMap { entry -> Candidate? in
+        let ruby = normalizeReading(entry.reading)
+        guard ruby.count > reading.count, ruby.hasPrefix(reading) else {
+            return nil
+        }
+        var element = DicdataElement(
+            word: entry.word,
+            ruby: ruby,
+            cid: userDictionaryCID(partOfSpeech: entry.part_of_speech),
+            mid: MIDData.一般.mid,
+            value: 0
+        )
+        element.metadata = .isFromUserDictionary
+        return Candidate(
+            text: entry.word,
+            value: 0,
+            composingCount: composingCount,
+            lastMid: MIDData.一般.mid,
+            data: [element]
+        )
+    }
+    let options = getOptions(
+        context: (config["context"] as? String) ?? "",
+        zenzaiEnabled: false,
+        japanesePrediction: .manualMix
+    )
+    let converted = converter.requestCandidates(composingText, options: options)
+
+    var seen = Set<String>()
+    let predictions = (learned + userWords + converted.predictionResults).filter {
+        $0.text != hiragana && seen.insert($0.text).inserted
+    }
+    let learningCandidateBatchFirstId = cacheLearningCandidates(predictions)
+    let result = predictions.enumerated().map { i, candidate in
+        FFICandidate(
+            text: _strdup(candidate.text),
+            subtext: _strdup(""),
+            hiragana: i == 0 ? _strdup(hiragana) : nil,
+            correspondingCount: Int32(composingText.input.count),
+            candidateId: learningCandidateId(at: i, batchFirstId: learningCandidateBatchFirstId),
+            origin: candidateOrigin(candidate),
+            lcid: UInt32(candidate.data.first?.lcid ?? 0),
+            rcid: UInt32(candidate.rcid),
+            annotation: candidateAnnotation(candidate.text)
+        )
+    }
+    serverLog("DEBUG", "GetComposedTextForPrediction: completed candidateCount=\(result.count) learnedCount=\(learned.count) userWordCount=\(userWords.count)")
+
+    lengthPtr.pointee = CInt(result.count)
+    return to_list_pointer(result)
+}
+
 @MainActor private func get_composed_text_impl(
     lengthPtr: UnsafeMutablePointer<CInt>,
     allowZenzai: Bool
@@ -3199,8 +3682,9 @@ public func free_candidate_list(
     let performanceEnabled = serverLogCallbacks.isPerformanceLogEnabled()
     let originalHiragana = composingText.convertTarget
     let contextString = (config["context"] as? String) ?? ""
+    let rightContextString = (config["right_context"] as? String) ?? ""
     let diagnosticSnapshot = zenzaiDiagnosticSnapshot()
-    let runtimeZenzaiEnabled = diagnosticSnapshot.runtimeEnabled && allowZenzai
+    let runtimeZenzaiEnabled = diagnosticSnapshot.runtimeEnabled && allowZenzai && sessionZenzaiAllowed
     let previewState = makeCandidatePreviewComposingText(from: composingText)
     let previewComposingText = previewState.composingText
     let previewHiragana = previewComposingText.convertTarget
@@ -3222,7 +3706,11 @@ public func free_candidate_list(
         "DEBUG",
         "GetComposedText: start \(diagnosticDetails)"
     )
-    let options = getOptions(context: contextString, zenzaiEnabled: useZenzai)
+    let options = getOptions(
+        context: contextString,
+        rightContext: rightContextString,
+        zenzaiEnabled: useZenzai
+    )
     candidateCrashTrace(
         useZenzai: useZenzai,
         operation: "GetComposedText",
@@ -3325,6 +3813,7 @@ public func free_candidate_list(
         let text = _strdup(candidateText)
         let subtext = _strdup(resolvedCandidate.remainingConvertTarget)
         let hiragana = i == 0 ? _strdup(previewHiragana) : nil
+        let annotation = candidateAnnotation(candidateText)
         if performanceEnabled {
             strdupCandidatesMs += elapsedPerformanceMilliseconds(since: strdupStart)
         }
@@ -3335,7 +3824,11 @@ public func free_candidate_list(
                 subtext: subtext,
                 hiragana: hiragana,
                 correspondingCount: Int32(correspondingCount),
-                candidateId: learningCandidateId(at: i, batchFirstId: learningCandidateBatchFirstId)
+                candidateId: learningCandidateId(at: i, batchFirstId: learningCandidateBatchFirstId),
+                origin: candidateOrigin(candidate),
+                lcid: UInt32(candidate.data.first?.lcid ?? 0),
+                rcid: UInt32(candidate.rcid),
+                annotation: annotation
             )
         )
     }
@@ -3410,7 +3903,7 @@ public func free_candidate_list(
     let previewPrefixHiragana = previewPrefixComposingText.convertTarget
     let contextString = (config["context"] as? String) ?? ""
     let diagnosticSnapshot = zenzaiDiagnosticSnapshot()
-    let runtimeZenzaiEnabled = diagnosticSnapshot.runtimeEnabled
+    let runtimeZenzaiEnabled = diagnosticSnapshot.runtimeEnabled && sessionZenzaiAllowed
     let useZenzai = effectiveZenzaiEnabledForCandidates(
         isConfigured: runtimeZenzaiEnabled,
         inputCount: prefixComposingText.input.count,
@@ -3662,6 +4155,7 @@ public func free_candidate_list(
         let text = _strdup(cursorPrefixResult.displayText)
         let subtext = _strdup(resolvedCandidate.remainingConvertTarget + suffixAfterCursor)
         let hiragana = i == 0 ? _strdup(ffiHiragana) : nil
+        let annotation = candidateAnnotation(cursorPrefixResult.displayText)
         if performanceEnabled {
             strdupCandidatesMs += elapsedPerformanceMilliseconds(since: strdupStart)
         }
@@ -3672,7 +4166,11 @@ public func free_candidate_list(
                 subtext: subtext,
                 hiragana: hiragana,
                 correspondingCount: Int32(correspondingCount),
-                candidateId: learningCandidateId(at: i, batchFirstId: learningCandidateBatchFirstId)
+                candidateId: learningCandidateId(at: i, batchFirstId: learningCandidateBatchFirstId),
+                origin: candidateOrigin(candidate),
+                lcid: UInt32(candidate.data.first?.lcid ?? 0),
+                rcid: UInt32(candidate.rcid),
+                annotation: annotation
             )
         )
     }
@@ -3730,9 +4228,15 @@ public func free_candidate_list(
 
 @_silgen_name("SetContext")
 @MainActor public func set_context(
-    context: UnsafePointer<CChar>
+    context: UnsafePointer<CChar>,
+    rightContext: UnsafePointer<CChar>
 ) {
     let contextString = String(cString: context)
+    let rightContextString = String(cString: rightContext)
     config["context"] = contextString
-    serverLog("DEBUG", "SetContext: contextLength=\(contextString.count)")
+    config["right_context"] = rightContextString
+    serverLog(
+        "DEBUG",
+        "SetContext: contextLength=\(contextString.count) rightContextLength=\(rightContextString.count)"
+    )
 }
@@ -14,12 +14,22 @@ struct FFICandidate {
     char *hiragana;
     int correspondingCount;
     unsigned long long candidateId;
+    int origin;
+    unsigned int lcid;
+    unsigned int rcid;
+    char *annotation;
 };
 
 void FreeCString(char *ptr);
 void FreeCandidateList(struct FFICandidate **ptr, int length);
 bool CommitLearningCandidate(unsigned long long candidateId, int commitKind);
 bool ResetLearningMemory(void);
+bool SetUserDictionary(const char *wordsJson);
+bool AddUserDictionaryWord(const char *wordsJson);
+bool RemoveUserDictionaryWord(const char *wordsJson);
+char *ListLearnedEntries(void);
+bool ForgetLearnedEntry(const char *reading, const char *word);
+bool ForgetLearningCandidate(unsigned long long candidateId);
 
 #ifdef __cplusplus
 }